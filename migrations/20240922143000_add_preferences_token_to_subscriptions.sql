@@ -0,0 +1,12 @@
+-- Add migration script here
+BEGIN;
+    ALTER TABLE subscriptions ADD COLUMN preferences_token TEXT NULL;
+    -- Backfill `preferences_token` for historical entries
+    UPDATE subscriptions
+        SET preferences_token = substr(md5(random()::text || id::text), 1, 25)
+        WHERE preferences_token IS NULL;
+    ALTER TABLE subscriptions ALTER COLUMN preferences_token SET NOT NULL;
+    ALTER TABLE subscriptions ADD CONSTRAINT subscriptions_preferences_token_key UNIQUE (preferences_token);
+    -- The address a subscriber changed to, until it is confirmed
+    ALTER TABLE subscriptions ADD COLUMN pending_email TEXT NULL;
+COMMIT;
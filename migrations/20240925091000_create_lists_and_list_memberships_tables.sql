@@ -0,0 +1,34 @@
+-- Add migration script here
+BEGIN;
+    CREATE TABLE lists(
+        list_id uuid PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        created_at timestamptz NOT NULL
+    );
+    -- The list every historical subscription and issue belonged to
+    INSERT INTO lists (list_id, name, created_at)
+    VALUES ('6b1f7a52-4c0e-4d5e-9a3b-1f2e8c7d9a01', 'Newsletter', now());
+
+    CREATE TABLE list_memberships(
+        list_id uuid NOT NULL
+            REFERENCES lists (list_id),
+        subscriber_id uuid NOT NULL
+            REFERENCES subscriptions (id) ON DELETE CASCADE,
+        status TEXT NOT NULL,
+        subscribed_at timestamptz NOT NULL,
+        PRIMARY KEY (list_id, subscriber_id)
+    );
+    INSERT INTO list_memberships (list_id, subscriber_id, status, subscribed_at)
+    SELECT '6b1f7a52-4c0e-4d5e-9a3b-1f2e8c7d9a01', id, status, subscribed_at
+    FROM subscriptions;
+    -- `subscriptions.status` now only tracks whether the email address was confirmed
+    UPDATE subscriptions SET status = 'confirmed' WHERE status = 'unsubscribed';
+
+    -- Tokens without a list only confirm the email address (e.g. after it was changed)
+    ALTER TABLE subscription_tokens ADD COLUMN list_id uuid NULL REFERENCES lists (list_id);
+    UPDATE subscription_tokens SET list_id = '6b1f7a52-4c0e-4d5e-9a3b-1f2e8c7d9a01';
+
+    ALTER TABLE newsletter_issues ADD COLUMN list_id uuid NULL REFERENCES lists (list_id);
+    UPDATE newsletter_issues SET list_id = '6b1f7a52-4c0e-4d5e-9a3b-1f2e8c7d9a01';
+    ALTER TABLE newsletter_issues ALTER COLUMN list_id SET NOT NULL;
+COMMIT;
@@ -0,0 +1,9 @@
+-- Add migration script here
+CREATE TABLE failed_logins(
+    scope TEXT NOT NULL,
+    key TEXT NOT NULL,
+    failed_attempts INT NOT NULL,
+    last_failed_at timestamptz NOT NULL,
+    locked_until timestamptz NULL,
+    PRIMARY KEY (scope, key)
+);
@@ -0,0 +1,19 @@
+-- Add migration script here
+CREATE TABLE two_factor_credentials(
+    user_id uuid PRIMARY KEY
+        REFERENCES users (user_id) ON DELETE CASCADE,
+    secret TEXT NOT NULL,
+    confirmed_at timestamptz NULL,
+    last_used_step BIGINT NULL
+);
+CREATE TABLE two_factor_recovery_codes(
+    user_id uuid NOT NULL
+        REFERENCES users (user_id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    PRIMARY KEY (user_id, code_hash)
+);
+CREATE TABLE security_policy(
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    require_two_factor BOOLEAN NOT NULL
+);
+INSERT INTO security_policy (id, require_two_factor) VALUES (TRUE, FALSE);
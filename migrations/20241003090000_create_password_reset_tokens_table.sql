@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE password_reset_tokens(
+    token_hash TEXT PRIMARY KEY,
+    user_id uuid NOT NULL
+        REFERENCES users (user_id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL
+);
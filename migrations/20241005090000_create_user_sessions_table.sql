@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE user_sessions(
+    session_id uuid PRIMARY KEY,
+    user_id uuid NOT NULL
+        REFERENCES users (user_id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL,
+    last_seen_at timestamptz NOT NULL,
+    ip TEXT NOT NULL,
+    user_agent TEXT NOT NULL,
+    password_fingerprint TEXT NOT NULL,
+    revoked_at timestamptz NULL
+);
+CREATE INDEX user_sessions_user_id_idx ON user_sessions (user_id);
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- NULL for issues published before the count was recorded.
+ALTER TABLE newsletter_issues ADD COLUMN recipient_count INT NULL;
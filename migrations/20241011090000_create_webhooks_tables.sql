@@ -0,0 +1,33 @@
+-- Add migration script here
+CREATE TABLE webhook_endpoints(
+    endpoint_id uuid PRIMARY KEY,
+    url TEXT NOT NULL,
+    events TEXT[] NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+-- One row per event and endpoint, written in the same transaction as the
+-- change the event is about.
+CREATE TABLE webhook_outbox(
+    delivery_id uuid PRIMARY KEY,
+    endpoint_id uuid NOT NULL
+        REFERENCES webhook_endpoints (endpoint_id) ON DELETE CASCADE,
+    event_id uuid NOT NULL,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    n_attempts SMALLINT NOT NULL DEFAULT 0,
+    execute_after timestamptz NULL
+);
+CREATE INDEX webhook_outbox_pending_idx ON webhook_outbox (created_at) WHERE status = 'pending';
+CREATE INDEX webhook_outbox_endpoint_id_idx ON webhook_outbox (endpoint_id);
+
+CREATE TABLE webhook_attempts(
+    delivery_id uuid NOT NULL
+        REFERENCES webhook_outbox (delivery_id) ON DELETE CASCADE,
+    attempted_at timestamptz NOT NULL,
+    status_code SMALLINT NULL,
+    error TEXT NULL
+);
+CREATE INDEX webhook_attempts_delivery_id_idx ON webhook_attempts (delivery_id);
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE users ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
+ALTER TABLE users ADD COLUMN deactivated_at timestamptz NULL;
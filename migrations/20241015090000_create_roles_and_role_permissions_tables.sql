@@ -0,0 +1,26 @@
+-- Add migration script here
+CREATE TABLE roles(
+    name TEXT PRIMARY KEY,
+    created_at timestamptz NOT NULL DEFAULT now()
+);
+CREATE TABLE role_permissions(
+    role TEXT NOT NULL
+        REFERENCES roles (name) ON DELETE CASCADE,
+    permission TEXT NOT NULL,
+    PRIMARY KEY (role, permission)
+);
+
+INSERT INTO roles (name) VALUES ('admin'), ('collabolator');
+INSERT INTO role_permissions (role, permission)
+SELECT 'admin', permission
+FROM unnest(ARRAY[
+    'issues:publish', 'issues:draft', 'subscribers:manage', 'users:manage', 'stats:view'
+]) AS permission;
+INSERT INTO role_permissions (role, permission)
+SELECT 'collabolator', permission
+FROM unnest(ARRAY['issues:publish', 'issues:draft', 'stats:view']) AS permission;
+
+-- An early migration spelled the role differently from the application.
+UPDATE users SET role = 'collabolator' WHERE role = 'collaborator';
+ALTER TABLE users
+    ADD CONSTRAINT users_role_fkey FOREIGN KEY (role) REFERENCES roles (name);
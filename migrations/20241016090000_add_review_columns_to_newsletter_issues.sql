@@ -0,0 +1,22 @@
+-- Add migration script here
+-- Issues written by users who can only draft them wait for a review. The
+-- segment is kept so that the issue goes to the intended audience once
+-- approved.
+ALTER TABLE newsletter_issues
+    ADD COLUMN review_status TEXT NOT NULL DEFAULT 'published'
+        CHECK (review_status IN ('pending_review', 'rejected', 'published')),
+    ADD COLUMN author_id uuid NULL REFERENCES users (user_id) ON DELETE SET NULL,
+    ADD COLUMN submitted_at timestamptz NULL,
+    ADD COLUMN reviewed_by uuid NULL REFERENCES users (user_id) ON DELETE SET NULL,
+    ADD COLUMN reviewed_at timestamptz NULL,
+    ADD COLUMN review_comment TEXT NULL,
+    ADD COLUMN segment_include_tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN segment_exclude_tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN segment_subscribed_after DATE NULL,
+    ADD COLUMN segment_subscribed_before DATE NULL;
+-- Set once the issue is sent.
+ALTER TABLE newsletter_issues ALTER COLUMN published_at DROP NOT NULL;
+
+-- Collaborators write issues, admins send them.
+DELETE FROM role_permissions
+WHERE role = 'collabolator' AND permission = 'issues:publish';
@@ -0,0 +1,17 @@
+-- Add migration script here
+-- Who did what, and from where. Events outlive the accounts of their actors.
+CREATE TABLE audit_events(
+    event_id uuid PRIMARY KEY,
+    occurred_at timestamptz NOT NULL,
+    actor_id uuid NULL REFERENCES users (user_id) ON DELETE SET NULL,
+    -- The username when the event was recorded, kept once the account is gone.
+    actor_username TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target TEXT NULL,
+    ip TEXT NOT NULL
+);
+CREATE INDEX audit_events_occurred_at_idx ON audit_events (occurred_at);
+CREATE INDEX audit_events_actor_id_idx ON audit_events (actor_id);
+CREATE INDEX audit_events_actor_username_idx ON audit_events (actor_username);
+
+INSERT INTO role_permissions (role, permission) VALUES ('admin', 'audit:view');
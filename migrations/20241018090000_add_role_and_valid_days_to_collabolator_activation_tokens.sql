@@ -0,0 +1,6 @@
+-- Add migration script here
+-- Invitations say which role the activated account gets, and how many days
+-- after being sent the activation link keeps working.
+ALTER TABLE collabolator_activation_tokens
+    ADD COLUMN role TEXT NOT NULL DEFAULT 'collabolator' REFERENCES roles (name),
+    ADD COLUMN valid_days SMALLINT NOT NULL DEFAULT 3 CHECK (valid_days BETWEEN 1 AND 30);
@@ -0,0 +1,10 @@
+-- Add migration script here
+-- A new email address is only stored on the account once the link sent to it
+-- has been followed. Each user has at most one change pending.
+CREATE TABLE email_verification_tokens(
+    user_id uuid PRIMARY KEY
+        REFERENCES users (user_id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at timestamptz NOT NULL
+);
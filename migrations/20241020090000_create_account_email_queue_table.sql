@@ -0,0 +1,9 @@
+-- Add migration script here
+CREATE TABLE account_email_queue(
+    task_id uuid PRIMARY KEY,
+    kind TEXT NOT NULL,
+    username TEXT NOT NULL,
+    n_retries SMALLINT NOT NULL DEFAULT 0,
+    execute_after timestamptz NULL,
+    created_at timestamptz NOT NULL
+);
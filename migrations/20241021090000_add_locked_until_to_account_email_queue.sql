@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE account_email_queue ADD COLUMN locked_until timestamptz NULL;
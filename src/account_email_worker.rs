@@ -0,0 +1,215 @@
+//! Emails about user accounts - password reset links and lockout notices -
+//! sent in the background, so that the requests causing them answer the same
+//! way, and as fast, whether the account exists or not.
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgExecutor, PgPool, Postgres, Transaction};
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+use crate::authentication::send_lockout_notification;
+use crate::configuration::Settings;
+use crate::email_client::EmailClient;
+use crate::routes::send_password_reset_link;
+use crate::startup::get_connection_pool;
+
+/// Emails still failing after this many attempts are given up on.
+const MAX_ATTEMPTS: i16 = 5;
+const MAX_RETRY_DELAY_SEC: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountEmail {
+    PasswordReset,
+    AccountLocked { until: DateTime<Utc> },
+}
+
+impl AccountEmail {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AccountEmail::PasswordReset => "password_reset",
+            AccountEmail::AccountLocked { .. } => "account_locked",
+        }
+    }
+
+    fn locked_until(&self) -> Option<DateTime<Utc>> {
+        match self {
+            AccountEmail::AccountLocked { until } => Some(*until),
+            AccountEmail::PasswordReset => None,
+        }
+    }
+}
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+struct Task {
+    task_id: Uuid,
+    kind: String,
+    username: String,
+    locked_until: Option<DateTime<Utc>>,
+    n_retries: i16,
+}
+
+impl Task {
+    fn email(&self) -> Option<AccountEmail> {
+        match (self.kind.as_str(), self.locked_until) {
+            ("password_reset", _) => Some(AccountEmail::PasswordReset),
+            ("account_locked", Some(until)) => Some(AccountEmail::AccountLocked { until }),
+            _ => None,
+        }
+    }
+}
+
+/// Queue `email` for the account of `username`, whether it exists or not -
+/// the worker finds out.
+#[tracing::instrument(name = "Enqueue an account email", skip(executor))]
+pub async fn enqueue_account_email(
+    executor: impl PgExecutor<'_>,
+    email: AccountEmail,
+    username: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO account_email_queue (task_id, kind, username, locked_until, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        Uuid::new_v4(),
+        email.kind(),
+        username,
+        email.locked_until(),
+        Utc::now()
+    )
+    .execute(executor)
+    .await
+    .context("Failed to enqueue the account email.")?;
+    Ok(())
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: String,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_execute_task(&pool, &email_client, &base_url).await {
+            Ok(ExecutionOutcome::EmptyQueue) => tokio::time::sleep(Duration::from_secs(1)).await,
+            Err(_) => tokio::time::sleep(Duration::from_secs(1)).await,
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    let email_client = configuration.email_client.client();
+    worker_loop(
+        connection_pool,
+        email_client,
+        configuration.application.base_url,
+    )
+    .await
+}
+
+/// Send the oldest due account email. Failed attempts are retried with an
+/// exponential backoff, up to `MAX_ATTEMPTS` attempts in total.
+#[tracing::instrument(
+    skip_all,
+    fields(task_id=tracing::field::Empty, kind=tracing::field::Empty),
+    err
+)]
+pub async fn try_execute_task(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let Some((transaction, task)) = dequeue_task(pool).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current()
+        .record("task_id", &display(task.task_id))
+        .record("kind", &display(&task.kind));
+
+    let outcome = match task.email() {
+        Some(AccountEmail::PasswordReset) => {
+            send_password_reset_link(pool, email_client, base_url, &task.username).await
+        }
+        Some(AccountEmail::AccountLocked { until }) => {
+            send_lockout_notification(pool, email_client, &task.username, until).await
+        }
+        None => Err(anyhow::anyhow!("Unknown account email: {}", task.kind)),
+    };
+    match outcome {
+        Ok(()) => delete_task(transaction, &task).await?,
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send an account email.",
+            );
+            mark_as_error(transaction, &task).await?;
+        }
+    }
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_task(pool: &PgPool) -> Result<Option<(PgTransaction, Task)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let task = sqlx::query_as!(
+        Task,
+        r#"
+        SELECT task_id, kind, username, locked_until, n_retries
+        FROM account_email_queue
+        WHERE COALESCE(execute_after, now()) <= now()
+        ORDER BY created_at
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    Ok(task.map(|task| (transaction, task)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(mut transaction: PgTransaction, task: &Task) -> Result<(), anyhow::Error> {
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM account_email_queue WHERE task_id = $1",
+            task.task_id
+        ))
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn mark_as_error(mut transaction: PgTransaction, task: &Task) -> Result<(), anyhow::Error> {
+    let n_retries = task.n_retries + 1;
+    if n_retries >= MAX_ATTEMPTS {
+        return delete_task(transaction, task).await;
+    }
+    let backoff_delay = Duration::from_secs(2_u64.pow(n_retries as u32).min(MAX_RETRY_DELAY_SEC));
+    let execute_after = Utc::now() + backoff_delay;
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            UPDATE account_email_queue
+            SET n_retries = $2, execute_after = $3
+            WHERE task_id = $1
+            "#,
+            task.task_id,
+            n_retries,
+            execute_after
+        ))
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
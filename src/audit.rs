@@ -0,0 +1,258 @@
+//! A record of who did what to the application: logins, published issues,
+//! invitations, password and email changes, subscriber data requests and
+//! the administration of users, roles, sessions, lockouts, the security
+//! policy, API tokens and webhooks.
+//! Events are written by the handlers and read on `/admin/audit`. A handler
+//! that runs the change in its own transaction records the event in it; when
+//! the change is committed by the function making it (approving an issue,
+//! changing a password...), the event is recorded right after, so a failure
+//! in between can leave a change without its event.
+use actix_web::HttpRequest;
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::utils::client_ip;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    LoggedIn,
+    LoggedOut,
+    PasswordChanged,
+    PasswordReset,
+    EmailChanged,
+    UserDeactivated,
+    UserReactivated,
+    UserDeleted,
+    UserRoleChanged,
+    UserSessionsRevoked,
+    SessionRevoked,
+    OtherSessionsRevoked,
+    LoginUnlocked,
+    TwoFactorRequired,
+    TwoFactorOptional,
+    RoleCreated,
+    RolePermissionsChanged,
+    RoleDeleted,
+    ApiTokenCreated,
+    ApiTokenRevoked,
+    WebhookCreated,
+    WebhookDeleted,
+    CollaboratorInvited,
+    InvitationResent,
+    InvitationRevoked,
+    AccountActivated,
+    IssuePublished,
+    IssueSubmitted,
+    IssueApproved,
+    IssueRejected,
+    SubscriberExported,
+    SubscriberErased,
+    SubscriberTagged,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 33] = [
+        AuditAction::LoggedIn,
+        AuditAction::LoggedOut,
+        AuditAction::PasswordChanged,
+        AuditAction::PasswordReset,
+        AuditAction::EmailChanged,
+        AuditAction::UserDeactivated,
+        AuditAction::UserReactivated,
+        AuditAction::UserDeleted,
+        AuditAction::UserRoleChanged,
+        AuditAction::UserSessionsRevoked,
+        AuditAction::SessionRevoked,
+        AuditAction::OtherSessionsRevoked,
+        AuditAction::LoginUnlocked,
+        AuditAction::TwoFactorRequired,
+        AuditAction::TwoFactorOptional,
+        AuditAction::RoleCreated,
+        AuditAction::RolePermissionsChanged,
+        AuditAction::RoleDeleted,
+        AuditAction::ApiTokenCreated,
+        AuditAction::ApiTokenRevoked,
+        AuditAction::WebhookCreated,
+        AuditAction::WebhookDeleted,
+        AuditAction::CollaboratorInvited,
+        AuditAction::InvitationResent,
+        AuditAction::InvitationRevoked,
+        AuditAction::AccountActivated,
+        AuditAction::IssuePublished,
+        AuditAction::IssueSubmitted,
+        AuditAction::IssueApproved,
+        AuditAction::IssueRejected,
+        AuditAction::SubscriberExported,
+        AuditAction::SubscriberErased,
+        AuditAction::SubscriberTagged,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::LoggedIn => "user.logged_in",
+            AuditAction::LoggedOut => "user.logged_out",
+            AuditAction::PasswordChanged => "user.password_changed",
+            AuditAction::PasswordReset => "user.password_reset",
+            AuditAction::EmailChanged => "user.email_changed",
+            AuditAction::UserDeactivated => "user.deactivated",
+            AuditAction::UserReactivated => "user.reactivated",
+            AuditAction::UserDeleted => "user.deleted",
+            AuditAction::UserRoleChanged => "user.role_changed",
+            AuditAction::UserSessionsRevoked => "user.sessions_revoked",
+            AuditAction::SessionRevoked => "session.revoked",
+            AuditAction::OtherSessionsRevoked => "session.others_revoked",
+            AuditAction::LoginUnlocked => "login.unlocked",
+            AuditAction::TwoFactorRequired => "security.two_factor_required",
+            AuditAction::TwoFactorOptional => "security.two_factor_optional",
+            AuditAction::RoleCreated => "role.created",
+            AuditAction::RolePermissionsChanged => "role.permissions_changed",
+            AuditAction::RoleDeleted => "role.deleted",
+            AuditAction::ApiTokenCreated => "api_token.created",
+            AuditAction::ApiTokenRevoked => "api_token.revoked",
+            AuditAction::WebhookCreated => "webhook.created",
+            AuditAction::WebhookDeleted => "webhook.deleted",
+            AuditAction::CollaboratorInvited => "collaborator.invited",
+            AuditAction::InvitationResent => "collaborator.invitation_resent",
+            AuditAction::InvitationRevoked => "collaborator.invitation_revoked",
+            AuditAction::AccountActivated => "collaborator.activated",
+            AuditAction::IssuePublished => "issue.published",
+            AuditAction::IssueSubmitted => "issue.submitted",
+            AuditAction::IssueApproved => "issue.approved",
+            AuditAction::IssueRejected => "issue.rejected",
+            AuditAction::SubscriberExported => "subscriber.exported",
+            AuditAction::SubscriberErased => "subscriber.erased",
+            AuditAction::SubscriberTagged => "subscriber.tagged",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.as_str() == s)
+    }
+}
+
+/// `action`, done by `actor_id` from the client of a request.
+pub struct AuditEvent {
+    pub actor_id: Uuid,
+    pub action: AuditAction,
+    /// What the action was done to, e.g. `issue:<id>` - `None` when the
+    /// actor is the target.
+    pub target: Option<String>,
+    pub ip: String,
+}
+
+impl AuditEvent {
+    pub fn new(request: &HttpRequest, actor_id: Uuid, action: AuditAction) -> Self {
+        Self {
+            actor_id,
+            action,
+            target: None,
+            ip: client_ip(request),
+        }
+    }
+
+    pub fn target(mut self, kind: &str, id: impl std::fmt::Display) -> Self {
+        self.target = Some(format!("{kind}:{id}"));
+        self
+    }
+}
+
+/// Stands for `email` in audit targets, so that the log can tell events about
+/// the same address apart without keeping the address itself.
+pub fn email_digest(email: &str) -> String {
+    let digest = Sha256::digest(email.trim().to_lowercase().as_bytes());
+    hex::encode(&digest[..8])
+}
+
+#[tracing::instrument(name = "Record an audit event", skip_all, fields(action = event.action.as_str()))]
+pub async fn record_audit_event(
+    executor: impl PgExecutor<'_>,
+    event: AuditEvent,
+) -> Result<(), anyhow::Error> {
+    let recorded = sqlx::query!(
+        r#"
+        INSERT INTO audit_events (
+            event_id, occurred_at, actor_id, actor_username, action, target, ip
+        )
+        SELECT $1, now(), $2, username, $3, $4, $5
+        FROM users
+        WHERE user_id = $2
+        "#,
+        Uuid::new_v4(),
+        event.actor_id,
+        event.action.as_str(),
+        event.target,
+        event.ip
+    )
+    .execute(executor)
+    .await
+    .context("Failed to record the audit event.")?;
+    if recorded.rows_affected() == 0 {
+        anyhow::bail!("The actor of the audit event does not exist.");
+    }
+    Ok(())
+}
+
+/// Every field narrows the events down; empty fields match everything.
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    /// Events whose target contains this text.
+    pub target: Option<String>,
+    /// Events on or after the start of this day (UTC).
+    pub since: Option<NaiveDate>,
+    /// Events before the end of this day (UTC).
+    pub until: Option<NaiveDate>,
+}
+
+pub struct AuditEntry {
+    pub occurred_at: DateTime<Utc>,
+    /// The username of the actor when the event was recorded.
+    pub actor: String,
+    pub action: String,
+    pub target: Option<String>,
+    pub ip: String,
+}
+
+/// The latest `limit` events matching `filter`, latest first.
+#[tracing::instrument(name = "Get audit events", skip(executor))]
+pub async fn get_audit_events(
+    executor: impl PgExecutor<'_>,
+    filter: &AuditFilter,
+    limit: i64,
+) -> Result<Vec<AuditEntry>, anyhow::Error> {
+    let start_of_day = |day: NaiveDate| day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let since = filter.since.map(start_of_day);
+    let until = filter
+        .until
+        .and_then(|day| day.succ_opt())
+        .map(start_of_day);
+    let entries = sqlx::query_as!(
+        AuditEntry,
+        r#"
+        SELECT e.occurred_at, e.actor_username AS actor, e.action, e.target, e.ip
+        FROM audit_events e
+        WHERE
+            ($1::text IS NULL OR e.actor_username = $1) AND
+            ($2::text IS NULL OR e.action = $2) AND
+            ($3::text IS NULL OR strpos(e.target, $3) > 0) AND
+            ($4::timestamptz IS NULL OR e.occurred_at >= $4) AND
+            ($5::timestamptz IS NULL OR e.occurred_at < $5)
+        ORDER BY e.occurred_at DESC
+        LIMIT $6
+        "#,
+        filter.actor,
+        filter.action.map(|a| a.as_str()),
+        filter.target,
+        since,
+        until,
+        limit
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to fetch the audit events.")?;
+    Ok(entries)
+}
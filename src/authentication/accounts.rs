@@ -0,0 +1,208 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{revoke_user_sessions, Permission};
+
+#[derive(thiserror::Error, Debug)]
+pub enum AccountError {
+    #[error("There is no such user.")]
+    UnknownUser,
+    #[error("There is no such role.")]
+    UnknownRole,
+    #[error("A role with that name already exists.")]
+    RoleExists,
+    #[error("The role is still given to some users.")]
+    RoleInUse,
+    #[error("The role is still offered in pending invitations.")]
+    RoleOffered,
+    #[error("Built-in roles cannot be deleted.")]
+    BuiltInRole,
+    #[error("Role names are 1 to 32 lowercase letters, digits, '-' or '_'.")]
+    InvalidRoleName,
+    #[error("At least one active user has to be able to manage users.")]
+    LastUserManager,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+pub struct UserAccount {
+    pub user_id: Uuid,
+    pub username: String,
+    pub email: Option<String>,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+    pub deactivated_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Get user accounts", skip(pool))]
+pub async fn get_user_accounts(pool: &PgPool) -> Result<Vec<UserAccount>, anyhow::Error> {
+    let accounts = sqlx::query_as!(
+        UserAccount,
+        r#"
+        SELECT user_id, username, email, role, created_at, deactivated_at
+        FROM users
+        ORDER BY created_at, username
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the user accounts.")?;
+    Ok(accounts)
+}
+
+/// Deactivated users can neither log in nor use their sessions or API tokens,
+/// which are kept for when they get reactivated.
+///
+/// The account functions make their change in the caller's `transaction`, so
+/// that it can record the change along with it before committing.
+#[tracing::instrument(name = "Deactivate a user", skip(transaction))]
+pub async fn deactivate_user(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<(), AccountError> {
+    lock_user_managers(transaction).await?;
+    let updated = sqlx::query!(
+        r#"
+        UPDATE users SET deactivated_at = COALESCE(deactivated_at, $2)
+        WHERE user_id = $1
+        "#,
+        user_id,
+        Utc::now()
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to deactivate the user.")?;
+    if updated.rows_affected() == 0 {
+        return Err(AccountError::UnknownUser);
+    }
+    ensure_user_managers_remain(transaction).await?;
+    revoke_user_sessions(&mut **transaction, user_id).await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Reactivate a user", skip(transaction))]
+pub async fn reactivate_user(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<(), AccountError> {
+    let updated = sqlx::query!(
+        "UPDATE users SET deactivated_at = NULL WHERE user_id = $1",
+        user_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to reactivate the user.")?;
+    match updated.rows_affected() {
+        0 => Err(AccountError::UnknownUser),
+        _ => Ok(()),
+    }
+}
+
+/// Remove the user along with their sessions, API tokens and idempotency keys,
+/// returning the username they had.
+#[tracing::instrument(name = "Delete a user", skip(transaction))]
+pub async fn delete_user(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<String, AccountError> {
+    lock_user_managers(transaction).await?;
+    sqlx::query!("DELETE FROM idempotency WHERE user_id = $1", user_id)
+        .execute(&mut **transaction)
+        .await
+        .context("Failed to delete the idempotency keys of the user.")?;
+    let username = sqlx::query_scalar!(
+        "DELETE FROM users WHERE user_id = $1 RETURNING username",
+        user_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .context("Failed to delete the user.")?
+    .ok_or(AccountError::UnknownUser)?;
+    ensure_user_managers_remain(transaction).await?;
+    Ok(username)
+}
+
+#[tracing::instrument(name = "Change the role of a user", skip(transaction))]
+pub async fn change_user_role(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    role: &str,
+) -> Result<(), AccountError> {
+    lock_user_managers(transaction).await?;
+    let role_exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1) AS "exists!""#,
+        role
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .context("Failed to look up the role.")?;
+    if !role_exists {
+        return Err(AccountError::UnknownRole);
+    }
+    let updated = sqlx::query!(
+        "UPDATE users SET role = $2 WHERE user_id = $1",
+        user_id,
+        role
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to change the role of the user.")?;
+    if updated.rows_affected() == 0 {
+        return Err(AccountError::UnknownUser);
+    }
+    ensure_user_managers_remain(transaction).await?;
+    Ok(())
+}
+
+/// Changes that could leave nobody able to manage users take this lock
+/// before making them, and check `ensure_user_managers_remain` after - so
+/// that two admins cannot demote each other at the same time.
+pub(super) async fn lock_user_managers(
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!("LOCK TABLE role_permissions IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut **transaction)
+        .await
+        .context("Failed to lock the role permissions.")?;
+    Ok(())
+}
+
+/// Fails if no active user is left with the `ManageUsers` permission. The
+/// caller drops the transaction, undoing the change.
+pub(super) async fn ensure_user_managers_remain(
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), AccountError> {
+    let remain = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM users u
+            JOIN role_permissions p ON p.role = u.role
+            WHERE u.deactivated_at IS NULL AND p.permission = $1
+        ) AS "remain!"
+        "#,
+        Permission::ManageUsers.as_str()
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .context("Failed to look for users who can manage users.")?;
+    match remain {
+        true => Ok(()),
+        false => Err(AccountError::LastUserManager),
+    }
+}
+
+pub(super) async fn begin(pool: &PgPool) -> Result<Transaction<'static, Postgres>, anyhow::Error> {
+    pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+}
+
+pub(super) async fn commit(transaction: Transaction<'_, Postgres>) -> Result<(), AccountError> {
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the account change.")?;
+    Ok(())
+}
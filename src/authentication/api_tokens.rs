@@ -0,0 +1,211 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{Permission, UserData};
+use crate::utils::generate_token;
+
+/// Makes tokens recognisable, e.g. for secret scanners.
+const TOKEN_PREFIX: &str = "z2p_";
+
+pub const MAX_TOKEN_LIFETIME_IN_DAYS: i64 = 365;
+
+/// What an API token may be used for - on top of what its owner's role allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiScope {
+    ReadNewsletters,
+    PublishNewsletters,
+    ReadSubscribers,
+    WriteSubscribers,
+    ReadCollaborators,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 5] = [
+        ApiScope::ReadNewsletters,
+        ApiScope::PublishNewsletters,
+        ApiScope::ReadSubscribers,
+        ApiScope::WriteSubscribers,
+        ApiScope::ReadCollaborators,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::ReadNewsletters => "newsletters:read",
+            ApiScope::PublishNewsletters => "newsletters:publish",
+            ApiScope::ReadSubscribers => "subscribers:read",
+            ApiScope::WriteSubscribers => "subscribers:write",
+            ApiScope::ReadCollaborators => "collaborators:read",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.as_str() == s)
+    }
+}
+
+/// The token a request was authenticated with - available to handlers behind
+/// `reject_invalid_api_tokens`.
+#[derive(Clone, Debug)]
+pub struct ApiToken {
+    pub token_id: Uuid,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ApiToken {
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+pub struct ApiTokenSummary {
+    pub token_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// API tokens are credentials, so only their hash is stored.
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Create a token for `user_id`. Its id and the token itself are returned -
+/// the token cannot be retrieved again later.
+#[tracing::instrument(name = "Create an API token", skip(pool))]
+pub async fn create_api_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    scopes: &[ApiScope],
+    lifetime: Duration,
+) -> Result<(Uuid, String), anyhow::Error> {
+    let token_id = Uuid::new_v4();
+    let token = format!("{TOKEN_PREFIX}{}{}", generate_token(), generate_token());
+    let scopes: Vec<String> = scopes.iter().map(|s| s.as_str().to_owned()).collect();
+    let now = Utc::now();
+    sqlx::query!(
+        r#"
+        INSERT INTO api_tokens (token_id, user_id, name, token_hash, scopes, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        token_id,
+        user_id,
+        name,
+        hash_token(&token),
+        &scopes,
+        now,
+        now + lifetime
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the API token.")?;
+    Ok((token_id, token))
+}
+
+/// The user and scopes of a valid, unexpired `token`.
+#[tracing::instrument(name = "Authenticate an API token", skip_all)]
+pub async fn authenticate_api_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<(UserData, ApiToken)>, anyhow::Error> {
+    let now = Utc::now();
+    let row = sqlx::query!(
+        r#"
+        UPDATE api_tokens t SET last_used_at = $2
+        FROM users u
+        WHERE t.token_hash = $1 AND t.expires_at > $2
+            AND u.user_id = t.user_id AND u.deactivated_at IS NULL
+        RETURNING t.token_id, t.scopes, u.user_id, u.username, u.email, u.role,
+            ARRAY(
+                SELECT permission FROM role_permissions WHERE role = u.role
+            ) AS "permissions!"
+        "#,
+        hash_token(token),
+        now
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to look up the API token.")?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let user = UserData {
+        user_id: row.user_id,
+        username: row.username,
+        email: row.email,
+        role: row.role,
+        permissions: Permission::parse_all(&row.permissions),
+    };
+    let token = ApiToken {
+        token_id: row.token_id,
+        // Scopes that are no longer known grant nothing.
+        scopes: row
+            .scopes
+            .iter()
+            .filter_map(|s| ApiScope::parse(s))
+            .collect(),
+    };
+    Ok(Some((user, token)))
+}
+
+/// The tokens of `user_id`, including expired ones, newest first.
+#[tracing::instrument(name = "Get API tokens", skip(pool))]
+pub async fn get_api_tokens(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<ApiTokenSummary>, anyhow::Error> {
+    let tokens = sqlx::query_as!(
+        ApiTokenSummary,
+        r#"
+        SELECT token_id, name, scopes, created_at, expires_at, last_used_at
+        FROM api_tokens
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the API tokens of the user.")?;
+    Ok(tokens)
+}
+
+/// Delete a token of `user_id`. Returns `false` if there was no such token.
+#[tracing::instrument(name = "Revoke an API token", skip(pool))]
+pub async fn revoke_api_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    token_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM api_tokens WHERE token_id = $1 AND user_id = $2",
+        token_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to delete the API token.")?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiScope;
+
+    #[test]
+    fn scopes_round_trip_through_their_names() {
+        for scope in ApiScope::ALL {
+            assert_eq!(ApiScope::parse(scope.as_str()), Some(scope));
+        }
+    }
+
+    #[test]
+    fn unknown_scopes_are_rejected() {
+        assert_eq!(ApiScope::parse("everything"), None);
+    }
+}
@@ -0,0 +1,129 @@
+//! Users choose the address they are emailed at - about lockouts, password
+//! resets or issues to review. A new address is only stored once the link
+//! sent to it has been followed, so nobody gets mail meant for someone else.
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::Email;
+use crate::utils::generate_token;
+
+/// Verification links stop working this long after they were sent.
+pub const EMAIL_VERIFICATION_TIMEOUT_IN_HOURS: i64 = 24;
+
+/// Verification tokens prove ownership of an address, so only their hash is
+/// stored.
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub struct PendingEmailChange {
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PendingEmailChange {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.created_at + Duration::hours(EMAIL_VERIFICATION_TIMEOUT_IN_HOURS)
+    }
+}
+
+/// Remember that `user_id` wants to be emailed at `email`, replacing any
+/// change they asked for before. The token to send to the new address is
+/// returned - it cannot be retrieved again later.
+#[tracing::instrument(name = "Request an email change", skip(pool))]
+pub async fn request_email_change(
+    pool: &PgPool,
+    user_id: Uuid,
+    email: &Email,
+) -> Result<String, anyhow::Error> {
+    let token = generate_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO email_verification_tokens (user_id, email, token_hash, created_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id)
+        DO UPDATE SET email = EXCLUDED.email, token_hash = EXCLUDED.token_hash,
+            created_at = EXCLUDED.created_at
+        "#,
+        user_id,
+        email.as_ref(),
+        hash_token(&token),
+        Utc::now()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the email verification token.")?;
+    Ok(token)
+}
+
+/// The change `user_id` asked for and has not verified yet, expired or not.
+#[tracing::instrument(name = "Get a pending email change", skip(pool))]
+pub async fn get_pending_email_change(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<PendingEmailChange>, anyhow::Error> {
+    let change = sqlx::query_as!(
+        PendingEmailChange,
+        "SELECT email, created_at FROM email_verification_tokens WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the pending email change.")?;
+    Ok(change)
+}
+
+/// Store the address `token` was sent to on the account of `user_id`, and
+/// return it. `None` if the token is unknown, belongs to someone else or has
+/// expired.
+#[tracing::instrument(name = "Confirm an email change", skip(pool, token))]
+pub async fn confirm_email_change(
+    pool: &PgPool,
+    user_id: Uuid,
+    token: &str,
+) -> Result<Option<String>, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let change = sqlx::query_as!(
+        PendingEmailChange,
+        r#"
+        DELETE FROM email_verification_tokens
+        WHERE token_hash = $1 AND user_id = $2
+        RETURNING email, created_at
+        "#,
+        hash_token(token),
+        user_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to consume the email verification token.")?;
+    let Some(change) = change else {
+        return Ok(None);
+    };
+    if change.expires_at() < Utc::now() {
+        // The expired token stays deleted.
+        transaction
+            .commit()
+            .await
+            .context("Failed to delete the expired token.")?;
+        return Ok(None);
+    }
+    sqlx::query!(
+        "UPDATE users SET email = $2 WHERE user_id = $1",
+        user_id,
+        change.email
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store the new email address.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the email change.")?;
+    Ok(Some(change.email))
+}
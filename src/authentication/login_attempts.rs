@@ -0,0 +1,296 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+
+use crate::account_email_worker::{enqueue_account_email, AccountEmail};
+use crate::configuration::LoginProtectionSettings;
+use crate::domain::Email;
+use crate::email_client::EmailClient;
+
+/// What a failed login is counted against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginScope {
+    Username,
+    Ip,
+}
+
+impl LoginScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginScope::Username => "username",
+            LoginScope::Ip => "ip",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "username" => Some(LoginScope::Username),
+            "ip" => Some(LoginScope::Ip),
+            _ => None,
+        }
+    }
+
+    fn lockout_threshold(&self, policy: &LoginProtectionSettings) -> u32 {
+        match self {
+            LoginScope::Username => policy.lockout_threshold_per_username,
+            LoginScope::Ip => policy.lockout_threshold_per_ip,
+        }
+    }
+}
+
+/// Why a login attempt is refused before the credentials are even looked at.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LoginBlock {
+    Delayed { retry_after: Duration },
+    Locked { until: DateTime<Utc> },
+}
+
+struct FailedLogins {
+    failed_attempts: i32,
+    last_failed_at: DateTime<Utc>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+/// Check whether `username` may attempt to log in from `ip` right now.
+///
+/// Unknown usernames are tracked exactly like existing ones, so the outcome does
+/// not reveal whether an account exists.
+#[tracing::instrument(name = "Check failed logins", skip(pool, policy))]
+pub async fn check_login_attempt(
+    pool: &PgPool,
+    policy: &LoginProtectionSettings,
+    username: &str,
+    ip: &str,
+) -> Result<Option<LoginBlock>, anyhow::Error> {
+    let now = Utc::now();
+    let rows = sqlx::query_as!(
+        FailedLogins,
+        r#"
+        SELECT failed_attempts, last_failed_at, locked_until
+        FROM failed_logins
+        WHERE (scope = 'username' AND key = $1) OR (scope = 'ip' AND key = $2)
+        "#,
+        username,
+        ip
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch failed login attempts.")?;
+
+    let mut retry_after = Duration::zero();
+    for row in rows {
+        if let Some(until) = row.locked_until.filter(|until| *until > now) {
+            return Ok(Some(LoginBlock::Locked { until }));
+        }
+        if row.last_failed_at < now - policy.lockout() {
+            continue;
+        }
+        let next_attempt_at = row.last_failed_at + policy.delay(row.failed_attempts as u32);
+        retry_after = retry_after.max(next_attempt_at - now);
+    }
+    if retry_after > Duration::zero() {
+        return Ok(Some(LoginBlock::Delayed { retry_after }));
+    }
+    Ok(None)
+}
+
+/// Count a failed login against `key`. If this attempt triggered a lockout,
+/// the time it ends is returned.
+#[tracing::instrument(name = "Record failed login", skip(pool, policy))]
+async fn record_failed_login(
+    pool: &PgPool,
+    policy: &LoginProtectionSettings,
+    scope: LoginScope,
+    key: &str,
+) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+    let now = Utc::now();
+    let failed_attempts = sqlx::query!(
+        r#"
+        INSERT INTO failed_logins (scope, key, failed_attempts, last_failed_at)
+        VALUES ($1, $2, 1, $3)
+        ON CONFLICT (scope, key) DO UPDATE SET
+            failed_attempts = CASE
+                WHEN failed_logins.last_failed_at < $4 THEN 1
+                ELSE failed_logins.failed_attempts + 1
+            END,
+            last_failed_at = EXCLUDED.last_failed_at,
+            locked_until = NULL
+        RETURNING failed_attempts
+        "#,
+        scope.as_str(),
+        key,
+        now,
+        now - policy.lockout()
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to record a failed login attempt.")?
+    .failed_attempts;
+
+    if failed_attempts as u32 != scope.lockout_threshold(policy) {
+        return Ok(None);
+    }
+    let locked_until = now + policy.lockout();
+    sqlx::query!(
+        "UPDATE failed_logins SET locked_until = $3 WHERE scope = $1 AND key = $2",
+        scope.as_str(),
+        key,
+        locked_until
+    )
+    .execute(pool)
+    .await
+    .context("Failed to lock logins.")?;
+    Ok(Some(locked_until))
+}
+
+/// Count a failed login against both the username and the client IP, and let
+/// the account owner know if this attempt locked the account.
+pub async fn record_login_failure(
+    pool: &PgPool,
+    policy: &LoginProtectionSettings,
+    username: &str,
+    client_ip: &str,
+) -> Result<(), anyhow::Error> {
+    record_failed_login(pool, policy, LoginScope::Ip, client_ip).await?;
+    if let Some(locked_until) =
+        record_failed_login(pool, policy, LoginScope::Username, username).await?
+    {
+        // Failing to notify the owner must not change the outcome of the attempt.
+        let email = AccountEmail::AccountLocked {
+            until: locked_until,
+        };
+        if let Err(e) = enqueue_account_email(pool, email, username).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to queue the notification of a locked account."
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Forget the failed logins of `username` - called after a successful login.
+#[tracing::instrument(name = "Reset failed logins", skip(pool))]
+pub async fn reset_failed_logins(pool: &PgPool, username: &str) -> Result<(), anyhow::Error> {
+    unlock_login(pool, LoginScope::Username, username).await
+}
+
+#[tracing::instrument(name = "Unlock login", skip(pool))]
+pub async fn unlock_login(
+    pool: &PgPool,
+    scope: LoginScope,
+    key: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "DELETE FROM failed_logins WHERE scope = $1 AND key = $2",
+        scope.as_str(),
+        key
+    )
+    .execute(pool)
+    .await
+    .context("Failed to delete failed login attempts.")?;
+    Ok(())
+}
+
+pub struct LockedLogin {
+    pub scope: String,
+    pub key: String,
+    pub failed_attempts: i32,
+    pub locked_until: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get locked logins", skip_all)]
+pub async fn get_locked_logins(pool: &PgPool) -> Result<Vec<LockedLogin>, anyhow::Error> {
+    let locked = sqlx::query_as!(
+        LockedLogin,
+        r#"
+        SELECT scope, key, failed_attempts, locked_until AS "locked_until!"
+        FROM failed_logins
+        WHERE locked_until > $1
+        ORDER BY locked_until DESC
+        "#,
+        Utc::now()
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch locked logins.")?;
+    Ok(locked)
+}
+
+/// Let the owner of `username` know that their account has been locked, if
+/// the account exists and has an email address on record.
+#[tracing::instrument(name = "Notify about account lockout", skip(pool, email_client))]
+pub async fn send_lockout_notification(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    username: &str,
+    until: DateTime<Utc>,
+) -> Result<(), anyhow::Error> {
+    let row = sqlx::query!("SELECT email FROM users WHERE username = $1", username)
+        .fetch_optional(pool)
+        .await
+        .context("Failed to fetch the email address of the user.")?;
+    let Some(email) = row.and_then(|r| r.email) else {
+        return Ok(());
+    };
+    let email = Email::parse(email).map_err(|e| anyhow::anyhow!(e))?;
+    let until = until.format("%Y-%m-%d %H:%M UTC");
+    let html_body = format!(
+        "Your account <b>{}</b> has been locked until {until} \
+        after too many failed login attempts.<br />\
+        If this was not you, ask an administrator to unlock it and change your password.",
+        htmlescape::encode_minimal(username)
+    );
+    let plain_body = format!(
+        "Your account {username} has been locked until {until} \
+        after too many failed login attempts.\n\
+        If this was not you, ask an administrator to unlock it and change your password."
+    );
+    email_client
+        .send_email(
+            &email,
+            "Your account has been locked",
+            &html_body,
+            &plain_body,
+        )
+        .await
+        .context("Failed to send the lockout notification.")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::LoginProtectionSettings;
+    use chrono::Duration;
+
+    fn policy() -> LoginProtectionSettings {
+        LoginProtectionSettings {
+            free_attempts: 3,
+            base_delay_seconds: 1,
+            max_delay_seconds: 30,
+            lockout_threshold_per_username: 10,
+            lockout_threshold_per_ip: 50,
+            lockout_seconds: 900,
+        }
+    }
+
+    #[test]
+    fn free_attempts_are_not_delayed() {
+        assert_eq!(policy().delay(0), Duration::zero());
+        assert_eq!(policy().delay(2), Duration::zero());
+    }
+
+    #[test]
+    fn the_delay_doubles_with_every_failed_attempt() {
+        assert_eq!(policy().delay(3), Duration::seconds(1));
+        assert_eq!(policy().delay(4), Duration::seconds(2));
+        assert_eq!(policy().delay(6), Duration::seconds(8));
+    }
+
+    #[test]
+    fn the_delay_is_capped() {
+        assert_eq!(policy().delay(8), Duration::seconds(30));
+        assert_eq!(policy().delay(1000), Duration::seconds(30));
+    }
+}
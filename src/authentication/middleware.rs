@@ -1,15 +1,24 @@
+use crate::routes::{ApiError, LoginError};
 use crate::session_state::TypedSession;
 use crate::utils::{e500, see_other};
 use actix_web::body::MessageBody;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
 use actix_web::error::{ErrorForbidden, InternalError};
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::web;
 use actix_web::FromRequest;
 use actix_web::HttpMessage;
 use actix_web_lab::middleware::Next;
+use sqlx::PgPool;
 use std::fmt::Debug as _;
+use std::future::Future;
 use std::ops::Deref;
+use std::pin::Pin;
 
-use super::{UserData, UserRole};
+use super::{
+    authenticate_api_token, is_two_factor_enabled, is_two_factor_required, Permission,
+    SessionCache, UserData,
+};
 
 const PERMISSION_DENIED_ERR_MSG: &str = "User has no permission to access this endpoint";
 
@@ -22,6 +31,17 @@ impl std::fmt::Display for AuthenticatedUser {
     }
 }
 
+impl AuthenticatedUser {
+    /// For handlers that only need `permission` for some of what they do.
+    pub fn require(&self, permission: Permission) -> Result<(), actix_web::Error> {
+        if self.can(permission) {
+            return Ok(());
+        }
+        let e = anyhow::anyhow!("{PERMISSION_DENIED_ERR_MSG}: {}", permission.as_str());
+        Err(ErrorForbidden(e))
+    }
+}
+
 impl Deref for AuthenticatedUser {
     type Target = UserData;
 
@@ -39,43 +59,134 @@ pub async fn reject_anonymous_users(
         TypedSession::from_request(http_request, payload).await
     }?;
 
-    match session.get_user().map_err(e500)? {
+    let Some(user) = session.get_user().map_err(e500)? else {
+        let response = see_other("/login");
+        let e = anyhow::anyhow!("The user has not logged in");
+        return Err(InternalError::from_response(e, response).into());
+    };
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .cloned()
+        .ok_or_else(|| e500("The database pool is not configured"))?;
+    let cache = req
+        .app_data::<web::Data<SessionCache>>()
+        .cloned()
+        .ok_or_else(|| e500("The session cache is not configured"))?;
+    // The user data in the session is a snapshot from logging in - the role
+    // or the account itself may have changed since.
+    let current_user = match session.get_session_id().map_err(e500)? {
+        Some(session_id) => cache
+            .validate(&pool, user.user_id, session_id)
+            .await
+            .map_err(e500)?,
+        None => None,
+    };
+    match current_user {
         Some(user) => {
             req.extensions_mut().insert(AuthenticatedUser(user));
             next.call(req).await
         }
         None => {
+            session.log_out();
             let response = see_other("/login");
-            let e = anyhow::anyhow!("The user has not logged in");
+            let e = anyhow::anyhow!("The session of the user is no longer valid");
             Err(InternalError::from_response(e, response).into())
         }
     }
 }
 
-pub async fn reject_not_admin_users(
-    mut req: ServiceRequest,
+/// The API counterpart of `reject_anonymous_users`: authenticates requests
+/// with an `Authorization: Bearer <token>` header instead of a session.
+/// Handlers get the same `AuthenticatedUser`, plus the `ApiToken` used.
+pub async fn reject_invalid_api_tokens(
+    req: ServiceRequest,
     next: Next<impl MessageBody>,
 ) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
-    let session = {
-        let (http_request, payload) = req.parts_mut();
-        TypedSession::from_request(http_request, payload).await
-    }?;
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::trim)
+        .map(str::to_owned);
+    let Some(token) = token else {
+        return Err(unauthorized("The request has no bearer token"));
+    };
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .cloned()
+        .ok_or_else(|| e500("The database pool is not configured"))?;
 
-    match session.get_user().map_err(e500)? {
-        Some(user) => match user.role {
-            UserRole::Admin => {
-                req.extensions_mut().insert(AuthenticatedUser(user));
-                next.call(req).await
-            }
-            _ => {
-                let e = anyhow::anyhow!(PERMISSION_DENIED_ERR_MSG);
-                Err(ErrorForbidden(e))
-            }
-        },
-        None => {
-            let response = see_other("/login");
-            let e = anyhow::anyhow!("The user has not logged in");
-            Err(InternalError::from_response(e, response).into())
+    match authenticate_api_token(&pool, &token).await.map_err(e500)? {
+        Some((user, token)) => {
+            req.extensions_mut().insert(AuthenticatedUser(user));
+            req.extensions_mut().insert(token);
+            next.call(req).await
         }
+        None => Err(unauthorized("The API token is invalid or has expired")),
+    }
+}
+
+/// Rendered like every other API error. The reason is only logged.
+fn unauthorized(message: &'static str) -> actix_web::Error {
+    ApiError::from(LoginError::AuthError(anyhow::anyhow!(message))).into()
+}
+
+type MiddlewareFuture<B> =
+    Pin<Box<dyn Future<Output = Result<ServiceResponse<B>, actix_web::Error>>>>;
+
+/// A middleware function to be wrapped with `actix_web_lab::middleware::from_fn`,
+/// refusing users whose role does not grant `permission`. Has to run after
+/// `reject_anonymous_users` or `reject_invalid_api_tokens`.
+pub fn require_permission<B: MessageBody + 'static>(
+    permission: Permission,
+) -> impl Fn(ServiceRequest, Next<B>) -> MiddlewareFuture<B> {
+    move |req, next| Box::pin(enforce_permission(permission, req, next))
+}
+
+async fn enforce_permission<B: MessageBody>(
+    permission: Permission,
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, actix_web::Error> {
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .cloned()
+        .ok_or_else(|| e500("The user has not been authenticated"))?;
+    user.require(permission)?;
+    next.call(req).await
+}
+
+/// While an admin requires two-factor authentication, users who have not
+/// enabled it can only set it up (or log out). Has to run after
+/// `reject_anonymous_users`.
+pub async fn reject_users_without_two_factor(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let path = req.path();
+    if path.starts_with("/admin/security") || path == "/admin/logout" {
+        return next.call(req).await;
+    }
+    let user = req
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .cloned()
+        .ok_or_else(|| e500("The user has not been authenticated"))?;
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .cloned()
+        .ok_or_else(|| e500("The database pool is not configured"))?;
+
+    if is_two_factor_required(&pool).await.map_err(e500)?
+        && !is_two_factor_enabled(&pool, user.user_id)
+            .await
+            .map_err(e500)?
+    {
+        let response = see_other("/admin/security");
+        let e = anyhow::anyhow!("The user has to enable two-factor authentication");
+        return Err(InternalError::from_response(e, response).into());
     }
+    next.call(req).await
 }
@@ -1,9 +1,52 @@
+mod accounts;
+mod api_tokens;
+mod email_verification;
+mod login_attempts;
 mod middleware;
 mod password;
+mod password_policy;
+mod permissions;
+mod roles;
+mod session_cache;
+mod sessions;
+mod two_factor;
 mod user;
+pub use accounts::{
+    change_user_role, deactivate_user, delete_user, get_user_accounts, reactivate_user,
+    AccountError, UserAccount,
+};
+pub use api_tokens::{
+    authenticate_api_token, create_api_token, get_api_tokens, revoke_api_token, ApiScope, ApiToken,
+    ApiTokenSummary, MAX_TOKEN_LIFETIME_IN_DAYS,
+};
+pub use email_verification::{
+    confirm_email_change, get_pending_email_change, request_email_change, PendingEmailChange,
+    EMAIL_VERIFICATION_TIMEOUT_IN_HOURS,
+};
+pub use login_attempts::{
+    check_login_attempt, get_locked_logins, record_login_failure, reset_failed_logins,
+    send_lockout_notification, unlock_login, LockedLogin, LoginBlock, LoginScope,
+};
 pub use middleware::AuthenticatedUser;
-pub use middleware::{reject_anonymous_users, reject_not_admin_users};
+pub use middleware::{
+    reject_anonymous_users, reject_invalid_api_tokens, reject_users_without_two_factor,
+    require_permission,
+};
 pub use password::{
     change_password, compute_password_hash, validate_credentials, AuthError, Credentials,
 };
+pub use password_policy::{check_password_policy, PasswordPolicyError};
+pub use permissions::Permission;
+pub use roles::{create_role, delete_role, get_roles, set_role_permissions, Role};
+pub use session_cache::SessionCache;
+pub use sessions::{
+    get_user_sessions, keep_user_session, revoke_other_user_sessions, revoke_user_session,
+    revoke_user_sessions, start_user_session, touch_user_session, UserSession,
+    SESSION_TTL_IN_HOURS,
+};
+pub use two_factor::{
+    confirm_two_factor_enrollment, disable_two_factor, get_two_factor_status,
+    is_two_factor_enabled, is_two_factor_required, set_two_factor_required,
+    start_two_factor_enrollment, totp_code, totp_uri, verify_second_factor, TwoFactorStatus,
+};
 pub use user::*;
@@ -2,12 +2,13 @@ use anyhow::Context;
 use argon2::password_hash::SaltString;
 use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
 use secrecy::{ExposeSecret, Secret};
-use sqlx::PgPool;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
 
+use crate::configuration::PasswordHashingSettings;
 use crate::telemetry::spawn_blocking_with_tracing;
 
 use super::User;
-use crate::authentication::UserRole;
 
 #[derive(thiserror::Error, Debug)]
 pub enum AuthError {
@@ -22,18 +23,22 @@ pub struct Credentials {
     pub password: Secret<String>,
 }
 
-#[tracing::instrument(name = "Validate credentials", skip(credentials, pool))]
+/// A password whose hash is outdated gets a new hash, computed with `hashing`.
+#[tracing::instrument(name = "Validate credentials", skip(credentials, hashing, pool))]
 pub async fn validate_credentials(
     credentials: Credentials,
+    hashing: &PasswordHashingSettings,
     pool: &PgPool,
 ) -> Result<User, AuthError> {
     let mut user = None;
-    let mut expected_password_hash = Secret::new(
-        "$argon2id$v=19$m=15000,t=2,p=1$\
+    // Unknown usernames take as long to check as known ones: the hash has the
+    // costs new hashes get, it just never matches.
+    let mut expected_password_hash = Secret::new(format!(
+        "$argon2id$v=19$m={},t={},p={}$\
         gZiV/M1gPc22ElAH/Jh1Hw$\
-        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno"
-            .to_string(),
-    );
+        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno",
+        hashing.memory_kib, hashing.iterations, hashing.parallelism
+    ));
     if let Some(stored_user) = get_user(&credentials.username, pool)
         .await
         .map_err(AuthError::UnexpectedError)?
@@ -42,15 +47,104 @@ pub async fn validate_credentials(
         user = Some(stored_user);
     }
 
-    spawn_blocking_with_tracing(move || {
-        verify_password_hash(expected_password_hash, credentials.password)
-    })
-    .await
-    .context("Failed to spawn blocking task.")
-    .map_err(AuthError::UnexpectedError)??;
+    let password = credentials.password.clone();
+    let password_hash = expected_password_hash.clone();
+    spawn_blocking_with_tracing(move || verify_password_hash(password_hash, password))
+        .await
+        .context("Failed to spawn blocking task.")
+        .map_err(AuthError::UnexpectedError)??;
 
-    user.ok_or_else(|| anyhow::anyhow!("Unknown username."))
-        .map_err(AuthError::InvalidCredentials)
+    let user = user
+        .ok_or_else(|| anyhow::anyhow!("Unknown username."))
+        .map_err(AuthError::InvalidCredentials)?;
+    if is_hash_outdated(&expected_password_hash, hashing) {
+        // The password was right: failing to upgrade its hash must not keep
+        // the user out.
+        if let Err(e) = rehash_password(
+            pool,
+            user.user_id,
+            expected_password_hash,
+            credentials.password,
+            hashing,
+        )
+        .await
+        {
+            tracing::warn!(error.cause_chain = ?e, "Failed to rehash a password.");
+        }
+    }
+    Ok(user)
+}
+
+/// Whether `password_hash` was not computed with Argon2id and the costs in
+/// `hashing`.
+fn is_hash_outdated(password_hash: &Secret<String>, hashing: &PasswordHashingSettings) -> bool {
+    let Ok(password_hash) = PasswordHash::new(password_hash.expose_secret()) else {
+        return true;
+    };
+    let Ok(params) = Params::try_from(&password_hash) else {
+        return true;
+    };
+    password_hash.algorithm != Algorithm::Argon2id.ident()
+        || password_hash.version != Some(Version::V0x13.into())
+        || (params.m_cost(), params.t_cost(), params.p_cost())
+            != (hashing.memory_kib, hashing.iterations, hashing.parallelism)
+}
+
+/// Replace `old_password_hash` with a hash of the same password computed with
+/// `hashing`. Sessions started with the old hash stay valid.
+#[tracing::instrument(
+    name = "Rehash a password",
+    skip(pool, old_password_hash, password, hashing)
+)]
+async fn rehash_password(
+    pool: &PgPool,
+    user_id: Uuid,
+    old_password_hash: Secret<String>,
+    password: Secret<String>,
+    hashing: &PasswordHashingSettings,
+) -> Result<(), anyhow::Error> {
+    let hashing = hashing.clone();
+    let password_hash =
+        spawn_blocking_with_tracing(move || compute_password_hash(password, &hashing))
+            .await?
+            .context("Failed to hash password")?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    // Nothing to do if the password changed in the meantime.
+    let updated = sqlx::query!(
+        "UPDATE users SET password_hash = $3 WHERE user_id = $1 AND password_hash = $2",
+        user_id,
+        old_password_hash.expose_secret(),
+        password_hash.expose_secret()
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store the new password hash.")?;
+    if updated.rows_affected() == 0 {
+        return Ok(());
+    }
+    sqlx::query!(
+        r#"
+        UPDATE user_sessions
+        SET password_fingerprint = encode(sha256(convert_to($3, 'UTF8')), 'hex')
+        WHERE user_id = $1
+            AND password_fingerprint = encode(sha256(convert_to($2, 'UTF8')), 'hex')
+        "#,
+        user_id,
+        old_password_hash.expose_secret(),
+        password_hash.expose_secret()
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to keep the sessions of the user.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the new password hash.")?;
+    Ok(())
 }
 
 #[tracing::instrument(name = "Get stored credentials", skip(username, pool))]
@@ -58,9 +152,12 @@ async fn get_user(username: &str, pool: &PgPool) -> Result<Option<User>, anyhow:
     let user = sqlx::query_as!(
         User,
         r#"
-        SELECT user_id, username, password_hash, role as "role: UserRole"
-        FROM users
-        WHERE username = $1
+        SELECT user_id, username, password_hash, email, role,
+            ARRAY(
+                SELECT permission FROM role_permissions WHERE role = u.role
+            ) AS "permissions!"
+        FROM users u
+        WHERE username = $1 AND deactivated_at IS NULL
         "#,
         username,
     )
@@ -91,15 +188,18 @@ fn verify_password_hash(
         .map_err(AuthError::InvalidCredentials)
 }
 
-#[tracing::instrument(name = "Change password", skip(password, pool))]
+#[tracing::instrument(name = "Change password", skip(password, hashing, executor))]
 pub async fn change_password(
-    user_id: uuid::Uuid,
+    user_id: Uuid,
     password: Secret<String>,
-    pool: &PgPool,
+    hashing: &PasswordHashingSettings,
+    executor: impl PgExecutor<'_>,
 ) -> Result<(), anyhow::Error> {
-    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
-        .await?
-        .context("Failed to hash password")?;
+    let hashing = hashing.clone();
+    let password_hash =
+        spawn_blocking_with_tracing(move || compute_password_hash(password, &hashing))
+            .await?
+            .context("Failed to hash password")?;
 
     sqlx::query!(
         r#"
@@ -110,18 +210,23 @@ pub async fn change_password(
         password_hash.expose_secret(),
         user_id
     )
-    .execute(pool)
+    .execute(executor)
     .await
     .context("Failed to change user's password in the database.")?;
     Ok(())
 }
 
-pub fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
+pub fn compute_password_hash(
+    password: Secret<String>,
+    hashing: &PasswordHashingSettings,
+) -> Result<Secret<String>, anyhow::Error> {
     let salt = SaltString::generate(&mut rand::thread_rng());
     let password_hash = Argon2::new(
         Algorithm::Argon2id,
         Version::V0x13,
-        Params::new(15000, 2, 1, None).unwrap(),
+        hashing
+            .params()
+            .context("Invalid password hashing parameters.")?,
     )
     .hash_password(password.expose_secret().as_bytes(), &salt)?
     .to_string();
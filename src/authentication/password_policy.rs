@@ -0,0 +1,209 @@
+//! What makes a new password acceptable. The rules are checked wherever a
+//! password is chosen: on activation, on a password change and on a reset.
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sha1::{Digest, Sha1};
+
+use crate::configuration::PasswordPolicySettings;
+use crate::telemetry::spawn_blocking_with_tracing;
+
+/// Passwords long enough to pass the length check, yet among the first ones
+/// to be tried. Lowercase, one per line.
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// Usernames shorter than this are too likely to appear in any password by
+/// chance to be looked for.
+const MIN_USERNAME_LENGTH_TO_CHECK: usize = 3;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PasswordPolicyError {
+    #[error(
+        "The new password should be longer than {} characters and shorter than {} characters",
+        .min - 1,
+        .max + 1
+    )]
+    Length { min: usize, max: usize },
+    #[error("The new password must not contain your username.")]
+    ContainsUsername,
+    #[error("The new password is too common - choose one that is harder to guess.")]
+    Common,
+    #[error("The new password is too easy to guess - make it longer or less repetitive.")]
+    Predictable,
+    #[error("The new password has appeared in a data breach - choose another one.")]
+    Breached,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+/// Check `password`, chosen by `username`, against every rule of `policy`.
+#[tracing::instrument(name = "Check a new password", skip(policy, password))]
+pub async fn check_password_policy(
+    policy: &PasswordPolicySettings,
+    username: &str,
+    password: &Secret<String>,
+) -> Result<(), PasswordPolicyError> {
+    check_password_rules(policy, username, password.expose_secret())?;
+    if let Some(dir) = &policy.breached_passwords_dir {
+        if is_breached(dir, password).await? {
+            return Err(PasswordPolicyError::Breached);
+        }
+    }
+    Ok(())
+}
+
+/// The rules that need nothing but the password itself.
+fn check_password_rules(
+    policy: &PasswordPolicySettings,
+    username: &str,
+    password: &str,
+) -> Result<(), PasswordPolicyError> {
+    if !(policy.min_length..=policy.max_length).contains(&password.len()) {
+        return Err(PasswordPolicyError::Length {
+            min: policy.min_length,
+            max: policy.max_length,
+        });
+    }
+    let lowercase = password.to_lowercase();
+    let username = username.trim().to_lowercase();
+    if username.chars().count() >= MIN_USERNAME_LENGTH_TO_CHECK && lowercase.contains(&username) {
+        return Err(PasswordPolicyError::ContainsUsername);
+    }
+    if COMMON_PASSWORDS.lines().any(|common| common == lowercase) {
+        return Err(PasswordPolicyError::Common);
+    }
+    if estimate_entropy_bits(password) < f64::from(policy.min_entropy_bits) {
+        return Err(PasswordPolicyError::Predictable);
+    }
+    Ok(())
+}
+
+/// A rough estimate of the bits of randomness in `password`. Every character
+/// is worth the bits needed to pick it among the character classes the
+/// password uses - except for characters used before, or continuing a run or
+/// a sequence (`aaa`, `abc`, `321`), which are worth a single bit.
+fn estimate_entropy_bits(password: &str) -> f64 {
+    let classes = [
+        (password.chars().any(|c| c.is_ascii_lowercase()), 26),
+        (password.chars().any(|c| c.is_ascii_uppercase()), 26),
+        (password.chars().any(|c| c.is_ascii_digit()), 10),
+        (
+            password
+                .chars()
+                .any(|c| c.is_ascii_punctuation() || c == ' '),
+            33,
+        ),
+        (password.chars().any(|c| !c.is_ascii()), 100),
+    ];
+    let pool: u32 = classes
+        .iter()
+        .filter(|(used, _)| *used)
+        .map(|(_, size)| size)
+        .sum();
+    let bits_per_character = f64::from(pool.max(1)).log2();
+
+    let mut seen = HashSet::new();
+    let mut previous: Option<char> = None;
+    let mut bits = 0.0;
+    for c in password.chars() {
+        let predictable = !seen.insert(c)
+            || previous
+                .is_some_and(|p| (i64::from(u32::from(c)) - i64::from(u32::from(p))).abs() <= 1);
+        bits += if predictable { 1.0 } else { bits_per_character };
+        previous = Some(c);
+    }
+    bits
+}
+
+/// Look `password` up in the local copy of the Pwned Passwords range files.
+/// Like the online range API, only the file of the first five digits of its
+/// SHA-1 is read.
+#[tracing::instrument(name = "Look for a password in breaches", skip(password))]
+async fn is_breached(dir: &str, password: &Secret<String>) -> Result<bool, anyhow::Error> {
+    let hash = hex::encode_upper(Sha1::digest(password.expose_secret().as_bytes()));
+    let (prefix, suffix) = hash.split_at(5);
+    let path = Path::new(dir).join(format!("{prefix}.txt"));
+    let suffix = suffix.to_owned();
+    spawn_blocking_with_tracing(move || {
+        let range = match std::fs::read_to_string(&path) {
+            Ok(range) => range,
+            // No breached password has this prefix.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}.", path.display()));
+            }
+        };
+        Ok(range.lines().any(|line| {
+            line.split(':')
+                .next()
+                .is_some_and(|s| s.trim().eq_ignore_ascii_case(&suffix))
+        }))
+    })
+    .await
+    .context("Failed to spawn blocking task.")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_password_rules, estimate_entropy_bits, PasswordPolicyError};
+    use crate::configuration::PasswordPolicySettings;
+
+    fn policy() -> PasswordPolicySettings {
+        PasswordPolicySettings {
+            min_length: 13,
+            max_length: 128,
+            min_entropy_bits: 45,
+            breached_passwords_dir: None,
+        }
+    }
+
+    #[test]
+    fn runs_and_sequences_are_worth_little() {
+        assert!(estimate_entropy_bits("0000000000000000") < 20.0);
+        assert!(estimate_entropy_bits("abcdefghijklmnop") < 20.0);
+        assert!(estimate_entropy_bits("9876543210987654") < 20.0);
+        assert!(estimate_entropy_bits("Vq3#zL9!mT2@xR") > 80.0);
+    }
+
+    #[test]
+    fn passwords_are_checked_against_every_rule() {
+        let test_cases = [
+            (
+                "short",
+                "The new password should be longer than 12 characters",
+            ),
+            (
+                "ursula-is-my-name!",
+                "The new password must not contain your username.",
+            ),
+            ("PasswordPassword", "The new password is too common"),
+            (
+                "aaaaaaaaaaaaaaaaaaaaaaaa",
+                "The new password is too easy to guess",
+            ),
+        ];
+        for (password, message) in test_cases {
+            let error = check_password_rules(&policy(), "Ursula", password).unwrap_err();
+            assert!(
+                error.to_string().starts_with(message),
+                "{password} was refused with '{error}'"
+            );
+        }
+    }
+
+    #[test]
+    fn short_usernames_are_not_looked_for() {
+        assert!(check_password_rules(&policy(), "al", "a-brand-new-password").is_ok());
+    }
+
+    #[test]
+    fn length_errors_tell_the_bounds() {
+        let error = PasswordPolicyError::Length { min: 13, max: 128 };
+        assert_eq!(
+            error.to_string(),
+            "The new password should be longer than 12 characters and shorter than 129 characters"
+        );
+    }
+}
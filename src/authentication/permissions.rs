@@ -0,0 +1,86 @@
+/// What a role allows its users to do. Roles and the permissions they grant
+/// live in the `roles` and `role_permissions` tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(into = "&str", try_from = "String")]
+pub enum Permission {
+    /// Send newsletter issues to subscribers.
+    PublishIssues,
+    /// Write newsletter issues.
+    DraftIssues,
+    /// Mailing lists, tags, subscriber data and webhooks.
+    ManageSubscribers,
+    /// Users, roles, invitations, login lockouts and the security policy.
+    ManageUsers,
+    /// Delivery status of newsletter issues.
+    ViewStats,
+    /// The audit log of logins, publications and administrative actions.
+    ViewAuditLog,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 6] = [
+        Permission::PublishIssues,
+        Permission::DraftIssues,
+        Permission::ManageSubscribers,
+        Permission::ManageUsers,
+        Permission::ViewStats,
+        Permission::ViewAuditLog,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::PublishIssues => "issues:publish",
+            Permission::DraftIssues => "issues:draft",
+            Permission::ManageSubscribers => "subscribers:manage",
+            Permission::ManageUsers => "users:manage",
+            Permission::ViewStats => "stats:view",
+            Permission::ViewAuditLog => "audit:view",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == s)
+    }
+
+    /// The known permissions among `names`, e.g. as stored in the database.
+    pub fn parse_all<S: AsRef<str>>(names: &[S]) -> Vec<Self> {
+        names
+            .iter()
+            .filter_map(|n| Self::parse(n.as_ref()))
+            .collect()
+    }
+}
+
+impl From<Permission> for &'static str {
+    fn from(permission: Permission) -> Self {
+        permission.as_str()
+    }
+}
+
+impl TryFrom<String> for Permission {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s).ok_or_else(|| format!("'{s}' is not a known permission."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Permission;
+
+    #[test]
+    fn permissions_round_trip() {
+        for permission in Permission::ALL {
+            assert_eq!(Permission::parse(permission.as_str()), Some(permission));
+        }
+    }
+
+    #[test]
+    fn unknown_permissions_are_skipped() {
+        let parsed = Permission::parse_all(&["users:manage", "users:delete"]);
+        assert_eq!(parsed, vec![Permission::ManageUsers]);
+    }
+}
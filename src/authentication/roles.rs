@@ -0,0 +1,187 @@
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use super::accounts::{begin, commit, ensure_user_managers_remain, lock_user_managers};
+use super::{AccountError, Permission, ADMIN_ROLE, COLLABORATOR_ROLE};
+
+/// Roles the application itself hands out, e.g. to invited collaborators.
+const BUILT_IN_ROLES: [&str; 2] = [ADMIN_ROLE, COLLABORATOR_ROLE];
+
+pub struct Role {
+    pub name: String,
+    pub permissions: Vec<Permission>,
+    /// How many users have the role.
+    pub users: i64,
+}
+
+impl Role {
+    pub fn is_built_in(&self) -> bool {
+        BUILT_IN_ROLES.contains(&self.name.as_str())
+    }
+}
+
+#[tracing::instrument(name = "Get roles", skip(pool))]
+pub async fn get_roles(pool: &PgPool) -> Result<Vec<Role>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT r.name,
+            ARRAY(
+                SELECT permission FROM role_permissions WHERE role = r.name
+            ) AS "permissions!",
+            (SELECT COUNT(*) FROM users WHERE role = r.name) AS "users!"
+        FROM roles r
+        ORDER BY r.created_at, r.name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the roles.")?;
+    Ok(rows
+        .into_iter()
+        .map(|row| Role {
+            name: row.name,
+            permissions: Permission::parse_all(&row.permissions),
+            users: row.users,
+        })
+        .collect())
+}
+
+#[tracing::instrument(name = "Create a role", skip(pool))]
+pub async fn create_role(
+    pool: &PgPool,
+    name: &str,
+    permissions: &[Permission],
+) -> Result<(), AccountError> {
+    if !is_valid_role_name(name) {
+        return Err(AccountError::InvalidRoleName);
+    }
+    let mut transaction = begin(pool).await?;
+    let inserted = sqlx::query!(
+        "INSERT INTO roles (name) VALUES ($1) ON CONFLICT DO NOTHING",
+        name
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store the role.")?;
+    if inserted.rows_affected() == 0 {
+        return Err(AccountError::RoleExists);
+    }
+    insert_permissions(&mut transaction, name, permissions).await?;
+    commit(transaction).await
+}
+
+/// Replace what `name` grants. Users with the role get the new permissions
+/// with their next request.
+#[tracing::instrument(name = "Change the permissions of a role", skip(pool))]
+pub async fn set_role_permissions(
+    pool: &PgPool,
+    name: &str,
+    permissions: &[Permission],
+) -> Result<(), AccountError> {
+    let mut transaction = begin(pool).await?;
+    lock_user_managers(&mut transaction).await?;
+    ensure_role_exists(&mut transaction, name).await?;
+    sqlx::query!("DELETE FROM role_permissions WHERE role = $1", name)
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete the permissions of the role.")?;
+    insert_permissions(&mut transaction, name, permissions).await?;
+    ensure_user_managers_remain(&mut transaction).await?;
+    commit(transaction).await
+}
+
+/// Only roles nobody has, or is invited to have, can be deleted.
+#[tracing::instrument(name = "Delete a role", skip(pool))]
+pub async fn delete_role(pool: &PgPool, name: &str) -> Result<(), AccountError> {
+    if BUILT_IN_ROLES.contains(&name) {
+        return Err(AccountError::BuiltInRole);
+    }
+    let mut transaction = begin(pool).await?;
+    ensure_role_exists(&mut transaction, name).await?;
+    let in_use = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM users WHERE role = $1) AS "in_use!""#,
+        name
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .context("Failed to look for users with the role.")?;
+    if in_use {
+        return Err(AccountError::RoleInUse);
+    }
+    let offered = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM collabolator_activation_tokens WHERE role = $1
+        ) AS "offered!"
+        "#,
+        name
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .context("Failed to look for invitations with the role.")?;
+    if offered {
+        return Err(AccountError::RoleOffered);
+    }
+    sqlx::query!("DELETE FROM roles WHERE name = $1", name)
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete the role.")?;
+    commit(transaction).await
+}
+
+/// Locks the role, so that it cannot be given to anyone until the
+/// transaction ends.
+async fn ensure_role_exists(
+    transaction: &mut Transaction<'_, Postgres>,
+    name: &str,
+) -> Result<(), AccountError> {
+    sqlx::query_scalar!("SELECT name FROM roles WHERE name = $1 FOR UPDATE", name)
+        .fetch_optional(&mut **transaction)
+        .await
+        .context("Failed to look up the role.")?
+        .ok_or(AccountError::UnknownRole)?;
+    Ok(())
+}
+
+async fn insert_permissions(
+    transaction: &mut Transaction<'_, Postgres>,
+    name: &str,
+    permissions: &[Permission],
+) -> Result<(), anyhow::Error> {
+    let permissions: Vec<String> = permissions.iter().map(|p| p.as_str().to_owned()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO role_permissions (role, permission)
+        SELECT $1, permission FROM unnest($2::text[]) AS permission
+        ON CONFLICT DO NOTHING
+        "#,
+        name,
+        &permissions
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to store the permissions of the role.")?;
+    Ok(())
+}
+
+fn is_valid_role_name(name: &str) -> bool {
+    (1..=32).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_valid_role_name;
+
+    #[test]
+    fn role_names_are_short_lowercase_identifiers() {
+        for name in ["editor", "stats-reader", "team_2"] {
+            assert!(is_valid_role_name(name), "{name} was rejected");
+        }
+        for name in ["", "Editor", "chief editor", "<b>", &"a".repeat(33)] {
+            assert!(!is_valid_role_name(name), "{name} was accepted");
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{touch_user_session, UserData};
+
+struct CachedSession {
+    user: UserData,
+    checked_at: Instant,
+}
+
+/// Remembers for a short while which sessions were found valid, so that not
+/// every request has to go to the database.
+///
+/// Changes made through this instance evict the affected entries right away;
+/// other instances pick them up once their entries expire.
+#[derive(Clone)]
+pub struct SessionCache {
+    ttl: Duration,
+    sessions: Arc<Mutex<HashMap<Uuid, CachedSession>>>,
+}
+
+impl SessionCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// The current data of the user of `session_id`, or `None` if the session
+    /// is no longer valid.
+    pub async fn validate(
+        &self,
+        pool: &PgPool,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<Option<UserData>, anyhow::Error> {
+        if let Some(user) = self.get(session_id) {
+            if user.user_id == user_id {
+                return Ok(Some(user));
+            }
+        }
+        let user = touch_user_session(pool, user_id, session_id).await?;
+        match &user {
+            Some(user) => self.insert(session_id, user.clone()),
+            None => self.forget_session(session_id),
+        }
+        Ok(user)
+    }
+
+    pub fn forget_session(&self, session_id: Uuid) {
+        self.sessions.lock().unwrap().remove(&session_id);
+    }
+
+    pub fn forget_user(&self, user_id: Uuid) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .retain(|_, cached| cached.user.user_id != user_id);
+    }
+
+    /// For when the permissions granted by `role` change.
+    pub fn forget_role(&self, role: &str) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .retain(|_, cached| cached.user.role != role);
+    }
+
+    fn get(&self, session_id: Uuid) -> Option<UserData> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(&session_id)
+            .filter(|cached| cached.checked_at.elapsed() < self.ttl)
+            .map(|cached| cached.user.clone())
+    }
+
+    fn insert(&self, session_id: Uuid, user: UserData) {
+        let mut sessions = self.sessions.lock().unwrap();
+        // Expired entries are only dropped here, so the map does not outgrow
+        // the sessions active within one TTL.
+        sessions.retain(|_, cached| cached.checked_at.elapsed() < self.ttl);
+        sessions.insert(
+            session_id,
+            CachedSession {
+                user,
+                checked_at: Instant::now(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use super::SessionCache;
+    use crate::authentication::{UserData, COLLABORATOR_ROLE};
+
+    fn user() -> UserData {
+        UserData {
+            user_id: Uuid::new_v4(),
+            username: "ursula".into(),
+            email: None,
+            role: COLLABORATOR_ROLE.into(),
+            permissions: vec![],
+        }
+    }
+
+    #[test]
+    fn fresh_entries_are_returned() {
+        let cache = SessionCache::new(Duration::from_secs(60));
+        let session_id = Uuid::new_v4();
+        cache.insert(session_id, user());
+        assert!(cache.get(session_id).is_some());
+    }
+
+    #[test]
+    fn expired_entries_are_not_returned() {
+        let cache = SessionCache::new(Duration::ZERO);
+        let session_id = Uuid::new_v4();
+        cache.insert(session_id, user());
+        assert!(cache.get(session_id).is_none());
+    }
+
+    #[test]
+    fn forgetting_a_user_evicts_all_of_their_sessions() {
+        let cache = SessionCache::new(Duration::from_secs(60));
+        let (user, other_user) = (user(), user());
+        let (first, second, third) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        cache.insert(first, user.clone());
+        cache.insert(second, user.clone());
+        cache.insert(third, other_user);
+
+        cache.forget_user(user.user_id);
+
+        assert!(cache.get(first).is_none());
+        assert!(cache.get(second).is_none());
+        assert!(cache.get(third).is_some());
+    }
+}
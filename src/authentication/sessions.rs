@@ -0,0 +1,204 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+use super::{Permission, UserData};
+
+/// How long the session state is kept in Redis after logging in.
+pub const SESSION_TTL_IN_HOURS: i64 = 24;
+
+/// User agents are client-controlled, so only this much of them is kept.
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
+// Sessions store a fingerprint of the password hash they were started with:
+// the one in `users` no longer matching means the password has changed since.
+// Both sides are computed in SQL, with
+// `encode(sha256(convert_to(password_hash, 'UTF8')), 'hex')`.
+
+pub struct UserSession {
+    pub session_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub ip: String,
+    pub user_agent: String,
+}
+
+/// Record a new session of `user_id` - called when logging in.
+#[tracing::instrument(name = "Start a user session", skip(pool, user_agent))]
+pub async fn start_user_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    ip: &str,
+    user_agent: &str,
+) -> Result<Uuid, anyhow::Error> {
+    let session_id = Uuid::new_v4();
+    let user_agent: String = user_agent.chars().take(MAX_USER_AGENT_LENGTH).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO user_sessions (
+            session_id, user_id, created_at, last_seen_at, ip, user_agent, password_fingerprint
+        )
+        SELECT $1, user_id, $3, $3, $4, $5,
+            encode(sha256(convert_to(password_hash, 'UTF8')), 'hex')
+        FROM users
+        WHERE user_id = $2
+        "#,
+        session_id,
+        user_id,
+        Utc::now(),
+        ip,
+        user_agent
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the user session.")?;
+    Ok(session_id)
+}
+
+/// Mark the session as seen now and return the current data of its user.
+/// Returns `None` if the session has been revoked, if the user no longer
+/// exists or if their password has changed since the session was started.
+#[tracing::instrument(name = "Touch a user session", skip(pool))]
+pub async fn touch_user_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<Option<UserData>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE user_sessions s SET last_seen_at = $3
+        FROM users u
+        WHERE s.session_id = $1
+            AND s.user_id = $2
+            AND u.user_id = s.user_id
+            AND u.deactivated_at IS NULL
+            AND s.revoked_at IS NULL
+            AND s.password_fingerprint = encode(sha256(convert_to(u.password_hash, 'UTF8')), 'hex')
+        RETURNING u.user_id, u.username, u.email, u.role,
+            ARRAY(
+                SELECT permission FROM role_permissions WHERE role = u.role
+            ) AS "permissions!"
+        "#,
+        session_id,
+        user_id,
+        Utc::now()
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to update the user session.")?;
+    Ok(row.map(|row| UserData {
+        user_id: row.user_id,
+        username: row.username,
+        email: row.email,
+        role: row.role,
+        permissions: Permission::parse_all(&row.permissions),
+    }))
+}
+
+/// Keep the session valid after its user changed their password.
+#[tracing::instrument(name = "Keep a user session", skip(pool))]
+pub async fn keep_user_session(pool: &PgPool, session_id: Uuid) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE user_sessions s
+        SET password_fingerprint = encode(sha256(convert_to(u.password_hash, 'UTF8')), 'hex')
+        FROM users u
+        WHERE s.session_id = $1 AND u.user_id = s.user_id
+        "#,
+        session_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the password fingerprint of the session.")?;
+    Ok(())
+}
+
+/// The sessions of `user_id` that are still usable, most recently seen first.
+#[tracing::instrument(name = "Get user sessions", skip(pool))]
+pub async fn get_user_sessions(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<UserSession>, anyhow::Error> {
+    let sessions = sqlx::query_as!(
+        UserSession,
+        r#"
+        SELECT s.session_id, s.created_at, s.last_seen_at, s.ip, s.user_agent
+        FROM user_sessions s
+        JOIN users u ON u.user_id = s.user_id
+        WHERE s.user_id = $1
+            AND s.revoked_at IS NULL
+            AND s.created_at > $2
+            AND s.password_fingerprint = encode(sha256(convert_to(u.password_hash, 'UTF8')), 'hex')
+        ORDER BY s.last_seen_at DESC
+        "#,
+        user_id,
+        Utc::now() - Duration::hours(SESSION_TTL_IN_HOURS)
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the sessions of the user.")?;
+    Ok(sessions)
+}
+
+/// Revoke a single session of `user_id`. Returns `false` if there was no
+/// such session.
+#[tracing::instrument(name = "Revoke a user session", skip(pool))]
+pub async fn revoke_user_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE user_sessions SET revoked_at = $3
+        WHERE session_id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        session_id,
+        user_id,
+        Utc::now()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke the user session.")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revoke every session of `user_id` except `keep`.
+#[tracing::instrument(name = "Revoke other user sessions", skip(pool))]
+pub async fn revoke_other_user_sessions(
+    pool: &PgPool,
+    user_id: Uuid,
+    keep: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE user_sessions SET revoked_at = $3
+        WHERE user_id = $1 AND session_id <> $2 AND revoked_at IS NULL
+        "#,
+        user_id,
+        keep,
+        Utc::now()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke the other sessions of the user.")?;
+    Ok(())
+}
+
+/// Log `user_id` out everywhere.
+#[tracing::instrument(name = "Revoke user sessions", skip(executor))]
+pub async fn revoke_user_sessions<'c, E>(executor: E, user_id: Uuid) -> Result<(), anyhow::Error>
+where
+    E: Executor<'c, Database = Postgres>,
+{
+    sqlx::query!(
+        "UPDATE user_sessions SET revoked_at = $2 WHERE user_id = $1 AND revoked_at IS NULL",
+        user_id,
+        Utc::now()
+    )
+    .execute(executor)
+    .await
+    .context("Failed to revoke the sessions of the user.")?;
+    Ok(())
+}
@@ -0,0 +1,448 @@
+//! Time-based one-time passwords (RFC 6238) as a second login factor.
+use anyhow::Context;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::distributions::Alphanumeric;
+use rand::{Rng, RngCore};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const TOTP_ISSUER: &str = "zero2prod";
+const TOTP_PERIOD_SECONDS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+/// Codes of the previous and the next period are accepted too, to make up for clock drift.
+const TOTP_ALLOWED_DRIFT: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// A new random secret, base32-encoded as authenticator apps expect it.
+pub fn generate_totp_secret() -> String {
+    let mut secret = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut secret);
+    base32_encode(&secret)
+}
+
+/// The URI to add the secret to an authenticator app - usually shown as a QR code.
+pub fn totp_uri(username: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(TOTP_ISSUER),
+        percent_encode(username),
+        secret,
+        percent_encode(TOTP_ISSUER),
+        TOTP_DIGITS,
+        TOTP_PERIOD_SECONDS
+    )
+}
+
+/// The code an authenticator app shows at `unix_time` for a base32 `secret`.
+pub fn totp_code(secret: &str, unix_time: i64) -> Result<String, anyhow::Error> {
+    let secret = base32_decode(secret).context("The TOTP secret is not valid base32.")?;
+    Ok(hotp(&secret, unix_time.div_euclid(TOTP_PERIOD_SECONDS)))
+}
+
+/// The time step `code` belongs to, if it is valid at `unix_time` and newer
+/// than `last_used_step` - a code cannot be used twice.
+fn verify_totp(
+    secret: &[u8],
+    code: &str,
+    unix_time: i64,
+    last_used_step: Option<i64>,
+) -> Option<i64> {
+    let code = code.trim();
+    let current_step = unix_time.div_euclid(TOTP_PERIOD_SECONDS);
+    (current_step - TOTP_ALLOWED_DRIFT..=current_step + TOTP_ALLOWED_DRIFT)
+        .filter(|step| last_used_step.map_or(true, |last| *step > last))
+        .find(|step| hotp(secret, *step) == code)
+}
+
+fn hotp(secret: &[u8], counter: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in encoded.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn generate_recovery_code() -> String {
+    let code: String = rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .take(10)
+        .collect();
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let normalised: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hex::encode(Sha256::digest(normalised.as_bytes()))
+}
+
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    /// A secret waiting to be confirmed with a code from the authenticator app.
+    pub pending_secret: Option<String>,
+    pub recovery_codes_left: i64,
+}
+
+#[tracing::instrument(name = "Get two-factor status", skip(pool))]
+pub async fn get_two_factor_status(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<TwoFactorStatus, anyhow::Error> {
+    let credential = sqlx::query!(
+        "SELECT secret, confirmed_at FROM two_factor_credentials WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the two-factor credential.")?;
+    let recovery_codes_left = sqlx::query!(
+        r#"SELECT COUNT(*) AS "n!" FROM two_factor_recovery_codes WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count recovery codes.")?
+    .n;
+    Ok(match credential {
+        Some(c) if c.confirmed_at.is_some() => TwoFactorStatus {
+            enabled: true,
+            pending_secret: None,
+            recovery_codes_left,
+        },
+        Some(c) => TwoFactorStatus {
+            enabled: false,
+            pending_secret: Some(c.secret),
+            recovery_codes_left: 0,
+        },
+        None => TwoFactorStatus {
+            enabled: false,
+            pending_secret: None,
+            recovery_codes_left: 0,
+        },
+    })
+}
+
+pub async fn is_two_factor_enabled(pool: &PgPool, user_id: Uuid) -> Result<bool, anyhow::Error> {
+    Ok(get_two_factor_status(pool, user_id).await?.enabled)
+}
+
+/// Store a new secret for `user_id` - it only takes effect once confirmed.
+/// An already enabled second factor is left untouched.
+#[tracing::instrument(name = "Start two-factor enrollment", skip(pool))]
+pub async fn start_two_factor_enrollment(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO two_factor_credentials (user_id, secret)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET secret = EXCLUDED.secret
+        WHERE two_factor_credentials.confirmed_at IS NULL
+        "#,
+        user_id,
+        generate_totp_secret()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the two-factor secret.")?;
+    Ok(())
+}
+
+/// Enable the pending secret of `user_id` if `code` matches it. Returns the
+/// freshly generated recovery codes - they are only stored hashed.
+#[tracing::instrument(name = "Confirm two-factor enrollment", skip(pool, code))]
+pub async fn confirm_two_factor_enrollment(
+    pool: &PgPool,
+    user_id: Uuid,
+    code: &str,
+) -> Result<Option<Vec<String>>, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let Some(credential) = sqlx::query!(
+        r#"
+        SELECT secret FROM two_factor_credentials
+        WHERE user_id = $1 AND confirmed_at IS NULL
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to fetch the pending two-factor secret.")?
+    else {
+        return Ok(None);
+    };
+    let secret = base32_decode(&credential.secret).context("The stored TOTP secret is invalid.")?;
+    let Some(step) = verify_totp(&secret, code, Utc::now().timestamp(), None) else {
+        return Ok(None);
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE two_factor_credentials
+        SET confirmed_at = now(), last_used_step = $2
+        WHERE user_id = $1
+        "#,
+        user_id,
+        step
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to enable the second factor.")?;
+    let recovery_codes: Vec<String> = std::iter::repeat_with(generate_recovery_code)
+        .take(RECOVERY_CODE_COUNT)
+        .collect();
+    let code_hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|c| hash_recovery_code(c))
+        .collect();
+    sqlx::query!(
+        "DELETE FROM two_factor_recovery_codes WHERE user_id = $1",
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete old recovery codes.")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO two_factor_recovery_codes (user_id, code_hash)
+        SELECT $1, code_hash FROM UNNEST($2::text[]) AS code_hash
+        "#,
+        user_id,
+        &code_hashes
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store recovery codes.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to enable the second factor.")?;
+    Ok(Some(recovery_codes))
+}
+
+/// Check a code from the authenticator app or a recovery code - which is
+/// consumed. Returns `false` for users without an enabled second factor.
+#[tracing::instrument(name = "Verify second factor", skip(pool, code))]
+pub async fn verify_second_factor(
+    pool: &PgPool,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let Some(credential) = sqlx::query!(
+        r#"
+        SELECT secret, last_used_step FROM two_factor_credentials
+        WHERE user_id = $1 AND confirmed_at IS NOT NULL
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to fetch the two-factor credential.")?
+    else {
+        return Ok(false);
+    };
+    let secret = base32_decode(&credential.secret).context("The stored TOTP secret is invalid.")?;
+
+    if let Some(step) = verify_totp(
+        &secret,
+        code,
+        Utc::now().timestamp(),
+        credential.last_used_step,
+    ) {
+        sqlx::query!(
+            "UPDATE two_factor_credentials SET last_used_step = $2 WHERE user_id = $1",
+            user_id,
+            step
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to record the used TOTP code.")?;
+    } else {
+        let consumed = sqlx::query!(
+            "DELETE FROM two_factor_recovery_codes WHERE user_id = $1 AND code_hash = $2",
+            user_id,
+            hash_recovery_code(code)
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to consume a recovery code.")?
+        .rows_affected();
+        if consumed == 0 {
+            return Ok(false);
+        }
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to verify the second factor.")?;
+    Ok(true)
+}
+
+#[tracing::instrument(name = "Disable two-factor authentication", skip(pool))]
+pub async fn disable_two_factor(pool: &PgPool, user_id: Uuid) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    sqlx::query!(
+        "DELETE FROM two_factor_credentials WHERE user_id = $1",
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete the two-factor credential.")?;
+    sqlx::query!(
+        "DELETE FROM two_factor_recovery_codes WHERE user_id = $1",
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete recovery codes.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the removal of the second factor.")?;
+    Ok(())
+}
+
+/// Whether an admin requires every user to enable a second factor.
+pub async fn is_two_factor_required(pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let policy = sqlx::query!("SELECT require_two_factor FROM security_policy")
+        .fetch_one(pool)
+        .await
+        .context("Failed to fetch the security policy.")?;
+    Ok(policy.require_two_factor)
+}
+
+#[tracing::instrument(name = "Update the security policy", skip(pool))]
+pub async fn set_two_factor_required(pool: &PgPool, required: bool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE security_policy SET require_two_factor = $1",
+        required
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the security policy.")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base32_decode, base32_encode, hotp, totp_uri, verify_totp};
+
+    // The SHA-1 test secret of RFC 6238, appendix B.
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn codes_match_the_rfc_test_vectors() {
+        assert_eq!(hotp(SECRET, 59 / 30), "287082");
+        assert_eq!(hotp(SECRET, 1111111109 / 30), "081804");
+        assert_eq!(hotp(SECRET, 1234567890 / 30), "005924");
+        assert_eq!(hotp(SECRET, 2000000000 / 30), "279037");
+    }
+
+    #[test]
+    fn codes_of_neighbouring_periods_are_accepted() {
+        assert_eq!(verify_totp(SECRET, "287082", 59, None), Some(1));
+        assert_eq!(verify_totp(SECRET, "287082", 89, None), Some(1));
+        assert_eq!(verify_totp(SECRET, "287082", 29, None), Some(1));
+        assert_eq!(verify_totp(SECRET, "287082", 119, None), None);
+    }
+
+    #[test]
+    fn codes_cannot_be_reused() {
+        assert_eq!(verify_totp(SECRET, "287082", 59, Some(1)), None);
+        assert_eq!(verify_totp(SECRET, "287082", 59, Some(0)), Some(1));
+    }
+
+    #[test]
+    fn base32_round_trips() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("MZXW6YTBOI").unwrap(), b"foobar");
+        assert_eq!(base32_decode("mzxw 6ytb oi======").unwrap(), b"foobar");
+        assert_eq!(base32_decode("MZXW1"), None);
+    }
+
+    #[test]
+    fn the_uri_escapes_the_username() {
+        assert_eq!(
+            totp_uri("le guin", "MZXW6YTBOI"),
+            "otpauth://totp/zero2prod:le%20guin?secret=MZXW6YTBOI&issuer=zero2prod\
+            &algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
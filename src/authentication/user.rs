@@ -2,23 +2,13 @@ use secrecy::{ExposeSecret, Secret};
 use sqlx::{postgres::PgValueRef, Decode, Encode, Postgres};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, sqlx::Type, serde::Serialize, serde::Deserialize)]
-#[sqlx(type_name = "role")]
-#[sqlx(rename_all = "lowercase")]
-pub enum UserRole {
-    Admin,
-    Collabolator,
-}
+use super::Permission;
+
+/// The roles every database starts with. More can be created by users with
+/// the `ManageUsers` permission.
+pub const ADMIN_ROLE: &str = "admin";
+pub const COLLABORATOR_ROLE: &str = "collabolator";
 
-impl ToString for UserRole {
-    fn to_string(&self) -> String {
-        match self {
-            UserRole::Admin => "admin",
-            UserRole::Collabolator => "collabolator",
-        }
-        .to_owned()
-    }
-}
 pub struct UserPassword(Secret<String>);
 
 impl UserPassword {
@@ -55,19 +45,33 @@ impl From<String> for UserPassword {
     }
 }
 
-#[derive(sqlx::Type)]
 pub struct User {
     pub user_id: Uuid,
     pub username: String,
     pub password_hash: UserPassword,
-    pub role: UserRole,
+    /// Only addresses the user proved to receive mail at are stored.
+    pub email: Option<String>,
+    pub role: String,
+    pub permissions: Vec<String>,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct UserData {
     pub user_id: Uuid,
     pub username: String,
-    pub role: UserRole,
+    /// Where the user is emailed, if they gave an address.
+    #[serde(default)]
+    pub email: Option<String>,
+    pub role: String,
+    /// What `role` granted when the data was loaded.
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+}
+
+impl UserData {
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
 }
 
 impl From<User> for UserData {
@@ -75,7 +79,9 @@ impl From<User> for UserData {
         UserData {
             user_id: value.user_id,
             username: value.username,
+            email: value.email,
             role: value.role,
+            permissions: Permission::parse_all(&value.permissions),
         }
     }
 }
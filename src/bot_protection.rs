@@ -0,0 +1,274 @@
+//! Defences of the public subscribe form against automated submissions.
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+
+const FORM_TOKEN_CONTEXT: &[u8] = b"subscribe-form";
+
+/// A token embedded in the subscribe form: the time it was rendered, signed
+/// with the application's HMAC secret - `{unix_timestamp}.{hex_signature}`.
+pub fn generate_form_token(secret: &Secret<String>, issued_at: DateTime<Utc>) -> String {
+    let timestamp = issued_at.timestamp();
+    let signature = hex::encode(form_token_mac(secret, timestamp).finalize().into_bytes());
+    format!("{}.{}", timestamp, signature)
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum FormTokenError {
+    #[error("The form token is not valid.")]
+    Invalid,
+    #[error("The form was submitted too quickly.")]
+    TooFresh,
+    #[error("The form has expired - reload the page and try again.")]
+    Expired,
+}
+
+/// Check the signature of `token` and that it was issued between `max_age`
+/// and `min_age` ago - humans take a moment to fill in a form.
+pub fn verify_form_token(
+    secret: &Secret<String>,
+    token: &str,
+    now: DateTime<Utc>,
+    min_age: Duration,
+    max_age: Duration,
+) -> Result<(), FormTokenError> {
+    let (timestamp, signature) = token.split_once('.').ok_or(FormTokenError::Invalid)?;
+    let timestamp: i64 = timestamp.parse().map_err(|_| FormTokenError::Invalid)?;
+    let signature = hex::decode(signature).map_err(|_| FormTokenError::Invalid)?;
+    form_token_mac(secret, timestamp)
+        .verify_slice(&signature)
+        .map_err(|_| FormTokenError::Invalid)?;
+
+    let issued_at = DateTime::from_timestamp(timestamp, 0).ok_or(FormTokenError::Invalid)?;
+    let age = now - issued_at;
+    if age < min_age {
+        return Err(FormTokenError::TooFresh);
+    }
+    if age > max_age {
+        return Err(FormTokenError::Expired);
+    }
+    Ok(())
+}
+
+fn form_token_mac(secret: &Secret<String>, timestamp: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(FORM_TOKEN_CONTEXT);
+    mac.update(timestamp.to_string().as_bytes());
+    mac
+}
+
+pub enum CaptchaVerifier {
+    /// Every submission passes.
+    Disabled,
+    /// Accepts a fixed answer - a stand-in for a real CAPTCHA in tests and local development.
+    Local { expected_response: Secret<String> },
+    Hcaptcha {
+        http_client: Client,
+        site_key: String,
+        secret_key: Secret<String>,
+        verify_url: String,
+    },
+}
+
+#[derive(serde::Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+}
+
+impl CaptchaVerifier {
+    /// The markup rendered inside the subscribe form.
+    pub fn widget_html(&self) -> String {
+        match self {
+            CaptchaVerifier::Disabled => String::new(),
+            CaptchaVerifier::Local { .. } => r#"<label>Are you human?
+            <input type="text" name="captcha_response" autocomplete="off">
+        </label>"#
+                .to_string(),
+            CaptchaVerifier::Hcaptcha { site_key, .. } => format!(
+                r#"<script src="https://js.hcaptcha.com/1/api.js" async defer></script>
+        <div class="h-captcha" data-sitekey="{}"></div>"#,
+                htmlescape::encode_attribute(site_key)
+            ),
+        }
+    }
+
+    #[tracing::instrument(name = "Verify CAPTCHA response", skip_all)]
+    pub async fn verify(&self, response: Option<&str>) -> Result<bool, anyhow::Error> {
+        let response = response.unwrap_or_default();
+        match self {
+            CaptchaVerifier::Disabled => Ok(true),
+            CaptchaVerifier::Local { expected_response } => {
+                Ok(response == expected_response.expose_secret())
+            }
+            CaptchaVerifier::Hcaptcha {
+                http_client,
+                site_key,
+                secret_key,
+                verify_url,
+            } => {
+                if response.is_empty() {
+                    return Ok(false);
+                }
+                let outcome: SiteVerifyResponse = http_client
+                    .post(verify_url)
+                    .form(&[
+                        ("secret", secret_key.expose_secret().as_str()),
+                        ("response", response),
+                        ("sitekey", site_key.as_str()),
+                    ])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                Ok(outcome.success)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bot_protection::{
+        generate_form_token, verify_form_token, CaptchaVerifier, FormTokenError,
+    };
+    use chrono::{Duration, Utc};
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+    use reqwest::Client;
+    use secrecy::Secret;
+    use wiremock::matchers::{body_string_contains, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn secret() -> Secret<String> {
+        Secret::new("a-secret".to_string())
+    }
+
+    fn verify(token: &str, min_age_seconds: i64) -> Result<(), FormTokenError> {
+        verify_form_token(
+            &secret(),
+            token,
+            Utc::now(),
+            Duration::seconds(min_age_seconds),
+            Duration::hours(1),
+        )
+    }
+
+    #[test]
+    fn a_freshly_generated_token_is_valid() {
+        let token = generate_form_token(&secret(), Utc::now() - Duration::seconds(5));
+        assert_ok!(verify(&token, 3));
+    }
+
+    #[test]
+    fn a_token_submitted_too_quickly_is_rejected() {
+        let token = generate_form_token(&secret(), Utc::now());
+        assert_eq!(verify(&token, 3), Err(FormTokenError::TooFresh));
+    }
+
+    #[test]
+    fn an_expired_token_is_rejected() {
+        let token = generate_form_token(&secret(), Utc::now() - Duration::hours(2));
+        assert_eq!(verify(&token, 0), Err(FormTokenError::Expired));
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let token = generate_form_token(&Secret::new("other".into()), Utc::now());
+        assert_eq!(verify(&token, 0), Err(FormTokenError::Invalid));
+    }
+
+    #[test]
+    fn a_token_with_a_tampered_timestamp_is_rejected() {
+        let token = generate_form_token(&secret(), Utc::now() - Duration::hours(2));
+        let (_, signature) = token.split_once('.').unwrap();
+        let tampered = format!("{}.{}", Utc::now().timestamp(), signature);
+        assert_eq!(verify(&tampered, 0), Err(FormTokenError::Invalid));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for token in ["", "garbage", "123.not-hex", "abc.00"] {
+            assert_err!(verify(token, 0));
+        }
+    }
+
+    #[tokio::test]
+    async fn the_local_verifier_accepts_only_the_expected_response() {
+        let verifier = CaptchaVerifier::Local {
+            expected_response: Secret::new("not-a-robot".into()),
+        };
+        assert_ok_eq!(verifier.verify(Some("not-a-robot")).await, true);
+        assert_ok_eq!(verifier.verify(Some("robot")).await, false);
+        assert_ok_eq!(verifier.verify(None).await, false);
+    }
+
+    fn hcaptcha_verifier(verify_url: String) -> CaptchaVerifier {
+        CaptchaVerifier::Hcaptcha {
+            http_client: Client::new(),
+            site_key: "site-key".into(),
+            secret_key: Secret::new("secret-key".into()),
+            verify_url,
+        }
+    }
+
+    #[tokio::test]
+    async fn hcaptcha_responses_are_checked_with_the_verify_endpoint() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("secret=secret-key"))
+            .and(body_string_contains("response=a-response"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "success": true
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let verifier = hcaptcha_verifier(mock_server.uri());
+
+        // Act
+        let outcome = verifier.verify(Some("a-response")).await;
+
+        // Assert
+        assert_ok_eq!(outcome, true);
+    }
+
+    #[tokio::test]
+    async fn failed_hcaptcha_verifications_are_reported() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "success": false
+            })))
+            .mount(&mock_server)
+            .await;
+        let verifier = hcaptcha_verifier(mock_server.uri());
+
+        // Act
+        let outcome = verifier.verify(Some("a-response")).await;
+
+        // Assert
+        assert_ok_eq!(outcome, false);
+    }
+
+    #[tokio::test]
+    async fn hcaptcha_errors_are_propagated() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+        let verifier = hcaptcha_verifier(mock_server.uri());
+
+        // Act
+        let outcome = verifier.verify(Some("a-response")).await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+}
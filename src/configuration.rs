@@ -1,4 +1,6 @@
+use crate::bot_protection::CaptchaVerifier;
 use crate::email_client::EmailClient;
+use reqwest::Client;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use serde_aux::field_attributes::deserialize_number_from_string;
@@ -13,6 +15,11 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
     pub redis_uri: Secret<String>,
+    pub subscriptions: SubscriptionSettings,
+    pub rate_limits: RateLimitSettings,
+    pub login_protection: LoginProtectionSettings,
+    pub password_hashing: PasswordHashingSettings,
+    pub password_policy: PasswordPolicySettings,
 }
 
 #[derive(Deserialize, Clone)]
@@ -44,6 +51,185 @@ impl EmailClientSettings {
     }
 }
 
+#[derive(Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub confirmation_token_ttl_hours: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub pending_subscriber_ttl_days: u32,
+    /// Submissions of the subscribe form faster than this are considered automated.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub form_token_min_age_seconds: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub form_token_max_age_seconds: u32,
+    pub captcha: CaptchaSettings,
+}
+
+impl SubscriptionSettings {
+    pub fn confirmation_token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.confirmation_token_ttl_hours.into())
+    }
+
+    pub fn pending_subscriber_ttl(&self) -> chrono::Duration {
+        chrono::Duration::days(self.pending_subscriber_ttl_days.into())
+    }
+
+    pub fn form_token_min_age(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.form_token_min_age_seconds.into())
+    }
+
+    pub fn form_token_max_age(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.form_token_max_age_seconds.into())
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum CaptchaSettings {
+    Disabled,
+    /// Accepts a fixed answer - a stand-in for a real CAPTCHA in tests and local development.
+    Local {
+        expected_response: Secret<String>,
+    },
+    Hcaptcha {
+        site_key: String,
+        secret_key: Secret<String>,
+        verify_url: String,
+    },
+}
+
+impl CaptchaSettings {
+    pub fn verifier(self) -> CaptchaVerifier {
+        match self {
+            CaptchaSettings::Disabled => CaptchaVerifier::Disabled,
+            CaptchaSettings::Local { expected_response } => {
+                CaptchaVerifier::Local { expected_response }
+            }
+            CaptchaSettings::Hcaptcha {
+                site_key,
+                secret_key,
+                verify_url,
+            } => CaptchaVerifier::Hcaptcha {
+                http_client: Client::builder()
+                    .timeout(std::time::Duration::from_secs(10))
+                    .build()
+                    .unwrap(),
+                site_key,
+                secret_key,
+                verify_url,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RateLimitSettings {
+    /// Prepended to every Redis key used for rate limiting.
+    pub key_prefix: String,
+    pub login: RateLimit,
+    pub subscriptions: RateLimit,
+    pub account_activation: RateLimit,
+    pub password_reset: RateLimit,
+}
+
+/// At most `max_requests_per_ip` requests from the same client and
+/// `max_requests_per_key` requests for the same email or username are
+/// accepted within any window of `window_seconds`.
+#[derive(Deserialize, Clone)]
+pub struct RateLimit {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub window_seconds: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_requests_per_ip: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_requests_per_key: u32,
+}
+
+impl RateLimit {
+    pub fn window(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.window_seconds.into())
+    }
+}
+
+/// Failed logins are counted per username and per client IP. Once a counter
+/// goes past `free_attempts`, every further attempt has to wait an exponentially
+/// growing delay; once it reaches its lockout threshold, logging in is refused
+/// for `lockout_seconds`. Counters are forgotten after `lockout_seconds`
+/// without a failed attempt.
+#[derive(Deserialize, Clone)]
+pub struct LoginProtectionSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub free_attempts: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub base_delay_seconds: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_delay_seconds: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lockout_threshold_per_username: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lockout_threshold_per_ip: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub lockout_seconds: u32,
+}
+
+impl LoginProtectionSettings {
+    /// How long to wait before the next attempt after `failed_attempts` failures.
+    pub fn delay(&self, failed_attempts: u32) -> chrono::Duration {
+        if failed_attempts < self.free_attempts {
+            return chrono::Duration::zero();
+        }
+        let exponent = (failed_attempts - self.free_attempts).min(16);
+        let delay = u64::from(self.base_delay_seconds)
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay_seconds.into());
+        chrono::Duration::seconds(delay as i64)
+    }
+
+    pub fn lockout(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.lockout_seconds.into())
+    }
+}
+
+/// Costs of the Argon2id hashes computed for new passwords. Stored hashes made
+/// with other costs, or with another algorithm, are replaced the next time
+/// their password is checked successfully.
+#[derive(Deserialize, Clone)]
+pub struct PasswordHashingSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub memory_kib: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub iterations: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub parallelism: u32,
+}
+
+impl PasswordHashingSettings {
+    pub fn params(&self) -> Result<argon2::Params, argon2::Error> {
+        argon2::Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+    }
+}
+
+/// What new passwords have to satisfy, whether they are chosen on activation,
+/// on a password change or on a reset.
+#[derive(Deserialize, Clone)]
+pub struct PasswordPolicySettings {
+    /// Bounds on the length in bytes, both included.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_length: usize,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_length: usize,
+    /// Passwords estimated to be guessable with fewer attempts than 2 to this
+    /// power are refused.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_entropy_bits: u32,
+    /// A local copy of the Pwned Passwords range files: one
+    /// `<first 5 hex digits of the SHA-1>.txt` file per prefix, listing the
+    /// remaining digits of breached passwords as `SUFFIX:COUNT` lines. The
+    /// breach check is skipped when it is not set.
+    #[serde(default)]
+    pub breached_passwords_dir: Option<String>,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub username: String,
@@ -85,6 +271,20 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    /// How long a session, once checked against the database, is trusted
+    /// without checking it again.
+    pub session_cache_milliseconds: u64,
+    /// The reverse proxies in front of the application. The client address
+    /// is only taken from `X-Forwarded-For` for requests coming from one of
+    /// them - for anything else the address of the peer is used.
+    #[serde(default)]
+    pub trusted_proxies: Vec<std::net::IpAddr>,
+}
+
+impl ApplicationSettings {
+    pub fn session_cache(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.session_cache_milliseconds)
+    }
 }
 
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
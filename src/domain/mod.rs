@@ -1,7 +1,11 @@
 mod email;
 mod new_subscriber;
+mod segment;
 mod subscriber_name;
+mod subscriber_tag;
 
 pub use email::Email;
 pub use new_subscriber::NewSubscriber;
+pub use segment::{MembershipStatus, Segment};
 pub use subscriber_name::SubscriberName;
+pub use subscriber_tag::SubscriberTag;
@@ -0,0 +1,152 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+use super::SubscriberTag;
+
+/// A subset of the members of a mailing list.
+#[derive(Debug)]
+pub struct Segment {
+    /// Members tagged with at least one of these tags - everyone if empty.
+    pub include_tags: Vec<SubscriberTag>,
+    /// Members tagged with any of these tags are left out.
+    pub exclude_tags: Vec<SubscriberTag>,
+    /// Members who subscribed on or after the start of this day (UTC).
+    pub subscribed_after: Option<NaiveDate>,
+    /// Members who subscribed before the start of this day (UTC).
+    pub subscribed_before: Option<NaiveDate>,
+    pub status: MembershipStatus,
+}
+
+impl Segment {
+    pub fn parse(
+        include_tags: &str,
+        exclude_tags: &str,
+        subscribed_after: &str,
+        subscribed_before: &str,
+        status: &str,
+    ) -> Result<Segment, String> {
+        let segment = Segment {
+            include_tags: SubscriberTag::parse_list(include_tags)?,
+            exclude_tags: SubscriberTag::parse_list(exclude_tags)?,
+            subscribed_after: parse_date(subscribed_after)?,
+            subscribed_before: parse_date(subscribed_before)?,
+            status: MembershipStatus::parse(status)?,
+        };
+        if let (Some(after), Some(before)) = (segment.subscribed_after, segment.subscribed_before) {
+            if after >= before {
+                return Err(format!("{} is not before {}.", after, before));
+            }
+        }
+        Ok(segment)
+    }
+
+    pub fn include_tags(&self) -> Vec<String> {
+        self.include_tags
+            .iter()
+            .map(|t| t.as_ref().to_owned())
+            .collect()
+    }
+
+    pub fn exclude_tags(&self) -> Vec<String> {
+        self.exclude_tags
+            .iter()
+            .map(|t| t.as_ref().to_owned())
+            .collect()
+    }
+
+    pub fn subscribed_after(&self) -> Option<DateTime<Utc>> {
+        self.subscribed_after.map(start_of_day)
+    }
+
+    pub fn subscribed_before(&self) -> Option<DateTime<Utc>> {
+        self.subscribed_before.map(start_of_day)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MembershipStatus {
+    #[default]
+    Confirmed,
+    PendingConfirmation,
+    Unsubscribed,
+}
+
+impl MembershipStatus {
+    /// An empty string selects confirmed members.
+    pub fn parse(s: &str) -> Result<MembershipStatus, String> {
+        match s.trim() {
+            "" | "confirmed" => Ok(Self::Confirmed),
+            "pending_confirmation" => Ok(Self::PendingConfirmation),
+            "unsubscribed" => Ok(Self::Unsubscribed),
+            other => Err(format!("{} is not a valid subscription status.", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Confirmed => "confirmed",
+            Self::PendingConfirmation => "pending_confirmation",
+            Self::Unsubscribed => "unsubscribed",
+        }
+    }
+}
+
+fn parse_date(s: &str) -> Result<Option<NaiveDate>, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| format!("{} is not a valid date (YYYY-MM-DD).", s))
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{MembershipStatus, Segment};
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn an_empty_segment_selects_all_confirmed_members() {
+        let segment = Segment::parse("", "", "", "", "").unwrap();
+        assert!(segment.include_tags.is_empty());
+        assert!(segment.exclude_tags.is_empty());
+        assert_eq!(segment.subscribed_after(), None);
+        assert_eq!(segment.subscribed_before(), None);
+        assert_eq!(segment.status, MembershipStatus::Confirmed);
+    }
+
+    #[test]
+    fn dates_are_parsed_as_the_start_of_the_day() {
+        let segment = Segment::parse("", "", "2024-01-31", "", "").unwrap();
+        assert_eq!(
+            segment.subscribed_after().unwrap().to_rfc3339(),
+            "2024-01-31T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        assert_err!(Segment::parse("", "", "31/01/2024", "", ""));
+    }
+
+    #[test]
+    fn an_empty_date_range_is_rejected() {
+        assert_err!(Segment::parse("", "", "2024-02-01", "2024-02-01", ""));
+        assert_ok!(Segment::parse("", "", "2024-02-01", "2024-02-02", ""));
+    }
+
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        assert_err!(Segment::parse("", "", "", "", "deleted"));
+    }
+
+    #[test]
+    fn invalid_tags_are_rejected() {
+        assert_err!(Segment::parse("beta,<b>", "", "", "", ""));
+        assert_err!(Segment::parse("", "beta!", "", "", ""));
+    }
+}
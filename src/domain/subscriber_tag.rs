@@ -0,0 +1,74 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriberTag(String);
+
+impl SubscriberTag {
+    /// Tags are case-insensitive and stored in lowercase.
+    pub fn parse(s: &str) -> Result<SubscriberTag, String> {
+        let tag = s.trim().to_lowercase();
+        let is_valid = !tag.is_empty()
+            && tag.chars().count() <= 32
+            && tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if is_valid {
+            Ok(Self(tag))
+        } else {
+            Err(format!("{} is not a valid tag.", s.trim()))
+        }
+    }
+
+    /// Parse a comma separated list of tags, ignoring empty entries.
+    pub fn parse_list(s: &str) -> Result<Vec<SubscriberTag>, String> {
+        s.split(',')
+            .filter(|tag| !tag.trim().is_empty())
+            .map(SubscriberTag::parse)
+            .collect()
+    }
+}
+
+impl AsRef<str> for SubscriberTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriberTag;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn tags_are_lowercased_and_trimmed() {
+        let tag = SubscriberTag::parse(" Beta ").unwrap();
+        assert_eq!(tag.as_ref(), "beta");
+    }
+
+    #[test]
+    fn a_32_character_long_tag_is_valid() {
+        assert_ok!(SubscriberTag::parse(&"a".repeat(32)));
+    }
+
+    #[test]
+    fn a_tag_longer_than_32_characters_is_rejected() {
+        assert_err!(SubscriberTag::parse(&"a".repeat(33)));
+    }
+
+    #[test]
+    fn empty_tags_are_rejected() {
+        assert_err!(SubscriberTag::parse(" "));
+    }
+
+    #[test]
+    fn tags_containing_an_invalid_character_are_rejected() {
+        for tag in ["early adopter", "beta!", "<b>", "ё"] {
+            assert_err!(SubscriberTag::parse(tag));
+        }
+    }
+
+    #[test]
+    fn empty_entries_of_a_list_are_ignored() {
+        let tags = SubscriberTag::parse_list("beta, ,early-adopter,").unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[1].as_ref(), "early-adopter");
+    }
+}
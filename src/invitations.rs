@@ -0,0 +1,88 @@
+//! Invitations sent to prospective collaborators. They are stored in
+//! `collabolator_activation_tokens` until the account is activated.
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgExecutor, PgPool};
+
+/// Activation links stop working this long after they were sent, unless the
+/// admin chose otherwise.
+pub const TOKEN_EXPIRE_TIMEOUT_IN_DAYS: u8 = 3;
+/// The longest an admin can keep an activation link working.
+pub const MAX_TOKEN_EXPIRE_TIMEOUT_IN_DAYS: u8 = 30;
+
+pub fn invitation_ttl() -> Duration {
+    Duration::days(TOKEN_EXPIRE_TIMEOUT_IN_DAYS.into())
+}
+
+pub struct PendingInvitation {
+    pub email: String,
+    /// Given to the account once activated.
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+    pub valid_days: i16,
+}
+
+impl PendingInvitation {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.created_at + Duration::days(self.valid_days.into())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        let now = Utc::now();
+        self.created_at > now || now >= self.expires_at()
+    }
+}
+
+/// Invitations that were not accepted yet, expired ones included, newest first.
+#[tracing::instrument(name = "Get pending invitations", skip(pool))]
+pub async fn get_pending_invitations(
+    pool: &PgPool,
+) -> Result<Vec<PendingInvitation>, anyhow::Error> {
+    let invitations = sqlx::query_as!(
+        PendingInvitation,
+        r#"
+        SELECT email, role, created_at, valid_days
+        FROM collabolator_activation_tokens
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the pending invitations.")?;
+    Ok(invitations)
+}
+
+/// The invitation the activation link with `token` was sent for, expired or not.
+#[tracing::instrument(name = "Get an invitation by token", skip_all)]
+pub async fn get_invitation_by_token(
+    executor: impl PgExecutor<'_>,
+    token: &str,
+) -> Result<Option<PendingInvitation>, anyhow::Error> {
+    let invitation = sqlx::query_as!(
+        PendingInvitation,
+        r#"
+        SELECT email, role, created_at, valid_days
+        FROM collabolator_activation_tokens
+        WHERE token = $1
+        "#,
+        token
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to fetch the invitation.")?;
+    Ok(invitation)
+}
+
+/// Invalidate the activation link sent to `email`. Returns `false` if there
+/// was no invitation for it.
+#[tracing::instrument(name = "Revoke an invitation", skip(pool))]
+pub async fn delete_invitation(pool: &PgPool, email: &str) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM collabolator_activation_tokens WHERE email = $1",
+        email
+    )
+    .execute(pool)
+    .await
+    .context("Failed to delete the invitation.")?;
+    Ok(result.rows_affected() > 0)
+}
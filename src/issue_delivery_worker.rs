@@ -2,6 +2,8 @@ use std::time::Duration;
 
 use crate::domain::Email;
 use crate::email_client::EmailClient;
+use crate::newsletter_issues::notify_if_issue_delivered;
+use crate::routes::preferences_link;
 use crate::{configuration::Settings, startup::get_connection_pool};
 use anyhow::Context;
 use chrono::{DateTime, Utc};
@@ -32,9 +34,13 @@ pub struct Task {
     execute_after: Option<DateTime<Utc>>,
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: String,
+) -> Result<(), anyhow::Error> {
     loop {
-        match try_execute_task(&pool, &email_client).await {
+        match try_execute_task(&pool, &email_client, &base_url).await {
             Ok(ExecutionOutcome::EmptyQueue) => {
                 tokio::time::sleep(Duration::from_secs(10)).await;
             }
@@ -50,7 +56,12 @@ async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyh
 pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
     let connection_pool = get_connection_pool(&configuration.database);
     let email_client = configuration.email_client.client();
-    worker_loop(connection_pool, email_client).await
+    worker_loop(
+        connection_pool,
+        email_client,
+        configuration.application.base_url,
+    )
+    .await
 }
 
 #[tracing::instrument(
@@ -65,6 +76,7 @@ pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), any
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    base_url: &str,
 ) -> Result<ExecutionOutcome, WorkerError> {
     let task = dequeue_task(pool).await?;
     if task.is_none() {
@@ -80,6 +92,10 @@ pub async fn try_execute_task(
     match Email::parse(task.subscriber_email.clone()) {
         Ok(email) => {
             let issue = get_issue(pool, task.newsletter_issue_id).await?;
+            let issue = match get_preferences_token(pool, &task.subscriber_email).await? {
+                Some(token) => issue.with_preferences_link(&preferences_link(base_url, &token)),
+                None => issue,
+            };
             match email_client
                 .send_email(
                     &email,
@@ -161,6 +177,7 @@ async fn delete_task(mut transaction: PgTransaction, task: &Task) -> Result<(),
         task.subscriber_email
     );
     transaction.execute(query).await?;
+    notify_if_issue_delivered(&mut transaction, task.newsletter_issue_id).await?;
     transaction.commit().await?;
     Ok(())
 }
@@ -171,6 +188,22 @@ struct NewsletterIssue {
     html_content: String,
 }
 
+impl NewsletterIssue {
+    fn with_preferences_link(self, link: &str) -> Self {
+        Self {
+            html_content: format!(
+                "{}<hr /><p><a href=\"{}\">Manage your subscription preferences</a></p>",
+                self.html_content, link
+            ),
+            text_content: format!(
+                "{}\n\n--\nManage your subscription preferences: {}",
+                self.text_content, link
+            ),
+            ..self
+        }
+    }
+}
+
 #[tracing::instrument(skip_all)]
 async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
     let issue = sqlx::query_as!(
@@ -188,6 +221,20 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
     Ok(issue)
 }
 
+#[tracing::instrument(skip_all)]
+async fn get_preferences_token(
+    pool: &PgPool,
+    subscriber_email: &str,
+) -> Result<Option<String>, anyhow::Error> {
+    let token = sqlx::query_scalar!(
+        "SELECT preferences_token FROM subscriptions WHERE email = $1",
+        subscriber_email
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(token)
+}
+
 #[tracing::instrument(skip_all)]
 async fn mark_as_error(mut transaction: PgTransaction, task: &Task) -> Result<(), anyhow::Error> {
     let backoff_delay =
@@ -0,0 +1,278 @@
+//! Issues written by users who can only draft them are submitted for review:
+//! users with the `PublishIssues` permission get an email, and approve the
+//! issue - which sends it - or reject it with a comment for its author.
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{Executor, PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::authentication::Permission;
+use crate::domain::{Email, MembershipStatus, Segment, SubscriberTag};
+use crate::email_client::EmailClient;
+use crate::newsletter_issues::enqueue_delivery_tasks;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReviewError {
+    #[error("The issue is no longer waiting for a review.")]
+    NotPending,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+/// Store an issue to be sent to the members of `list_id` within `segment`
+/// once approved.
+#[tracing::instrument(skip_all)]
+pub async fn submit_issue_for_review(
+    transaction: &mut Transaction<'_, Postgres>,
+    author_id: Uuid,
+    list_id: Uuid,
+    title: &str,
+    text_content: &str,
+    html_content: &str,
+    segment: &Segment,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id, title, text_content, html_content, list_id,
+            review_status, author_id, submitted_at,
+            segment_include_tags, segment_exclude_tags,
+            segment_subscribed_after, segment_subscribed_before
+        )
+        VALUES ($1, $2, $3, $4, $5, 'pending_review', $6, now(), $7, $8, $9, $10)
+        "#,
+        newsletter_issue_id,
+        title,
+        text_content,
+        html_content,
+        list_id,
+        author_id,
+        &segment.include_tags(),
+        &segment.exclude_tags(),
+        segment.subscribed_after,
+        segment.subscribed_before,
+    );
+    transaction.execute(query).await?;
+    Ok(newsletter_issue_id)
+}
+
+pub struct IssueUnderReview {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub text_content: String,
+    pub list_name: String,
+    /// `None` if the author's account has been deleted since.
+    pub author: Option<String>,
+    pub submitted_at: DateTime<Utc>,
+    pub include_tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+    pub subscribed_after: Option<NaiveDate>,
+    pub subscribed_before: Option<NaiveDate>,
+}
+
+/// Oldest submissions first.
+#[tracing::instrument(name = "Get issues under review", skip(executor))]
+pub async fn get_issues_under_review(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<IssueUnderReview>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        IssueUnderReview,
+        r#"
+        SELECT i.newsletter_issue_id, i.title, i.text_content, l.name AS list_name,
+            u.username AS "author?", i.submitted_at AS "submitted_at!",
+            i.segment_include_tags AS include_tags, i.segment_exclude_tags AS exclude_tags,
+            i.segment_subscribed_after AS subscribed_after,
+            i.segment_subscribed_before AS subscribed_before
+        FROM newsletter_issues i
+        JOIN lists l ON l.list_id = i.list_id
+        LEFT JOIN users u ON u.user_id = i.author_id
+        WHERE i.review_status = 'pending_review'
+        ORDER BY i.submitted_at
+        "#
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to fetch the issues under review.")?;
+    Ok(issues)
+}
+
+pub struct SubmittedIssue {
+    pub title: String,
+    pub review_status: String,
+    pub submitted_at: DateTime<Utc>,
+    pub reviewer: Option<String>,
+    pub review_comment: Option<String>,
+}
+
+/// What `author_id` submitted lately, and how it was reviewed.
+#[tracing::instrument(name = "Get submitted issues", skip(executor))]
+pub async fn get_submitted_issues(
+    executor: impl PgExecutor<'_>,
+    author_id: Uuid,
+    limit: i64,
+) -> Result<Vec<SubmittedIssue>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        SubmittedIssue,
+        r#"
+        SELECT i.title, i.review_status, i.submitted_at AS "submitted_at!",
+            u.username AS "reviewer?", i.review_comment
+        FROM newsletter_issues i
+        LEFT JOIN users u ON u.user_id = i.reviewed_by
+        WHERE i.author_id = $1 AND i.submitted_at IS NOT NULL
+        ORDER BY i.submitted_at DESC
+        LIMIT $2
+        "#,
+        author_id,
+        limit
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to fetch the submitted issues.")?;
+    Ok(issues)
+}
+
+/// Send the issue to the audience chosen by its author. Returns the number
+/// of recipients.
+#[tracing::instrument(name = "Approve an issue", skip(pool))]
+pub async fn approve_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    reviewer_id: Uuid,
+) -> Result<u64, ReviewError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    // The lock makes sure that the issue is approved (and sent) only once.
+    let issue = sqlx::query!(
+        r#"
+        SELECT list_id, segment_include_tags, segment_exclude_tags,
+            segment_subscribed_after, segment_subscribed_before
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1 AND review_status = 'pending_review'
+        FOR UPDATE
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to fetch the issue under review.")?
+    .ok_or(ReviewError::NotPending)?;
+
+    let parse_tags = |tags: &[String]| {
+        tags.iter()
+            .map(|t| SubscriberTag::parse(t))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow::anyhow!(e))
+            .context("The issue was submitted with an invalid tag.")
+    };
+    let segment = Segment {
+        include_tags: parse_tags(&issue.segment_include_tags)?,
+        exclude_tags: parse_tags(&issue.segment_exclude_tags)?,
+        subscribed_after: issue.segment_subscribed_after,
+        subscribed_before: issue.segment_subscribed_before,
+        status: MembershipStatus::Confirmed,
+    };
+    let n_recipients = enqueue_delivery_tasks(
+        &mut transaction,
+        newsletter_issue_id,
+        issue.list_id,
+        &segment,
+    )
+    .await
+    .context("Failed to enqueue delivery tasks")?;
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET review_status = 'published', published_at = now(),
+            reviewed_by = $2, reviewed_at = now()
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+        reviewer_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to mark the issue as published.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the approval.")?;
+    Ok(n_recipients)
+}
+
+/// The issue is kept, with `comment`, for its author to see.
+#[tracing::instrument(name = "Reject an issue", skip(pool, comment))]
+pub async fn reject_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    reviewer_id: Uuid,
+    comment: &str,
+) -> Result<(), ReviewError> {
+    let updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET review_status = 'rejected', review_comment = $3,
+            reviewed_by = $2, reviewed_at = now()
+        WHERE newsletter_issue_id = $1 AND review_status = 'pending_review'
+        "#,
+        newsletter_issue_id,
+        reviewer_id,
+        comment
+    )
+    .execute(pool)
+    .await
+    .context("Failed to reject the issue.")?;
+    match updated.rows_affected() {
+        0 => Err(ReviewError::NotPending),
+        _ => Ok(()),
+    }
+}
+
+/// Email every active user who can approve the issue, skipping those
+/// without an email address.
+#[tracing::instrument(name = "Notify reviewers", skip(pool, email_client, base_url))]
+pub async fn notify_reviewers(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    title: &str,
+) -> Result<(), anyhow::Error> {
+    let emails = sqlx::query_scalar!(
+        r#"
+        SELECT u.email AS "email!" FROM users u
+        JOIN role_permissions p ON p.role = u.role
+        WHERE p.permission = $1 AND u.deactivated_at IS NULL AND u.email IS NOT NULL
+        "#,
+        Permission::PublishIssues.as_str()
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the reviewers.")?;
+
+    let review_link = format!("{base_url}/admin/reviews");
+    let plain_body = format!(
+        "\"{title}\" has been submitted for review.\nVisit {review_link} to approve or reject it."
+    );
+    let html_body = format!(
+        "\"{}\" has been submitted for review.<br />\
+        Click <a href=\"{review_link}\">here</a> to approve or reject it.",
+        htmlescape::encode_minimal(title)
+    );
+    for email in emails {
+        let Ok(email) = Email::parse(email) else {
+            continue;
+        };
+        email_client
+            .send_email(
+                &email,
+                "An issue waits for your review",
+                &html_body,
+                &plain_body,
+            )
+            .await
+            .with_context(|| format!("Failed to notify {}.", email.as_ref()))?;
+    }
+    Ok(())
+}
@@ -1,11 +1,25 @@
+pub mod account_email_worker;
+pub mod audit;
 pub mod authentication;
+pub mod bot_protection;
 pub mod configuration;
 pub mod domain;
 pub mod email_client;
 pub mod idempotency;
+pub mod invitation_cleanup_worker;
+pub mod invitations;
 pub mod issue_delivery_worker;
+pub mod issue_reviews;
+pub mod mailing_lists;
+pub mod newsletter_issues;
+pub mod openapi;
+pub mod rate_limiting;
 pub mod routes;
 pub mod session_state;
 pub mod startup;
+pub mod subscriber_data;
+pub mod subscription_cleanup_worker;
 pub mod telemetry;
 pub mod utils;
+pub mod webhook_dispatcher;
+pub mod webhooks;
@@ -0,0 +1,49 @@
+use sqlx::PgExecutor;
+use uuid::{uuid, Uuid};
+
+/// The list that existed before multiple lists were supported. It is used
+/// whenever a subscription or an issue does not name a list explicitly.
+pub const DEFAULT_LIST_ID: Uuid = uuid!("6b1f7a52-4c0e-4d5e-9a3b-1f2e8c7d9a01");
+
+pub struct MailingList {
+    pub list_id: Uuid,
+    pub name: String,
+}
+
+impl MailingList {
+    /// Render the list as an `<option>` of a `<select>` element.
+    pub fn html_option(&self, selected: bool) -> String {
+        format!(
+            r#"<option value="{}"{}>{}</option>"#,
+            self.list_id,
+            if selected { " selected" } else { "" },
+            htmlescape::encode_minimal(&self.name)
+        )
+    }
+}
+
+#[tracing::instrument(name = "Get mailing lists", skip_all)]
+pub async fn get_mailing_lists(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<MailingList>, sqlx::Error> {
+    sqlx::query_as!(
+        MailingList,
+        "SELECT list_id, name FROM lists ORDER BY created_at, name"
+    )
+    .fetch_all(executor)
+    .await
+}
+
+#[tracing::instrument(name = "Get mailing list", skip(executor))]
+pub async fn get_mailing_list(
+    executor: impl PgExecutor<'_>,
+    list_id: Uuid,
+) -> Result<Option<MailingList>, sqlx::Error> {
+    sqlx::query_as!(
+        MailingList,
+        "SELECT list_id, name FROM lists WHERE list_id = $1",
+        list_id
+    )
+    .fetch_optional(executor)
+    .await
+}
@@ -1,11 +1,14 @@
 use std::fmt::{Debug, Display};
 use tokio::task::JoinError;
 use zero2prod::{
+    account_email_worker,
     configuration::get_configuration,
-    idempotency,
+    idempotency, invitation_cleanup_worker,
     issue_delivery_worker::run_worker_until_stopped,
     startup::Application,
+    subscription_cleanup_worker,
     telemetry::{get_subscriber, init_subscriber},
+    webhook_dispatcher,
 };
 
 #[tokio::main]
@@ -18,12 +21,28 @@ async fn main() -> Result<(), anyhow::Error> {
     let application_task = tokio::spawn(application.run_until_stopped());
     let worker_task = tokio::spawn(run_worker_until_stopped(configuration.clone()));
     let idempotency_keys_remover_task = tokio::spawn(idempotency::run_worker_until_stopped(
-        configuration.database,
+        configuration.database.clone(),
     ));
+    let expired_invitations_remover_task = tokio::spawn(
+        invitation_cleanup_worker::run_worker_until_stopped(configuration.database.clone()),
+    );
+    let webhook_dispatcher_task = tokio::spawn(webhook_dispatcher::run_worker_until_stopped(
+        configuration.clone(),
+    ));
+    let account_email_task = tokio::spawn(account_email_worker::run_worker_until_stopped(
+        configuration.clone(),
+    ));
+    let stale_subscriptions_remover_task = tokio::spawn(
+        subscription_cleanup_worker::run_worker_until_stopped(configuration),
+    );
     tokio::select! {
         o = application_task => report_exit("API", o),
         o = worker_task => report_exit("Newsletter email delivery worker", o),
-        o = idempotency_keys_remover_task => report_exit("Idempotency keys remover worker", o)
+        o = idempotency_keys_remover_task => report_exit("Idempotency keys remover worker", o),
+        o = expired_invitations_remover_task => report_exit("Expired invitations remover worker", o),
+        o = webhook_dispatcher_task => report_exit("Webhook dispatcher worker", o),
+        o = account_email_task => report_exit("Account email worker", o),
+        o = stale_subscriptions_remover_task => report_exit("Stale subscriptions remover worker", o)
     };
 
     Ok(())
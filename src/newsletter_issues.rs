@@ -0,0 +1,221 @@
+//! Newsletter issues and their delivery - shared by the publishing form and
+//! the JSON API.
+use sqlx::postgres::PgArguments;
+use sqlx::{Arguments, Executor, PgExecutor, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::Segment;
+use crate::webhooks::{enqueue_webhook_event, WebhookEvent};
+
+#[tracing::instrument(skip_all)]
+pub async fn insert_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    author_id: Uuid,
+    list_id: Uuid,
+    title: &str,
+    text_content: &str,
+    html_content: &str,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            published_at,
+            list_id,
+            author_id
+        )
+        VALUES ($1, $2, $3, $4, now(), $5, $6)
+        "#,
+        newsletter_issue_id,
+        title,
+        text_content,
+        html_content,
+        list_id,
+        author_id
+    );
+    transaction.execute(query).await?;
+    Ok(newsletter_issue_id)
+}
+
+/// The members of list `$1` that fall within the segment bound to `$2`..`$6`
+/// by `segment_arguments` - who an issue sent to it goes to. Shared by the
+/// recipient count and the delivery queue, so that they cannot differ.
+const SEGMENT_MEMBERS: &str = r#"
+    FROM subscriptions s
+    JOIN list_memberships m ON m.subscriber_id = s.id
+    WHERE
+        m.list_id = $1 AND
+        m.status = $6 AND
+        ($6 <> 'confirmed' OR s.status = 'confirmed') AND
+        (
+            cardinality($2::text[]) = 0 OR
+            EXISTS (
+                SELECT 1 FROM subscriber_tags t
+                WHERE t.subscriber_id = s.id AND t.tag = ANY($2)
+            )
+        ) AND
+        NOT EXISTS (
+            SELECT 1 FROM subscriber_tags t
+            WHERE t.subscriber_id = s.id AND t.tag = ANY($3)
+        ) AND
+        ($4::timestamptz IS NULL OR m.subscribed_at >= $4) AND
+        ($5::timestamptz IS NULL OR m.subscribed_at < $5)
+"#;
+
+fn segment_arguments(list_id: Uuid, segment: &Segment) -> Result<PgArguments, sqlx::Error> {
+    let mut arguments = PgArguments::default();
+    arguments.add(list_id).map_err(sqlx::Error::Encode)?;
+    arguments
+        .add(segment.include_tags())
+        .map_err(sqlx::Error::Encode)?;
+    arguments
+        .add(segment.exclude_tags())
+        .map_err(sqlx::Error::Encode)?;
+    arguments
+        .add(segment.subscribed_after())
+        .map_err(sqlx::Error::Encode)?;
+    arguments
+        .add(segment.subscribed_before())
+        .map_err(sqlx::Error::Encode)?;
+    arguments
+        .add(segment.status.as_str())
+        .map_err(sqlx::Error::Encode)?;
+    Ok(arguments)
+}
+
+/// How many subscribers an issue sent to `segment` of the list goes to.
+#[tracing::instrument(skip(executor))]
+pub async fn count_segment_recipients(
+    executor: impl PgExecutor<'_>,
+    list_id: Uuid,
+    segment: &Segment,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar_with(
+        &format!("SELECT COUNT(*) {SEGMENT_MEMBERS}"),
+        segment_arguments(list_id, segment)?,
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Queue the issue for every recipient of `segment`, and record how many
+/// recipients that makes. An issue nobody receives is delivered right away.
+#[tracing::instrument(skip_all)]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    list_id: Uuid,
+    segment: &Segment,
+) -> Result<u64, anyhow::Error> {
+    let mut arguments = segment_arguments(list_id, segment)?;
+    arguments
+        .add(newsletter_issue_id)
+        .map_err(sqlx::Error::Encode)?;
+    let query = format!(
+        "INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email) \
+        SELECT $7, s.email {SEGMENT_MEMBERS}"
+    );
+    let n_recipients = transaction
+        .execute(sqlx::query_with(&query, arguments))
+        .await?
+        .rows_affected();
+    transaction
+        .execute(sqlx::query!(
+            "UPDATE newsletter_issues SET recipient_count = $2 WHERE newsletter_issue_id = $1",
+            newsletter_issue_id,
+            n_recipients as i32
+        ))
+        .await?;
+    if n_recipients == 0 {
+        notify_if_issue_delivered(transaction, newsletter_issue_id).await?;
+    }
+    Ok(n_recipients)
+}
+
+/// Emit the `issue.delivered` webhook event once the last task of the issue
+/// is gone - delivered, erased along with its subscriber, or never queued
+/// because nobody matched. The issue row is locked so that, of the
+/// transactions deleting the last tasks concurrently, exactly one sees an
+/// empty queue.
+#[tracing::instrument(skip(transaction))]
+pub async fn notify_if_issue_delivered(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    let recipients = sqlx::query_scalar!(
+        r#"
+        SELECT recipient_count
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+    let remaining = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM issue_delivery_queue WHERE newsletter_issue_id = $1
+        ) AS "remaining!"
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+    if !remaining {
+        enqueue_webhook_event(
+            transaction,
+            WebhookEvent::IssueDelivered {
+                newsletter_issue_id,
+                recipients,
+            },
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct DeliveryStatus {
+    /// `None` for issues published before the count was recorded.
+    pub recipients: Option<i32>,
+    /// Emails still waiting in the queue, including those being retried.
+    pub pending: i64,
+    /// Emails whose delivery failed at least once and is being retried.
+    pub retrying: i64,
+    pub completed: bool,
+}
+
+/// Delivered emails leave the queue, so what is still in it is what is left.
+#[tracing::instrument(skip(executor))]
+pub async fn get_delivery_status(
+    executor: impl PgExecutor<'_>,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<DeliveryStatus>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            i.recipient_count,
+            COUNT(q.subscriber_email) AS "pending!",
+            COUNT(q.subscriber_email) FILTER (WHERE q.n_retries > 0) AS "retrying!"
+        FROM newsletter_issues i
+        LEFT JOIN issue_delivery_queue q ON q.newsletter_issue_id = i.newsletter_issue_id
+        WHERE i.newsletter_issue_id = $1
+        GROUP BY i.newsletter_issue_id
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(row.map(|r| DeliveryStatus {
+        recipients: r.recipient_count,
+        pending: r.pending,
+        retrying: r.retrying,
+        completed: r.pending == 0,
+    }))
+}
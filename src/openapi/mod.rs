@@ -0,0 +1,285 @@
+//! The OpenAPI 3 document served on `/api/openapi.json`.
+//!
+//! Every route group describes its own routes next to the handlers, reusing
+//! the handlers' extractor types for the request schemas - see
+//! `routes::openapi_document`.
+
+mod schema;
+
+pub use schema::{password, schema_of};
+
+use actix_web::http::Method;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use crate::authentication::{ApiScope, Permission};
+
+#[derive(Default)]
+pub struct OpenApi {
+    operations: Vec<Operation>,
+}
+
+impl OpenApi {
+    pub fn add(&mut self, operation: Operation) -> &mut Self {
+        self.operations.push(operation);
+        self
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut paths = Map::new();
+        for operation in &self.operations {
+            let path = paths
+                .entry(operation.path)
+                .or_insert_with(|| Value::Object(Map::new()));
+            path[operation.method.as_str().to_lowercase()] = operation.to_json();
+        }
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "paths": paths,
+            "components": {
+                "securitySchemes": {
+                    "session": { "type": "apiKey", "in": "cookie", "name": "id" },
+                    "token": { "type": "http", "scheme": "bearer" },
+                },
+                "schemas": {
+                    "Error": {
+                        "type": "object",
+                        "properties": {
+                            "error": {
+                                "type": "object",
+                                "properties": {
+                                    "code": { "type": "string" },
+                                    "message": { "type": "string" },
+                                },
+                                "required": ["code", "message"],
+                            },
+                        },
+                        "required": ["error"],
+                    },
+                },
+            },
+        })
+    }
+}
+
+/// One method on one path, as registered in `startup::run`.
+pub struct Operation {
+    method: Method,
+    path: &'static str,
+    summary: &'static str,
+    parameters: Vec<Value>,
+    request_body: Option<Value>,
+    responses: Map<String, Value>,
+    security: Option<Value>,
+    scope: Option<ApiScope>,
+    permission: Option<Permission>,
+}
+
+impl Operation {
+    pub fn get(path: &'static str, summary: &'static str) -> Self {
+        Self::new(Method::GET, path, summary)
+    }
+
+    pub fn post(path: &'static str, summary: &'static str) -> Self {
+        Self::new(Method::POST, path, summary)
+    }
+
+    fn new(method: Method, path: &'static str, summary: &'static str) -> Self {
+        let parameters = path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .collect();
+        Self {
+            method,
+            path,
+            summary,
+            parameters,
+            request_body: None,
+            responses: Map::new(),
+            security: None,
+            scope: None,
+            permission: None,
+        }
+    }
+
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Query string parameters, from the fields of `T`.
+    pub fn query<T: JsonSchema>(mut self) -> Self {
+        let schema = schema_of::<T>();
+        let required = schema["required"].as_array().cloned().unwrap_or_default();
+        if let Some(properties) = schema["properties"].as_object() {
+            self.parameters
+                .extend(properties.iter().map(|(name, schema)| {
+                    json!({
+                        "name": name,
+                        "in": "query",
+                        "required": required.iter().any(|r| r == name),
+                        "schema": schema,
+                    })
+                }));
+        }
+        self
+    }
+
+    /// A url-encoded form body, from the fields of `T`.
+    pub fn form<T: JsonSchema>(self) -> Self {
+        self.form_schema(schema_of::<T>())
+    }
+
+    /// A url-encoded form body that isn't deserialized into a struct.
+    pub fn form_schema(mut self, schema: Value) -> Self {
+        self.request_body = Some(json!({
+            "required": true,
+            "content": { "application/x-www-form-urlencoded": { "schema": schema } },
+        }));
+        self
+    }
+
+    /// A JSON body, from the fields of `T`.
+    pub fn json<T: JsonSchema>(mut self) -> Self {
+        self.request_body = Some(json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema_of::<T>() } },
+        }));
+        self
+    }
+
+    pub fn html(self) -> Self {
+        self.response(
+            200,
+            json!({
+                "description": "An HTML page",
+                "content": { "text/html": { "schema": { "type": "string" } } },
+            }),
+        )
+    }
+
+    pub fn redirect(self, description: &str) -> Self {
+        self.response(
+            303,
+            json!({
+                "description": description,
+                "headers": { "Location": { "schema": { "type": "string" } } },
+            }),
+        )
+    }
+
+    pub fn respond(self, status: u16, description: &str) -> Self {
+        self.response(status, json!({ "description": description }))
+    }
+
+    pub fn json_response<T: JsonSchema>(self, status: u16, description: &str) -> Self {
+        self.response(
+            status,
+            json!({
+                "description": description,
+                "content": { "application/json": { "schema": schema_of::<T>() } },
+            }),
+        )
+    }
+
+    /// An error of the JSON API.
+    pub fn error(self, status: u16, description: &str) -> Self {
+        self.response(
+            status,
+            json!({
+                "description": description,
+                "content": {
+                    "application/json": {
+                        "schema": { "$ref": "#/components/schemas/Error" },
+                    },
+                },
+            }),
+        )
+    }
+
+    /// Only for logged in users - anonymous ones are sent to `/login`.
+    pub fn session(mut self) -> Self {
+        self.security = Some(json!([{ "session": [] }]));
+        self
+    }
+
+    /// Only for users whose role grants `permission` - others get a 403.
+    pub fn permission(mut self, permission: Permission) -> Self {
+        self.permission = Some(permission);
+        // API routes already describe their 403 along with the scope.
+        if self.responses.contains_key("403") {
+            return self;
+        }
+        self.respond(403, "The user's role lacks the permission")
+    }
+
+    /// Authenticated with an API token.
+    pub fn bearer(mut self) -> Self {
+        self.security = Some(json!([{ "token": [] }]));
+        self.error(401, "The bearer token is missing, invalid or expired")
+    }
+
+    /// Only for API tokens with `scope`.
+    pub fn scope(mut self, scope: ApiScope) -> Self {
+        self.scope = Some(scope);
+        self.error(403, "The token lacks the scope, or its user the role")
+    }
+
+    /// Retried requests with the same `Idempotency-Key` get the first response.
+    pub fn idempotent(mut self) -> Self {
+        self.parameters.push(json!({
+            "name": "Idempotency-Key",
+            "in": "header",
+            "required": true,
+            "schema": { "type": "string" },
+        }));
+        self
+    }
+
+    fn response(mut self, status: u16, response: Value) -> Self {
+        self.responses.insert(status.to_string(), response);
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let mut operation = json!({
+            "summary": self.summary,
+            "responses": self.responses,
+        });
+        if !self.parameters.is_empty() {
+            operation["parameters"] = self.parameters.clone().into();
+        }
+        if let Some(body) = &self.request_body {
+            operation["requestBody"] = body.clone();
+        }
+        if let Some(security) = &self.security {
+            operation["security"] = security.clone();
+        }
+        if let Some(scope) = self.scope {
+            operation["x-scopes"] = json!([scope.as_str()]);
+        }
+        if let Some(permission) = self.permission {
+            operation["x-permission"] = permission.as_str().into();
+        }
+        operation
+    }
+}
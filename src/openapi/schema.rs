@@ -0,0 +1,101 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde_json::Value;
+
+/// The JSON schema of `T`, as derived by `schemars::JsonSchema`.
+///
+/// Every subschema is inlined, so the document has no `components` to refer to.
+pub fn schema_of<T: JsonSchema>() -> Value {
+    let mut generator = SchemaSettings::openapi3()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    let schema = generator.subschema_for::<T>();
+    serde_json::to_value(schema).expect("A JSON schema is always serializable")
+}
+
+/// The schema of a `Secret<String>` field, to use with
+/// `#[schemars(schema_with = "crate::openapi::password")]`.
+pub fn password(_: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        format: Some("password".into()),
+        ..Default::default()
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::schema_of;
+    use secrecy::Secret;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    #[allow(dead_code)]
+    struct Inner {
+        tag: String,
+    }
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    #[allow(dead_code)]
+    struct Outer {
+        id: Uuid,
+        name: Option<String>,
+        #[serde(default)]
+        website: String,
+        #[serde(flatten)]
+        inner: Inner,
+        #[schemars(schema_with = "crate::openapi::password")]
+        password: Secret<String>,
+    }
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    #[allow(dead_code)]
+    struct Renamed {
+        list_id: Uuid,
+        #[serde(rename = "max")]
+        page_limit: u32,
+    }
+
+    #[test]
+    fn fields_are_required_unless_optional_or_defaulted() {
+        let schema = schema_of::<Outer>();
+        let mut required: Vec<_> = schema["required"].as_array().unwrap().clone();
+        required.sort_by_key(|r| r.to_string());
+        assert_eq!(required, [json!("id"), json!("password"), json!("tag")]);
+        assert_eq!(schema["properties"]["name"]["nullable"], true);
+        assert_eq!(schema["properties"]["id"]["format"], "uuid");
+    }
+
+    #[test]
+    fn flattened_fields_are_inlined() {
+        let schema = schema_of::<Outer>();
+        assert_eq!(schema["properties"]["tag"]["type"], "string");
+        assert!(schema["properties"].get("inner").is_none());
+    }
+
+    #[test]
+    fn secrets_are_passwords() {
+        let schema = schema_of::<Outer>();
+        assert_eq!(
+            schema["properties"]["password"],
+            json!({ "type": "string", "format": "password" })
+        );
+    }
+
+    #[test]
+    fn fields_are_named_as_serde_names_them() {
+        let schema = schema_of::<Renamed>();
+        let mut names: Vec<_> = schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        names.sort();
+        assert_eq!(names, ["listId", "max"]);
+    }
+}
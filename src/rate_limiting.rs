@@ -0,0 +1,233 @@
+//! Sliding-window rate limiting backed by Redis.
+//!
+//! Every accepted request is recorded in a sorted set per key, scored by its
+//! arrival time in milliseconds. A request is rejected with `429 Too Many
+//! Requests` if any of its sets already holds the maximum number of entries
+//! younger than the window.
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{self, ContentType};
+use actix_web::http::Method;
+use actix_web::{web, HttpResponse};
+use actix_web_lab::middleware::Next;
+use anyhow::Context;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+use crate::configuration::RateLimit;
+use crate::utils::{client_ip, e500};
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    connection: ConnectionManager,
+    key_prefix: String,
+}
+
+impl RateLimiter {
+    pub async fn new(
+        redis_uri: &Secret<String>,
+        key_prefix: String,
+    ) -> Result<Self, anyhow::Error> {
+        let client = redis::Client::open(redis_uri.expose_secret().as_str())?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(Self {
+            connection,
+            key_prefix,
+        })
+    }
+
+    /// Record a request against each of `limits` - a key and how many
+    /// requests it may make within `window`. If any of them is exceeded the
+    /// request is taken back out of every window, so a rejected request
+    /// never counts against the others, and the time until a slot frees up
+    /// is returned.
+    #[tracing::instrument(name = "Check rate limit", skip(self))]
+    pub async fn hit(
+        &self,
+        limits: &[(String, u32)],
+        window: Duration,
+    ) -> Result<Option<Duration>, redis::RedisError> {
+        let keys: Vec<String> = limits
+            .iter()
+            .map(|(key, _)| format!("{}:{}", self.key_prefix, key))
+            .collect();
+        let window_ms = window.as_millis() as i64;
+        let now_ms = Utc::now().timestamp_millis();
+        let member = format!("{}:{}", now_ms, Uuid::new_v4());
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for key in &keys {
+            pipe.cmd("ZREMRANGEBYSCORE")
+                .arg(key)
+                .arg("-inf")
+                .arg(now_ms - window_ms)
+                .ignore()
+                .cmd("ZADD")
+                .arg(key)
+                .arg(now_ms)
+                .arg(&member)
+                .ignore()
+                .cmd("ZCARD")
+                .arg(key)
+                .cmd("ZRANGE")
+                .arg(key)
+                .arg(0)
+                .arg(0)
+                .arg("WITHSCORES")
+                .cmd("PEXPIRE")
+                .arg(key)
+                .arg(window_ms)
+                .ignore();
+        }
+        let mut connection = self.connection.clone();
+        let windows: Vec<(u32, Vec<(String, f64)>)> = pipe.query_async(&mut connection).await?;
+
+        let retry_after_ms = windows
+            .iter()
+            .zip(limits)
+            .filter(|((n_requests, _), (_, max_requests))| n_requests > max_requests)
+            .map(|((_, oldest), _)| {
+                let oldest_ms = oldest
+                    .first()
+                    .map(|(_, score)| *score as i64)
+                    .unwrap_or(now_ms);
+                (oldest_ms + window_ms - now_ms).max(0)
+            })
+            .max();
+        let Some(retry_after_ms) = retry_after_ms else {
+            return Ok(None);
+        };
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for key in &keys {
+            pipe.cmd("ZREM").arg(key).arg(&member).ignore();
+        }
+        pipe.query_async::<_, ()>(&mut connection).await?;
+        Ok(Some(Duration::from_millis(retry_after_ms as u64)))
+    }
+}
+
+/// The limits applied to a group of routes, e.g. everything related to logging in.
+#[derive(Clone)]
+pub struct RateLimitGroup {
+    limiter: RateLimiter,
+    name: &'static str,
+    /// The form field holding the per-key identifier (an email or a username).
+    key_field: &'static str,
+    limits: RateLimit,
+}
+
+type MiddlewareFuture<B> =
+    Pin<Box<dyn Future<Output = Result<ServiceResponse<B>, actix_web::Error>>>>;
+
+impl RateLimitGroup {
+    pub fn new(
+        limiter: RateLimiter,
+        name: &'static str,
+        key_field: &'static str,
+        limits: RateLimit,
+    ) -> Self {
+        Self {
+            limiter,
+            name,
+            key_field,
+            limits,
+        }
+    }
+
+    /// A middleware function to be wrapped with `actix_web_lab::middleware::from_fn`.
+    /// Only form submissions are limited - safe methods (e.g. rendering the
+    /// form) always go through.
+    pub fn middleware<B: MessageBody + 'static>(
+        self,
+    ) -> impl Fn(ServiceRequest, Next<B>) -> MiddlewareFuture<B> {
+        move |req, next| Box::pin(enforce_rate_limit(self.clone(), req, next))
+    }
+}
+
+async fn enforce_rate_limit<B: MessageBody>(
+    group: RateLimitGroup,
+    mut req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, actix_web::Error> {
+    if req.method() == Method::GET || req.method() == Method::HEAD {
+        return next.call(req).await;
+    }
+
+    let client_ip = client_ip(req.request());
+    let mut keys = vec![(
+        format!("{}:ip:{}", group.name, client_ip),
+        group.limits.max_requests_per_ip,
+    )];
+    // The body has to be put back for the handler to read it.
+    let body = req.extract::<web::Bytes>().await?;
+    if let Some(value) = form_field(&body, group.key_field) {
+        keys.push((
+            format!("{}:{}:{}", group.name, group.key_field, value),
+            group.limits.max_requests_per_key,
+        ));
+    }
+    let (_, mut payload) = actix_http::h1::Payload::create(true);
+    payload.unread_data(body);
+    req.set_payload(payload.into());
+
+    let retry_after = group
+        .limiter
+        .hit(&keys, group.limits.window())
+        .await
+        .context("Failed to check the rate limit")
+        .map_err(e500)?;
+
+    match retry_after {
+        None => next.call(req).await,
+        Some(wait) => {
+            let response = HttpResponse::TooManyRequests()
+                .insert_header((
+                    header::RETRY_AFTER,
+                    wait.as_secs_f64().ceil().max(1.0) as u64,
+                ))
+                .content_type(ContentType::plaintext())
+                .body("Too many requests - try again later.");
+            let e = anyhow::anyhow!("The rate limit of '{}' was exceeded", group.name);
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
+
+/// The trimmed, lowercase value of a field of an urlencoded form, if present.
+fn form_field(body: &[u8], name: &str) -> Option<String> {
+    form_urlencoded::parse(body)
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.trim().to_lowercase())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rate_limiting::form_field;
+
+    #[test]
+    fn form_fields_are_normalised() {
+        let body = b"name=le%20guin&email=%20Ursula%40Gmail.com";
+        assert_eq!(
+            form_field(body, "email").as_deref(),
+            Some("ursula@gmail.com")
+        );
+    }
+
+    #[test]
+    fn missing_or_empty_form_fields_are_ignored() {
+        assert_eq!(form_field(b"name=le%20guin&email=", "email"), None);
+        assert_eq!(form_field(b"name=le%20guin", "email"), None);
+        assert_eq!(form_field(b"", "email"), None);
+    }
+}
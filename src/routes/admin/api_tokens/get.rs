@@ -0,0 +1,100 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{
+    authentication::{get_api_tokens, ApiScope, AuthenticatedUser, MAX_TOKEN_LIFETIME_IN_DAYS},
+    utils::e500,
+};
+
+pub async fn api_tokens_form(
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let now = Utc::now();
+    let mut tokens_html = String::new();
+    for token in get_api_tokens(&pool, user.user_id).await.map_err(e500)? {
+        let expiry = if token.expires_at > now {
+            format!("expires {}", token.expires_at.format("%Y-%m-%d"))
+        } else {
+            "expired".to_owned()
+        };
+        let last_used = match token.last_used_at {
+            Some(at) => format!("last used {}", at.format("%Y-%m-%d %H:%M UTC")),
+            None => "never used".to_owned(),
+        };
+        writeln!(
+            tokens_html,
+            r#"<li>
+                <form action="/admin/api-tokens/revoke" method="post">
+                    {name} ({scopes}) - created {created_at}, {expiry}, {last_used}
+                    <input type="hidden" name="token_id" value="{token_id}">
+                    <button type="submit">Revoke</button>
+                </form>
+            </li>"#,
+            name = htmlescape::encode_minimal(&token.name),
+            scopes = htmlescape::encode_minimal(&token.scopes.join(", ")),
+            created_at = token.created_at.format("%Y-%m-%d"),
+            token_id = token.token_id,
+        )
+        .unwrap();
+    }
+    if tokens_html.is_empty() {
+        tokens_html.push_str("<li>You have no API tokens.</li>");
+    }
+
+    let mut scopes_html = String::new();
+    for scope in ApiScope::ALL {
+        writeln!(
+            scopes_html,
+            r#"<label><input type="checkbox" name="scope" value="{0}"> {0}</label><br>"#,
+            scope.as_str()
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>API tokens</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <p>Your API tokens:</p>
+                    <ul>
+                        {tokens_html}
+                    </ul>
+                    <form action="/admin/api-tokens" method="post">
+                        <label>Name
+                            <input type="text" placeholder="What is it for?" name="name">
+                        </label>
+                        <br>
+                        {scopes_html}
+                        <label>Expires in (days)
+                            <input type="number" name="expires_in_days" value="30" min="1" max="{MAX_TOKEN_LIFETIME_IN_DAYS}">
+                        </label>
+                        <br>
+                        <button type="submit">Create token</button>
+                    </form>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+        )))
+}
@@ -0,0 +1,42 @@
+mod get;
+mod post;
+
+pub use get::api_tokens_form;
+pub use post::{issue_api_token, remove_api_token};
+
+use serde_json::json;
+
+use crate::authentication::ApiScope;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    let scopes: Vec<_> = ApiScope::ALL.iter().map(|s| s.as_str()).collect();
+    api.add(
+        Operation::get("/admin/api-tokens", "Personal API tokens")
+            .session()
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/api-tokens", "Issue a personal API token")
+            .session()
+            .form_schema(json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "expires_in_days": { "type": "integer", "minimum": 1 },
+                    "scope": {
+                        "type": "array",
+                        "items": { "type": "string", "enum": scopes },
+                    },
+                },
+                "required": ["name", "expires_in_days"],
+            }))
+            .redirect("Back to the tokens, with the new one in a flash message"),
+    )
+    .add(
+        Operation::post("/admin/api-tokens/revoke", "Revoke a personal API token")
+            .session()
+            .form::<post::RevokeFormData>()
+            .redirect("Back to the tokens"),
+    );
+}
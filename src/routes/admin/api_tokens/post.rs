@@ -0,0 +1,99 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use chrono::Duration;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::{
+        create_api_token, revoke_api_token, ApiScope, AuthenticatedUser, MAX_TOKEN_LIFETIME_IN_DAYS,
+    },
+    utils::{e400, e500, see_other},
+};
+
+/// The form repeats `scope` once per checked box, hence the list of pairs.
+#[tracing::instrument(name = "Issue an API token", skip(request, form, pool, user), fields(user_id=%user.user_id))]
+pub async fn issue_api_token(
+    request: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut name = "";
+    let mut expires_in_days = "";
+    let mut scopes = Vec::new();
+    for (key, value) in form.iter() {
+        match key.as_str() {
+            "name" => name = value.trim(),
+            "expires_in_days" => expires_in_days = value.trim(),
+            "scope" => scopes.push(
+                ApiScope::parse(value)
+                    .ok_or_else(|| e400(format!("'{value}' is not a known scope.")))?,
+            ),
+            _ => {}
+        }
+    }
+
+    if name.is_empty() {
+        FlashMessage::error("The token needs a name.").send();
+        return Ok(see_other("/admin/api-tokens"));
+    }
+    if scopes.is_empty() {
+        FlashMessage::error("Choose at least one scope.").send();
+        return Ok(see_other("/admin/api-tokens"));
+    }
+    let days = match expires_in_days.parse::<i64>() {
+        Ok(days) if (1..=MAX_TOKEN_LIFETIME_IN_DAYS).contains(&days) => days,
+        _ => {
+            FlashMessage::error(format!(
+                "Tokens must expire within 1 to {MAX_TOKEN_LIFETIME_IN_DAYS} days."
+            ))
+            .send();
+            return Ok(see_other("/admin/api-tokens"));
+        }
+    };
+
+    let (token_id, token) =
+        create_api_token(&pool, user.user_id, name, &scopes, Duration::days(days))
+            .await
+            .map_err(e500)?;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::ApiTokenCreated)
+        .target("api_token", token_id);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info(format!(
+        "Your new API token - copy it now, it will not be shown again: {token}"
+    ))
+    .send();
+    Ok(see_other("/admin/api-tokens"))
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct RevokeFormData {
+    token_id: Uuid,
+}
+
+#[tracing::instrument(name = "Remove an API token", skip(request, form, pool, user), fields(user_id=%user.user_id))]
+pub async fn remove_api_token(
+    request: HttpRequest,
+    form: web::Form<RevokeFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if revoke_api_token(&pool, user.user_id, form.token_id)
+        .await
+        .map_err(e500)?
+    {
+        let event = AuditEvent::new(&request, user.user_id, AuditAction::ApiTokenRevoked)
+            .target("api_token", form.token_id);
+        record_audit_event(pool.get_ref(), event)
+            .await
+            .map_err(e500)?;
+        FlashMessage::info("The API token has been revoked.").send();
+    } else {
+        FlashMessage::error("The API token does not exist.").send();
+    }
+    Ok(see_other("/admin/api-tokens"))
+}
@@ -0,0 +1,145 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use chrono::NaiveDate;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::audit::{get_audit_events, AuditAction, AuditFilter};
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+use crate::utils::e500;
+
+/// How many events the page shows at most.
+const MAX_EVENTS: i64 = 200;
+
+/// Empty fields match every event.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct QueryData {
+    /// Username of the user who did it.
+    #[serde(default)]
+    actor: String,
+    #[serde(default)]
+    action: String,
+    /// Part of the target, e.g. the id of an issue.
+    #[serde(default)]
+    target: String,
+    /// YYYY-MM-DD
+    #[serde(default)]
+    since: String,
+    /// YYYY-MM-DD, included.
+    #[serde(default)]
+    until: String,
+}
+
+impl QueryData {
+    fn parse(&self) -> Result<AuditFilter, String> {
+        let non_empty = |s: &str| Some(s.trim().to_owned()).filter(|s| !s.is_empty());
+        let date = |s: &str| match non_empty(s) {
+            None => Ok(None),
+            Some(s) => NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+                .map(Some)
+                .map_err(|_| format!("{} is not a valid date (YYYY-MM-DD).", s)),
+        };
+        let action = match non_empty(&self.action) {
+            None => None,
+            Some(s) => Some(
+                AuditAction::parse(&s).ok_or_else(|| format!("{} is not a known action.", s))?,
+            ),
+        };
+        Ok(AuditFilter {
+            actor: non_empty(&self.actor),
+            action,
+            target: non_empty(&self.target),
+            since: date(&self.since)?,
+            until: date(&self.until)?,
+        })
+    }
+}
+
+pub async fn audit_log(
+    query: web::Query<QueryData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    let mut events_html = String::new();
+    match query.parse() {
+        Ok(filter) => {
+            let events = get_audit_events(pool.get_ref(), &filter, MAX_EVENTS)
+                .await
+                .map_err(e500)?;
+            if events.is_empty() {
+                msg_html.push_str("<p><i>No event matches the filter.</i></p>");
+            }
+            for event in events {
+                writeln!(
+                    events_html,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    event.occurred_at.format("%Y-%m-%d %H:%M:%S"),
+                    encode_minimal(&event.actor),
+                    event.action,
+                    event.target.as_deref().map_or("-".into(), encode_minimal),
+                    encode_minimal(&event.ip),
+                )
+                .unwrap();
+            }
+        }
+        Err(e) => writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(&e)).unwrap(),
+    }
+
+    let mut action_options = String::from(r#"<option value="">any</option>"#);
+    for action in AuditAction::ALL {
+        let selected = if action.as_str() == query.action {
+            " selected"
+        } else {
+            ""
+        };
+        writeln!(
+            action_options,
+            r#"<option value="{0}"{selected}>{0}</option>"#,
+            action.as_str()
+        )
+        .unwrap();
+    }
+    let actor = encode_attribute(&query.actor);
+    let target = encode_attribute(&query.target);
+    let since = encode_attribute(&query.since);
+    let until = encode_attribute(&query.until);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Audit log</title>
+                </head>
+                <body>
+                    <form action="/admin/audit" method="get">
+                        <label>User <input type="text" name="actor" value="{actor}"></label>
+                        <label>Action <select name="action">{action_options}</select></label>
+                        <label>Target <input type="text" name="target" value="{target}"></label>
+                        <label>From <input type="date" name="since" value="{since}"></label>
+                        <label>To <input type="date" name="until" value="{until}"></label>
+                        <button type="submit">Filter</button>
+                    </form>
+                    {msg_html}
+                    <table>
+                        <tr><th>When (UTC)</th><th>User</th><th>Action</th><th>Target</th><th>IP</th></tr>
+                        {events_html}
+                    </table>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#
+        )))
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/audit", "Audit log of user actions, latest first")
+            .session()
+            .permission(Permission::ViewAuditLog)
+            .query::<QueryData>()
+            .html(),
+    );
+}
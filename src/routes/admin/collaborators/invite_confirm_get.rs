@@ -1,23 +1,46 @@
 use actix_web::http::header::ContentType;
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_attribute;
+use sqlx::PgPool;
 use std::fmt::Write;
 
-#[derive(serde::Deserialize)]
+use crate::{invitations::get_invitation_by_token, utils::e500};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 pub struct QueryData {
     token: String,
 }
 
+/// The username is pre-filled from the invited address; an unknown or expired
+/// token still gets the form, the activation tells what is wrong with it.
 pub async fn activate_account_form(
     query: web::Query<QueryData>,
+    pool: web::Data<PgPool>,
     flash_messages: IncomingFlashMessages,
-) -> HttpResponse {
+) -> Result<HttpResponse, actix_web::Error> {
     let mut error_html = String::new();
     for m in flash_messages.iter() {
         writeln!(error_html, "<p><i>{}</i></p>", m.content()).unwrap();
     }
     let token = query.into_inner().token;
-    HttpResponse::Ok()
+    let invitation = get_invitation_by_token(pool.get_ref(), &token)
+        .await
+        .map_err(e500)?;
+    let (username, role_html) = match &invitation {
+        Some(invitation) => (
+            invitation
+                .email
+                .split('@')
+                .next()
+                .map(encode_attribute)
+                .unwrap_or_default(),
+            format!("<p>You are invited as {}.</p>", invitation.role),
+        ),
+        None => (String::new(), String::new()),
+    };
+    let token = encode_attribute(&token);
+    Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(format!(
             r#"<!DOCTYPE html>
@@ -28,9 +51,10 @@ pub async fn activate_account_form(
                 </head>
                 <body>
                     {error_html}
+                        {role_html}
                         <form action="/collabolators/activate" method="post">
                             <label>Username
-                            <input type="text" placeholder="Enter Username" name="username">
+                            <input type="text" placeholder="Enter Username" name="username" value="{username}">
                             </label>
                             <label>Password
                             <input type="password" placeholder="Enter password" name="password">
@@ -42,5 +66,5 @@ pub async fn activate_account_form(
                         </form>
                     </body>
             </html>"#,
-        ))
+        )))
 }
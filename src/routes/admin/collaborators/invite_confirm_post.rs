@@ -1,23 +1,25 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
-use chrono::{DateTime, Duration, Utc};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
-const TOKEN_EXPIRE_TIMEOUT_IN_DAYS: u8 = 3;
-
 use crate::{
-    authentication::{compute_password_hash, UserRole},
+    audit::{email_digest, record_audit_event, AuditAction, AuditEvent},
+    authentication::{check_password_policy, compute_password_hash, PasswordPolicyError},
+    configuration::{PasswordHashingSettings, PasswordPolicySettings},
+    invitations::PendingInvitation,
     telemetry::spawn_blocking_with_tracing,
-    utils::{e500, is_password_invalid, see_other},
+    utils::{e500, see_other},
 };
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 pub struct FormData {
     username: String,
+    #[schemars(schema_with = "crate::openapi::password")]
     password: Secret<String>,
+    #[schemars(schema_with = "crate::openapi::password")]
     password_check: Secret<String>,
     token: String,
 }
@@ -30,24 +32,20 @@ impl FormData {
         if self.password.expose_secret() != self.password_check.expose_secret() {
             return Err("You entered two different passwords - the field values must match.");
         }
-        if is_password_invalid(&self.password) {
-            return Err(
-                "The new password should be longer than 12 characters and shorter than 129 characters",
-            );
-        }
         Ok(())
     }
 }
 
-#[derive(Debug, sqlx::FromRow)]
-struct TokenCreationTime {
-    created_at: DateTime<Utc>,
-}
-
-#[tracing::instrument(name = "Confirm collabolator account", skip(form, pool))]
+#[tracing::instrument(
+    name = "Confirm collabolator account",
+    skip(request, form, pool, hashing, policy)
+)]
 pub async fn activate_account(
+    request: HttpRequest,
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashingSettings>,
+    policy: web::Data<PasswordPolicySettings>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let form = form.into_inner();
 
@@ -61,15 +59,29 @@ pub async fn activate_account(
         return Ok(redirect_to_form(&form.token));
     }
 
+    match check_password_policy(&policy, &form.username, &form.password).await {
+        Ok(()) => {}
+        Err(PasswordPolicyError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(redirect_to_form(&form.token));
+        }
+    }
+
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")
         .map_err(e500)?;
 
-    let row: Option<TokenCreationTime> = sqlx::query_as!(
-        TokenCreationTime,
-        "SELECT created_at FROM collabolator_activation_tokens WHERE token = $1 FOR UPDATE",
+    let row = sqlx::query_as!(
+        PendingInvitation,
+        r#"
+        SELECT email, role, created_at, valid_days
+        FROM collabolator_activation_tokens
+        WHERE token = $1
+        FOR UPDATE
+        "#,
         &form.token
     )
     .fetch_optional(&mut *transaction)
@@ -77,15 +89,20 @@ pub async fn activate_account(
     .context("Cannot fetch invitation info from databsae")
     .map_err(e500)?;
 
-    if let Some(token_creation_time) = row {
-        if is_token_expired(&token_creation_time) {
+    if let Some(invitation) = row {
+        if invitation.is_expired() {
             FlashMessage::error("Activation link is expired.").send();
             return Ok(redirect_to_form(&form.token));
         }
-        add_new_user_to_db(&form, &mut transaction)
+        let user_id = add_new_user_to_db(&form, &invitation, &hashing, &mut transaction)
             .await
             .context("Cannot add new user to database")
             .map_err(e500)?;
+        let event = AuditEvent::new(&request, user_id, AuditAction::AccountActivated)
+            .target("invitation", email_digest(&invitation.email));
+        record_audit_event(&mut *transaction, event)
+            .await
+            .map_err(e500)?;
         delete_activation_token(&form.token, &mut transaction)
             .await
             .context("Cannot delete activation token from database")
@@ -109,13 +126,6 @@ fn redirect_to_form(token: &str) -> HttpResponse {
     see_other(&url)
 }
 
-fn is_token_expired(token_creation_time: &TokenCreationTime) -> bool {
-    let now = Utc::now();
-    token_creation_time.created_at > now
-        || now - token_creation_time.created_at
-            > Duration::days(TOKEN_EXPIRE_TIMEOUT_IN_DAYS.into())
-}
-
 async fn is_user_exists(username: &str, pool: &PgPool) -> Result<bool, anyhow::Error> {
     let row = sqlx::query!("SELECT user_id from users WHERE username = $1", username)
         .fetch_optional(pool)
@@ -124,29 +134,37 @@ async fn is_user_exists(username: &str, pool: &PgPool) -> Result<bool, anyhow::E
     Ok(row.is_some())
 }
 
+/// The account gets the role the invitation was sent for, and the invited
+/// address as its email.
 async fn add_new_user_to_db(
     form: &FormData,
+    invitation: &PendingInvitation,
+    hashing: &PasswordHashingSettings,
     transaction: &mut Transaction<'_, Postgres>,
-) -> Result<(), anyhow::Error> {
+) -> Result<Uuid, anyhow::Error> {
     let password = form.password.clone();
-    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
-        .await?
-        .context("Failed to hash password")?;
+    let hashing = hashing.clone();
+    let user_id = Uuid::new_v4();
+    let password_hash =
+        spawn_blocking_with_tracing(move || compute_password_hash(password, &hashing))
+            .await?
+            .context("Failed to hash password")?;
 
     transaction
         .execute(sqlx::query!(
             r#"
-            INSERT INTO users (user_id, username, password_hash, role)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO users (user_id, username, password_hash, role, email)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
-            Uuid::new_v4(),
+            user_id,
             form.username,
             password_hash.expose_secret(),
-            UserRole::Collabolator.to_string()
+            invitation.role,
+            invitation.email
         ))
         .await
         .context("Cannot add new user to database")?;
-    Ok(())
+    Ok(user_id)
 }
 
 async fn delete_activation_token(
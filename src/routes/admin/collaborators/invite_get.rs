@@ -1,13 +1,76 @@
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{http::header::ContentType, web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
+use chrono::Utc;
+use sqlx::PgPool;
 use std::fmt::Write as _;
 
+use crate::{
+    authentication::{get_roles, COLLABORATOR_ROLE},
+    invitations::{
+        get_pending_invitations, MAX_TOKEN_EXPIRE_TIMEOUT_IN_DAYS, TOKEN_EXPIRE_TIMEOUT_IN_DAYS,
+    },
+    utils::e500,
+};
+
 pub async fn invite_collaborator_form(
+    pool: web::Data<PgPool>,
     flash_messages: IncomingFlashMessages,
 ) -> Result<HttpResponse, actix_web::Error> {
     let mut msg_html = String::new();
     for m in flash_messages.iter() {
-        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let now = Utc::now();
+    let mut invitations_html = String::new();
+    for invitation in get_pending_invitations(&pool).await.map_err(e500)? {
+        let age = (now - invitation.created_at).num_days();
+        let expiry = match invitation.expires_at() - now {
+            left if left.num_seconds() <= 0 => "expired".to_owned(),
+            left if left.num_hours() < 24 => format!("expires in {} hour(s)", left.num_hours()),
+            left => format!("expires in {} day(s)", left.num_days()),
+        };
+        writeln!(
+            invitations_html,
+            r#"<li>
+                {email} as {role} - sent {age} day(s) ago, {expiry}
+                <form action="/admin/collabolators/resend" method="post">
+                    <input type="hidden" name="email" value="{email_attr}">
+                    <button type="submit">Resend</button>
+                </form>
+                <form action="/admin/collabolators/revoke" method="post">
+                    <input type="hidden" name="email" value="{email_attr}">
+                    <button type="submit">Revoke</button>
+                </form>
+            </li>"#,
+            email = htmlescape::encode_minimal(&invitation.email),
+            role = invitation.role,
+            email_attr = htmlescape::encode_attribute(&invitation.email),
+        )
+        .unwrap();
+    }
+    if invitations_html.is_empty() {
+        invitations_html.push_str("<li>No pending invitations.</li>");
+    }
+
+    let mut role_options = String::new();
+    for role in get_roles(&pool).await.map_err(e500)? {
+        let selected = if role.name == COLLABORATOR_ROLE {
+            " selected"
+        } else {
+            ""
+        };
+        writeln!(
+            role_options,
+            r#"<option value="{0}"{selected}>{0}</option>"#,
+            role.name
+        )
+        .unwrap();
     }
 
     Ok(HttpResponse::Ok()
@@ -28,8 +91,19 @@ pub async fn invite_collaborator_form(
                         placeholder="Enter email"
                         name="email"
                         >
+                        </label>
+                        <label>Role
+                            <select name="role">{role_options}</select>
+                        </label>
+                        <label>Link expires in (days)
+                            <input type="number" name="expires_in_days" value="{TOKEN_EXPIRE_TIMEOUT_IN_DAYS}" min="1" max="{MAX_TOKEN_EXPIRE_TIMEOUT_IN_DAYS}">
+                        </label>
                         <button type="submit">Invite</button>
                     </form>
+                    <p>Pending invitations:</p>
+                    <ul>
+                        {invitations_html}
+                    </ul>
                     <p><a href="/admin/dashboard">&lt;- Back</a></p>
                 </body>
             </html>"#,
@@ -1,32 +1,79 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
 use chrono::Utc;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 
 use crate::{
+    audit::{email_digest, record_audit_event, AuditAction, AuditEvent},
+    authentication::{AuthenticatedUser, COLLABORATOR_ROLE},
     domain::Email,
     email_client::EmailClient,
+    invitations::{
+        delete_invitation, MAX_TOKEN_EXPIRE_TIMEOUT_IN_DAYS, TOKEN_EXPIRE_TIMEOUT_IN_DAYS,
+    },
     startup::ApplicationBaseUrl,
     utils::{e500, generate_token, see_other},
 };
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 pub struct FormData {
     email: String,
+    /// Given to the account once activated, collabolator if empty.
+    #[serde(default)]
+    role: String,
+    /// How long the activation link keeps working, 3 days if empty.
+    #[serde(default)]
+    expires_in_days: String,
+}
+
+impl FormData {
+    fn role(&self) -> &str {
+        match self.role.trim() {
+            "" => COLLABORATOR_ROLE,
+            role => role,
+        }
+    }
+
+    fn valid_days(&self) -> Result<i16, String> {
+        match self.expires_in_days.trim() {
+            "" => Ok(TOKEN_EXPIRE_TIMEOUT_IN_DAYS.into()),
+            days => days
+                .parse::<u8>()
+                .ok()
+                .filter(|d| (1..=MAX_TOKEN_EXPIRE_TIMEOUT_IN_DAYS).contains(d))
+                .map(i16::from)
+                .ok_or_else(|| {
+                    format!(
+                        "Invitations must expire within 1 to {MAX_TOKEN_EXPIRE_TIMEOUT_IN_DAYS} days."
+                    )
+                }),
+        }
+    }
 }
 
 #[tracing::instrument(
     name = "Invite a new collaborator",
-    skip(form, email_client, base_url, pool)
+    skip(request, form, email_client, base_url, pool, user)
 )]
 pub async fn invite_collaborator(
+    request: HttpRequest,
     form: web::Form<FormData>,
     email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseUrl>,
     pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let email = Email::parse(form.into_inner().email);
+    let form = form.into_inner();
+    let valid_days = match form.valid_days() {
+        Ok(valid_days) => valid_days,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(redirect_to_form());
+        }
+    };
+    let role = form.role().to_owned();
+    let email = Email::parse(form.email);
     if email.is_err() {
         FlashMessage::error("Email is invalid.").send();
         return Ok(redirect_to_form());
@@ -40,7 +87,23 @@ pub async fn invite_collaborator(
         .context("Failed to acquire a Postgres connection from the pool")
         .map_err(e500)?;
 
-    store_activation_token(&mut transaction, &email, &token)
+    // The lock keeps the role from being deleted until the invitation is stored.
+    let role_exists = sqlx::query_scalar!("SELECT name FROM roles WHERE name = $1 FOR SHARE", role)
+        .fetch_optional(&mut *transaction)
+        .await
+        .context("Failed to look up the role.")
+        .map_err(e500)?
+        .is_some();
+    if !role_exists {
+        FlashMessage::error("There is no such role.").send();
+        return Ok(redirect_to_form());
+    }
+    store_activation_token(&mut transaction, &email, &token, &role, valid_days)
+        .await
+        .map_err(e500)?;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::CollaboratorInvited)
+        .target("invitation", email_digest(email.as_ref()));
+    record_audit_event(&mut *transaction, event)
         .await
         .map_err(e500)?;
 
@@ -58,6 +121,104 @@ pub async fn invite_collaborator(
     Ok(redirect_to_form())
 }
 
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct InvitationFormData {
+    email: String,
+}
+
+/// Send a new activation link, which invalidates the previous one and restarts
+/// the expiry. The invited role is kept.
+#[tracing::instrument(
+    name = "Resend an invitation",
+    skip(request, form, email_client, base_url, pool, user),
+    fields(email=%form.email)
+)]
+pub async fn resend_invitation(
+    request: HttpRequest,
+    form: web::Form<InvitationFormData>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Ok(email) = Email::parse(form.into_inner().email) else {
+        FlashMessage::error("Email is invalid.").send();
+        return Ok(redirect_to_form());
+    };
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let invitation = sqlx::query!(
+        r#"
+        SELECT role, valid_days FROM collabolator_activation_tokens
+        WHERE email = $1 FOR UPDATE
+        "#,
+        email.as_ref()
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Cannot fetch invitation info from database")
+    .map_err(e500)?;
+    let Some(invitation) = invitation else {
+        FlashMessage::error(format!("There is no pending invitation for {email}.")).send();
+        return Ok(redirect_to_form());
+    };
+
+    let token = generate_token();
+    store_activation_token(
+        &mut transaction,
+        &email,
+        &token,
+        &invitation.role,
+        invitation.valid_days,
+    )
+    .await
+    .map_err(e500)?;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::InvitationResent)
+        .target("invitation", email_digest(email.as_ref()));
+    record_audit_event(&mut *transaction, event)
+        .await
+        .map_err(e500)?;
+    send_activation_email(&email_client, &email, &base_url.get_ref().0, &token)
+        .await
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!("A new activation link was sent to {email}.")).send();
+    Ok(redirect_to_form())
+}
+
+#[tracing::instrument(name = "Revoke an invitation", skip(request, form, pool, user), fields(email=%form.email))]
+pub async fn revoke_invitation(
+    request: HttpRequest,
+    form: web::Form<InvitationFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if delete_invitation(&pool, &form.email).await.map_err(e500)? {
+        let event = AuditEvent::new(&request, user.user_id, AuditAction::InvitationRevoked)
+            .target("invitation", email_digest(&form.email));
+        record_audit_event(pool.get_ref(), event)
+            .await
+            .map_err(e500)?;
+        FlashMessage::info(format!("The invitation of {} was revoked.", form.email)).send();
+    } else {
+        FlashMessage::error(format!(
+            "There is no pending invitation for {}.",
+            form.email
+        ))
+        .send();
+    }
+    Ok(redirect_to_form())
+}
+
 fn redirect_to_form() -> HttpResponse {
     see_other("/admin/collabolators")
 }
@@ -70,18 +231,23 @@ async fn store_activation_token(
     transaction: &mut Transaction<'_, Postgres>,
     email: &Email,
     activation_token: &str,
+    role: &str,
+    valid_days: i16,
 ) -> Result<(), sqlx::Error> {
     transaction
         .execute(sqlx::query!(
             r#"
-            INSERT INTO collabolator_activation_tokens (email, token, created_at)
-            VALUES ($1, $2, $3)
+            INSERT INTO collabolator_activation_tokens (email, token, created_at, role, valid_days)
+            VALUES ($1, $2, $3, $4, $5)
             ON CONFLICT (email) 
-            DO UPDATE SET token = EXCLUDED.token, created_at = EXCLUDED.created_at;
+            DO UPDATE SET token = EXCLUDED.token, created_at = EXCLUDED.created_at,
+                role = EXCLUDED.role, valid_days = EXCLUDED.valid_days;
             "#,
             email.as_ref(),
             activation_token,
-            Utc::now()
+            Utc::now(),
+            role,
+            valid_days
         ))
         .await?;
     Ok(())
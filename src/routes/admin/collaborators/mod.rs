@@ -6,4 +6,54 @@ mod invite_post;
 pub use invite_confirm_get::activate_account_form;
 pub use invite_confirm_post::activate_account;
 pub use invite_get::invite_collaborator_form;
-pub use invite_post::invite_collaborator;
+pub use invite_post::{invite_collaborator, resend_invitation, revoke_invitation};
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/collabolators/activate", "Account activation form")
+            .query::<invite_confirm_get::QueryData>()
+            .html(),
+    )
+    .add(
+        Operation::post("/collabolators/activate", "Activate an invited account")
+            .form::<invite_confirm_post::FormData>()
+            .redirect("To the login form, back to the form on failure")
+            .respond(429, "Too many activation attempts"),
+    )
+    .add(
+        Operation::get(
+            "/admin/collabolators",
+            "Invitation form and pending invitations",
+        )
+        .session()
+        .permission(Permission::ManageUsers)
+        .html(),
+    )
+    .add(
+        Operation::post("/admin/collabolators", "Invite a collaborator")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form::<invite_post::FormData>()
+            .redirect("Back to the invitation form"),
+    )
+    .add(
+        Operation::post(
+            "/admin/collabolators/resend",
+            "Resend an invitation with a new link",
+        )
+        .session()
+        .permission(Permission::ManageUsers)
+        .form::<invite_post::InvitationFormData>()
+        .redirect("Back to the invitation form"),
+    )
+    .add(
+        Operation::post("/admin/collabolators/revoke", "Revoke a pending invitation")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form::<invite_post::InvitationFormData>()
+            .redirect("Back to the invitation form"),
+    );
+}
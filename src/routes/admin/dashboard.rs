@@ -1,16 +1,63 @@
-use crate::authentication::{AuthenticatedUser, UserRole};
+use crate::authentication::{AuthenticatedUser, Permission};
+use crate::openapi::{OpenApi, Operation};
 use actix_web::{http::header::ContentType, web, HttpResponse};
+use std::fmt::Write as _;
 
 pub async fn admin_dashboard(
     user: web::ReqData<AuthenticatedUser>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let username = user.username.as_str();
-    let invite_link = match user.role {
-        UserRole::Admin => {
-            r#"<li><a href="/admin/collabolators">Invite a new collaborator</a></li>"#
+    let links: &[(Permission, &str, &str)] = &[
+        (
+            Permission::DraftIssues,
+            "/admin/newsletters",
+            "Send a newsletter issue",
+        ),
+        (
+            Permission::PublishIssues,
+            "/admin/reviews",
+            "Review submitted issues",
+        ),
+        (Permission::ManageUsers, "/admin/users", "Manage users"),
+        (
+            Permission::ManageUsers,
+            "/admin/roles",
+            "Roles and permissions",
+        ),
+        (
+            Permission::ManageUsers,
+            "/admin/collabolators",
+            "Invite a new collaborator",
+        ),
+        (
+            Permission::ManageSubscribers,
+            "/admin/lists",
+            "Manage mailing lists",
+        ),
+        (
+            Permission::ManageSubscribers,
+            "/admin/tags",
+            "Tag subscribers",
+        ),
+        (
+            Permission::ManageSubscribers,
+            "/admin/subscribers",
+            "Export or erase subscriber data",
+        ),
+        (
+            Permission::ManageUsers,
+            "/admin/lockouts",
+            "Unlock locked logins",
+        ),
+        (Permission::ManageSubscribers, "/admin/webhooks", "Webhooks"),
+        (Permission::ViewAuditLog, "/admin/audit", "Audit log"),
+    ];
+    let mut permitted_links = String::new();
+    for (permission, href, label) in links {
+        if user.can(*permission) {
+            writeln!(permitted_links, r#"<li><a href="{href}">{label}</a></li>"#).unwrap();
         }
-        _ => "",
-    };
+    }
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(format!(
@@ -25,8 +72,11 @@ pub async fn admin_dashboard(
                     <p>Available actions:</p>
                     <ol>
                         <li><a href="/admin/password">Change password</a></li>
-                        <li><a href="/admin/newsletters">Send a newsletter issue</a></li>
-                        {invite_link}
+                        <li><a href="/admin/email">Email address</a></li>
+                        <li><a href="/admin/security">Two-factor authentication</a></li>
+                        <li><a href="/admin/sessions">Active sessions</a></li>
+                        <li><a href="/admin/api-tokens">API tokens</a></li>
+                        {permitted_links}
                         <li>
                             <form name="logoutForm" action="/admin/logout" method="post">
                                 <input type="submit" value="Logout">
@@ -37,3 +87,11 @@ pub async fn admin_dashboard(
             </html>"#
         )))
 }
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/dashboard", "Admin dashboard")
+            .session()
+            .html(),
+    );
+}
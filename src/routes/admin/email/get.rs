@@ -0,0 +1,72 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::authentication::{get_pending_email_change, AuthenticatedUser};
+use crate::utils::e500;
+
+pub async fn change_email_form(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
+    }
+    let current_html = match &user.email {
+        Some(email) => format!("<p>Your email address is {}.</p>", encode_minimal(email)),
+        None => "<p>You have no email address yet.</p>".to_owned(),
+    };
+    let pending_html = match get_pending_email_change(&pool, user.user_id)
+        .await
+        .map_err(e500)?
+    {
+        Some(change) => format!(
+            "<p>A verification link was sent to {}, it expires at {} UTC.</p>",
+            encode_minimal(&change.email),
+            change.expires_at().format("%Y-%m-%d %H:%M")
+        ),
+        None => String::new(),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Email address</title>
+                </head>
+                <body>
+                    {msg_html}
+                    {current_html}
+                    {pending_html}
+                    <form action="/admin/email" method="post">
+                        <label>New email address
+                        <input
+                        type="text"
+                        placeholder="Enter email"
+                        name="email"
+                        >
+                        </label>
+                        <br>
+                        <label>Current password
+                        <input
+                        type="password"
+                        placeholder="Enter current password"
+                        name="current_password"
+                        >
+                        </label>
+                        <br>
+                        <button type="submit">Change email address</button>
+                    </form>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+        )))
+}
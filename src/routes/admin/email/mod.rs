@@ -0,0 +1,26 @@
+mod get;
+pub use get::change_email_form;
+mod post;
+pub use post::{change_email, confirm_email};
+
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/email", "Email address form")
+            .session()
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/email", "Send a link to verify a new email address")
+            .session()
+            .form::<post::FormData>()
+            .redirect("Back to the form"),
+    )
+    .add(
+        Operation::get("/admin/email/confirm", "Verify a new email address")
+            .session()
+            .query::<post::QueryData>()
+            .redirect("Back to the form"),
+    );
+}
@@ -0,0 +1,136 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use secrecy::Secret;
+use sqlx::PgPool;
+
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{
+    confirm_email_change, request_email_change, validate_credentials, AuthError, AuthenticatedUser,
+    Credentials, SessionCache, EMAIL_VERIFICATION_TIMEOUT_IN_HOURS,
+};
+use crate::configuration::PasswordHashingSettings;
+use crate::domain::Email;
+use crate::email_client::EmailClient;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    email: String,
+    #[schemars(schema_with = "crate::openapi::password")]
+    current_password: Secret<String>,
+}
+
+/// The address is only changed once the link sent to it is followed.
+#[tracing::instrument(
+    name = "Change the email address",
+    skip(form, pool, hashing, email_client, base_url, user),
+    fields(user_id=%user.user_id)
+)]
+pub async fn change_email(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashingSettings>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        email,
+        current_password,
+    } = form.into_inner();
+    let Ok(email) = Email::parse(email.trim().to_owned()) else {
+        FlashMessage::error("Email is invalid.").send();
+        return Ok(see_other("/admin/email"));
+    };
+    if user.email.as_deref() == Some(email.as_ref()) {
+        FlashMessage::error("This is already your email address.").send();
+        return Ok(see_other("/admin/email"));
+    }
+
+    let credentials = Credentials {
+        username: user.username.clone(),
+        password: current_password,
+    };
+    if let Err(e) = validate_credentials(credentials, &hashing, &pool).await {
+        return match e {
+            AuthError::InvalidCredentials(_) => {
+                FlashMessage::error("The current password is incorrect.").send();
+                Ok(see_other("/admin/email"))
+            }
+            AuthError::UnexpectedError(_) => Err(e500(e)),
+        };
+    }
+
+    let token = request_email_change(&pool, user.user_id, &email)
+        .await
+        .map_err(e500)?;
+    send_verification_email(&email_client, &email, &base_url.0, &token)
+        .await
+        .context("Failed to send an email verification link.")
+        .map_err(e500)?;
+    FlashMessage::info(format!(
+        "A verification link was sent to {email} - your address changes once you follow it."
+    ))
+    .send();
+    Ok(see_other("/admin/email"))
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct QueryData {
+    token: String,
+}
+
+/// Only works for the user who asked for the change.
+#[tracing::instrument(
+    name = "Confirm an email address",
+    skip(request, query, pool, user, cache),
+    fields(user_id=%user.user_id)
+)]
+pub async fn confirm_email(
+    request: HttpRequest,
+    query: web::Query<QueryData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(email) = confirm_email_change(&pool, user.user_id, &query.token)
+        .await
+        .map_err(e500)?
+    else {
+        FlashMessage::error("The verification link is invalid or has expired.").send();
+        return Ok(see_other("/admin/email"));
+    };
+    cache.forget_user(user.user_id);
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::EmailChanged);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info(format!("Your email address is now {email}.")).send();
+    Ok(see_other("/admin/email"))
+}
+
+async fn send_verification_email(
+    email_client: &EmailClient,
+    email: &Email,
+    base_url: &str,
+    token: &str,
+) -> Result<(), reqwest::Error> {
+    let verification_link = format!("{}/admin/email/confirm?token={}", base_url, token);
+    let plain_body = format!(
+        "Visit {} to use this address for your newsletter account.\n\
+        The link expires in {} hours. \
+        If you did not ask for it, you can ignore this email.",
+        verification_link, EMAIL_VERIFICATION_TIMEOUT_IN_HOURS
+    );
+    let html_body = format!(
+        "Click <a href=\"{}\">here</a> to use this address for your newsletter account.<br />\
+        The link expires in {} hours. \
+        If you did not ask for it, you can ignore this email.",
+        verification_link, EMAIL_VERIFICATION_TIMEOUT_IN_HOURS
+    );
+    email_client
+        .send_email(email, "Verify your email address", &html_body, &plain_body)
+        .await
+}
@@ -0,0 +1,61 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{mailing_lists::get_mailing_lists, utils::e500};
+
+pub async fn mailing_lists_form(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let mut lists_html = String::new();
+    for list in get_mailing_lists(pool.get_ref()).await.map_err(e500)? {
+        writeln!(
+            lists_html,
+            "<li>{}</li>",
+            htmlescape::encode_minimal(&list.name)
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Mailing lists</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <p>Mailing lists:</p>
+                    <ul>
+                        {lists_html}
+                    </ul>
+                    <form action="/admin/lists" method="post">
+                        <label>Name
+                        <input
+                        type="text"
+                        placeholder="Enter list name"
+                        name="name"
+                        >
+                        </label>
+                        <button type="submit">Create list</button>
+                    </form>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+        )))
+}
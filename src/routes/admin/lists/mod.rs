@@ -0,0 +1,24 @@
+mod get;
+mod post;
+
+pub use get::mailing_lists_form;
+pub use post::create_mailing_list;
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/lists", "Mailing lists")
+            .session()
+            .permission(Permission::ManageSubscribers)
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/lists", "Create a mailing list")
+            .session()
+            .permission(Permission::ManageSubscribers)
+            .form::<post::FormData>()
+            .redirect("Back to the mailing lists"),
+    );
+}
@@ -0,0 +1,59 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    name: String,
+}
+
+#[tracing::instrument(name = "Create a mailing list", skip(form, pool))]
+pub async fn create_mailing_list(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = form.into_inner().name;
+    let name = name.trim();
+    if name.is_empty() {
+        FlashMessage::error("The list name cannot be empty.").send();
+        return Ok(redirect_to_form());
+    }
+
+    let inserted = insert_mailing_list(&pool, name)
+        .await
+        .context("Failed to store a new mailing list")
+        .map_err(e500)?;
+    if inserted {
+        FlashMessage::info(format!("The list '{name}' has been created.")).send();
+    } else {
+        FlashMessage::error(format!("A list named '{name}' already exists.")).send();
+    }
+    Ok(redirect_to_form())
+}
+
+fn redirect_to_form() -> HttpResponse {
+    see_other("/admin/lists")
+}
+
+/// Returns `false` if a list with the same name already exists.
+#[tracing::instrument(name = "Saving a new mailing list in the database", skip(pool))]
+async fn insert_mailing_list(pool: &PgPool, name: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO lists (list_id, name, created_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (name) DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        name,
+        Utc::now()
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
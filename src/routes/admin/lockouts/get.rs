@@ -0,0 +1,66 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{authentication::get_locked_logins, utils::e500};
+
+pub async fn login_lockouts_form(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let mut lockouts_html = String::new();
+    for locked in get_locked_logins(&pool).await.map_err(e500)? {
+        writeln!(
+            lockouts_html,
+            r#"<li>
+                <form action="/admin/lockouts" method="post">
+                    {scope} {key} - locked until {until} after {failed_attempts} failed attempt(s)
+                    <input type="hidden" name="scope" value="{scope_attr}">
+                    <input type="hidden" name="key" value="{key_attr}">
+                    <button type="submit">Unlock</button>
+                </form>
+            </li>"#,
+            scope = htmlescape::encode_minimal(&locked.scope),
+            key = htmlescape::encode_minimal(&locked.key),
+            until = locked.locked_until.format("%Y-%m-%d %H:%M:%S UTC"),
+            failed_attempts = locked.failed_attempts,
+            scope_attr = htmlescape::encode_attribute(&locked.scope),
+            key_attr = htmlescape::encode_attribute(&locked.key),
+        )
+        .unwrap();
+    }
+    if lockouts_html.is_empty() {
+        lockouts_html.push_str("<li>No logins are locked.</li>");
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Locked logins</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <p>Locked usernames and IP addresses:</p>
+                    <ul>
+                        {lockouts_html}
+                    </ul>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+        )))
+}
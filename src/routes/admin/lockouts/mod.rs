@@ -0,0 +1,24 @@
+mod get;
+mod post;
+
+pub use get::login_lockouts_form;
+pub use post::remove_login_lockout;
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/lockouts", "Locked out usernames")
+            .session()
+            .permission(Permission::ManageUsers)
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/lockouts", "Lift a login lockout")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form::<post::FormData>()
+            .redirect("Back to the lockouts"),
+    );
+}
@@ -0,0 +1,39 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::{unlock_login, AuthenticatedUser, LoginScope},
+    utils::{e400, e500, see_other},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    scope: String,
+    key: String,
+}
+
+#[tracing::instrument(name = "Remove a login lockout", skip(request, form, pool, user))]
+pub async fn remove_login_lockout(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData { scope, key } = form.into_inner();
+    let scope = LoginScope::parse(&scope)
+        .ok_or_else(|| e400(format!("'{scope}' is not a known lockout scope.")))?;
+    unlock_login(&pool, scope, &key).await.map_err(e500)?;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::LoginUnlocked)
+        .target(scope.as_str(), &key);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    let subject = match scope {
+        LoginScope::Username => format!("The username '{key}'"),
+        LoginScope::Ip => format!("The IP address {key}"),
+    };
+    FlashMessage::info(format!("{subject} has been unlocked.")).send();
+    Ok(see_other("/admin/lockouts"))
+}
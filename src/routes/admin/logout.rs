@@ -1,10 +1,38 @@
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{revoke_user_session, AuthenticatedUser, SessionCache};
+use crate::openapi::{OpenApi, Operation};
 use crate::session_state::TypedSession;
-use crate::utils::see_other;
-use actix_web::HttpResponse;
+use crate::utils::{e500, see_other};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
 
-pub async fn log_out(session: TypedSession) -> Result<HttpResponse, actix_web::Error> {
+pub async fn log_out(
+    request: HttpRequest,
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Some(session_id) = session.get_session_id().map_err(e500)? {
+        revoke_user_session(&pool, user.user_id, session_id)
+            .await
+            .map_err(e500)?;
+        cache.forget_session(session_id);
+    }
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::LoggedOut);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
     session.log_out();
     FlashMessage::info("You have successfully logged out.").send();
     Ok(see_other("/login"))
 }
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::post("/admin/logout", "Log out")
+            .session()
+            .redirect("To the login form"),
+    );
+}
@@ -1,11 +1,60 @@
+mod api_tokens;
+mod audit;
 mod collaborators;
 mod dashboard;
+mod email;
+mod lists;
+mod lockouts;
 mod logout;
 mod newsletter;
 mod password;
+mod reviews;
+mod roles;
+mod security;
+mod sessions;
+mod subscribers;
+mod tags;
+mod users;
+mod webhooks;
 
+pub use api_tokens::*;
+pub use audit::audit_log;
 pub use collaborators::*;
 pub use dashboard::admin_dashboard;
+pub use email::*;
+pub use lists::*;
+pub use lockouts::*;
 pub use logout::*;
 pub use newsletter::*;
 pub use password::*;
+pub use reviews::*;
+pub use roles::*;
+pub use security::*;
+pub use sessions::*;
+pub use subscribers::*;
+pub use tags::*;
+pub use users::*;
+pub use webhooks::*;
+
+use crate::openapi::OpenApi;
+
+pub(super) fn document(api: &mut OpenApi) {
+    dashboard::document(api);
+    password::document(api);
+    email::document(api);
+    logout::document(api);
+    api_tokens::document(api);
+    sessions::document(api);
+    security::document(api);
+    newsletter::document(api);
+    reviews::document(api);
+    collaborators::document(api);
+    lists::document(api);
+    tags::document(api);
+    lockouts::document(api);
+    subscribers::document(api);
+    webhooks::document(api);
+    users::document(api);
+    roles::document(api);
+    audit::document(api);
+}
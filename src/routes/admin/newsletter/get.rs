@@ -1,16 +1,123 @@
 use actix_web::http::header::ContentType;
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
 use std::fmt::Write;
+use uuid::Uuid;
+
+use super::SegmentData;
+use crate::authentication::{AuthenticatedUser, Permission};
+use crate::domain::MembershipStatus;
+use crate::issue_reviews::{get_issues_under_review, get_submitted_issues, SubmittedIssue};
+use crate::mailing_lists::{get_mailing_lists, DEFAULT_LIST_ID};
+use crate::newsletter_issues::count_segment_recipients;
+use crate::utils::e500;
+
+/// Submitted by the "Count recipients" button - the form is rendered again,
+/// filled in with the same values, together with the size of the segment.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct QueryData {
+    list_id: Option<Uuid>,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    text_content: String,
+    #[serde(default)]
+    html_content: String,
+    #[serde(flatten)]
+    segment: SegmentData,
+}
 
 pub async fn send_newsletter_issue_form(
+    query: web::Query<QueryData>,
+    pool: web::Data<PgPool>,
     flash_messages: IncomingFlashMessages,
+    user: web::ReqData<AuthenticatedUser>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    let QueryData {
+        list_id,
+        title,
+        text_content,
+        html_content,
+        segment,
+    } = query.into_inner();
+
     let mut msg_html = String::new();
     for m in flash_messages.iter() {
         writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
     }
+    if let Some(list_id) = list_id {
+        match segment.parse() {
+            Ok(parsed) => {
+                let n_recipients = count_segment_recipients(pool.get_ref(), list_id, &parsed)
+                    .await
+                    .map_err(e500)?;
+                writeln!(
+                    msg_html,
+                    "<p><i>The segment contains {n_recipients} subscriber(s).</i></p>"
+                )
+                .unwrap();
+            }
+            Err(e) => writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(&e)).unwrap(),
+        }
+    }
+
+    let selected_list = list_id.unwrap_or(DEFAULT_LIST_ID);
+    let mut list_options = String::new();
+    for list in get_mailing_lists(pool.get_ref()).await.map_err(e500)? {
+        writeln!(
+            list_options,
+            "{}",
+            list.html_option(list.list_id == selected_list)
+        )
+        .unwrap();
+    }
+    let mut status_options = String::new();
+    for status in [
+        MembershipStatus::Confirmed,
+        MembershipStatus::PendingConfirmation,
+        MembershipStatus::Unsubscribed,
+    ] {
+        let selected = if status.as_str() == segment.status {
+            " selected"
+        } else {
+            ""
+        };
+        writeln!(
+            status_options,
+            r#"<option value="{0}"{selected}>{0}</option>"#,
+            status.as_str()
+        )
+        .unwrap();
+    }
 
+    let (submit_label, reviews_html) = if user.can(Permission::PublishIssues) {
+        let n_pending = get_issues_under_review(pool.get_ref())
+            .await
+            .map_err(e500)?
+            .len();
+        let reviews_html = match n_pending {
+            0 => String::new(),
+            n => {
+                format!(r#"<p><a href="/admin/reviews">{n} issue(s) waiting for a review</a></p>"#)
+            }
+        };
+        ("Send issue", reviews_html)
+    } else {
+        let submissions = get_submitted_issues(pool.get_ref(), user.user_id, 10)
+            .await
+            .map_err(e500)?;
+        ("Submit for review", submissions_html(&submissions))
+    };
+
+    let title = encode_attribute(&title);
+    let text_content = encode_minimal(&text_content);
+    let html_content = encode_minimal(&html_content);
+    let include_tags = encode_attribute(&segment.include_tags);
+    let exclude_tags = encode_attribute(&segment.exclude_tags);
+    let subscribed_after = encode_attribute(&segment.subscribed_after);
+    let subscribed_before = encode_attribute(&segment.subscribed_before);
     let idempotency_key = uuid::Uuid::new_v4();
 
     Ok(HttpResponse::Ok()
@@ -24,12 +131,49 @@ pub async fn send_newsletter_issue_form(
                 </head>
                 <body>
                     {msg_html}
+                    {reviews_html}
                     <form action="/admin/newsletters" method="post">
+                        <label>Mailing list:<br>
+                            <select name="list_id">
+                                {list_options}
+                            </select>
+                        </label>
+                        <br><br>
+                        <fieldset>
+                            <legend>Send only to subscribers</legend>
+                            <label>tagged with any of (comma separated):
+                                <input type="text" name="include_tags" value="{include_tags}">
+                            </label>
+                            <br>
+                            <label>not tagged with any of (comma separated):
+                                <input type="text" name="exclude_tags" value="{exclude_tags}">
+                            </label>
+                            <br>
+                            <label>who joined the list on or after:
+                                <input type="date" name="subscribed_after" value="{subscribed_after}">
+                            </label>
+                            <br>
+                            <label>who joined the list before:
+                                <input type="date" name="subscribed_before" value="{subscribed_before}">
+                            </label>
+                            <br>
+                            <label>with status:
+                                <select name="status">
+                                    {status_options}
+                                </select>
+                            </label>
+                            <br>
+                            <button type="submit" formaction="/admin/newsletters" formmethod="get">
+                                Count recipients
+                            </button>
+                        </fieldset>
+                        <br>
                         <label>Title:<br>
                             <input
                             type="text"
                             placeholder="Enter title"
-                            name="title">
+                            name="title"
+                            value="{title}">
                         </label>
                         <br><br>
                         <label>Plain text content:<br>
@@ -37,7 +181,7 @@ pub async fn send_newsletter_issue_form(
                                 placeholder="Enter the content in plain text"
                                 name="text_content"
                                 rows="15"
-                                cols="100"></textarea>
+                                cols="100">{text_content}</textarea>
                         </label>
                         <br><br>
                          <label>HTML text content:<br>
@@ -45,14 +189,46 @@ pub async fn send_newsletter_issue_form(
                                 placeholder="Enter the content in HTML"
                                 name="html_content"
                                 rows="15"
-                                cols="100"></textarea>
+                                cols="100">{html_content}</textarea>
                         </label>
                         <br><br>
                         <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
-                        <button type="submit">Send issue</button>
+                        <button type="submit">{submit_label}</button>
                     </form>
                     <p><a href="/admin/dashboard">&lt;- Back</a></p>
                 </body>
             </html>"#,
         )))
 }
+
+fn submissions_html(submissions: &[SubmittedIssue]) -> String {
+    if submissions.is_empty() {
+        return String::new();
+    }
+    let mut rows = String::new();
+    for issue in submissions {
+        let review = match (&issue.reviewer, &issue.review_comment) {
+            (Some(reviewer), Some(comment)) => {
+                format!("{}: {}", encode_minimal(reviewer), encode_minimal(comment))
+            }
+            (Some(reviewer), None) => encode_minimal(reviewer),
+            (None, Some(comment)) => encode_minimal(comment),
+            (None, None) => "-".into(),
+        };
+        writeln!(
+            rows,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{review}</td></tr>",
+            encode_minimal(&issue.title),
+            issue.submitted_at.format("%Y-%m-%d %H:%M"),
+            issue.review_status.replace('_', " "),
+        )
+        .unwrap();
+    }
+    format!(
+        r#"<h2>Your submissions</h2>
+        <table>
+            <tr><th>Title</th><th>Submitted</th><th>Status</th><th>Review</th></tr>
+            {rows}
+        </table>"#
+    )
+}
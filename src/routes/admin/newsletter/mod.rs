@@ -1,5 +1,56 @@
 mod get;
 mod post;
 
+use crate::authentication::Permission;
+use crate::domain::Segment;
+use crate::openapi::{OpenApi, Operation};
+
 pub use get::send_newsletter_issue_form;
 pub use post::publish_newsletter;
+
+/// The segment fields shared by the newsletter form and its submission.
+#[derive(serde::Deserialize, Default, schemars::JsonSchema)]
+struct SegmentData {
+    #[serde(default)]
+    include_tags: String,
+    #[serde(default)]
+    exclude_tags: String,
+    #[serde(default)]
+    subscribed_after: String,
+    #[serde(default)]
+    subscribed_before: String,
+    #[serde(default)]
+    status: String,
+}
+
+impl SegmentData {
+    fn parse(&self) -> Result<Segment, String> {
+        Segment::parse(
+            &self.include_tags,
+            &self.exclude_tags,
+            &self.subscribed_after,
+            &self.subscribed_before,
+            &self.status,
+        )
+    }
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/newsletters", "Newsletter issue form")
+            .session()
+            .permission(Permission::DraftIssues)
+            .query::<get::QueryData>()
+            .html(),
+    )
+    .add(
+        Operation::post(
+            "/admin/newsletters",
+            "Publish a newsletter issue, or submit it for review",
+        )
+        .session()
+        .permission(Permission::DraftIssues)
+        .form::<post::BodyData>()
+        .redirect("Back to the form"),
+    );
+}
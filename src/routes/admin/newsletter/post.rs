@@ -1,20 +1,30 @@
-use crate::authentication::AuthenticatedUser;
+use super::SegmentData;
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{AuthenticatedUser, Permission};
+use crate::domain::MembershipStatus;
+use crate::email_client::EmailClient;
 use crate::idempotency::{save_response, try_processing, IdempotencyKey, NextAction};
+use crate::issue_reviews::{notify_reviewers, submit_issue_for_review};
+use crate::mailing_lists::{get_mailing_list, DEFAULT_LIST_ID};
+use crate::newsletter_issues::{enqueue_delivery_tasks, insert_newsletter_issue};
+use crate::startup::ApplicationBaseUrl;
 use crate::utils::e400;
 use crate::utils::{e500, see_other};
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
 use sqlx::PgPool;
-use sqlx::{Executor, Postgres, Transaction};
 use uuid::Uuid;
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 pub struct BodyData {
     title: String,
     text_content: String,
     html_content: String,
     idempotency_key: String,
+    list_id: Option<Uuid>,
+    #[serde(flatten)]
+    segment: SegmentData,
 }
 
 #[tracing::instrument(
@@ -23,16 +33,37 @@ pub struct BodyData {
     fields(user_id=%&user.user_id)
 )]
 pub async fn publish_newsletter(
+    request: HttpRequest,
     body: web::Form<BodyData>,
     pool: web::Data<PgPool>,
     user: web::ReqData<AuthenticatedUser>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    // Writing an issue only takes `DraftIssues`, see `startup::run`; the
+    // issues of users who cannot publish them wait for a review.
+    let publishes = user.can(Permission::PublishIssues);
     let BodyData {
         title,
         text_content,
         html_content,
         idempotency_key,
+        list_id,
+        segment,
     } = body.0;
+    let list_id = list_id.unwrap_or(DEFAULT_LIST_ID);
+    let segment = match segment.parse() {
+        Ok(segment) if segment.status != MembershipStatus::Confirmed => {
+            FlashMessage::error("Newsletter issues can only be sent to confirmed subscribers.")
+                .send();
+            return Ok(see_other("/admin/newsletters"));
+        }
+        Ok(segment) => segment,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/newsletters"));
+        }
+    };
     let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(e400)?;
     let mut transaction = match try_processing(&pool, &idempotency_key, user.user_id)
         .await
@@ -40,80 +71,78 @@ pub async fn publish_newsletter(
     {
         NextAction::StartProcessing(t) => t,
         NextAction::ReturnSavedResponse(saved_response) => {
-            success_message().send();
+            success_message(publishes).send();
             return Ok(saved_response);
         }
     };
 
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
+    if get_mailing_list(&mut *transaction, list_id)
+        .await
+        .context("Failed to fetch the mailing list")
+        .map_err(e500)?
+        .is_none()
+    {
+        return Err(e400(format!("{} is not a known mailing list.", list_id)));
+    }
+
+    let (issue_id, action) = if publishes {
+        let issue_id = insert_newsletter_issue(
+            &mut transaction,
+            user.user_id,
+            list_id,
+            &title,
+            &text_content,
+            &html_content,
+        )
         .await
         .context("Failed to store newsletter issue details")
         .map_err(e500)?;
-    enqueue_delivery_tasks(&mut transaction, issue_id)
+        enqueue_delivery_tasks(&mut transaction, issue_id, list_id, &segment)
+            .await
+            .context("Failed to enqueue delivery tasks")
+            .map_err(e500)?;
+        (issue_id, AuditAction::IssuePublished)
+    } else {
+        let issue_id = submit_issue_for_review(
+            &mut transaction,
+            user.user_id,
+            list_id,
+            &title,
+            &text_content,
+            &html_content,
+            &segment,
+        )
+        .await
+        .context("Failed to store the issue for review")
+        .map_err(e500)?;
+        (issue_id, AuditAction::IssueSubmitted)
+    };
+    let event = AuditEvent::new(&request, user.user_id, action).target("issue", issue_id);
+    record_audit_event(&mut *transaction, event)
         .await
-        .context("Failed to enqueue delivery tasks")
         .map_err(e500)?;
     let response = see_other("/admin/newsletters");
 
     let response = save_response(transaction, &idempotency_key, user.user_id, response)
         .await
         .map_err(e500)?;
-    success_message().send();
+    if !publishes {
+        // The issue is safely stored - reviewers also find it on their page.
+        if let Err(e) = notify_reviewers(&pool, &email_client, &base_url.0, &title).await {
+            tracing::warn!(error.cause_chain = ?e, "Failed to notify the reviewers.");
+        }
+    }
+    success_message(publishes).send();
     Ok(response)
 }
 
-#[tracing::instrument(skip_all)]
-async fn insert_newsletter_issue(
-    transaction: &mut Transaction<'_, Postgres>,
-    title: &str,
-    text_content: &str,
-    html_content: &str,
-) -> Result<Uuid, sqlx::Error> {
-    let newsletter_issue_id = Uuid::new_v4();
-    let query = sqlx::query!(
-        r#"
-        INSERT INTO newsletter_issues (
-            newsletter_issue_id,
-            title,
-            text_content,
-            html_content,
-            published_at
-        )
-        VALUES ($1, $2, $3, $4, now())
-        "#,
-        newsletter_issue_id,
-        title,
-        text_content,
-        html_content
-    );
-    transaction.execute(query).await?;
-    Ok(newsletter_issue_id)
-}
-
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
-    transaction: &mut Transaction<'_, Postgres>,
-    newsletter_issue_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    let query = sqlx::query!(
-        r#"
-        INSERT INTO issue_delivery_queue (
-            newsletter_issue_id,
-            subscriber_email
+fn success_message(publishes: bool) -> FlashMessage {
+    if publishes {
+        FlashMessage::info(
+            "The newsletter issue has been accepted - \
+            emails will go out shortly.",
         )
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
-        "#,
-        newsletter_issue_id,
-    );
-    transaction.execute(query).await?;
-    Ok(())
-}
-
-fn success_message() -> FlashMessage {
-    FlashMessage::info(
-        "The newsletter issue has been accepted - \
-        emails will go out shortly.",
-    )
+    } else {
+        FlashMessage::info("The newsletter issue has been submitted for review.")
+    }
 }
@@ -2,3 +2,19 @@ mod get;
 pub use get::change_password_form;
 mod post;
 pub use post::change_password;
+
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/password", "Password change form")
+            .session()
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/password", "Change the password")
+            .session()
+            .form::<post::FormData>()
+            .redirect("Back to the form"),
+    );
+}
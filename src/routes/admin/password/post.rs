@@ -1,23 +1,38 @@
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
 use crate::authentication::AuthenticatedUser;
-use crate::authentication::{validate_credentials, AuthError, Credentials};
-use crate::utils::{e500, is_password_invalid, see_other};
-use actix_web::{web, HttpResponse};
+use crate::authentication::{
+    check_password_policy, keep_user_session, validate_credentials, AuthError, Credentials,
+    PasswordPolicyError, SessionCache,
+};
+use crate::configuration::{PasswordHashingSettings, PasswordPolicySettings};
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use secrecy::ExposeSecret;
 use secrecy::Secret;
 use sqlx::PgPool;
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 pub struct FormData {
+    #[schemars(schema_with = "crate::openapi::password")]
     current_password: Secret<String>,
+    #[schemars(schema_with = "crate::openapi::password")]
     new_password: Secret<String>,
+    #[schemars(schema_with = "crate::openapi::password")]
     new_password_check: Secret<String>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn change_password(
+    request: HttpRequest,
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashingSettings>,
+    policy: web::Data<PasswordPolicySettings>,
     user: web::ReqData<AuthenticatedUser>,
+    session: TypedSession,
+    cache: web::Data<SessionCache>,
 ) -> Result<HttpResponse, actix_web::Error> {
     if form.new_password.expose_secret() != form.new_password_check.expose_secret() {
         FlashMessage::error(
@@ -27,19 +42,20 @@ pub async fn change_password(
         return Ok(see_other("/admin/password"));
     }
 
-    if is_password_invalid(&form.new_password) {
-        FlashMessage::error(
-            "The new password should be longer than 12 characters and shorter than 129 characters",
-        )
-        .send();
-        return Ok(see_other("/admin/password"));
+    match check_password_policy(&policy, &user.username, &form.new_password).await {
+        Ok(()) => {}
+        Err(PasswordPolicyError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other("/admin/password"));
+        }
     }
 
     let credentials = Credentials {
         username: user.username.clone(),
         password: form.0.current_password,
     };
-    if let Err(e) = validate_credentials(credentials, &pool).await {
+    if let Err(e) = validate_credentials(credentials, &hashing, &pool).await {
         return match e {
             AuthError::InvalidCredentials(_) => {
                 FlashMessage::error("The current password is incorrect.").send();
@@ -49,7 +65,21 @@ pub async fn change_password(
         };
     }
 
-    crate::authentication::change_password(user.user_id, form.0.new_password, &pool)
+    crate::authentication::change_password(
+        user.user_id,
+        form.0.new_password,
+        &hashing,
+        pool.get_ref(),
+    )
+    .await
+    .map_err(e500)?;
+    // Every other session of the user belongs to the old password from now on.
+    if let Some(session_id) = session.get_session_id().map_err(e500)? {
+        keep_user_session(&pool, session_id).await.map_err(e500)?;
+    }
+    cache.forget_user(user.user_id);
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::PasswordChanged);
+    record_audit_event(pool.get_ref(), event)
         .await
         .map_err(e500)?;
     FlashMessage::info("Your password has been changed.").send();
@@ -0,0 +1,104 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{
+    issue_reviews::{get_issues_under_review, IssueUnderReview},
+    utils::e500,
+};
+
+pub async fn issue_reviews_form(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
+    }
+
+    let issues = get_issues_under_review(pool.get_ref())
+        .await
+        .map_err(e500)?;
+    let mut issues_html = String::new();
+    if issues.is_empty() {
+        issues_html.push_str("<p>No issue is waiting for a review.</p>");
+    }
+    for issue in &issues {
+        writeln!(
+            issues_html,
+            r#"<section>
+                <h2>{title}</h2>
+                <p>By {author} on {submitted_at}, for {audience}</p>
+                <pre>{text_content}</pre>
+                <form action="/admin/reviews/approve" method="post">
+                    <input type="hidden" name="newsletter_issue_id" value="{id}">
+                    <button type="submit">Approve and send</button>
+                </form>
+                <form action="/admin/reviews/reject" method="post">
+                    <input type="hidden" name="newsletter_issue_id" value="{id}">
+                    <label>Comment for the author
+                        <input type="text" name="comment">
+                    </label>
+                    <button type="submit">Reject</button>
+                </form>
+            </section>"#,
+            title = encode_minimal(&issue.title),
+            author = issue.author.as_deref().map_or("-".into(), encode_minimal),
+            submitted_at = issue.submitted_at.format("%Y-%m-%d %H:%M"),
+            audience = audience(issue),
+            text_content = encode_minimal(&issue.text_content),
+            id = encode_attribute(&issue.newsletter_issue_id.to_string()),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Issue reviews</title>
+                </head>
+                <body>
+                    {msg_html}
+                    {issues_html}
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#
+        )))
+}
+
+/// The mailing list, and the segment the author picked within it.
+fn audience(issue: &IssueUnderReview) -> String {
+    let mut audience = format!(
+        "the confirmed members of {}",
+        encode_minimal(&issue.list_name)
+    );
+    if !issue.include_tags.is_empty() {
+        write!(
+            audience,
+            " tagged with any of {}",
+            encode_minimal(&issue.include_tags.join(", "))
+        )
+        .unwrap();
+    }
+    if !issue.exclude_tags.is_empty() {
+        write!(
+            audience,
+            " not tagged with any of {}",
+            encode_minimal(&issue.exclude_tags.join(", "))
+        )
+        .unwrap();
+    }
+    if let Some(after) = issue.subscribed_after {
+        write!(audience, " who joined on or after {after}").unwrap();
+    }
+    if let Some(before) = issue.subscribed_before {
+        write!(audience, " who joined before {before}").unwrap();
+    }
+    audience
+}
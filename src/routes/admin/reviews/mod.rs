@@ -0,0 +1,31 @@
+mod get;
+mod post;
+
+pub use get::issue_reviews_form;
+pub use post::{approve_issue_review, reject_issue_review};
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/reviews", "Issues waiting for a review")
+            .session()
+            .permission(Permission::PublishIssues)
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/reviews/approve", "Approve and send an issue")
+            .session()
+            .permission(Permission::PublishIssues)
+            .form::<post::ApproveFormData>()
+            .redirect("Back to the reviews"),
+    )
+    .add(
+        Operation::post("/admin/reviews/reject", "Reject an issue with a comment")
+            .session()
+            .permission(Permission::PublishIssues)
+            .form::<post::RejectFormData>()
+            .redirect("Back to the reviews"),
+    );
+}
@@ -0,0 +1,75 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::AuthenticatedUser,
+    issue_reviews::{approve_issue, reject_issue, ReviewError},
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct ApproveFormData {
+    newsletter_issue_id: Uuid,
+}
+
+#[tracing::instrument(name = "Approve an issue review", skip(request, form, pool, user))]
+pub async fn approve_issue_review(
+    request: HttpRequest,
+    form: web::Form<ApproveFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    match approve_issue(&pool, form.newsletter_issue_id, user.user_id).await {
+        Ok(n_recipients) => {
+            let event = AuditEvent::new(&request, user.user_id, AuditAction::IssueApproved)
+                .target("issue", form.newsletter_issue_id);
+            record_audit_event(pool.get_ref(), event)
+                .await
+                .map_err(e500)?;
+            FlashMessage::info(format!(
+                "The issue has been approved - it goes out to {n_recipients} subscriber(s) shortly."
+            ))
+            .send()
+        }
+        Err(ReviewError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/admin/reviews"))
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct RejectFormData {
+    newsletter_issue_id: Uuid,
+    /// Shown to the author of the issue.
+    comment: String,
+}
+
+#[tracing::instrument(name = "Reject an issue review", skip(request, form, pool, user))]
+pub async fn reject_issue_review(
+    request: HttpRequest,
+    form: web::Form<RejectFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let comment = form.comment.trim();
+    if comment.is_empty() {
+        FlashMessage::error("Tell the author why the issue is rejected.").send();
+        return Ok(see_other("/admin/reviews"));
+    }
+    match reject_issue(&pool, form.newsletter_issue_id, user.user_id, comment).await {
+        Ok(()) => {
+            let event = AuditEvent::new(&request, user.user_id, AuditAction::IssueRejected)
+                .target("issue", form.newsletter_issue_id);
+            record_audit_event(pool.get_ref(), event)
+                .await
+                .map_err(e500)?;
+            FlashMessage::info("The issue has been rejected.").send()
+        }
+        Err(ReviewError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/admin/reviews"))
+}
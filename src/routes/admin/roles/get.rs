@@ -0,0 +1,105 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{
+    authentication::{get_roles, Permission},
+    utils::e500,
+};
+
+pub async fn roles_form(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let checkboxes = |granted: &[Permission]| {
+        let mut html = String::new();
+        for permission in Permission::ALL {
+            let checked = if granted.contains(&permission) {
+                " checked"
+            } else {
+                ""
+            };
+            writeln!(
+                html,
+                r#"<label><input type="checkbox" name="permission" value="{0}"{checked}> {0}</label>"#,
+                permission.as_str()
+            )
+            .unwrap();
+        }
+        html
+    };
+
+    let mut roles_html = String::new();
+    for role in get_roles(&pool).await.map_err(e500)? {
+        let name = htmlescape::encode_attribute(&role.name);
+        let delete = match role.is_built_in() || role.users > 0 {
+            true => String::new(),
+            false => format!(
+                r#"<form action="/admin/roles/delete" method="post">
+                    <input type="hidden" name="role" value="{name}">
+                    <button type="submit">Delete</button>
+                </form>"#
+            ),
+        };
+        writeln!(
+            roles_html,
+            r#"<tr>
+                <td>{label}</td><td>{users}</td>
+                <td>
+                    <form action="/admin/roles/permissions" method="post">
+                        <input type="hidden" name="role" value="{name}">
+                        {permissions}
+                        <button type="submit">Save</button>
+                    </form>
+                </td>
+                <td>{delete}</td>
+            </tr>"#,
+            label = htmlescape::encode_minimal(&role.name),
+            users = role.users,
+            permissions = checkboxes(&role.permissions),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Roles</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <table>
+                        <tr><th>Role</th><th>Users</th><th>Permissions</th><th></th></tr>
+                        {roles_html}
+                    </table>
+                    <p>New role:</p>
+                    <form action="/admin/roles" method="post">
+                        <label>Name
+                            <input type="text" placeholder="editor" name="name">
+                        </label>
+                        <br>
+                        {new_role_permissions}
+                        <button type="submit">Create role</button>
+                    </form>
+                    <p><a href="/admin/users">Assign roles to users</a></p>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+            new_role_permissions = checkboxes(&[]),
+        )))
+}
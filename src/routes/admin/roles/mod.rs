@@ -0,0 +1,57 @@
+mod get;
+mod post;
+
+pub use get::roles_form;
+pub use post::{add_role, remove_role, update_role_permissions};
+
+use serde_json::json;
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    let permissions: Vec<_> = Permission::ALL.iter().map(|p| p.as_str()).collect();
+    let role_form = |name_field: &str| {
+        json!({
+            "type": "object",
+            "properties": {
+                name_field: { "type": "string" },
+                "permission": {
+                    "type": "array",
+                    "items": { "type": "string", "enum": permissions },
+                },
+            },
+            "required": [name_field],
+        })
+    };
+    api.add(
+        Operation::get("/admin/roles", "Roles and the permissions they grant")
+            .session()
+            .permission(Permission::ManageUsers)
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/roles", "Create a role")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form_schema(role_form("name"))
+            .redirect("Back to the roles"),
+    )
+    .add(
+        Operation::post(
+            "/admin/roles/permissions",
+            "Change the permissions of a role",
+        )
+        .session()
+        .permission(Permission::ManageUsers)
+        .form_schema(role_form("role"))
+        .redirect("Back to the roles"),
+    )
+    .add(
+        Operation::post("/admin/roles/delete", "Delete a role nobody has")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form::<post::RoleFormData>()
+            .redirect("Back to the roles"),
+    );
+}
@@ -0,0 +1,121 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::{
+        create_role, delete_role, set_role_permissions, AccountError, AuthenticatedUser,
+        Permission, SessionCache,
+    },
+    utils::{e400, e500, see_other},
+};
+
+/// The form repeats `permission` once per checked box, hence the list of pairs.
+#[tracing::instrument(name = "Add a role", skip(request, form, pool, user))]
+pub async fn add_role(
+    request: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (name, permissions) = parse_role_form(&form, "name")?;
+    let result = create_role(&pool, &name, &permissions).await;
+    let event =
+        AuditEvent::new(&request, user.user_id, AuditAction::RoleCreated).target("role", &name);
+    back_to_roles(
+        &pool,
+        result,
+        event,
+        &format!("The role {name} has been created."),
+    )
+    .await
+}
+
+#[tracing::instrument(
+    name = "Update the permissions of a role",
+    skip(request, form, pool, user, cache)
+)]
+pub async fn update_role_permissions(
+    request: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (role, permissions) = parse_role_form(&form, "role")?;
+    let result = set_role_permissions(&pool, &role, &permissions).await;
+    // The cached sessions still carry the old permissions.
+    cache.forget_role(&role);
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::RolePermissionsChanged)
+        .target("role", &role);
+    back_to_roles(
+        &pool,
+        result,
+        event,
+        &format!("The permissions of {role} have been updated."),
+    )
+    .await
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct RoleFormData {
+    role: String,
+}
+
+#[tracing::instrument(name = "Remove a role", skip(request, form, pool, user))]
+pub async fn remove_role(
+    request: HttpRequest,
+    form: web::Form<RoleFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let result = delete_role(&pool, &form.role).await;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::RoleDeleted)
+        .target("role", &form.role);
+    back_to_roles(
+        &pool,
+        result,
+        event,
+        &format!("The role {} has been deleted.", form.role),
+    )
+    .await
+}
+
+/// The role named by `name_field`, and the permissions checked for it.
+fn parse_role_form(
+    form: &[(String, String)],
+    name_field: &str,
+) -> Result<(String, Vec<Permission>), actix_web::Error> {
+    let mut name = String::new();
+    let mut permissions = Vec::new();
+    for (key, value) in form {
+        if key == name_field {
+            name = value.trim().to_owned();
+        } else if key == "permission" {
+            permissions.push(
+                Permission::parse(value)
+                    .ok_or_else(|| e400(format!("'{value}' is not a known permission.")))?,
+            );
+        }
+    }
+    Ok((name, permissions))
+}
+
+/// Record `event` if the change went through.
+async fn back_to_roles(
+    pool: &PgPool,
+    result: Result<(), AccountError>,
+    event: AuditEvent,
+    success: &str,
+) -> Result<HttpResponse, actix_web::Error> {
+    match result {
+        Ok(()) => {
+            record_audit_event(pool, event).await.map_err(e500)?;
+            FlashMessage::info(success).send()
+        }
+        Err(AccountError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/admin/roles"))
+}
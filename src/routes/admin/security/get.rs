@@ -0,0 +1,108 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{
+    authentication::{
+        get_two_factor_status, is_two_factor_required, totp_uri, AuthenticatedUser, Permission,
+    },
+    utils::e500,
+};
+
+pub async fn security_settings_form(
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let status = get_two_factor_status(&pool, user.user_id)
+        .await
+        .map_err(e500)?;
+    let required = is_two_factor_required(&pool).await.map_err(e500)?;
+
+    let mut two_factor_html = String::new();
+    if status.enabled {
+        write!(
+            two_factor_html,
+            r#"<p>Two-factor authentication is enabled. {} recovery code(s) left.</p>
+            <form action="/admin/security/two-factor/disable" method="post">
+                <label>Current code
+                <input type="text" placeholder="Enter code" name="code">
+                </label>
+                <button type="submit">Disable two-factor authentication</button>
+            </form>"#,
+            status.recovery_codes_left
+        )
+        .unwrap();
+    } else if let Some(secret) = status.pending_secret {
+        let uri = totp_uri(&user.username, &secret);
+        write!(
+            two_factor_html,
+            r#"<p>Add this account to your authenticator app by scanning or opening
+            <a href="{uri_attr}">{uri}</a>, or enter the secret <code>{secret}</code> manually.</p>
+            <form action="/admin/security/two-factor/confirm" method="post">
+                <label>Code shown by the app
+                <input type="text" placeholder="Enter code" name="code" autocomplete="one-time-code">
+                </label>
+                <button type="submit">Enable two-factor authentication</button>
+            </form>"#,
+            uri_attr = htmlescape::encode_attribute(&uri),
+            uri = htmlescape::encode_minimal(&uri),
+        )
+        .unwrap();
+    } else {
+        two_factor_html.push_str(
+            r#"<p>Two-factor authentication is disabled.</p>
+            <form action="/admin/security/two-factor" method="post">
+                <button type="submit">Set up two-factor authentication</button>
+            </form>"#,
+        );
+    }
+    if required && !status.enabled {
+        two_factor_html.insert_str(
+            0,
+            "<p>An administrator requires two-factor authentication - \
+            set it up to continue.</p>",
+        );
+    }
+
+    let policy_html = match user.can(Permission::ManageUsers) {
+        true => format!(
+            r#"<form action="/admin/security/policy" method="post">
+                <label><input type="checkbox" name="require_two_factor" value="on"{checked}>
+                Require two-factor authentication for all users</label>
+                <button type="submit">Update policy</button>
+            </form>"#,
+            checked = if required { " checked" } else { "" }
+        ),
+        false => String::new(),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Security</title>
+                </head>
+                <body>
+                    {msg_html}
+                    {two_factor_html}
+                    {policy_html}
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+        )))
+}
@@ -0,0 +1,53 @@
+mod get;
+mod post;
+
+pub use get::security_settings_form;
+pub use post::{confirm_two_factor, enroll_two_factor, remove_two_factor, update_security_policy};
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/security", "Two-factor authentication settings")
+            .session()
+            .html(),
+    )
+    .add(
+        Operation::post(
+            "/admin/security/two-factor",
+            "Start enrolling a second factor",
+        )
+        .session()
+        .redirect("Back to the settings, with the secret to scan"),
+    )
+    .add(
+        Operation::post(
+            "/admin/security/two-factor/confirm",
+            "Enable the second factor",
+        )
+        .session()
+        .form::<post::CodeFormData>()
+        .html()
+        .redirect("Back to the settings, if the code is not valid"),
+    )
+    .add(
+        Operation::post(
+            "/admin/security/two-factor/disable",
+            "Disable the second factor",
+        )
+        .session()
+        .form::<post::CodeFormData>()
+        .redirect("Back to the settings"),
+    )
+    .add(
+        Operation::post(
+            "/admin/security/policy",
+            "Require a second factor from everyone",
+        )
+        .session()
+        .permission(Permission::ManageUsers)
+        .form::<post::PolicyFormData>()
+        .redirect("Back to the settings"),
+    );
+}
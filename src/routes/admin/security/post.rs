@@ -0,0 +1,132 @@
+use actix_web::{
+    http::header::{CacheControl, CacheDirective, ContentType},
+    web, HttpRequest, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::{
+        confirm_two_factor_enrollment, disable_two_factor, is_two_factor_required,
+        set_two_factor_required, start_two_factor_enrollment, verify_second_factor,
+        AuthenticatedUser,
+    },
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct CodeFormData {
+    code: String,
+}
+
+#[tracing::instrument(name = "Enroll a second factor", skip(pool, user))]
+pub async fn enroll_two_factor(
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    start_two_factor_enrollment(&pool, user.user_id)
+        .await
+        .map_err(e500)?;
+    Ok(redirect_to_form())
+}
+
+/// The recovery codes are shown once, in the response itself: a flash message
+/// would carry them in a cookie.
+#[tracing::instrument(name = "Confirm a second factor", skip(form, pool, user))]
+pub async fn confirm_two_factor(
+    form: web::Form<CodeFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(recovery_codes) = confirm_two_factor_enrollment(&pool, user.user_id, &form.code)
+        .await
+        .map_err(e500)?
+    else {
+        FlashMessage::error("The code is not valid - try again.").send();
+        return Ok(redirect_to_form());
+    };
+    let mut codes_html = String::new();
+    for code in recovery_codes {
+        write!(codes_html, "<li><code>{code}</code></li>").unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Recovery codes</title>
+                </head>
+                <body>
+                    <p>Two-factor authentication is enabled. Store these recovery codes
+                    somewhere safe - each of them can be used once instead of a code.
+                    They will not be shown again.</p>
+                    <ul>{codes_html}</ul>
+                    <p><a href="/admin/security">Continue</a></p>
+                </body>
+            </html>"#,
+        )))
+}
+
+#[tracing::instrument(name = "Remove a second factor", skip(form, pool, user))]
+pub async fn remove_two_factor(
+    form: web::Form<CodeFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if is_two_factor_required(&pool).await.map_err(e500)? {
+        FlashMessage::error("An administrator requires two-factor authentication.").send();
+        return Ok(redirect_to_form());
+    }
+    if !verify_second_factor(&pool, user.user_id, &form.code)
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error("The code is not valid - try again.").send();
+        return Ok(redirect_to_form());
+    }
+    disable_two_factor(&pool, user.user_id)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("Two-factor authentication is disabled.").send();
+    Ok(redirect_to_form())
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct PolicyFormData {
+    require_two_factor: Option<String>,
+}
+
+#[tracing::instrument(name = "Update the security policy", skip(request, form, pool, user))]
+pub async fn update_security_policy(
+    request: HttpRequest,
+    form: web::Form<PolicyFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let required = form.require_two_factor.is_some();
+    set_two_factor_required(&pool, required)
+        .await
+        .map_err(e500)?;
+    let action = if required {
+        AuditAction::TwoFactorRequired
+    } else {
+        AuditAction::TwoFactorOptional
+    };
+    record_audit_event(
+        pool.get_ref(),
+        AuditEvent::new(&request, user.user_id, action),
+    )
+    .await
+    .map_err(e500)?;
+    FlashMessage::info("The security policy has been updated.").send();
+    Ok(redirect_to_form())
+}
+
+fn redirect_to_form() -> HttpResponse {
+    see_other("/admin/security")
+}
@@ -0,0 +1,91 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{
+    authentication::{get_user_sessions, AuthenticatedUser, Permission},
+    session_state::TypedSession,
+    utils::e500,
+};
+
+pub async fn sessions_form(
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let current_session_id = session.get_session_id().map_err(e500)?;
+    let mut sessions_html = String::new();
+    for s in get_user_sessions(&pool, user.user_id).await.map_err(e500)? {
+        let action = if Some(s.session_id) == current_session_id {
+            "<b>This session</b>".to_owned()
+        } else {
+            format!(
+                r#"<input type="hidden" name="session_id" value="{}">
+                    <button type="submit">Revoke</button>"#,
+                s.session_id
+            )
+        };
+        writeln!(
+            sessions_html,
+            r#"<li>
+                <form action="/admin/sessions" method="post">
+                    {ip} - {user_agent} - signed in {created_at}, last seen {last_seen_at}
+                    {action}
+                </form>
+            </li>"#,
+            ip = htmlescape::encode_minimal(&s.ip),
+            user_agent = htmlescape::encode_minimal(&s.user_agent),
+            created_at = s.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            last_seen_at = s.last_seen_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        )
+        .unwrap();
+    }
+
+    let force_relogin_html = match user.can(Permission::ManageUsers) {
+        true => {
+            r#"<form action="/admin/sessions/force-relogin" method="post">
+                <label>Force a user to log in again
+                    <input type="text" placeholder="Enter username" name="username">
+                </label>
+                <button type="submit">Log out everywhere</button>
+            </form>"#
+        }
+        false => "",
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Active sessions</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <p>You are logged in on:</p>
+                    <ul>
+                        {sessions_html}
+                    </ul>
+                    <form action="/admin/sessions/others" method="post">
+                        <button type="submit">Log out everywhere else</button>
+                    </form>
+                    {force_relogin_html}
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+        )))
+}
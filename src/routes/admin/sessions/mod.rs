@@ -0,0 +1,34 @@
+mod get;
+mod post;
+
+pub use get::sessions_form;
+pub use post::{force_relogin, revoke_other_sessions, revoke_session};
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/sessions", "Active sessions")
+            .session()
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/sessions", "Revoke a session")
+            .session()
+            .form::<post::FormData>()
+            .redirect("Back to the sessions"),
+    )
+    .add(
+        Operation::post("/admin/sessions/others", "Revoke every other session")
+            .session()
+            .redirect("Back to the sessions"),
+    )
+    .add(
+        Operation::post("/admin/sessions/force-relogin", "Log a user out everywhere")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form::<post::ForceReloginFormData>()
+            .redirect("Back to the sessions"),
+    );
+}
@@ -0,0 +1,104 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::{
+        revoke_other_user_sessions, revoke_user_session, revoke_user_sessions, AuthenticatedUser,
+        SessionCache,
+    },
+    session_state::TypedSession,
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    session_id: Uuid,
+}
+
+#[tracing::instrument(name = "Revoke a session", skip(request, form, pool, user, cache), fields(user_id=%user.user_id))]
+pub async fn revoke_session(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if revoke_user_session(&pool, user.user_id, form.session_id)
+        .await
+        .map_err(e500)?
+    {
+        cache.forget_session(form.session_id);
+        let event = AuditEvent::new(&request, user.user_id, AuditAction::SessionRevoked)
+            .target("session", form.session_id);
+        record_audit_event(pool.get_ref(), event)
+            .await
+            .map_err(e500)?;
+        FlashMessage::info("The session has been revoked.").send();
+    } else {
+        FlashMessage::error("The session does not exist or has already ended.").send();
+    }
+    Ok(see_other("/admin/sessions"))
+}
+
+#[tracing::instrument(name = "Revoke other sessions", skip(request, pool, user, session, cache), fields(user_id=%user.user_id))]
+pub async fn revoke_other_sessions(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    session: TypedSession,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let session_id = session
+        .get_session_id()
+        .map_err(e500)?
+        .ok_or_else(|| e500("The session is not tracked"))?;
+    revoke_other_user_sessions(&pool, user.user_id, session_id)
+        .await
+        .map_err(e500)?;
+    cache.forget_user(user.user_id);
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::OtherSessionsRevoked);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("All your other sessions have been logged out.").send();
+    Ok(see_other("/admin/sessions"))
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct ForceReloginFormData {
+    username: String,
+}
+
+/// Log a user out of all of their sessions, so that they have to log in again.
+#[tracing::instrument(name = "Force a user to log in again", skip(request, form, pool, user, cache), fields(username=%form.username))]
+pub async fn force_relogin(
+    request: HttpRequest,
+    form: web::Form<ForceReloginFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let username = &form.username;
+    let row = sqlx::query!("SELECT user_id FROM users WHERE username = $1", username)
+        .fetch_optional(pool.get_ref())
+        .await
+        .map_err(e500)?;
+    let Some(row) = row else {
+        FlashMessage::error(format!("There is no user named '{username}'.")).send();
+        return Ok(see_other("/admin/sessions"));
+    };
+    revoke_user_sessions(pool.get_ref(), row.user_id)
+        .await
+        .map_err(e500)?;
+    cache.forget_user(row.user_id);
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::UserSessionsRevoked)
+        .target("user", row.user_id);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info(format!("'{username}' has to log in again.")).send();
+    Ok(see_other("/admin/sessions"))
+}
@@ -0,0 +1,86 @@
+use actix_web::{http::header::ContentType, web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::AuthenticatedUser,
+    subscriber_data::get_subscriber_data,
+    utils::{e500, see_other},
+};
+
+pub async fn subscriber_data_form(
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Subscriber data requests</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <form action="/admin/subscribers/erase" method="post">
+                        <label>Subscriber email
+                        <input type="text" placeholder="Enter email" name="email">
+                        </label>
+                        <br>
+                        <button type="submit" formaction="/admin/subscribers/export" formmethod="get">
+                            Export data
+                        </button>
+                        <button type="submit">Erase data</button>
+                    </form>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+        )))
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct QueryData {
+    email: String,
+}
+
+#[tracing::instrument(
+    name = "Export subscriber data for an admin",
+    skip(request, query, pool, user)
+)]
+pub async fn export_subscriber(
+    request: HttpRequest,
+    query: web::Query<QueryData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = query.into_inner().email;
+    let data = get_subscriber_data(&pool, email.trim())
+        .await
+        .context("Failed to export subscriber data")
+        .map_err(e500)?;
+    if data.is_empty() {
+        FlashMessage::error("No data is stored for this email address.").send();
+        return Ok(see_other("/admin/subscribers"));
+    }
+    let mut event = AuditEvent::new(&request, user.user_id, AuditAction::SubscriberExported);
+    if let Some(subscription) = &data.subscription {
+        event = event.target("subscriber", subscription.id);
+    }
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    Ok(data.into_response())
+}
@@ -0,0 +1,38 @@
+mod get;
+mod post;
+
+pub use get::{export_subscriber, subscriber_data_form};
+pub use post::erase_subscriber;
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/subscribers", "Subscriber data form")
+            .session()
+            .permission(Permission::ManageSubscribers)
+            .html(),
+    )
+    .add(
+        Operation::get(
+            "/admin/subscribers/export",
+            "Download the data stored about a subscriber",
+        )
+        .session()
+        .permission(Permission::ManageSubscribers)
+        .query::<get::QueryData>()
+        .respond(200, "The stored data, as a JSON attachment")
+        .redirect("Back to the form if nothing is stored"),
+    )
+    .add(
+        Operation::post(
+            "/admin/subscribers/erase",
+            "Erase the data stored about a subscriber",
+        )
+        .session()
+        .permission(Permission::ManageSubscribers)
+        .form::<post::FormData>()
+        .redirect("Back to the form"),
+    );
+}
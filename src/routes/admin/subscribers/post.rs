@@ -0,0 +1,57 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::AuthenticatedUser,
+    subscriber_data::erase_subscriber_data,
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    email: String,
+}
+
+#[tracing::instrument(
+    name = "Erase subscriber data for an admin",
+    skip(request, form, pool, user)
+)]
+pub async fn erase_subscriber(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = form.into_inner().email;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let erased = erase_subscriber_data(&mut transaction, email.trim())
+        .await
+        .context("Failed to erase subscriber data")
+        .map_err(e500)?;
+    if erased {
+        // Without a target: the log must not keep the address that was erased.
+        let event = AuditEvent::new(&request, user.user_id, AuditAction::SubscriberErased);
+        record_audit_event(&mut *transaction, event)
+            .await
+            .map_err(e500)?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit transaction")
+        .map_err(e500)?;
+
+    if erased {
+        FlashMessage::info("All data stored for this email address has been erased.").send();
+    } else {
+        FlashMessage::error("No data is stored for this email address.").send();
+    }
+    Ok(see_other("/admin/subscribers"))
+}
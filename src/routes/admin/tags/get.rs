@@ -0,0 +1,85 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::utils::e500;
+
+pub async fn subscriber_tags_form(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let mut tags_html = String::new();
+    for tag in get_tag_counts(&pool).await.map_err(e500)? {
+        writeln!(
+            tags_html,
+            "<li>{} ({} subscriber(s))</li>",
+            tag.tag, tag.n_subscribers
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Subscriber tags</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <p>Tags in use:</p>
+                    <ul>
+                        {tags_html}
+                    </ul>
+                    <form action="/admin/tags" method="post">
+                        <label>Subscriber email
+                        <input type="text" placeholder="Enter email" name="email">
+                        </label>
+                        <br>
+                        <label>Tags (comma separated)
+                        <input type="text" placeholder="beta, early-adopter" name="tags">
+                        </label>
+                        <br>
+                        <label><input type="radio" name="action" value="add" checked> Add</label>
+                        <label><input type="radio" name="action" value="remove"> Remove</label>
+                        <br>
+                        <button type="submit">Update tags</button>
+                    </form>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+        )))
+}
+
+struct TagCount {
+    tag: String,
+    n_subscribers: i64,
+}
+
+#[tracing::instrument(name = "Count subscribers per tag", skip_all)]
+async fn get_tag_counts(pool: &PgPool) -> Result<Vec<TagCount>, sqlx::Error> {
+    sqlx::query_as!(
+        TagCount,
+        r#"
+        SELECT tag, COUNT(*) AS "n_subscribers!"
+        FROM subscriber_tags
+        GROUP BY tag
+        ORDER BY tag
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
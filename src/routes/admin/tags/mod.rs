@@ -0,0 +1,24 @@
+mod get;
+mod post;
+
+pub use get::subscriber_tags_form;
+pub use post::update_subscriber_tags;
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/tags", "Subscriber tags form")
+            .session()
+            .permission(Permission::ManageSubscribers)
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/tags", "Add or remove tags of a subscriber")
+            .session()
+            .permission(Permission::ManageSubscribers)
+            .form::<post::FormData>()
+            .redirect("Back to the form"),
+    );
+}
@@ -0,0 +1,131 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::AuthenticatedUser,
+    domain::{Email, SubscriberTag},
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagAction {
+    Add,
+    Remove,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    email: String,
+    tags: String,
+    action: TagAction,
+}
+
+#[tracing::instrument(name = "Update subscriber tags", skip(request, form, pool, user))]
+pub async fn update_subscriber_tags(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        email,
+        tags,
+        action,
+    } = form.into_inner();
+    let email = match Email::parse(email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(redirect_to_form());
+        }
+    };
+    let tags: Vec<String> = match SubscriberTag::parse_list(&tags) {
+        Ok(tags) if tags.is_empty() => {
+            FlashMessage::error("Enter at least one tag.").send();
+            return Ok(redirect_to_form());
+        }
+        Ok(tags) => tags.iter().map(|t| t.as_ref().to_owned()).collect(),
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(redirect_to_form());
+        }
+    };
+
+    let subscriber_id = match get_subscriber_id(&pool, &email)
+        .await
+        .context("Failed to fetch the subscriber")
+        .map_err(e500)?
+    {
+        Some(subscriber_id) => subscriber_id,
+        None => {
+            FlashMessage::error("There is no subscriber with this email address.").send();
+            return Ok(redirect_to_form());
+        }
+    };
+    match action {
+        TagAction::Add => add_tags(&pool, subscriber_id, &tags).await,
+        TagAction::Remove => remove_tags(&pool, subscriber_id, &tags).await,
+    }
+    .context("Failed to update subscriber tags")
+    .map_err(e500)?;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::SubscriberTagged)
+        .target("subscriber", subscriber_id);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+
+    FlashMessage::info("The subscriber's tags have been updated.").send();
+    Ok(redirect_to_form())
+}
+
+fn redirect_to_form() -> HttpResponse {
+    see_other("/admin/tags")
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_subscriber_id(pool: &PgPool, email: &Email) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        "SELECT id FROM subscriptions WHERE email = $1",
+        email.as_ref()
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.id))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn add_tags(pool: &PgPool, subscriber_id: Uuid, tags: &[String]) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_tags (subscriber_id, tag)
+        SELECT $1, tag FROM UNNEST($2::text[]) AS tag
+        ON CONFLICT DO NOTHING
+        "#,
+        subscriber_id,
+        tags
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn remove_tags(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    tags: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM subscriber_tags WHERE subscriber_id = $1 AND tag = ANY($2)",
+        subscriber_id,
+        tags
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
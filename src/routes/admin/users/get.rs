@@ -0,0 +1,112 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{
+    authentication::{get_roles, get_user_accounts, AuthenticatedUser},
+    utils::e500,
+};
+
+pub async fn user_accounts_form(
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let action = |path: &str, label: &str, user_id, extra: &str| {
+        format!(
+            r#"<form action="/admin/users/{path}" method="post">
+                <input type="hidden" name="user_id" value="{user_id}">{extra}
+                <button type="submit">{label}</button>
+            </form>"#
+        )
+    };
+    let roles = get_roles(&pool).await.map_err(e500)?;
+    let mut users_html = String::new();
+    for account in get_user_accounts(&pool).await.map_err(e500)? {
+        let (status, toggle) = match account.deactivated_at {
+            Some(at) => (
+                format!("deactivated {}", at.format("%Y-%m-%d")),
+                action("reactivate", "Reactivate", account.user_id, ""),
+            ),
+            None => (
+                "active".to_owned(),
+                action("deactivate", "Deactivate", account.user_id, ""),
+            ),
+        };
+        let mut role_options = String::new();
+        for role in &roles {
+            let selected = if role.name == account.role {
+                " selected"
+            } else {
+                ""
+            };
+            writeln!(
+                role_options,
+                r#"<option value="{0}"{selected}>{0}</option>"#,
+                htmlescape::encode_attribute(&role.name)
+            )
+            .unwrap();
+        }
+        let role_change = action(
+            "role",
+            "Change role",
+            account.user_id,
+            &format!(r#"<select name="role">{role_options}</select>"#),
+        );
+        let you = if account.user_id == user.user_id {
+            " (you)"
+        } else {
+            ""
+        };
+        writeln!(
+            users_html,
+            r#"<tr>
+                <td>{username}{you}</td><td>{email}</td><td>{role}</td>
+                <td>{created_at}</td><td>{status}</td>
+                <td>{toggle}{role_change}{delete}</td>
+            </tr>"#,
+            username = htmlescape::encode_minimal(&account.username),
+            email = htmlescape::encode_minimal(account.email.as_deref().unwrap_or("-")),
+            role = htmlescape::encode_minimal(&account.role),
+            created_at = account.created_at.format("%Y-%m-%d"),
+            delete = action("delete", "Delete", account.user_id, ""),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Users</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <table>
+                        <tr>
+                            <th>Username</th><th>Email</th><th>Role</th>
+                            <th>Created</th><th>Status</th><th>Actions</th>
+                        </tr>
+                        {users_html}
+                    </table>
+                    <p><a href="/admin/roles">Roles and permissions</a></p>
+                    <p><a href="/admin/collabolators">Invite a new collaborator</a></p>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+        )))
+}
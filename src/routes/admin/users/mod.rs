@@ -0,0 +1,45 @@
+mod get;
+mod post;
+
+pub use get::user_accounts_form;
+pub use post::{change_account_role, deactivate_account, delete_account, reactivate_account};
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/admin/users", "Users, their roles and status")
+            .session()
+            .permission(Permission::ManageUsers)
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/users/deactivate", "Deactivate a user")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form::<post::AccountFormData>()
+            .redirect("Back to the users"),
+    )
+    .add(
+        Operation::post("/admin/users/reactivate", "Reactivate a user")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form::<post::AccountFormData>()
+            .redirect("Back to the users"),
+    )
+    .add(
+        Operation::post("/admin/users/delete", "Delete a user")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form::<post::AccountFormData>()
+            .redirect("Back to the users"),
+    )
+    .add(
+        Operation::post("/admin/users/role", "Change the role of a user")
+            .session()
+            .permission(Permission::ManageUsers)
+            .form::<post::RoleFormData>()
+            .redirect("Back to the users"),
+    );
+}
@@ -0,0 +1,139 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::{
+        change_user_role, deactivate_user, delete_user, reactivate_user, AccountError,
+        AuthenticatedUser, SessionCache,
+    },
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct AccountFormData {
+    user_id: Uuid,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct RoleFormData {
+    user_id: Uuid,
+    role: String,
+}
+
+#[tracing::instrument(name = "Deactivate a user account", skip(request, form, pool, user, cache), fields(user_id=%form.user_id))]
+pub async fn deactivate_account(
+    request: HttpRequest,
+    form: web::Form<AccountFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = begin(&pool).await?;
+    let result = deactivate_user(&mut transaction, form.user_id).await;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::UserDeactivated)
+        .target("user", form.user_id);
+    let response =
+        back_to_users(transaction, result, event, "The user has been deactivated.").await;
+    cache.forget_user(form.user_id);
+    response
+}
+
+#[tracing::instrument(name = "Reactivate a user account", skip(request, form, pool, user), fields(user_id=%form.user_id))]
+pub async fn reactivate_account(
+    request: HttpRequest,
+    form: web::Form<AccountFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = begin(&pool).await?;
+    let result = reactivate_user(&mut transaction, form.user_id).await;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::UserReactivated)
+        .target("user", form.user_id);
+    back_to_users(transaction, result, event, "The user has been reactivated.").await
+}
+
+#[tracing::instrument(name = "Delete a user account", skip(request, form, pool, user, cache), fields(user_id=%form.user_id))]
+pub async fn delete_account(
+    request: HttpRequest,
+    form: web::Form<AccountFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = begin(&pool).await?;
+    let result = delete_user(&mut transaction, form.user_id).await;
+    // The target keeps the username, which the log cannot look up any more.
+    let username = result.as_deref().unwrap_or_default();
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::UserDeleted)
+        .target("user", format!("{} username:{username}", form.user_id));
+    let response = back_to_users(
+        transaction,
+        result.map(|_| ()),
+        event,
+        "The user has been deleted.",
+    )
+    .await;
+    cache.forget_user(form.user_id);
+    response
+}
+
+#[tracing::instrument(name = "Change the role of a user account", skip(request, form, pool, user, cache), fields(user_id=%form.user_id))]
+pub async fn change_account_role(
+    request: HttpRequest,
+    form: web::Form<RoleFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = begin(&pool).await?;
+    let result = change_user_role(&mut transaction, form.user_id, &form.role).await;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::UserRoleChanged)
+        .target("user", format!("{} role:{}", form.user_id, form.role));
+    let response = back_to_users(
+        transaction,
+        result,
+        event,
+        &format!("The user is now {}.", form.role),
+    )
+    .await;
+    // The cached sessions still carry the old role.
+    cache.forget_user(form.user_id);
+    response
+}
+
+async fn begin(pool: &PgPool) -> Result<Transaction<'static, Postgres>, actix_web::Error> {
+    pool.begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)
+}
+
+/// Record `event` and commit if the change went through; a refused change is
+/// rolled back with the transaction.
+async fn back_to_users(
+    mut transaction: Transaction<'_, Postgres>,
+    result: Result<(), AccountError>,
+    event: AuditEvent,
+    success: &str,
+) -> Result<HttpResponse, actix_web::Error> {
+    match result {
+        Ok(()) => {
+            record_audit_event(&mut *transaction, event)
+                .await
+                .map_err(e500)?;
+            transaction
+                .commit()
+                .await
+                .context("Failed to commit the account change.")
+                .map_err(e500)?;
+            FlashMessage::info(success).send()
+        }
+        Err(AccountError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/admin/users"))
+}
@@ -0,0 +1,129 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use std::fmt::Write as _;
+
+use crate::{
+    startup::HmacSecret,
+    utils::e500,
+    webhook_dispatcher::MAX_WEBHOOK_ATTEMPTS,
+    webhooks::{get_webhook_deliveries, get_webhook_endpoints, signing_secret, WebhookEventType},
+};
+
+const DELIVERIES_SHOWN: i64 = 50;
+
+pub async fn webhooks_form(
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+
+    let mut endpoints_html = String::new();
+    for endpoint in get_webhook_endpoints(&pool).await.map_err(e500)? {
+        writeln!(
+            endpoints_html,
+            r#"<li>
+                <form action="/admin/webhooks/delete" method="post">
+                    {url} ({events}) - added {created_at}
+                    <input type="hidden" name="endpoint_id" value="{endpoint_id}">
+                    <button type="submit">Remove</button>
+                </form>
+            </li>"#,
+            url = htmlescape::encode_minimal(&endpoint.url),
+            events = htmlescape::encode_minimal(&endpoint.events.join(", ")),
+            created_at = endpoint.created_at.format("%Y-%m-%d"),
+            endpoint_id = endpoint.endpoint_id,
+        )
+        .unwrap();
+    }
+    if endpoints_html.is_empty() {
+        endpoints_html.push_str("<li>No webhook endpoints.</li>");
+    }
+
+    let mut events_html = String::new();
+    for event in WebhookEventType::ALL {
+        writeln!(
+            events_html,
+            r#"<label><input type="checkbox" name="event" value="{0}"> {0}</label><br>"#,
+            event.as_str()
+        )
+        .unwrap();
+    }
+
+    let mut deliveries_html = String::new();
+    for delivery in get_webhook_deliveries(pool.get_ref(), DELIVERIES_SHOWN)
+        .await
+        .map_err(e500)?
+    {
+        let outcome = match (delivery.last_status_code, &delivery.last_error) {
+            (_, Some(error)) => htmlescape::encode_minimal(error),
+            (Some(status_code), None) => format!("HTTP {status_code}"),
+            (None, None) => "not attempted yet".to_owned(),
+        };
+        writeln!(
+            deliveries_html,
+            "<tr><td>{created_at}</td><td>{event_type}</td><td>{url}</td>\
+            <td>{status}</td><td>{n_attempts}/{MAX_WEBHOOK_ATTEMPTS}</td><td>{outcome}</td></tr>",
+            created_at = delivery.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            event_type = htmlescape::encode_minimal(&delivery.event_type),
+            url = htmlescape::encode_minimal(&delivery.url),
+            status = htmlescape::encode_minimal(&delivery.status),
+            n_attempts = delivery.n_attempts,
+        )
+        .unwrap();
+    }
+
+    let signing_secret = signing_secret(&hmac_secret.0);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Webhooks</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <p>Webhook endpoints:</p>
+                    <ul>
+                        {endpoints_html}
+                    </ul>
+                    <p>
+                        Payloads are signed with <code>{secret}</code>: the
+                        <code>X-Webhook-Signature</code> header is <code>sha256=</code>
+                        followed by the hex HMAC-SHA256 of
+                        <code>{{X-Webhook-Timestamp}}.{{body}}</code>.
+                    </p>
+                    <form action="/admin/webhooks" method="post">
+                        <label>URL
+                            <input type="url" placeholder="https://example.com/hooks" name="url">
+                        </label>
+                        <br>
+                        {events_html}
+                        <button type="submit">Add endpoint</button>
+                    </form>
+                    <p>Latest deliveries:</p>
+                    <table>
+                        <tr>
+                            <th>Created</th><th>Event</th><th>Endpoint</th>
+                            <th>Status</th><th>Attempts</th><th>Last outcome</th>
+                        </tr>
+                        {deliveries_html}
+                    </table>
+                    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+                </body>
+            </html>"#,
+            secret = signing_secret.expose_secret(),
+        )))
+}
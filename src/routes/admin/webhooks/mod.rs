@@ -0,0 +1,45 @@
+mod get;
+mod post;
+
+pub use get::webhooks_form;
+pub use post::{create_webhook, remove_webhook};
+
+use serde_json::json;
+
+use crate::authentication::Permission;
+use crate::openapi::{OpenApi, Operation};
+use crate::webhooks::WebhookEventType;
+
+pub(super) fn document(api: &mut OpenApi) {
+    let events: Vec<_> = WebhookEventType::ALL.iter().map(|e| e.as_str()).collect();
+    api.add(
+        Operation::get("/admin/webhooks", "Webhook endpoints and their deliveries")
+            .session()
+            .permission(Permission::ManageSubscribers)
+            .html(),
+    )
+    .add(
+        Operation::post("/admin/webhooks", "Add a webhook endpoint")
+            .session()
+            .permission(Permission::ManageSubscribers)
+            .form_schema(json!({
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string", "format": "uri" },
+                    "event": {
+                        "type": "array",
+                        "items": { "type": "string", "enum": events },
+                    },
+                },
+                "required": ["url"],
+            }))
+            .redirect("Back to the webhooks"),
+    )
+    .add(
+        Operation::post("/admin/webhooks/delete", "Remove a webhook endpoint")
+            .session()
+            .permission(Permission::ManageSubscribers)
+            .form::<post::DeleteFormData>()
+            .redirect("Back to the webhooks"),
+    );
+}
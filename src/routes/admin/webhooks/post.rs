@@ -0,0 +1,85 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use reqwest::Url;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::AuthenticatedUser,
+    utils::{e400, e500, see_other},
+    webhooks::{create_webhook_endpoint, delete_webhook_endpoint, WebhookEventType},
+};
+
+/// The form repeats `event` once per checked box, hence the list of pairs.
+#[tracing::instrument(name = "Add a webhook endpoint", skip(request, form, pool, user))]
+pub async fn create_webhook(
+    request: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut url = "";
+    let mut events = Vec::new();
+    for (key, value) in form.iter() {
+        match key.as_str() {
+            "url" => url = value.trim(),
+            "event" => events.push(
+                WebhookEventType::parse(value)
+                    .ok_or_else(|| e400(format!("'{value}' is not a known event.")))?,
+            ),
+            _ => {}
+        }
+    }
+
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+        _ => {
+            FlashMessage::error("The endpoint needs an http(s) URL.").send();
+            return Ok(see_other("/admin/webhooks"));
+        }
+    }
+    if events.is_empty() {
+        FlashMessage::error("Choose at least one event.").send();
+        return Ok(see_other("/admin/webhooks"));
+    }
+
+    let endpoint_id = create_webhook_endpoint(&pool, url, &events)
+        .await
+        .map_err(e500)?;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::WebhookCreated)
+        .target("webhook", endpoint_id);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info(format!("Events will be sent to {url}.")).send();
+    Ok(see_other("/admin/webhooks"))
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct DeleteFormData {
+    endpoint_id: Uuid,
+}
+
+#[tracing::instrument(name = "Remove a webhook endpoint", skip(request, form, pool, user))]
+pub async fn remove_webhook(
+    request: HttpRequest,
+    form: web::Form<DeleteFormData>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if delete_webhook_endpoint(&pool, form.endpoint_id)
+        .await
+        .map_err(e500)?
+    {
+        let event = AuditEvent::new(&request, user.user_id, AuditAction::WebhookDeleted)
+            .target("webhook", form.endpoint_id);
+        record_audit_event(pool.get_ref(), event)
+            .await
+            .map_err(e500)?;
+        FlashMessage::info("The webhook endpoint has been removed.").send();
+    } else {
+        FlashMessage::error("The webhook endpoint does not exist.").send();
+    }
+    Ok(see_other("/admin/webhooks"))
+}
@@ -0,0 +1,60 @@
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::pagination::{Page, Pagination};
+use super::{require_permission, require_scope, ApiError};
+use crate::authentication::{ApiScope, ApiToken, AuthenticatedUser, Permission};
+use crate::openapi::{OpenApi, Operation};
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct Collaborator {
+    id: Uuid,
+    username: String,
+    role: String,
+    email: Option<String>,
+}
+
+#[tracing::instrument(name = "API: list collaborators", skip_all, fields(user_id=%user.user_id))]
+pub async fn api_list_collaborators(
+    pagination: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&token, ApiScope::ReadCollaborators)?;
+    require_permission(&user, Permission::ManageUsers)?;
+    let page = pagination.validate()?;
+    let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "total!" FROM users"#)
+        .fetch_one(pool.get_ref())
+        .await
+        .context("Failed to count the users.")?;
+    let collaborators = sqlx::query_as!(
+        Collaborator,
+        r#"
+        SELECT user_id AS id, username, role::text AS "role!", email
+        FROM users
+        ORDER BY username
+        LIMIT $1 OFFSET $2
+        "#,
+        page.limit(),
+        page.offset()
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch the users.")?;
+    Ok(HttpResponse::Ok().json(Page::new(collaborators, page, total)))
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/api/v1/collaborators", "List collaborators")
+            .bearer()
+            .scope(ApiScope::ReadCollaborators)
+            .permission(Permission::ManageUsers)
+            .query::<Pagination>()
+            .json_response::<Page<Collaborator>>(200, "A page of users, by username")
+            .error(400, "Invalid pagination"),
+    );
+}
@@ -0,0 +1,107 @@
+use actix_web::http::header::WWW_AUTHENTICATE;
+use actix_web::{HttpResponse, ResponseError};
+use reqwest::StatusCode;
+
+use crate::routes::{error_chain_fmt, ConfirmationError, LoginError, SubscribeError};
+
+/// The error of every `/api/v1` endpoint, rendered as
+/// `{"error": {"code": "...", "message": "..."}}`.
+#[derive(thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("{0}")]
+    AuthenticationError(String),
+    #[error("{0}")]
+    PermissionDenied(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ApiError {
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::ValidationError(_) => "validation_error",
+            ApiError::AuthenticationError(_) => "authentication_failed",
+            ApiError::PermissionDenied(_) => "permission_denied",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::UnexpectedError(_) => "internal_error",
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ErrorBody<'a> {
+    error: ErrorDetails<'a>,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorDetails<'a> {
+    code: &'a str,
+    message: String,
+}
+
+impl std::fmt::Debug for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            ApiError::AuthenticationError(_) => StatusCode::UNAUTHORIZED,
+            ApiError::PermissionDenied(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::AuthenticationError(_) = self {
+            response.insert_header((WWW_AUTHENTICATE, "Bearer"));
+        }
+        response.json(ErrorBody {
+            error: ErrorDetails {
+                code: self.code(),
+                message: self.to_string(),
+            },
+        })
+    }
+}
+
+impl From<SubscribeError> for ApiError {
+    fn from(e: SubscribeError) -> Self {
+        match e {
+            SubscribeError::ValidationError(e) | SubscribeError::BotSubmissionError(e) => {
+                ApiError::ValidationError(e)
+            }
+            SubscribeError::UnexpectedError(e) => ApiError::UnexpectedError(e),
+        }
+    }
+}
+
+impl From<ConfirmationError> for ApiError {
+    fn from(e: ConfirmationError) -> Self {
+        match e {
+            // The token is a credential - it is not echoed back.
+            ConfirmationError::TokenNotFoundError(_) => {
+                ApiError::NotFound("The subscription token was not found or has expired.".into())
+            }
+            ConfirmationError::UnexpectedError(e) => ApiError::UnexpectedError(e),
+        }
+    }
+}
+
+impl From<LoginError> for ApiError {
+    fn from(e: LoginError) -> Self {
+        match e {
+            LoginError::UnexpectedError(e) => ApiError::UnexpectedError(e),
+            e => ApiError::AuthenticationError(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use actix_web::http::header::LOCATION;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::pagination::{Page, Pagination};
+use super::{idempotency_key, require_permission, require_scope, ApiError};
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{ApiScope, ApiToken, AuthenticatedUser, Permission};
+use crate::domain::Segment;
+use crate::idempotency::{save_response, try_processing, NextAction};
+use crate::mailing_lists::{get_mailing_list, DEFAULT_LIST_ID};
+use crate::newsletter_issues::{
+    enqueue_delivery_tasks, get_delivery_status, insert_newsletter_issue, DeliveryStatus,
+};
+use crate::openapi::{OpenApi, Operation};
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct IssueSummary {
+    id: Uuid,
+    title: String,
+    list_id: Uuid,
+    published_at: String,
+}
+
+#[tracing::instrument(name = "API: list newsletter issues", skip_all)]
+pub async fn api_list_issues(
+    pagination: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+    token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&token, ApiScope::ReadNewsletters)?;
+    let page = pagination.validate()?;
+    // Issues waiting for a review (or rejected) have not been published.
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "total!" FROM newsletter_issues
+        WHERE review_status = 'published'
+        "#
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .context("Failed to count the newsletter issues.")?;
+    let issues = sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT newsletter_issue_id AS id, title, list_id, published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE review_status = 'published'
+        ORDER BY published_at DESC, newsletter_issue_id
+        LIMIT $1 OFFSET $2
+        "#,
+        page.limit(),
+        page.offset()
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch the newsletter issues.")?;
+    Ok(HttpResponse::Ok().json(Page::new(issues, page, total)))
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct IssueDetails {
+    #[serde(flatten)]
+    issue: IssueSummary,
+    text_content: String,
+    html_content: String,
+    delivery: DeliveryStatus,
+}
+
+#[tracing::instrument(name = "API: get a newsletter issue", skip(pool, token))]
+pub async fn api_get_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&token, ApiScope::ReadNewsletters)?;
+    let issue_id = issue_id.into_inner();
+    let row = sqlx::query!(
+        r#"
+        SELECT title, list_id, published_at AS "published_at!", text_content, html_content
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1 AND review_status = 'published'
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch the newsletter issue.")?
+    .ok_or_else(|| issue_not_found(issue_id))?;
+    let delivery = get_delivery_status(pool.get_ref(), issue_id)
+        .await
+        .context("Failed to fetch the delivery status of the newsletter issue.")?
+        .ok_or_else(|| issue_not_found(issue_id))?;
+    Ok(HttpResponse::Ok().json(IssueDetails {
+        issue: IssueSummary {
+            id: issue_id,
+            title: row.title,
+            list_id: row.list_id,
+            published_at: row.published_at,
+        },
+        text_content: row.text_content,
+        html_content: row.html_content,
+        delivery,
+    }))
+}
+
+#[tracing::instrument(
+    name = "API: get the delivery status of an issue",
+    skip(pool, user, token)
+)]
+pub async fn api_get_issue_delivery(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&token, ApiScope::ReadNewsletters)?;
+    require_permission(&user, Permission::ViewStats)?;
+    let issue_id = issue_id.into_inner();
+    let delivery = get_delivery_status(pool.get_ref(), issue_id)
+        .await
+        .context("Failed to fetch the delivery status of the newsletter issue.")?
+        .ok_or_else(|| issue_not_found(issue_id))?;
+    Ok(HttpResponse::Ok().json(delivery))
+}
+
+fn issue_not_found(issue_id: Uuid) -> ApiError {
+    ApiError::NotFound(format!("There is no newsletter issue {issue_id}."))
+}
+
+/// The issue goes to the confirmed members of the list, narrowed down by the
+/// optional segment fields - the same ones as on the publishing form.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct IssueBody {
+    title: String,
+    text_content: String,
+    html_content: String,
+    list_id: Option<Uuid>,
+    #[serde(default)]
+    include_tags: Vec<String>,
+    #[serde(default)]
+    exclude_tags: Vec<String>,
+    /// YYYY-MM-DD
+    #[serde(default)]
+    subscribed_after: String,
+    /// YYYY-MM-DD
+    #[serde(default)]
+    subscribed_before: String,
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct PublishedIssue {
+    id: Uuid,
+    recipients: u64,
+}
+
+#[tracing::instrument(name = "API: publish a newsletter issue", skip_all, fields(user_id=%user.user_id))]
+pub async fn api_publish_issue(
+    request: HttpRequest,
+    body: web::Json<IssueBody>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&token, ApiScope::PublishNewsletters)?;
+    require_permission(&user, Permission::PublishIssues)?;
+    let idempotency_key = idempotency_key(&request)?;
+    let IssueBody {
+        title,
+        text_content,
+        html_content,
+        list_id,
+        include_tags,
+        exclude_tags,
+        subscribed_after,
+        subscribed_before,
+    } = body.into_inner();
+    let list_id = list_id.unwrap_or(DEFAULT_LIST_ID);
+    let segment = Segment::parse(
+        &include_tags.join(","),
+        &exclude_tags.join(","),
+        &subscribed_after,
+        &subscribed_before,
+        "",
+    )
+    .map_err(ApiError::ValidationError)?;
+
+    let mut transaction = match try_processing(&pool, &idempotency_key, user.user_id).await? {
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+    };
+    if get_mailing_list(&mut *transaction, list_id)
+        .await
+        .context("Failed to fetch the mailing list.")?
+        .is_none()
+    {
+        return Err(ApiError::ValidationError(format!(
+            "{list_id} is not a known mailing list."
+        )));
+    }
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        user.user_id,
+        list_id,
+        &title,
+        &text_content,
+        &html_content,
+    )
+    .await
+    .context("Failed to store newsletter issue details.")?;
+    let recipients = enqueue_delivery_tasks(&mut transaction, issue_id, list_id, &segment)
+        .await
+        .context("Failed to enqueue delivery tasks.")?;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::IssuePublished)
+        .target("issue", issue_id);
+    record_audit_event(&mut *transaction, event).await?;
+    // Accepted rather than created: the emails go out in the background.
+    let response = HttpResponse::Accepted()
+        .insert_header((LOCATION, format!("/api/v1/issues/{issue_id}")))
+        .json(PublishedIssue {
+            id: issue_id,
+            recipients,
+        });
+    let response = save_response(transaction, &idempotency_key, user.user_id, response).await?;
+    Ok(response)
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/api/v1/issues", "List newsletter issues")
+            .bearer()
+            .scope(ApiScope::ReadNewsletters)
+            .query::<Pagination>()
+            .json_response::<Page<IssueSummary>>(200, "A page of issues, latest first")
+            .error(400, "Invalid pagination"),
+    )
+    .add(
+        Operation::post("/api/v1/issues", "Publish a newsletter issue")
+            .bearer()
+            .scope(ApiScope::PublishNewsletters)
+            .permission(Permission::PublishIssues)
+            .idempotent()
+            .json::<IssueBody>()
+            .json_response::<PublishedIssue>(202, "The issue is queued for delivery")
+            .error(400, "Invalid issue, list or segment"),
+    )
+    .add(
+        Operation::get("/api/v1/issues/{issue_id}", "Get a newsletter issue")
+            .bearer()
+            .scope(ApiScope::ReadNewsletters)
+            .json_response::<IssueDetails>(200, "The issue and its delivery status")
+            .error(404, "There is no such issue"),
+    )
+    .add(
+        Operation::get(
+            "/api/v1/issues/{issue_id}/delivery",
+            "Delivery status of an issue",
+        )
+        .bearer()
+        .scope(ApiScope::ReadNewsletters)
+        .permission(Permission::ViewStats)
+        .json_response::<DeliveryStatus>(200, "The delivery status")
+        .error(404, "There is no such issue"),
+    );
+}
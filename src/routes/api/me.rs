@@ -0,0 +1,39 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::authentication::{ApiToken, AuthenticatedUser};
+use crate::openapi::{OpenApi, Operation};
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct Me {
+    user_id: Uuid,
+    username: String,
+    email: Option<String>,
+    role: String,
+    permissions: Vec<&'static str>,
+    scopes: Vec<&'static str>,
+}
+
+/// Who the API token belongs to, what their role allows and what the token
+/// may be used for.
+pub async fn api_me(
+    user: web::ReqData<AuthenticatedUser>,
+    token: web::ReqData<ApiToken>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(Me {
+        user_id: user.user_id,
+        username: user.username.clone(),
+        email: user.email.clone(),
+        role: user.role.clone(),
+        permissions: user.permissions.iter().map(|p| p.as_str()).collect(),
+        scopes: token.scopes.iter().map(|s| s.as_str()).collect(),
+    })
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/api/v1/me", "The owner and the scopes of the token")
+            .bearer()
+            .json_response::<Me>(200, "The token's owner"),
+    );
+}
@@ -0,0 +1,82 @@
+mod collaborators;
+mod errors;
+mod issues;
+mod me;
+mod openapi;
+mod pagination;
+mod subscribers;
+
+pub use collaborators::*;
+pub use errors::ApiError;
+pub use issues::*;
+pub use me::*;
+pub use openapi::openapi_spec;
+pub use subscribers::*;
+
+use actix_web::{error::JsonPayloadError, web, HttpRequest};
+
+use crate::authentication::{ApiScope, ApiToken, AuthenticatedUser, Permission};
+use crate::idempotency::IdempotencyKey;
+use crate::openapi::OpenApi;
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Refuse the request unless the token it was made with grants `scope`.
+fn require_scope(token: &ApiToken, scope: ApiScope) -> Result<(), ApiError> {
+    if token.allows(scope) {
+        return Ok(());
+    }
+    Err(ApiError::PermissionDenied(format!(
+        "The API token lacks the '{}' scope.",
+        scope.as_str()
+    )))
+}
+
+/// Refuse the request unless the role of the token's user grants `permission`.
+fn require_permission(user: &AuthenticatedUser, permission: Permission) -> Result<(), ApiError> {
+    if user.can(permission) {
+        return Ok(());
+    }
+    Err(ApiError::PermissionDenied(format!(
+        "The user's role lacks the '{}' permission.",
+        permission.as_str()
+    )))
+}
+
+/// Every POST has to carry an `Idempotency-Key` header, so that clients can
+/// safely retry it.
+fn idempotency_key(request: &HttpRequest) -> Result<IdempotencyKey, ApiError> {
+    let key = request
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .ok_or_else(|| {
+            ApiError::ValidationError(format!("The {IDEMPOTENCY_KEY_HEADER} header is missing."))
+        })?;
+    key.to_owned()
+        .try_into()
+        .map_err(|e: anyhow::Error| ApiError::ValidationError(e.to_string()))
+}
+
+/// Malformed JSON bodies are reported like any other API error.
+pub fn api_json_config() -> web::JsonConfig {
+    web::JsonConfig::default()
+        .error_handler(|e: JsonPayloadError, _| ApiError::ValidationError(e.to_string()).into())
+}
+
+pub fn api_query_config() -> web::QueryConfig {
+    web::QueryConfig::default()
+        .error_handler(|e, _| ApiError::ValidationError(e.to_string()).into())
+}
+
+pub fn api_path_config() -> web::PathConfig {
+    web::PathConfig::default().error_handler(|e, _| ApiError::ValidationError(e.to_string()).into())
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    openapi::document(api);
+    me::document(api);
+    subscribers::document(api);
+    issues::document(api);
+    collaborators::document(api);
+}
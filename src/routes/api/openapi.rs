@@ -0,0 +1,16 @@
+use actix_web::HttpResponse;
+
+use crate::openapi::{OpenApi, Operation};
+use crate::routes::openapi_document;
+
+/// The OpenAPI document of every route - public, like the routes it lists.
+pub async fn openapi_spec() -> HttpResponse {
+    HttpResponse::Ok().json(openapi_document().to_json())
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/api/openapi.json", "This OpenAPI document")
+            .respond(200, "The OpenAPI 3 document"),
+    );
+}
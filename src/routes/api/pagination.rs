@@ -0,0 +1,139 @@
+use super::ApiError;
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+
+/// `?page=` (starting at 1) and `?per_page=` of list endpoints.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct Pagination {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+impl Pagination {
+    pub fn validate(&self) -> Result<PageRequest, ApiError> {
+        let page = self.page.unwrap_or(1);
+        let per_page = self.per_page.unwrap_or(DEFAULT_PER_PAGE);
+        if page < 1 {
+            return Err(ApiError::ValidationError(
+                "The page must be at least 1.".into(),
+            ));
+        }
+        if !(1..=MAX_PER_PAGE).contains(&per_page) {
+            return Err(ApiError::ValidationError(format!(
+                "The page size must be between 1 and {MAX_PER_PAGE}."
+            )));
+        }
+        let offset = (page - 1)
+            .checked_mul(per_page)
+            .ok_or_else(|| ApiError::ValidationError("The page is out of range.".into()))?;
+        Ok(PageRequest {
+            page,
+            per_page,
+            offset,
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct PageRequest {
+    page: i64,
+    per_page: i64,
+    offset: i64,
+}
+
+impl PageRequest {
+    pub fn limit(&self) -> i64 {
+        self.per_page
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct Page<T> {
+    data: Vec<T>,
+    pagination: PageInfo,
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct PageInfo {
+    page: i64,
+    per_page: i64,
+    total: i64,
+    total_pages: i64,
+}
+
+impl<T> Page<T> {
+    pub fn new(data: Vec<T>, request: PageRequest, total: i64) -> Self {
+        Self {
+            data,
+            pagination: PageInfo {
+                page: request.page,
+                per_page: request.per_page,
+                total,
+                total_pages: (total + request.per_page - 1) / request.per_page,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Page, Pagination};
+
+    #[test]
+    fn the_first_page_is_the_default() {
+        let request = Pagination {
+            page: None,
+            per_page: None,
+        }
+        .validate()
+        .unwrap();
+        assert_eq!(request.offset(), 0);
+        assert_eq!(request.limit(), 20);
+    }
+
+    #[test]
+    fn pages_are_offset_by_their_size() {
+        let request = Pagination {
+            page: Some(3),
+            per_page: Some(10),
+        }
+        .validate()
+        .unwrap();
+        assert_eq!(request.offset(), 20);
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        for (page, per_page) in [
+            (Some(0), None),
+            (None, Some(0)),
+            (None, Some(101)),
+            (Some(i64::MAX), Some(100)),
+        ] {
+            assert!(Pagination { page, per_page }.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn the_number_of_pages_is_rounded_up() {
+        let request = Pagination {
+            page: None,
+            per_page: Some(10),
+        }
+        .validate()
+        .unwrap();
+        assert_eq!(
+            Page::<()>::new(vec![], request, 21).pagination.total_pages,
+            3
+        );
+        assert_eq!(
+            Page::<()>::new(vec![], request, 0).pagination.total_pages,
+            0
+        );
+    }
+}
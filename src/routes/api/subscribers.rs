@@ -0,0 +1,320 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::pagination::{Page, Pagination};
+use super::{idempotency_key, require_permission, require_scope, ApiError};
+use crate::authentication::{ApiScope, ApiToken, AuthenticatedUser, Permission};
+use crate::configuration::SubscriptionSettings;
+use crate::domain::{Email, MembershipStatus, NewSubscriber, SubscriberName};
+use crate::email_client::EmailClient;
+use crate::idempotency::{save_response, try_processing, NextAction};
+use crate::mailing_lists::DEFAULT_LIST_ID;
+use crate::openapi::{OpenApi, Operation};
+use crate::routes::{add_subscriber, confirm_subscription, send_confirmation_email};
+use crate::startup::ApplicationBaseUrl;
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct SubscriberSummary {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+}
+
+/// Without `list_id`, `status` filters on the subscriber's own status;
+/// with it, on their membership of that list.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct SubscriberFilter {
+    list_id: Option<Uuid>,
+    status: Option<String>,
+}
+
+#[tracing::instrument(name = "API: list subscribers", skip_all, fields(user_id=%user.user_id))]
+pub async fn api_list_subscribers(
+    pagination: web::Query<Pagination>,
+    filter: web::Query<SubscriberFilter>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&token, ApiScope::ReadSubscribers)?;
+    require_permission(&user, Permission::ManageSubscribers)?;
+    let page = pagination.validate()?;
+    let status = match filter.status.as_deref() {
+        Some(status) => Some(
+            MembershipStatus::parse(status)
+                .map_err(ApiError::ValidationError)?
+                .as_str(),
+        ),
+        None => None,
+    };
+
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "total!"
+        FROM subscriptions s
+        WHERE
+            ($1::uuid IS NULL AND ($2::text IS NULL OR s.status = $2)) OR
+            EXISTS (
+                SELECT 1 FROM list_memberships m
+                WHERE m.subscriber_id = s.id AND m.list_id = $1
+                    AND ($2::text IS NULL OR m.status = $2)
+            )
+        "#,
+        filter.list_id,
+        status
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .context("Failed to count the subscribers.")?;
+    let subscribers = sqlx::query_as!(
+        SubscriberSummary,
+        r#"
+        SELECT s.id, s.email, s.name, s.status, s.subscribed_at
+        FROM subscriptions s
+        WHERE
+            ($1::uuid IS NULL AND ($2::text IS NULL OR s.status = $2)) OR
+            EXISTS (
+                SELECT 1 FROM list_memberships m
+                WHERE m.subscriber_id = s.id AND m.list_id = $1
+                    AND ($2::text IS NULL OR m.status = $2)
+            )
+        ORDER BY s.subscribed_at, s.id
+        LIMIT $3 OFFSET $4
+        "#,
+        filter.list_id,
+        status,
+        page.limit(),
+        page.offset()
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch the subscribers.")?;
+    Ok(HttpResponse::Ok().json(Page::new(subscribers, page, total)))
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct SubscriberDetails {
+    #[serde(flatten)]
+    subscriber: SubscriberSummary,
+    lists: Vec<ListMembership>,
+    tags: Vec<String>,
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct ListMembership {
+    list_id: Uuid,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "API: get a subscriber", skip(pool, user, token))]
+pub async fn api_get_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: web::ReqData<AuthenticatedUser>,
+    token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&token, ApiScope::ReadSubscribers)?;
+    require_permission(&user, Permission::ManageSubscribers)?;
+    let subscriber_id = subscriber_id.into_inner();
+    let subscriber = sqlx::query_as!(
+        SubscriberSummary,
+        "SELECT id, email, name, status, subscribed_at FROM subscriptions WHERE id = $1",
+        subscriber_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch the subscriber.")?
+    .ok_or_else(|| ApiError::NotFound(format!("There is no subscriber {subscriber_id}.")))?;
+    let lists = sqlx::query_as!(
+        ListMembership,
+        r#"
+        SELECT m.list_id, l.name, m.status, m.subscribed_at
+        FROM list_memberships m
+        JOIN lists l ON l.list_id = m.list_id
+        WHERE m.subscriber_id = $1
+        ORDER BY l.created_at, l.name
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch the list memberships of the subscriber.")?;
+    let tags = sqlx::query_scalar!(
+        "SELECT tag FROM subscriber_tags WHERE subscriber_id = $1 ORDER BY tag",
+        subscriber_id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to fetch the tags of the subscriber.")?;
+    Ok(HttpResponse::Ok().json(SubscriberDetails {
+        subscriber,
+        lists,
+        tags,
+    }))
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct SubscriberBody {
+    email: String,
+    name: String,
+    list_id: Option<Uuid>,
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct CreatedSubscriber {
+    id: Uuid,
+    list_id: Uuid,
+    status: &'static str,
+}
+
+/// Subscribe someone to a list - they still have to confirm by email, unless
+/// they already are a confirmed member.
+#[tracing::instrument(
+    name = "API: add a subscriber",
+    skip_all,
+    fields(user_id=%user.user_id, subscriber_email=%body.email)
+)]
+pub async fn api_create_subscriber(
+    request: HttpRequest,
+    body: web::Json<SubscriberBody>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user: web::ReqData<AuthenticatedUser>,
+    token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&token, ApiScope::WriteSubscribers)?;
+    require_permission(&user, Permission::ManageSubscribers)?;
+    let idempotency_key = idempotency_key(&request)?;
+    let SubscriberBody {
+        email,
+        name,
+        list_id,
+    } = body.into_inner();
+    let new_subscriber = NewSubscriber {
+        email: Email::parse(email).map_err(ApiError::ValidationError)?,
+        name: SubscriberName::parse(name).map_err(ApiError::ValidationError)?,
+    };
+    let list_id = list_id.unwrap_or(DEFAULT_LIST_ID);
+
+    let mut transaction = match try_processing(&pool, &idempotency_key, user.user_id).await? {
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+    };
+    let subscription = add_subscriber(&mut transaction, &new_subscriber, list_id).await?;
+    let status = match subscription.confirmation {
+        Some(_) => MembershipStatus::PendingConfirmation,
+        None => MembershipStatus::Confirmed,
+    };
+    let response = HttpResponse::Created().json(CreatedSubscriber {
+        id: subscription.subscriber_id,
+        list_id,
+        status: status.as_str(),
+    });
+    let response = save_response(transaction, &idempotency_key, user.user_id, response).await?;
+
+    if let Some(tokens) = subscription.confirmation {
+        send_confirmation_email(
+            &email_client,
+            new_subscriber,
+            &base_url.0,
+            &tokens.subscription_token,
+            &tokens.preferences_token,
+        )
+        .await
+        .context("Failed to send a confirmation email.")?;
+    }
+    Ok(response)
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct ConfirmationBody {
+    subscription_token: String,
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct ConfirmedSubscriber {
+    id: Uuid,
+    status: &'static str,
+}
+
+/// Confirm a subscription with the token from the confirmation email - for
+/// sites that host the confirmation page themselves.
+#[tracing::instrument(name = "API: confirm a subscription", skip_all, fields(user_id=%user.user_id))]
+pub async fn api_confirm_subscriber(
+    request: HttpRequest,
+    body: web::Json<ConfirmationBody>,
+    pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
+    user: web::ReqData<AuthenticatedUser>,
+    token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&token, ApiScope::WriteSubscribers)?;
+    require_permission(&user, Permission::ManageSubscribers)?;
+    let idempotency_key = idempotency_key(&request)?;
+
+    let mut transaction = match try_processing(&pool, &idempotency_key, user.user_id).await? {
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+    };
+    let subscriber_id = confirm_subscription(
+        &mut transaction,
+        &body.subscription_token,
+        settings.confirmation_token_ttl(),
+    )
+    .await?;
+    let response = HttpResponse::Ok().json(ConfirmedSubscriber {
+        id: subscriber_id,
+        status: MembershipStatus::Confirmed.as_str(),
+    });
+    let response = save_response(transaction, &idempotency_key, user.user_id, response).await?;
+    Ok(response)
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/api/v1/subscribers", "List subscribers")
+            .bearer()
+            .scope(ApiScope::ReadSubscribers)
+            .permission(Permission::ManageSubscribers)
+            .query::<Pagination>()
+            .query::<SubscriberFilter>()
+            .json_response::<Page<SubscriberSummary>>(200, "A page of subscribers")
+            .error(400, "Invalid filters or pagination"),
+    )
+    .add(
+        Operation::post("/api/v1/subscribers", "Add a subscriber to a list")
+            .bearer()
+            .scope(ApiScope::WriteSubscribers)
+            .permission(Permission::ManageSubscribers)
+            .idempotent()
+            .json::<SubscriberBody>()
+            .json_response::<CreatedSubscriber>(201, "The subscriber, emailed unless confirmed")
+            .error(400, "Invalid subscriber details"),
+    )
+    .add(
+        Operation::post("/api/v1/subscribers/confirm", "Confirm a subscription")
+            .bearer()
+            .scope(ApiScope::WriteSubscribers)
+            .permission(Permission::ManageSubscribers)
+            .idempotent()
+            .json::<ConfirmationBody>()
+            .json_response::<ConfirmedSubscriber>(200, "The subscription is confirmed")
+            .error(404, "The subscription token is unknown or expired"),
+    )
+    .add(
+        Operation::get("/api/v1/subscribers/{subscriber_id}", "Get a subscriber")
+            .bearer()
+            .scope(ApiScope::ReadSubscribers)
+            .permission(Permission::ManageSubscribers)
+            .json_response::<SubscriberDetails>(200, "The subscriber, with lists and tags")
+            .error(404, "There is no such subscriber"),
+    );
+}
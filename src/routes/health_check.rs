@@ -1,5 +1,13 @@
+use crate::openapi::{OpenApi, Operation};
 use actix_web::HttpResponse;
 
 pub async fn health_check() -> HttpResponse {
     HttpResponse::Ok().finish()
 }
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/health_check", "Check that the application is up")
+            .respond(200, "The application is up"),
+    );
+}
@@ -1,7 +1,38 @@
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::fmt::Write;
 
-pub async fn home() -> HttpResponse {
-    HttpResponse::Ok()
+use crate::bot_protection::{generate_form_token, CaptchaVerifier};
+use crate::mailing_lists::{get_mailing_lists, DEFAULT_LIST_ID};
+use crate::openapi::{OpenApi, Operation};
+use crate::startup::HmacSecret;
+use crate::utils::e500;
+
+pub async fn home(
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    captcha: web::Data<CaptchaVerifier>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut list_options = String::new();
+    for list in get_mailing_lists(pool.get_ref()).await.map_err(e500)? {
+        writeln!(
+            list_options,
+            "{}",
+            list.html_option(list.list_id == DEFAULT_LIST_ID)
+        )
+        .unwrap();
+    }
+    Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
-        .body(include_str!("home.html"))
+        .body(format!(
+            include_str!("home.html"),
+            list_options = list_options,
+            captcha_widget = captcha.widget_html(),
+            form_token = generate_form_token(&hmac_secret.0, Utc::now()),
+        )))
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(Operation::get("/", "Home page, with the subscription form").html());
 }
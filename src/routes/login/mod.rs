@@ -1,5 +1,28 @@
 mod get;
 mod post;
+mod two_factor_get;
+mod two_factor_post;
 
 pub use get::login_form;
-pub use post::login;
+pub use post::{login, LoginError};
+pub use two_factor_get::two_factor_form;
+pub use two_factor_post::verify_two_factor;
+
+use crate::openapi::{OpenApi, Operation};
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(Operation::get("/login", "Login form").html())
+        .add(
+            Operation::post("/login", "Log in")
+                .form::<post::FormData>()
+                .redirect("To the dashboard or the second factor, back to the form on failure")
+                .respond(429, "Too many login attempts"),
+        )
+        .add(Operation::get("/login/two-factor", "Second factor form").html())
+        .add(
+            Operation::post("/login/two-factor", "Log in with the second factor")
+                .form::<two_factor_post::FormData>()
+                .redirect("To the dashboard, back to the form on failure")
+                .respond(429, "Too many login attempts"),
+        );
+}
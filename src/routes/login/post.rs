@@ -1,6 +1,7 @@
 use crate::session_state::TypedSession;
 use actix_web::error::InternalError;
 use actix_web::web;
+use actix_web::HttpRequest;
 use actix_web::HttpResponse;
 use actix_web::ResponseError;
 use actix_web_flash_messages::FlashMessage;
@@ -9,48 +10,99 @@ use reqwest::StatusCode;
 use secrecy::Secret;
 use sqlx::PgPool;
 
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
 use crate::authentication::validate_credentials;
 use crate::authentication::AuthError;
 use crate::authentication::Credentials;
+use crate::authentication::{
+    check_login_attempt, is_two_factor_enabled, record_login_failure, reset_failed_logins,
+    start_user_session, LoginBlock,
+};
+use crate::configuration::{LoginProtectionSettings, PasswordHashingSettings};
 use crate::routes::error_chain_fmt;
+use crate::utils::{client_ip, user_agent};
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 pub struct FormData {
     username: String,
+    #[schemars(schema_with = "crate::openapi::password")]
     password: Secret<String>,
 }
 
 #[tracing::instrument(
-    skip(form, pool, session),
+    skip(request, form, pool, session, policy, hashing),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
+    request: HttpRequest,
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     session: TypedSession,
+    policy: web::Data<LoginProtectionSettings>,
+    hashing: web::Data<PasswordHashingSettings>,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
     let credentials = Credentials {
         username: form.0.username,
         password: form.0.password,
     };
+    let username = credentials.username.clone();
+    let client_ip = client_ip(&request);
 
     tracing::Span::current().record("username", &tracing::field::display(&credentials.username));
-    match validate_credentials(credentials, &pool).await {
+    // Refused attempts never reach `validate_credentials`, whether the account
+    // exists or not.
+    if let Some(block) = check_login_attempt(&pool, &policy, &username, &client_ip)
+        .await
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?
+    {
+        return Err(login_redirect(block.into()));
+    }
+    match validate_credentials(credentials, &hashing, &pool).await {
         Ok(user) => {
             tracing::Span::current().record("user_id", &tracing::field::display(&user.user_id));
+            let two_factor_enabled = is_two_factor_enabled(&pool, user.user_id)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
             session.renew();
-            session
-                .insert_user(user.into())
-                .map_err(|e: actix_session::SessionInsertError| {
+            if two_factor_enabled {
+                // Failed attempts are only forgotten once the second factor is in.
+                session.insert_pending_user(user.into()).map_err(
+                    |e: actix_session::SessionInsertError| {
+                        login_redirect(LoginError::UnexpectedError(e.into()))
+                    },
+                )?;
+                return Ok(HttpResponse::SeeOther()
+                    .insert_header((LOCATION, "/login/two-factor"))
+                    .finish());
+            }
+            reset_failed_logins(&pool, &user.username)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            let session_id =
+                start_user_session(&pool, user.user_id, &client_ip, user_agent(&request))
+                    .await
+                    .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            let event = AuditEvent::new(&request, user.user_id, AuditAction::LoggedIn);
+            record_audit_event(pool.get_ref(), event)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            session.insert_user(user.into(), session_id).map_err(
+                |e: actix_session::SessionInsertError| {
                     login_redirect(LoginError::UnexpectedError(e.into()))
-                })?;
+                },
+            )?;
             Ok(HttpResponse::SeeOther()
                 .insert_header((LOCATION, "/admin/dashboard"))
                 .finish())
         }
         Err(e) => {
             let e = match e {
-                AuthError::InvalidCredentials(_) => LoginError::AuthError(e.into()),
+                AuthError::InvalidCredentials(_) => {
+                    record_login_failure(&pool, &policy, &username, &client_ip)
+                        .await
+                        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+                    LoginError::AuthError(e.into())
+                }
                 AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
             };
             Err(login_redirect(e))
@@ -58,7 +110,7 @@ pub async fn login(
     }
 }
 
-fn login_redirect(e: LoginError) -> InternalError<LoginError> {
+pub(super) fn login_redirect(e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
     let response = HttpResponse::SeeOther()
         .insert_header((LOCATION, "/login"))
@@ -70,9 +122,26 @@ fn login_redirect(e: LoginError) -> InternalError<LoginError> {
 pub enum LoginError {
     #[error("Authentication failed")]
     AuthError(#[source] anyhow::Error),
+    #[error("Too many failed login attempts - try again in {0} seconds.")]
+    TooManyAttempts(i64),
+    #[error("Too many failed login attempts - logging in is temporarily locked.")]
+    LockedOut,
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
+
+impl From<LoginBlock> for LoginError {
+    fn from(block: LoginBlock) -> Self {
+        match block {
+            LoginBlock::Delayed { retry_after } => {
+                // Round up, so that retrying after the advertised delay succeeds.
+                let seconds = (retry_after.num_milliseconds() + 999) / 1000;
+                LoginError::TooManyAttempts(seconds.max(1))
+            }
+            LoginBlock::Locked { .. } => LoginError::LockedOut,
+        }
+    }
+}
 impl std::fmt::Debug for LoginError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         error_chain_fmt(self, f)
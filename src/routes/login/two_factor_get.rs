@@ -0,0 +1,45 @@
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+use actix_web_flash_messages::IncomingFlashMessages;
+use std::fmt::Write;
+
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+pub async fn two_factor_form(
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_pending_user().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let mut error_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Two-factor authentication</title>
+                </head>
+                <body>
+                    {error_html}
+                        <form action="/login/two-factor" method="post">
+                            <label>Code from your authenticator app, or a recovery code
+                            <input
+                            type="text"
+                            placeholder="Enter code"
+                            name="code"
+                            autocomplete="one-time-code"
+                            >
+                            </label>
+                            <button type="submit">Verify</button>
+                        </form>
+                    </body>
+            </html>"#,
+        )))
+}
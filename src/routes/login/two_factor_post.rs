@@ -0,0 +1,87 @@
+use actix_web::error::InternalError;
+use actix_web::http::header::LOCATION;
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+use super::post::{login_redirect, LoginError};
+use crate::audit::{record_audit_event, AuditAction, AuditEvent};
+use crate::authentication::{
+    check_login_attempt, record_login_failure, reset_failed_logins, start_user_session,
+    verify_second_factor,
+};
+use crate::configuration::LoginProtectionSettings;
+use crate::session_state::TypedSession;
+use crate::utils::{client_ip, user_agent};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    code: String,
+}
+
+#[tracing::instrument(
+    skip(request, form, pool, session, policy),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn verify_two_factor(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    policy: web::Data<LoginProtectionSettings>,
+) -> Result<HttpResponse, InternalError<LoginError>> {
+    let Some(user) = session
+        .get_pending_user()
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?
+    else {
+        return Ok(HttpResponse::SeeOther()
+            .insert_header((LOCATION, "/login"))
+            .finish());
+    };
+    tracing::Span::current().record("username", &tracing::field::display(&user.username));
+    tracing::Span::current().record("user_id", &tracing::field::display(&user.user_id));
+    let client_ip = client_ip(&request);
+
+    if let Some(block) = check_login_attempt(&pool, &policy, &user.username, &client_ip)
+        .await
+        .map_err(|e| two_factor_redirect(LoginError::UnexpectedError(e)))?
+    {
+        return Err(two_factor_redirect(block.into()));
+    }
+    let verified = verify_second_factor(&pool, user.user_id, &form.code)
+        .await
+        .map_err(|e| two_factor_redirect(LoginError::UnexpectedError(e)))?;
+    if !verified {
+        record_login_failure(&pool, &policy, &user.username, &client_ip)
+            .await
+            .map_err(|e| two_factor_redirect(LoginError::UnexpectedError(e)))?;
+        let e = anyhow::anyhow!("Invalid second factor.");
+        return Err(two_factor_redirect(LoginError::AuthError(e)));
+    }
+
+    reset_failed_logins(&pool, &user.username)
+        .await
+        .map_err(|e| two_factor_redirect(LoginError::UnexpectedError(e)))?;
+    let session_id = start_user_session(&pool, user.user_id, &client_ip, user_agent(&request))
+        .await
+        .map_err(|e| two_factor_redirect(LoginError::UnexpectedError(e)))?;
+    let event = AuditEvent::new(&request, user.user_id, AuditAction::LoggedIn);
+    record_audit_event(pool.get_ref(), event)
+        .await
+        .map_err(|e| two_factor_redirect(LoginError::UnexpectedError(e)))?;
+    session.renew();
+    session.remove_pending_user();
+    session
+        .insert_user(user, session_id)
+        .map_err(|e| two_factor_redirect(LoginError::UnexpectedError(e.into())))?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/dashboard"))
+        .finish())
+}
+
+fn two_factor_redirect(e: LoginError) -> InternalError<LoginError> {
+    actix_web_flash_messages::FlashMessage::error(e.to_string()).send();
+    let response = HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/login/two-factor"))
+        .finish();
+    InternalError::from_response(e, response)
+}
@@ -1,13 +1,36 @@
 mod admin;
+mod api;
 mod health_check;
 mod home;
 mod login;
+mod password_reset;
+mod preferences;
 mod subscriptions;
 mod subscriptions_confirm;
 
 pub use admin::*;
+pub use api::*;
 pub use health_check::*;
 pub use home::*;
 pub use login::*;
+pub use password_reset::*;
+pub use preferences::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+
+use crate::openapi::OpenApi;
+
+/// Describe every route registered in `startup::run`.
+pub fn openapi_document() -> OpenApi {
+    let mut api = OpenApi::default();
+    health_check::document(&mut api);
+    home::document(&mut api);
+    subscriptions::document(&mut api);
+    subscriptions_confirm::document(&mut api);
+    preferences::document(&mut api);
+    login::document(&mut api);
+    password_reset::document(&mut api);
+    admin::document(&mut api);
+    api::document(&mut api);
+    api
+}
@@ -0,0 +1,44 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use std::fmt::Write;
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct QueryData {
+    token: String,
+}
+
+pub async fn password_reset_confirm_form(
+    query: web::Query<QueryData>,
+    flash_messages: IncomingFlashMessages,
+) -> HttpResponse {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let token = htmlescape::encode_attribute(&query.into_inner().token);
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Choose a new password</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <form action="/password-reset/confirm" method="post">
+                        <label>New password
+                        <input type="password" placeholder="Enter new password" name="password">
+                        </label>
+                        <label>Confirm new password
+                        <input type="password" placeholder="Type the new password again" name="password_check">
+                        </label>
+                        <input type="hidden" name="token" value="{token}">
+                        <button type="submit">Reset password</button>
+                    </form>
+                </body>
+            </html>"#,
+        ))
+}
@@ -0,0 +1,166 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{hash_token, TOKEN_EXPIRE_TIMEOUT_IN_MINUTES};
+use crate::{
+    audit::{record_audit_event, AuditAction, AuditEvent},
+    authentication::{
+        change_password, check_password_policy, revoke_user_sessions, PasswordPolicyError,
+        SessionCache,
+    },
+    configuration::{PasswordHashingSettings, PasswordPolicySettings},
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    token: String,
+    #[schemars(schema_with = "crate::openapi::password")]
+    password: Secret<String>,
+    #[schemars(schema_with = "crate::openapi::password")]
+    password_check: Secret<String>,
+}
+
+#[tracing::instrument(
+    name = "Reset a password",
+    skip(request, form, pool, hashing, policy, cache)
+)]
+pub async fn reset_password(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashingSettings>,
+    policy: web::Data<PasswordPolicySettings>,
+    cache: web::Data<SessionCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        token,
+        password,
+        password_check,
+    } = form.into_inner();
+    if password.expose_secret() != password_check.expose_secret() {
+        FlashMessage::error("You entered two different passwords - the field values must match.")
+            .send();
+        return Ok(redirect_to_form(&token));
+    }
+    // The password is judged before the token is spent, so that a refused
+    // password can be replaced using the same link.
+    let Some(username) = get_reset_username(&pool, &token).await.map_err(e500)? else {
+        FlashMessage::error("The reset link is invalid or has expired.").send();
+        return Ok(redirect_to_form(&token));
+    };
+    match check_password_policy(&policy, &username, &password).await {
+        Ok(()) => {}
+        Err(PasswordPolicyError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(redirect_to_form(&token));
+        }
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let user_id = consume_reset_token(&mut transaction, &token)
+        .await
+        .map_err(e500)?;
+    let Some(user_id) = user_id else {
+        // The spent and expired tokens stay deleted.
+        transaction
+            .commit()
+            .await
+            .context("Failed to delete the expired reset tokens.")
+            .map_err(e500)?;
+        FlashMessage::error("The reset link is invalid or has expired.").send();
+        return Ok(redirect_to_form(&token));
+    };
+    change_password(user_id, password, &hashing, &mut *transaction)
+        .await
+        .map_err(e500)?;
+    revoke_user_sessions(&mut *transaction, user_id)
+        .await
+        .map_err(e500)?;
+    let event = AuditEvent::new(&request, user_id, AuditAction::PasswordReset);
+    record_audit_event(&mut *transaction, event)
+        .await
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the password reset.")
+        .map_err(e500)?;
+    cache.forget_user(user_id);
+
+    FlashMessage::info("Your password has been reset - you can log in now.").send();
+    Ok(see_other("/login"))
+}
+
+fn redirect_to_form(token: &str) -> HttpResponse {
+    let token: String = form_urlencoded::byte_serialize(token.as_bytes()).collect();
+    see_other(&format!("/password-reset/confirm?token={token}"))
+}
+
+/// The username of the account the token was issued to, if the token exists.
+#[tracing::instrument(skip_all)]
+async fn get_reset_username(pool: &PgPool, token: &str) -> Result<Option<String>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT users.username
+        FROM password_reset_tokens
+        JOIN users USING (user_id)
+        WHERE token_hash = $1
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the password reset token.")?;
+    Ok(row.map(|r| r.username))
+}
+
+/// Delete the token and return the user it was issued to - unless it has
+/// expired. Every other link sent to that user is spent with it, and the
+/// expired tokens of all users are cleared on the way.
+#[tracing::instrument(skip_all)]
+async fn consume_reset_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    token: &str,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        DELETE FROM password_reset_tokens
+        WHERE token_hash = $1
+        RETURNING user_id, created_at
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .context("Failed to consume the password reset token.")?;
+    let expired_before = Utc::now() - Duration::minutes(TOKEN_EXPIRE_TIMEOUT_IN_MINUTES);
+    sqlx::query!(
+        "DELETE FROM password_reset_tokens WHERE created_at < $1",
+        expired_before
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to delete the expired password reset tokens.")?;
+    let Some(row) = row.filter(|r| r.created_at >= expired_before) else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        "DELETE FROM password_reset_tokens WHERE user_id = $1",
+        row.user_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to delete the other password reset tokens of the user.")?;
+    Ok(Some(row.user_id))
+}
@@ -0,0 +1,40 @@
+mod confirm_get;
+mod confirm_post;
+mod request_get;
+mod request_post;
+
+pub use confirm_get::password_reset_confirm_form;
+pub use confirm_post::reset_password;
+pub use request_get::password_reset_form;
+pub use request_post::{request_password_reset, send_password_reset_link};
+
+use sha2::{Digest, Sha256};
+
+use crate::openapi::{OpenApi, Operation};
+
+const TOKEN_EXPIRE_TIMEOUT_IN_MINUTES: i64 = 60;
+
+/// Reset tokens are credentials, so only their hash is stored.
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(Operation::get("/password-reset", "Password reset request form").html())
+        .add(
+            Operation::post("/password-reset", "Email a password reset link")
+                .form::<request_post::FormData>()
+                .redirect("Back to the form")
+                .respond(429, "Too many password reset requests"),
+        )
+        .add(
+            Operation::get("/password-reset/confirm", "New password form")
+                .query::<confirm_get::QueryData>()
+                .html(),
+        )
+        .add(
+            Operation::post("/password-reset/confirm", "Set a new password")
+                .form::<confirm_post::FormData>()
+                .redirect("To the login form, back to the form on failure"),
+        );
+}
@@ -0,0 +1,32 @@
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+use actix_web_flash_messages::IncomingFlashMessages;
+use std::fmt::Write;
+
+pub async fn password_reset_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Reset password</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <form action="/password-reset" method="post">
+                        <label>Username
+                        <input type="text" placeholder="Enter Username" name="username">
+                        </label>
+                        <button type="submit">Send a reset link</button>
+                    </form>
+                    <p><a href="/login">&lt;- Back to login</a></p>
+                </body>
+            </html>"#,
+        ))
+}
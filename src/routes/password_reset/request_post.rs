@@ -0,0 +1,126 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::hash_token;
+use crate::{
+    account_email_worker::{enqueue_account_email, AccountEmail},
+    domain::Email,
+    email_client::EmailClient,
+    utils::{generate_token, see_other},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    username: String,
+}
+
+/// Every request is queued and answered the same way; the account email
+/// worker looks the account up and sends the link.
+#[tracing::instrument(name = "Request a password reset", skip(form, pool))]
+pub async fn request_password_reset(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    if let Err(e) =
+        enqueue_account_email(&**pool, AccountEmail::PasswordReset, form.username.trim()).await
+    {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to queue a password reset email."
+        );
+    }
+    FlashMessage::info(
+        "If the account exists and has an email address, \
+        a link to reset the password has been sent to it.",
+    )
+    .send();
+    see_other("/password-reset")
+}
+
+/// Email a reset link to the owner of `username`, if the account is active
+/// and has an email address.
+#[tracing::instrument(
+    name = "Send a password reset link",
+    skip(pool, email_client, base_url)
+)]
+pub async fn send_password_reset_link(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    username: &str,
+) -> Result<(), anyhow::Error> {
+    let Some((user_id, email)) = get_user_email(pool, username).await? else {
+        return Ok(());
+    };
+    let token = generate_token();
+    store_reset_token(pool, user_id, &token).await?;
+    send_reset_email(email_client, &email, base_url, &token)
+        .await
+        .context("Failed to send a password reset email.")
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_user_email(
+    pool: &PgPool,
+    username: &str,
+) -> Result<Option<(Uuid, Email)>, anyhow::Error> {
+    let row = sqlx::query!(
+        "SELECT user_id, email FROM users WHERE username = $1 AND deactivated_at IS NULL",
+        username
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the user.")?;
+    Ok(row.and_then(|r| {
+        let email = Email::parse(r.email?).ok()?;
+        Some((r.user_id, email))
+    }))
+}
+
+#[tracing::instrument(skip(pool, token))]
+async fn store_reset_token(pool: &PgPool, user_id: Uuid, token: &str) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (token_hash, user_id, created_at)
+        VALUES ($1, $2, $3)
+        "#,
+        hash_token(token),
+        user_id,
+        Utc::now()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the password reset token.")?;
+    Ok(())
+}
+
+async fn send_reset_email(
+    email_client: &EmailClient,
+    email: &Email,
+    base_url: &str,
+    token: &str,
+) -> Result<(), reqwest::Error> {
+    let reset_link = format!("{}/password-reset/confirm?token={}", base_url, token);
+    let plain_body = format!(
+        "Visit {} to choose a new password.\n\
+        The link can be used once and expires in {} minutes. \
+        If you did not ask for it, you can ignore this email.",
+        reset_link,
+        super::TOKEN_EXPIRE_TIMEOUT_IN_MINUTES
+    );
+    let html_body = format!(
+        "Click <a href=\"{}\">here</a> to choose a new password.<br />\
+        The link can be used once and expires in {} minutes. \
+        If you did not ask for it, you can ignore this email.",
+        reset_link,
+        super::TOKEN_EXPIRE_TIMEOUT_IN_MINUTES
+    );
+    email_client
+        .send_email(email, "Reset your password", &html_body, &plain_body)
+        .await
+}
@@ -0,0 +1,64 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+
+use super::{get_subscriber_from_token, PreferencesError};
+use crate::subscriber_data::{erase_subscriber_data, get_subscriber_data};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct TokenData {
+    token: String,
+}
+
+#[tracing::instrument(name = "Export subscriber data on request", skip_all)]
+pub async fn export_data(
+    query: web::Query<TokenData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, PreferencesError> {
+    let token = query.into_inner().token;
+    let subscriber = get_subscriber_from_token(pool.get_ref(), &token)
+        .await
+        .context("Failed to fetch subscriber preferences.")?
+        .ok_or_else(|| PreferencesError::TokenNotFoundError(token.clone()))?;
+    let data = get_subscriber_data(&pool, &subscriber.email)
+        .await
+        .context("Failed to export subscriber data.")?;
+    Ok(data.into_response())
+}
+
+#[tracing::instrument(name = "Erase subscriber data on request", skip_all)]
+pub async fn erase_data(
+    form: web::Form<TokenData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, PreferencesError> {
+    let token = form.into_inner().token;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let subscriber = get_subscriber_from_token(&mut *transaction, &token)
+        .await
+        .context("Failed to fetch subscriber preferences.")?
+        .ok_or_else(|| PreferencesError::TokenNotFoundError(token.clone()))?;
+    erase_subscriber_data(&mut transaction, &subscriber.email)
+        .await
+        .context("Failed to erase subscriber data.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to erase subscriber data.")?;
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Subscription preferences</title>
+                </head>
+                <body>
+                    <p>All your data has been erased - you will not hear from us again.</p>
+                </body>
+            </html>"#,
+    ))
+}
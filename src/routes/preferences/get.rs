@@ -0,0 +1,106 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use super::{get_list_memberships, get_subscriber_from_token, PreferencesError};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct QueryData {
+    token: String,
+}
+
+#[tracing::instrument(name = "Show subscriber preferences", skip_all)]
+pub async fn preferences_form(
+    query: web::Query<QueryData>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, PreferencesError> {
+    let token = query.into_inner().token;
+    let subscriber = get_subscriber_from_token(pool.get_ref(), &token)
+        .await
+        .context("Failed to fetch subscriber preferences.")?
+        .ok_or_else(|| PreferencesError::TokenNotFoundError(token.clone()))?;
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(
+            msg_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(m.content())
+        )
+        .unwrap();
+    }
+    let pending_note = if let Some(pending_email) = &subscriber.pending_email {
+        format!(
+            "<p>Your new email address, {}, has not been confirmed yet - \
+            check its inbox for the confirmation link.</p>",
+            htmlescape::encode_minimal(pending_email)
+        )
+    } else if subscriber.status == "pending_confirmation" {
+        "<p>Your email address has not been confirmed yet - \
+        check your inbox for the confirmation link.</p>"
+            .to_owned()
+    } else {
+        String::new()
+    };
+    let memberships = get_list_memberships(pool.get_ref(), subscriber.id)
+        .await
+        .context("Failed to fetch list memberships.")?;
+    let mut lists_html = String::new();
+    for membership in memberships {
+        let (checked, note) = match membership.status.as_deref() {
+            Some("confirmed") => ("checked", ""),
+            Some("pending_confirmation") => ("checked", " (awaiting confirmation)"),
+            _ => ("", ""),
+        };
+        writeln!(
+            lists_html,
+            r#"<label><input type="checkbox" name="list_{}" {}> {}{}</label><br>"#,
+            membership.list_id,
+            checked,
+            htmlescape::encode_minimal(&membership.name),
+            note
+        )
+        .unwrap();
+    }
+    let name = htmlescape::encode_attribute(&subscriber.name);
+    let email = htmlescape::encode_attribute(&subscriber.email);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+                    <title>Subscription preferences</title>
+                </head>
+                <body>
+                    {msg_html}
+                    {pending_note}
+                    <form action="/preferences" method="post">
+                        <label>Name
+                        <input type="text" placeholder="Enter name" name="name" value="{name}">
+                        </label>
+                        <br>
+                        <label>Email
+                        <input type="text" placeholder="Enter email" name="email" value="{email}">
+                        </label>
+                        <br>
+                        <p>Receive issues of:</p>
+                        {lists_html}
+                        <input hidden type="text" name="token" value="{token}">
+                        <button type="submit">Save preferences</button>
+                    </form>
+                    <p><a href="/preferences/export?token={token}">Download all data we hold about you</a></p>
+                    <form action="/preferences/erase" method="post">
+                        <input hidden type="text" name="token" value="{token}">
+                        <button type="submit">Erase all my data</button>
+                    </form>
+                </body>
+            </html>"#,
+        )))
+}
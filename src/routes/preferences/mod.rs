@@ -0,0 +1,129 @@
+mod data;
+mod get;
+mod post;
+
+use actix_web::ResponseError;
+use reqwest::StatusCode;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::error_chain_fmt;
+use crate::openapi::{OpenApi, Operation};
+
+pub use data::{erase_data, export_data};
+pub use get::preferences_form;
+pub use post::update_preferences;
+
+pub fn preferences_link(base_url: &str, preferences_token: &str) -> String {
+    format!("{}/preferences?token={}", base_url, preferences_token)
+}
+
+#[derive(thiserror::Error)]
+pub enum PreferencesError {
+    #[error("Token: {0} was not found in db")]
+    TokenNotFoundError(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for PreferencesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for PreferencesError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PreferencesError::TokenNotFoundError(_) => StatusCode::UNAUTHORIZED,
+            PreferencesError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+struct Subscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    /// A changed email address, waiting to be confirmed.
+    pending_email: Option<String>,
+}
+
+#[tracing::instrument(name = "Get subscriber from preferences token", skip_all)]
+async fn get_subscriber_from_token(
+    executor: impl PgExecutor<'_>,
+    preferences_token: &str,
+) -> Result<Option<Subscriber>, sqlx::Error> {
+    sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, email, name, status, pending_email
+        FROM subscriptions
+        WHERE preferences_token = $1
+        "#,
+        preferences_token
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+struct ListMembership {
+    list_id: Uuid,
+    name: String,
+    /// `None` if the subscriber has never joined the list.
+    status: Option<String>,
+}
+
+#[tracing::instrument(name = "Get list memberships of a subscriber", skip(executor))]
+async fn get_list_memberships(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<Vec<ListMembership>, sqlx::Error> {
+    sqlx::query_as!(
+        ListMembership,
+        r#"
+        SELECT l.list_id, l.name, m.status AS "status?"
+        FROM lists l
+        LEFT JOIN list_memberships m
+            ON m.list_id = l.list_id AND m.subscriber_id = $1
+        ORDER BY l.created_at, l.name
+        "#,
+        subscriber_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/preferences", "Subscription preferences of a subscriber")
+            .query::<get::QueryData>()
+            .html()
+            .respond(401, "The preferences token is unknown"),
+    )
+    .add(
+        Operation::post("/preferences", "Update the subscription preferences")
+            .form::<post::FormData>()
+            .redirect("Back to the preferences")
+            .respond(401, "The preferences token is unknown"),
+    )
+    .add(
+        Operation::get(
+            "/preferences/export",
+            "Download the data stored about the subscriber",
+        )
+        .query::<data::TokenData>()
+        .respond(200, "The stored data, as a JSON attachment")
+        .respond(401, "The preferences token is unknown"),
+    )
+    .add(
+        Operation::post(
+            "/preferences/erase",
+            "Erase the data stored about the subscriber",
+        )
+        .form::<data::TokenData>()
+        .html()
+        .respond(401, "The preferences token is unknown"),
+    );
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{get_subscriber_from_token, PreferencesError, Subscriber};
+use crate::{
+    domain::{Email, NewSubscriber, SubscriberName},
+    email_client::EmailClient,
+    routes::{send_confirmation_email, store_token},
+    startup::ApplicationBaseUrl,
+    utils::{generate_token, see_other},
+    webhooks::{enqueue_webhook_event, WebhookEvent},
+};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+pub struct FormData {
+    token: String,
+    name: String,
+    email: String,
+    /// One `list_<list_id>` checkbox per list the subscriber wants to receive.
+    #[serde(flatten)]
+    lists: HashMap<String, String>,
+}
+
+#[tracing::instrument(
+    name = "Update subscriber preferences",
+    skip(form, pool, email_client, base_url)
+)]
+pub async fn update_preferences(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, PreferencesError> {
+    let FormData {
+        token,
+        name,
+        email,
+        lists,
+    } = form.into_inner();
+    let wanted_lists: Vec<Uuid> = lists
+        .keys()
+        .filter_map(|key| key.strip_prefix("list_"))
+        .filter_map(|list_id| list_id.parse().ok())
+        .collect();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let subscriber = get_subscriber_from_token(&mut *transaction, &token)
+        .await
+        .context("Failed to fetch subscriber preferences.")?
+        .ok_or_else(|| PreferencesError::TokenNotFoundError(token.clone()))?;
+
+    let name = match SubscriberName::parse(name) {
+        Ok(name) => name,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(redirect_to_form(&token));
+        }
+    };
+    let email = match Email::parse(email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(redirect_to_form(&token));
+        }
+    };
+
+    if email.as_ref() == subscriber.email {
+        // A pending change of address is left to be confirmed.
+        update_subscriber_name(&mut transaction, subscriber.id, &name)
+            .await
+            .context("Failed to update subscriber preferences.")?;
+        update_list_memberships(&mut transaction, &subscriber, &wanted_lists)
+            .await
+            .context("Failed to update list memberships.")?;
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to update subscriber preferences.")?;
+        FlashMessage::info("Your preferences have been updated.").send();
+        return Ok(redirect_to_form(&token));
+    }
+
+    if is_email_taken(&mut transaction, &email)
+        .await
+        .context("Failed to check if the email address is already subscribed.")?
+    {
+        FlashMessage::error("This email address is already subscribed.").send();
+        return Ok(redirect_to_form(&token));
+    }
+    update_list_memberships(&mut transaction, &subscriber, &wanted_lists)
+        .await
+        .context("Failed to update list memberships.")?;
+    // Issues keep going to the current address until the new one is confirmed.
+    update_subscriber(&mut transaction, subscriber.id, &name, &email)
+        .await
+        .context("Failed to update subscriber preferences.")?;
+    let subscription_token = generate_token();
+    store_token(&mut transaction, subscriber.id, None, &subscription_token)
+        .await
+        .context("Failed to store the confirmation token for a changed email address.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to update subscriber preferences.")?;
+
+    send_confirmation_email(
+        &email_client,
+        NewSubscriber { email, name },
+        &base_url.0,
+        &subscription_token,
+        &token,
+    )
+    .await
+    .context("Failed to send a confirmation email.")?;
+
+    FlashMessage::info(
+        "Your preferences have been updated - \
+        check your inbox to confirm the new email address.",
+    )
+    .send();
+    Ok(redirect_to_form(&token))
+}
+
+fn redirect_to_form(token: &str) -> HttpResponse {
+    see_other(&format!("/preferences?token={token}"))
+}
+
+/// Leave every list that is not in `wanted_lists` and join the ones that are.
+/// Lists can only be joined from a confirmed email address - the preferences
+/// token was delivered to it, so no further confirmation is needed.
+#[tracing::instrument(skip(transaction, subscriber))]
+async fn update_list_memberships(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber: &Subscriber,
+    wanted_lists: &[Uuid],
+) -> Result<(), anyhow::Error> {
+    let left_lists = sqlx::query_scalar!(
+        r#"
+        UPDATE list_memberships SET status = 'unsubscribed'
+        WHERE
+            subscriber_id = $1 AND
+            status <> 'unsubscribed' AND
+            NOT (list_id = ANY($2))
+        RETURNING list_id
+        "#,
+        subscriber.id,
+        wanted_lists
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+    for list_id in left_lists {
+        enqueue_webhook_event(
+            transaction,
+            WebhookEvent::SubscriberUnsubscribed {
+                subscriber_id: subscriber.id,
+                email: subscriber.email.clone(),
+                list_id: Some(list_id),
+            },
+        )
+        .await?;
+    }
+    if subscriber.status != "confirmed" {
+        return Ok(());
+    }
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            INSERT INTO list_memberships (list_id, subscriber_id, status, subscribed_at)
+            SELECT list_id, $1, 'confirmed', now()
+            FROM lists
+            WHERE list_id = ANY($2)
+            ON CONFLICT (list_id, subscriber_id) DO UPDATE
+            SET status = EXCLUDED.status, subscribed_at = EXCLUDED.subscribed_at
+            WHERE list_memberships.status <> 'confirmed'
+            "#,
+            subscriber.id,
+            wanted_lists
+        ))
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn is_email_taken(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &Email,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        "SELECT id FROM subscriptions WHERE email = $1 OR pending_email = $1",
+        email.as_ref()
+    )
+    .fetch_optional(&mut **transaction)
+    .await?;
+    Ok(row.is_some())
+}
+
+#[tracing::instrument(skip_all)]
+async fn update_subscriber_name(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    name: &SubscriberName,
+) -> Result<(), sqlx::Error> {
+    transaction
+        .execute(sqlx::query!(
+            "UPDATE subscriptions SET name = $1 WHERE id = $2",
+            name.as_ref(),
+            subscriber_id
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Set the name of the subscriber and the address they are changing to. An
+/// earlier change is dropped along with its confirmation links, so that they
+/// cannot confirm an address they were not sent to.
+#[tracing::instrument(skip_all)]
+async fn update_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    name: &SubscriberName,
+    pending_email: &Email,
+) -> Result<(), sqlx::Error> {
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            UPDATE subscriptions
+            SET name = $1, pending_email = $2
+            WHERE id = $3
+            "#,
+            name.as_ref(),
+            pending_email.as_ref(),
+            subscriber_id
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM subscription_tokens WHERE subscriber_id = $1 AND list_id IS NULL",
+            subscriber_id
+        ))
+        .await?;
+    Ok(())
+}
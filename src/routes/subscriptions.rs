@@ -5,20 +5,36 @@ use reqwest::StatusCode;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::openapi::{OpenApi, Operation};
 use crate::{
-    domain::NewSubscriber, email_client::EmailClient, startup::ApplicationBaseUrl,
+    bot_protection::{verify_form_token, CaptchaVerifier},
+    configuration::SubscriptionSettings,
+    domain::{Email, NewSubscriber},
+    email_client::EmailClient,
+    mailing_lists::{get_mailing_list, DEFAULT_LIST_ID},
+    routes::preferences_link,
+    startup::{ApplicationBaseUrl, HmacSecret},
     utils::generate_token,
+    webhooks::{enqueue_webhook_event, WebhookEvent},
 };
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 pub struct FormData {
     pub email: String,
     pub name: String,
+    pub list_id: Option<Uuid>,
+    /// Hidden from humans - only bots fill it in.
+    #[serde(default)]
+    pub website: String,
+    #[serde(default)]
+    pub form_token: String,
+    #[serde(alias = "h-captcha-response")]
+    pub captcha_response: Option<String>,
 }
 
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool, email_client, base_url),
+    skip(form, pool, email_client, base_url, hmac_secret, settings, captcha),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
@@ -29,63 +45,224 @@ pub async fn subscribe(
     pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseUrl>,
+    hmac_secret: web::Data<HmacSecret>,
+    settings: web::Data<SubscriptionSettings>,
+    captcha: web::Data<CaptchaVerifier>,
 ) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber = form.0.try_into().map_err(SubscribeError::ValidationError)?;
+    reject_automated_submissions(&form, &hmac_secret, &settings, &captcha).await?;
+    let list_id = form.list_id.unwrap_or(DEFAULT_LIST_ID);
+    let new_subscriber: NewSubscriber =
+        form.0.try_into().map_err(SubscribeError::ValidationError)?;
 
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
+    let subscription = add_subscriber(&mut transaction, &new_subscriber, list_id).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a new subscriber.")?;
 
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    if let Some(tokens) = subscription.confirmation {
+        send_confirmation_email(
+            &email_client,
+            new_subscriber,
+            &base_url.0,
+            &tokens.subscription_token,
+            &tokens.preferences_token,
+        )
         .await
-        .context("Failed to insert new subscriber in the database.")?;
+        .context("Failed to send a confirmation email.")?;
+    }
 
-    let subscription_token = generate_token();
-    store_token(&mut transaction, subscriber_id, &subscription_token)
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub struct AddedSubscription {
+    pub subscriber_id: Uuid,
+    /// `None` if the subscriber already is a confirmed member of the list -
+    /// there is nothing to confirm then.
+    pub confirmation: Option<ConfirmationTokens>,
+}
+
+pub struct ConfirmationTokens {
+    pub subscription_token: String,
+    pub preferences_token: String,
+}
+
+/// Subscribe `new_subscriber` to `list_id`, pending confirmation. Sending the
+/// confirmation email is up to the caller, once the transaction is committed.
+#[tracing::instrument(name = "Add a subscriber to a list", skip(transaction, new_subscriber))]
+pub async fn add_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    new_subscriber: &NewSubscriber,
+    list_id: Uuid,
+) -> Result<AddedSubscription, SubscribeError> {
+    if get_mailing_list(&mut **transaction, list_id)
         .await
-        .context("Failed to store the confirmation token for a new subscriber.")?;
+        .context("Failed to fetch the mailing list.")?
+        .is_none()
+    {
+        return Err(SubscribeError::ValidationError(format!(
+            "{} is not a known mailing list.",
+            list_id
+        )));
+    }
 
-    transaction
-        .commit()
+    let (subscriber_id, preferences_token) =
+        match get_subscriber_by_email(transaction, &new_subscriber.email)
+            .await
+            .context("Failed to fetch the subscriber from the database.")?
+        {
+            Some(subscriber) => (subscriber.id, subscriber.preferences_token),
+            None => {
+                let preferences_token = generate_token();
+                let subscriber_id =
+                    insert_subscriber(transaction, new_subscriber, &preferences_token)
+                        .await
+                        .context("Failed to insert new subscriber in the database.")?;
+                (subscriber_id, preferences_token)
+            }
+        };
+
+    let is_pending = insert_list_membership(transaction, list_id, subscriber_id)
         .await
-        .context("Failed to commit SQL transaction to store a new subscriber.")?;
+        .context("Failed to store the list membership of a new subscriber.")?;
+    if !is_pending {
+        return Ok(AddedSubscription {
+            subscriber_id,
+            confirmation: None,
+        });
+    }
 
-    send_confirmation_email(
-        &email_client,
-        new_subscriber,
-        &base_url.0,
+    let subscription_token = generate_token();
+    store_token(
+        transaction,
+        subscriber_id,
+        Some(list_id),
         &subscription_token,
     )
     .await
-    .context("Failed to send a confirmation email.")?;
+    .context("Failed to store the confirmation token for a new subscriber.")?;
+    enqueue_webhook_event(
+        transaction,
+        WebhookEvent::SubscriberCreated {
+            subscriber_id,
+            email: new_subscriber.email.as_ref().to_owned(),
+            list_id: Some(list_id),
+        },
+    )
+    .await?;
+    Ok(AddedSubscription {
+        subscriber_id,
+        confirmation: Some(ConfirmationTokens {
+            subscription_token,
+            preferences_token,
+        }),
+    })
+}
 
-    Ok(HttpResponse::Ok().finish())
+#[tracing::instrument(name = "Check the subscribe form for automated submissions", skip_all)]
+async fn reject_automated_submissions(
+    form: &FormData,
+    hmac_secret: &HmacSecret,
+    settings: &SubscriptionSettings,
+    captcha: &CaptchaVerifier,
+) -> Result<(), SubscribeError> {
+    if !form.website.is_empty() {
+        return Err(SubscribeError::BotSubmissionError(
+            "The honeypot field was filled in.".into(),
+        ));
+    }
+    verify_form_token(
+        &hmac_secret.0,
+        &form.form_token,
+        Utc::now(),
+        settings.form_token_min_age(),
+        settings.form_token_max_age(),
+    )
+    .map_err(|e| SubscribeError::BotSubmissionError(e.to_string()))?;
+    if !captcha
+        .verify(form.captcha_response.as_deref())
+        .await
+        .context("Failed to verify the CAPTCHA response.")?
+    {
+        return Err(SubscribeError::BotSubmissionError(
+            "The CAPTCHA was not solved.".into(),
+        ));
+    }
+    Ok(())
 }
 
 #[tracing::instrument(
     name = "Saving new subscriber details in the database",
-    skip(new_subscriber, transaction)
+    skip(new_subscriber, preferences_token, transaction)
 )]
 pub async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
+    preferences_token: &str,
 ) -> Result<Uuid, sqlx::Error> {
     let subscriber_id = Uuid::new_v4();
     let query = sqlx::query!(
         r#"
-        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, preferences_token)
+        VALUES ($1, $2, $3, $4, 'pending_confirmation', $5)
         "#,
         subscriber_id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
-        Utc::now()
+        Utc::now(),
+        preferences_token
     );
     transaction.execute(query).await?;
     Ok(subscriber_id)
 }
 
+struct Subscriber {
+    id: Uuid,
+    preferences_token: String,
+}
+
+#[tracing::instrument(name = "Get subscriber by email", skip_all)]
+async fn get_subscriber_by_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &Email,
+) -> Result<Option<Subscriber>, sqlx::Error> {
+    sqlx::query_as!(
+        Subscriber,
+        "SELECT id, preferences_token FROM subscriptions WHERE email = $1",
+        email.as_ref()
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+}
+
+/// Returns `false` if the subscriber is already a confirmed member of the list.
+#[tracing::instrument(name = "Saving list membership in the database", skip(transaction))]
+pub async fn insert_list_membership(
+    transaction: &mut Transaction<'_, Postgres>,
+    list_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO list_memberships (list_id, subscriber_id, status, subscribed_at)
+        VALUES ($1, $2, 'pending_confirmation', $3)
+        ON CONFLICT (list_id, subscriber_id) DO UPDATE
+        SET status = EXCLUDED.status, subscribed_at = EXCLUDED.subscribed_at
+        WHERE list_memberships.status <> 'confirmed'
+        "#,
+        list_id,
+        subscriber_id,
+        Utc::now()
+    );
+    let n_affected_rows = transaction.execute(query).await?.rows_affected();
+    Ok(n_affected_rows > 0)
+}
+
+/// A token without a list only confirms a changed email address.
 #[tracing::instrument(
     name = "Store subscription token in the database",
     skip(subscription_token, transaction)
@@ -93,12 +270,17 @@ pub async fn insert_subscriber(
 pub async fn store_token(
     transaction: &mut Transaction<'_, Postgres>,
     subscriber_id: Uuid,
+    list_id: Option<Uuid>,
     subscription_token: &str,
 ) -> Result<(), StoreTokenError> {
     let query = sqlx::query!(
-        r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id) VALUES ($1, $2)"#,
+        r#"
+        INSERT INTO subscription_tokens (subscription_token, subscriber_id, list_id)
+        VALUES ($1, $2, $3)
+        "#,
         subscription_token,
-        subscriber_id
+        subscriber_id,
+        list_id
     );
     transaction.execute(query).await.map_err(StoreTokenError)?;
     Ok(())
@@ -106,26 +288,36 @@ pub async fn store_token(
 
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(email_client, new_subscriber, base_url, subscription_token)
+    skip(
+        email_client,
+        new_subscriber,
+        base_url,
+        subscription_token,
+        preferences_token
+    )
 )]
 pub async fn send_confirmation_email(
     email_client: &EmailClient,
     new_subscriber: NewSubscriber,
     base_url: &str,
     subscription_token: &str,
+    preferences_token: &str,
 ) -> Result<(), reqwest::Error> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
     );
+    let preferences_link = preferences_link(base_url, preferences_token);
     let plain_body = format!(
-        "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
-        confirmation_link
+        "Welcome to our newsletter!\nVisit {} to confirm your subscription.\n\n\
+        You can change your subscription preferences at any time: {}",
+        confirmation_link, preferences_link
     );
     let html_body = format!(
         "Welcome to our newsletter!<br />\
-        Click <a href=\"{}\">here</a> to confirm your subscription.",
-        confirmation_link
+        Click <a href=\"{}\">here</a> to confirm your subscription.<br /><br />\
+        You can change your subscription preferences <a href=\"{}\">here</a> at any time.",
+        confirmation_link, preferences_link
     );
     email_client
         .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
@@ -137,6 +329,8 @@ pub enum SubscribeError {
     #[error("{0}")]
     ValidationError(String),
     #[error("{0}")]
+    BotSubmissionError(String),
+    #[error("{0}")]
     UnexpectedError(#[from] anyhow::Error),
 }
 
@@ -150,6 +344,7 @@ impl ResponseError for SubscribeError {
     fn status_code(&self) -> StatusCode {
         match self {
             SubscribeError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            SubscribeError::BotSubmissionError(_) => StatusCode::BAD_REQUEST,
             SubscribeError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -191,3 +386,13 @@ pub fn error_chain_fmt(
     }
     Ok(())
 }
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::post("/subscriptions", "Subscribe to a mailing list")
+            .form::<FormData>()
+            .respond(200, "The confirmation email has been sent")
+            .respond(400, "Invalid subscriber details, or a bot submission")
+            .respond(429, "Too many subscription attempts for the email address"),
+    );
+}
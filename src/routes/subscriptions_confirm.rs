@@ -1,12 +1,16 @@
 use actix_web::{web, HttpResponse, ResponseError};
 use anyhow::Context;
+use chrono::{Duration, Utc};
 use reqwest::StatusCode;
-use sqlx::PgPool;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 use super::error_chain_fmt;
+use crate::configuration::SubscriptionSettings;
+use crate::openapi::{OpenApi, Operation};
+use crate::webhooks::{enqueue_webhook_event, WebhookEvent};
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 pub struct Parameters {
     subscription_token: String,
 }
@@ -34,45 +38,161 @@ impl ResponseError for ConfirmationError {
     }
 }
 
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(parameters, pool))]
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(parameters, pool, settings)
+)]
 pub async fn confirm(
     parameters: web::Query<Parameters>,
     pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
 ) -> Result<HttpResponse, ConfirmationError> {
-    let subscriber_id = get_subscriber_id_from_token(&pool, &parameters.subscription_token)
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    confirm_subscription(
+        &mut transaction,
+        &parameters.subscription_token,
+        settings.confirmation_token_ttl(),
+    )
+    .await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to confirm a subscriber.")?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Confirm the subscriber (and list membership) `subscription_token` was
+/// issued for. Returns the id of the subscriber.
+#[tracing::instrument(name = "Confirm a subscription", skip(transaction, subscription_token))]
+pub async fn confirm_subscription(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+    token_ttl: Duration,
+) -> Result<Uuid, ConfirmationError> {
+    let token = get_subscription_token(&mut **transaction, subscription_token, token_ttl)
         .await
         .context("Failed to get subscriber_id from token.")?
         .ok_or(ConfirmationError::TokenNotFoundError(
-            parameters.subscription_token.clone(),
+            subscription_token.to_owned(),
         ))?;
-    confirm_subscriber(&pool, subscriber_id)
-        .await
-        .context("Cannot set subcriber status to 'confirmed'")?;
-    Ok(HttpResponse::Ok().finish())
+
+    let email = match token.list_id {
+        Some(list_id) => {
+            let email = confirm_subscriber(transaction, token.subscriber_id)
+                .await
+                .context("Cannot set subcriber status to 'confirmed'")?;
+            confirm_list_membership(transaction, list_id, token.subscriber_id)
+                .await
+                .context("Cannot set list membership status to 'confirmed'")?;
+            email
+        }
+        None => confirm_email_change(transaction, token.subscriber_id)
+            .await
+            .context("Cannot confirm a changed email address")?,
+    };
+    enqueue_webhook_event(
+        transaction,
+        WebhookEvent::SubscriberConfirmed {
+            subscriber_id: token.subscriber_id,
+            email,
+            list_id: token.list_id,
+        },
+    )
+    .await?;
+    Ok(token.subscriber_id)
 }
 
-#[tracing::instrument(name = "Mark subscriber as confirmed", skip(subscriber_id, pool))]
-pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
+#[tracing::instrument(
+    name = "Mark subscriber as confirmed",
+    skip(subscriber_id, transaction)
+)]
+/// Returns the email address of the subscriber.
+pub async fn confirm_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1 RETURNING email"#,
         subscriber_id,
     )
-    .execute(pool)
-    .await?;
+    .fetch_one(&mut **transaction)
+    .await
+}
+
+/// Switch the subscriber over to the address they changed to, which the token
+/// was sent to. Returns the new email address.
+#[tracing::instrument(name = "Confirm a changed email address", skip(transaction))]
+pub async fn confirm_email_change(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        UPDATE subscriptions
+        SET status = 'confirmed', email = COALESCE(pending_email, email), pending_email = NULL
+        WHERE id = $1
+        RETURNING email
+        "#,
+        subscriber_id,
+    )
+    .fetch_one(&mut **transaction)
+    .await
+}
+
+#[tracing::instrument(name = "Mark list membership as confirmed", skip(transaction))]
+pub async fn confirm_list_membership(
+    transaction: &mut Transaction<'_, Postgres>,
+    list_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            UPDATE list_memberships SET status = 'confirmed'
+            WHERE list_id = $1 AND subscriber_id = $2 AND status = 'pending_confirmation'
+            "#,
+            list_id,
+            subscriber_id,
+        ))
+        .await?;
     Ok(())
 }
 
-#[tracing::instrument(name = "Get subscriber_id from token", skip(subscription_token, pool))]
-pub async fn get_subscriber_id_from_token(
-    pool: &PgPool,
+pub struct SubscriptionToken {
+    pub subscriber_id: Uuid,
+    /// `None` if the token only confirms a changed email address.
+    pub list_id: Option<Uuid>,
+}
+
+/// Tokens older than `token_ttl` are treated as if they did not exist.
+#[tracing::instrument(name = "Get subscription token", skip(subscription_token, executor))]
+pub async fn get_subscription_token<'c, E>(
+    executor: E,
     subscription_token: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
-        "SELECT subscriber_id FROM subscription_tokens \
-        WHERE subscription_token = $1",
+    token_ttl: Duration,
+) -> Result<Option<SubscriptionToken>, sqlx::Error>
+where
+    E: Executor<'c, Database = Postgres>,
+{
+    sqlx::query_as!(
+        SubscriptionToken,
+        "SELECT subscriber_id, list_id FROM subscription_tokens \
+        WHERE subscription_token = $1 AND created_at > $2",
         subscription_token,
+        Utc::now() - token_ttl,
     )
-    .fetch_optional(pool)
-    .await?;
-    Ok(result.map(|r| r.subscriber_id))
+    .fetch_optional(executor)
+    .await
+}
+
+pub(super) fn document(api: &mut OpenApi) {
+    api.add(
+        Operation::get("/subscriptions/confirm", "Confirm a subscription")
+            .query::<Parameters>()
+            .respond(200, "The subscription is confirmed")
+            .respond(401, "The subscription token is unknown or expired"),
+    );
 }
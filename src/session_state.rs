@@ -3,6 +3,7 @@ use actix_session::{Session, SessionGetError, SessionInsertError};
 use actix_web::dev::Payload;
 use actix_web::{FromRequest, HttpRequest};
 use std::future::{ready, Ready};
+use uuid::Uuid;
 
 use crate::authentication::UserData;
 
@@ -10,12 +11,17 @@ pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_KEY: &'static str = "user";
+    /// Identifies the session in `user_sessions`.
+    const SESSION_ID_KEY: &'static str = "session_id";
+    /// A user who entered the right password but still has to provide a second factor.
+    const PENDING_USER_KEY: &'static str = "pending_two_factor_user";
 
     pub fn renew(&self) {
         self.0.renew();
     }
 
-    pub fn insert_user(&self, user: UserData) -> Result<(), SessionInsertError> {
+    pub fn insert_user(&self, user: UserData, session_id: Uuid) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::SESSION_ID_KEY, session_id)?;
         self.0.insert(Self::USER_KEY, user)
     }
 
@@ -23,6 +29,23 @@ impl TypedSession {
         self.0.get(Self::USER_KEY)
     }
 
+    /// `None` for sessions that predate tracking them.
+    pub fn get_session_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get(Self::SESSION_ID_KEY)
+    }
+
+    pub fn insert_pending_user(&self, user: UserData) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::PENDING_USER_KEY, user)
+    }
+
+    pub fn get_pending_user(&self) -> Result<Option<UserData>, SessionGetError> {
+        self.0.get(Self::PENDING_USER_KEY)
+    }
+
+    pub fn remove_pending_user(&self) {
+        self.0.remove(Self::PENDING_USER_KEY);
+    }
+
     pub fn log_out(self) {
         self.0.purge()
     }
@@ -1,15 +1,33 @@
 use std::net::TcpListener;
 
+use anyhow::Context;
+
 use crate::{
-    authentication::{reject_anonymous_users, reject_not_admin_users},
+    authentication::{
+        reject_anonymous_users, reject_invalid_api_tokens, reject_users_without_two_factor,
+        require_permission, Permission, SessionCache, SESSION_TTL_IN_HOURS,
+    },
     routes::{
-        activate_account, activate_account_form, invite_collaborator, invite_collaborator_form,
-        publish_newsletter, send_newsletter_issue_form,
+        activate_account, activate_account_form, add_role, api_confirm_subscriber,
+        api_create_subscriber, api_get_issue, api_get_issue_delivery, api_get_subscriber,
+        api_json_config, api_list_collaborators, api_list_issues, api_list_subscribers, api_me,
+        api_path_config, api_publish_issue, api_query_config, api_tokens_form,
+        approve_issue_review, audit_log, change_account_role, change_email, change_email_form,
+        confirm_email, create_mailing_list, create_webhook, deactivate_account, delete_account,
+        erase_data, erase_subscriber, export_data, export_subscriber, force_relogin,
+        invite_collaborator, invite_collaborator_form, issue_api_token, issue_reviews_form,
+        login_lockouts_form, mailing_lists_form, openapi_spec, password_reset_confirm_form,
+        password_reset_form, preferences_form, publish_newsletter, reactivate_account,
+        reject_issue_review, remove_api_token, remove_login_lockout, remove_role, remove_webhook,
+        request_password_reset, resend_invitation, reset_password, revoke_invitation,
+        revoke_other_sessions, revoke_session, roles_form, send_newsletter_issue_form,
+        sessions_form, subscriber_data_form, subscriber_tags_form, update_preferences,
+        update_role_permissions, update_subscriber_tags, user_accounts_form, webhooks_form,
     },
 };
-use actix_session::{storage::RedisSessionStore, SessionMiddleware};
+use actix_session::{config::BrowserSession, storage::RedisSessionStore, SessionMiddleware};
 use actix_web::{
-    cookie::Key,
+    cookie::{time::Duration, Key},
     dev::Server,
     web::{self, Data},
     App, HttpServer,
@@ -22,9 +40,15 @@ use tracing_actix_web::TracingLogger;
 
 use crate::routes::{change_password, change_password_form, log_out};
 use crate::{
-    configuration::{DatabaseSettings, Settings},
+    configuration::{ApplicationSettings, DatabaseSettings, Settings},
     email_client::EmailClient,
-    routes::{admin_dashboard, confirm, health_check, home, login, login_form, subscribe},
+    rate_limiting::{RateLimitGroup, RateLimiter},
+    routes::{
+        admin_dashboard, confirm, confirm_two_factor, enroll_two_factor, health_check, home, login,
+        login_form, remove_two_factor, security_settings_form, subscribe, two_factor_form,
+        update_security_policy, verify_two_factor,
+    },
+    utils::TrustedProxies,
 };
 
 pub struct Application {
@@ -35,22 +59,14 @@ pub struct Application {
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
-        let email_client = configuration.email_client.client();
+        let email_client = configuration.email_client.clone().client();
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
         );
         let listener = TcpListener::bind(address)?;
         let port = listener.local_addr().unwrap().port();
-        let server = run(
-            listener,
-            connection_pool,
-            email_client,
-            configuration.application.base_url,
-            configuration.application.hmac_secret,
-            configuration.redis_uri,
-        )
-        .await?;
+        let server = run(listener, connection_pool, email_client, configuration).await?;
         Ok(Self { port, server })
     }
 
@@ -73,55 +89,266 @@ async fn run(
     listener: TcpListener,
     db_pool: PgPool,
     email_client: EmailClient,
-    base_url: String,
-    hmac_secret: Secret<String>,
-    redis_uri: Secret<String>,
+    configuration: Settings,
 ) -> Result<Server, anyhow::Error> {
+    let session_cache =
+        web::Data::new(SessionCache::new(configuration.application.session_cache()));
+    let Settings {
+        application:
+            ApplicationSettings {
+                base_url,
+                hmac_secret,
+                trusted_proxies,
+                ..
+            },
+        redis_uri,
+        subscriptions: subscription_settings,
+        rate_limits,
+        login_protection,
+        password_hashing,
+        password_policy,
+        ..
+    } = configuration;
     let db_pool = web::Data::new(db_pool);
     let email_client = web::Data::new(email_client);
     let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let captcha_verifier = web::Data::new(subscription_settings.captcha.clone().verifier());
+    let subscription_settings = web::Data::new(subscription_settings);
+    let login_protection = web::Data::new(login_protection);
+    let trusted_proxies = web::Data::new(TrustedProxies::new(trusted_proxies));
+    password_hashing
+        .params()
+        .context("Invalid password hashing parameters.")?;
+    let password_hashing = web::Data::new(password_hashing);
+    let password_policy = web::Data::new(password_policy);
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
     let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
+    let rate_limiter = RateLimiter::new(&redis_uri, rate_limits.key_prefix.clone()).await?;
+    let login_limits = RateLimitGroup::new(
+        rate_limiter.clone(),
+        "login",
+        "username",
+        rate_limits.login.clone(),
+    );
+    let subscription_limits = RateLimitGroup::new(
+        rate_limiter.clone(),
+        "subscriptions",
+        "email",
+        rate_limits.subscriptions.clone(),
+    );
+    let password_reset_limits = RateLimitGroup::new(
+        rate_limiter.clone(),
+        "password_reset",
+        "username",
+        rate_limits.password_reset.clone(),
+    );
+    let account_activation_limits = RateLimitGroup::new(
+        rate_limiter,
+        "account_activation",
+        "username",
+        rate_limits.account_activation.clone(),
+    );
     let server = HttpServer::new(move || {
         App::new()
             .wrap(message_framework.clone())
-            .wrap(SessionMiddleware::new(
-                redis_store.clone(),
-                secret_key.clone(),
-            ))
+            .wrap(
+                SessionMiddleware::builder(redis_store.clone(), secret_key.clone())
+                    .session_lifecycle(
+                        BrowserSession::default().state_ttl(Duration::hours(SESSION_TTL_IN_HOURS)),
+                    )
+                    .build(),
+            )
             .wrap(TracingLogger::default())
             .route("/health_check", web::get().to(health_check))
-            .route("/subscriptions", web::post().to(subscribe))
+            .service(
+                web::resource("/subscriptions")
+                    .wrap(from_fn(subscription_limits.clone().middleware()))
+                    .route(web::post().to(subscribe)),
+            )
             .route("/subscriptions/confirm", web::get().to(confirm))
+            .route("/preferences", web::get().to(preferences_form))
+            .route("/preferences", web::post().to(update_preferences))
+            .route("/preferences/export", web::get().to(export_data))
+            .route("/preferences/erase", web::post().to(erase_data))
             .route("/", web::get().to(home))
-            .route("/login", web::get().to(login_form))
-            .route("/login", web::post().to(login))
+            .service(
+                web::resource("/login")
+                    .wrap(from_fn(login_limits.clone().middleware()))
+                    .route(web::get().to(login_form))
+                    .route(web::post().to(login)),
+            )
+            .service(
+                web::resource("/login/two-factor")
+                    .wrap(from_fn(login_limits.clone().middleware()))
+                    .route(web::get().to(two_factor_form))
+                    .route(web::post().to(verify_two_factor)),
+            )
+            .service(
+                web::resource("/password-reset")
+                    .wrap(from_fn(password_reset_limits.clone().middleware()))
+                    .route(web::get().to(password_reset_form))
+                    .route(web::post().to(request_password_reset)),
+            )
             .route(
-                "/collabolators/activate",
-                web::get().to(activate_account_form),
+                "/password-reset/confirm",
+                web::get().to(password_reset_confirm_form),
+            )
+            .route("/password-reset/confirm", web::post().to(reset_password))
+            .service(
+                web::resource("/collabolators/activate")
+                    .wrap(from_fn(account_activation_limits.clone().middleware()))
+                    .route(web::get().to(activate_account_form))
+                    .route(web::post().to(activate_account)),
             )
-            .route("/collabolators/activate", web::post().to(activate_account))
             .service(
                 web::scope("/admin")
+                    .wrap(from_fn(reject_users_without_two_factor))
                     .wrap(from_fn(reject_anonymous_users))
                     .route("/dashboard", web::get().to(admin_dashboard))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
+                    .route("/email", web::get().to(change_email_form))
+                    .route("/email", web::post().to(change_email))
+                    .route("/email/confirm", web::get().to(confirm_email))
                     .route("/logout", web::post().to(log_out))
-                    .route("/newsletters", web::get().to(send_newsletter_issue_form))
-                    .route("/newsletters", web::post().to(publish_newsletter))
+                    .route("/api-tokens", web::get().to(api_tokens_form))
+                    .route("/api-tokens", web::post().to(issue_api_token))
+                    .route("/api-tokens/revoke", web::post().to(remove_api_token))
+                    .route("/sessions", web::get().to(sessions_form))
+                    .route("/sessions", web::post().to(revoke_session))
+                    .route("/sessions/others", web::post().to(revoke_other_sessions))
+                    .service(
+                        web::resource("/sessions/force-relogin")
+                            .wrap(from_fn(require_permission(Permission::ManageUsers)))
+                            .route(web::post().to(force_relogin)),
+                    )
+                    .service(
+                        web::scope("/security")
+                            .route("", web::get().to(security_settings_form))
+                            .route("/two-factor", web::post().to(enroll_two_factor))
+                            .route("/two-factor/confirm", web::post().to(confirm_two_factor))
+                            .route("/two-factor/disable", web::post().to(remove_two_factor))
+                            .service(
+                                web::resource("/policy")
+                                    .wrap(from_fn(require_permission(Permission::ManageUsers)))
+                                    .route(web::post().to(update_security_policy)),
+                            ),
+                    )
+                    .service(
+                        web::resource("/newsletters")
+                            .wrap(from_fn(require_permission(Permission::DraftIssues)))
+                            .route(web::get().to(send_newsletter_issue_form))
+                            .route(web::post().to(publish_newsletter)),
+                    )
+                    .service(
+                        web::scope("/reviews")
+                            .wrap(from_fn(require_permission(Permission::PublishIssues)))
+                            .route("", web::get().to(issue_reviews_form))
+                            .route("/approve", web::post().to(approve_issue_review))
+                            .route("/reject", web::post().to(reject_issue_review)),
+                    )
                     .service(
                         web::scope("/collabolators")
-                            .wrap(from_fn(reject_not_admin_users))
+                            .wrap(from_fn(require_permission(Permission::ManageUsers)))
                             .route("", web::get().to(invite_collaborator_form))
-                            .route("", web::post().to(invite_collaborator)),
+                            .route("", web::post().to(invite_collaborator))
+                            .route("/resend", web::post().to(resend_invitation))
+                            .route("/revoke", web::post().to(revoke_invitation)),
+                    )
+                    .service(
+                        web::scope("/lists")
+                            .wrap(from_fn(require_permission(Permission::ManageSubscribers)))
+                            .route("", web::get().to(mailing_lists_form))
+                            .route("", web::post().to(create_mailing_list)),
+                    )
+                    .service(
+                        web::scope("/tags")
+                            .wrap(from_fn(require_permission(Permission::ManageSubscribers)))
+                            .route("", web::get().to(subscriber_tags_form))
+                            .route("", web::post().to(update_subscriber_tags)),
+                    )
+                    .service(
+                        web::scope("/lockouts")
+                            .wrap(from_fn(require_permission(Permission::ManageUsers)))
+                            .route("", web::get().to(login_lockouts_form))
+                            .route("", web::post().to(remove_login_lockout)),
+                    )
+                    .service(
+                        web::scope("/subscribers")
+                            .wrap(from_fn(require_permission(Permission::ManageSubscribers)))
+                            .route("", web::get().to(subscriber_data_form))
+                            .route("/export", web::get().to(export_subscriber))
+                            .route("/erase", web::post().to(erase_subscriber)),
+                    )
+                    .service(
+                        web::scope("/users")
+                            .wrap(from_fn(require_permission(Permission::ManageUsers)))
+                            .route("", web::get().to(user_accounts_form))
+                            .route("/deactivate", web::post().to(deactivate_account))
+                            .route("/reactivate", web::post().to(reactivate_account))
+                            .route("/delete", web::post().to(delete_account))
+                            .route("/role", web::post().to(change_account_role)),
+                    )
+                    .service(
+                        web::scope("/roles")
+                            .wrap(from_fn(require_permission(Permission::ManageUsers)))
+                            .route("", web::get().to(roles_form))
+                            .route("", web::post().to(add_role))
+                            .route("/permissions", web::post().to(update_role_permissions))
+                            .route("/delete", web::post().to(remove_role)),
+                    )
+                    .service(
+                        web::scope("/webhooks")
+                            .wrap(from_fn(require_permission(Permission::ManageSubscribers)))
+                            .route("", web::get().to(webhooks_form))
+                            .route("", web::post().to(create_webhook))
+                            .route("/delete", web::post().to(remove_webhook)),
+                    )
+                    .service(
+                        web::resource("/audit")
+                            .wrap(from_fn(require_permission(Permission::ViewAuditLog)))
+                            .route(web::get().to(audit_log)),
                     ),
             )
+            .route("/api/openapi.json", web::get().to(openapi_spec))
+            .service(
+                web::scope("/api/v1")
+                    .wrap(from_fn(reject_invalid_api_tokens))
+                    .app_data(api_json_config())
+                    .app_data(api_query_config())
+                    .app_data(api_path_config())
+                    .route("/me", web::get().to(api_me))
+                    .route("/subscribers", web::get().to(api_list_subscribers))
+                    .route("/subscribers", web::post().to(api_create_subscriber))
+                    .route(
+                        "/subscribers/confirm",
+                        web::post().to(api_confirm_subscriber),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}",
+                        web::get().to(api_get_subscriber),
+                    )
+                    .route("/issues", web::get().to(api_list_issues))
+                    .route("/issues", web::post().to(api_publish_issue))
+                    .route("/issues/{issue_id}", web::get().to(api_get_issue))
+                    .route(
+                        "/issues/{issue_id}/delivery",
+                        web::get().to(api_get_issue_delivery),
+                    )
+                    .route("/collaborators", web::get().to(api_list_collaborators)),
+            )
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(subscription_settings.clone())
+            .app_data(captcha_verifier.clone())
+            .app_data(login_protection.clone())
+            .app_data(trusted_proxies.clone())
+            .app_data(password_hashing.clone())
+            .app_data(password_policy.clone())
+            .app_data(session_cache.clone())
             .app_data(Data::new(HmacSecret(hmac_secret.clone())))
     })
     .listen(listener)?
@@ -0,0 +1,204 @@
+//! Everything we store about a subscriber, keyed by their email address,
+//! so that data-subject requests (export and erasure) can be honoured.
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::HttpResponse;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::newsletter_issues::notify_if_issue_delivered;
+
+#[derive(serde::Serialize)]
+pub struct SubscriberData {
+    pub email: String,
+    pub subscription: Option<Subscription>,
+    pub list_memberships: Vec<ListMembership>,
+    pub tags: Vec<String>,
+    pub subscription_tokens: Vec<SubscriptionToken>,
+    pub deliveries: Vec<Delivery>,
+}
+
+#[derive(serde::Serialize)]
+pub struct Subscription {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub status: String,
+    pub pending_email: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ListMembership {
+    pub list_id: Uuid,
+    pub list_name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriptionToken {
+    pub subscription_token: String,
+    pub list_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A newsletter issue that is still waiting to be delivered.
+#[derive(serde::Serialize)]
+pub struct Delivery {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub n_retries: i16,
+    pub execute_after: Option<DateTime<Utc>>,
+}
+
+impl SubscriberData {
+    pub fn is_empty(&self) -> bool {
+        self.subscription.is_none() && self.deliveries.is_empty()
+    }
+
+    /// Render the data as a downloadable JSON document.
+    pub fn into_response(self) -> HttpResponse {
+        HttpResponse::Ok()
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename("subscriber-data.json".into())],
+            })
+            .json(self)
+    }
+}
+
+#[tracing::instrument(name = "Export subscriber data", skip(pool))]
+pub async fn get_subscriber_data(
+    pool: &PgPool,
+    email: &str,
+) -> Result<SubscriberData, sqlx::Error> {
+    let subscription = sqlx::query_as!(
+        Subscription,
+        r#"
+        SELECT id, email, name, subscribed_at, status, pending_email
+        FROM subscriptions
+        WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(pool)
+    .await?;
+    let deliveries = sqlx::query_as!(
+        Delivery,
+        r#"
+        SELECT q.newsletter_issue_id, i.title, q.n_retries AS "n_retries!", q.execute_after
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i ON i.newsletter_issue_id = q.newsletter_issue_id
+        WHERE q.subscriber_email = $1
+        ORDER BY i.published_at
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut data = SubscriberData {
+        email: email.to_owned(),
+        subscription: None,
+        list_memberships: vec![],
+        tags: vec![],
+        subscription_tokens: vec![],
+        deliveries,
+    };
+    let Some(subscription) = subscription else {
+        return Ok(data);
+    };
+    data.list_memberships = sqlx::query_as!(
+        ListMembership,
+        r#"
+        SELECT m.list_id, l.name AS list_name, m.status, m.subscribed_at
+        FROM list_memberships m
+        JOIN lists l ON l.list_id = m.list_id
+        WHERE m.subscriber_id = $1
+        ORDER BY l.created_at, l.name
+        "#,
+        subscription.id
+    )
+    .fetch_all(pool)
+    .await?;
+    data.tags = sqlx::query!(
+        "SELECT tag FROM subscriber_tags WHERE subscriber_id = $1 ORDER BY tag",
+        subscription.id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| r.tag)
+    .collect();
+    data.subscription_tokens = sqlx::query_as!(
+        SubscriptionToken,
+        r#"
+        SELECT subscription_token, list_id, created_at
+        FROM subscription_tokens
+        WHERE subscriber_id = $1
+        ORDER BY created_at
+        "#,
+        subscription.id
+    )
+    .fetch_all(pool)
+    .await?;
+    data.subscription = Some(subscription);
+    Ok(data)
+}
+
+/// Remove every trace of `email` - pending deliveries, confirmation tokens and
+/// the subscription itself (list memberships and tags are removed with it).
+/// Webhook events about the subscriber are kept, without the address, and
+/// issues whose queue this empties are reported delivered.
+/// Returns `false` if there was nothing to remove.
+#[tracing::instrument(name = "Erase subscriber data", skip(transaction))]
+pub async fn erase_subscriber_data(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<bool, anyhow::Error> {
+    let mut issue_ids = sqlx::query_scalar!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE subscriber_email = $1
+        RETURNING newsletter_issue_id
+        "#,
+        email
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+    let n_deliveries = issue_ids.len();
+    // Always in the same order, not to deadlock with another erasure.
+    issue_ids.sort();
+    for newsletter_issue_id in issue_ids {
+        notify_if_issue_delivered(transaction, newsletter_issue_id).await?;
+    }
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            DELETE FROM subscription_tokens
+            WHERE subscriber_id IN (SELECT id FROM subscriptions WHERE email = $1)
+            "#,
+            email
+        ))
+        .await?;
+    let n_subscriptions = transaction
+        .execute(sqlx::query!(
+            "DELETE FROM subscriptions WHERE email = $1",
+            email
+        ))
+        .await?
+        .rows_affected();
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            UPDATE webhook_outbox
+            SET payload = jsonb_set(payload::jsonb, '{data,email}', 'null')::text
+            WHERE event_type LIKE 'subscriber.%'
+                AND payload::jsonb #>> '{data,email}' = $1
+            "#,
+            email
+        ))
+        .await?;
+    Ok(n_deliveries > 0 || n_subscriptions > 0)
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use backoff::ExponentialBackoff;
+use chrono::Utc;
+use sqlx::{Executor, PgPool};
+
+use crate::configuration::{Settings, SubscriptionSettings};
+use crate::startup::get_connection_pool;
+
+pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    worker_loop(connection_pool, configuration.subscriptions).await
+}
+
+async fn worker_loop(pool: PgPool, settings: SubscriptionSettings) -> Result<(), anyhow::Error> {
+    loop {
+        let _ = try_delete_stale_subscriptions(&pool, &settings).await;
+        tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+    }
+}
+
+#[tracing::instrument(
+    "Delete expired subscription tokens, stale subscribers, list memberships and email changes",
+    skip_all
+)]
+pub async fn try_delete_stale_subscriptions(
+    pool: &PgPool,
+    settings: &SubscriptionSettings,
+) -> Result<(), anyhow::Error> {
+    let operation = || async {
+        delete_stale_subscriptions(pool, settings).await?;
+        Ok(())
+    };
+    let backoff = ExponentialBackoff::default();
+    backoff::future::retry(backoff, operation).await
+}
+
+async fn delete_stale_subscriptions(
+    pool: &PgPool,
+    settings: &SubscriptionSettings,
+) -> Result<(), anyhow::Error> {
+    let token_cutoff = Utc::now() - settings.confirmation_token_ttl();
+    let subscriber_cutoff = Utc::now() - settings.pending_subscriber_ttl();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM subscription_tokens WHERE created_at <= $1",
+            token_cutoff
+        ))
+        .await
+        .context("Cannot delete expired subscription tokens")?;
+    // Joining a list has to be confirmed with the token sent for it.
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            DELETE FROM list_memberships
+            WHERE
+                status = 'pending_confirmation' AND
+                NOT EXISTS (
+                    SELECT 1 FROM subscription_tokens
+                    WHERE
+                        subscriber_id = list_memberships.subscriber_id AND
+                        list_id = list_memberships.list_id
+                )
+            "#
+        ))
+        .await
+        .context("Cannot delete expired pending list memberships")?;
+    // Only subscribers who never confirmed their address are removed, unless
+    // they are still waiting on a live token (e.g. after subscribing again).
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            DELETE FROM subscriptions
+            WHERE
+                status = 'pending_confirmation' AND
+                subscribed_at <= $1 AND
+                NOT EXISTS (
+                    SELECT 1 FROM subscription_tokens
+                    WHERE subscriber_id = subscriptions.id
+                )
+            "#,
+            subscriber_cutoff
+        ))
+        .await
+        .context("Cannot delete stale pending subscribers")?;
+    // A changed address that was not confirmed in time is dropped - the
+    // subscriber keeps the address they had.
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            UPDATE subscriptions SET pending_email = NULL
+            WHERE
+                pending_email IS NOT NULL AND
+                NOT EXISTS (
+                    SELECT 1 FROM subscription_tokens
+                    WHERE subscriber_id = subscriptions.id AND list_id IS NULL
+                )
+            "#
+        ))
+        .await
+        .context("Cannot drop unconfirmed email address changes")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to delete stale subscriptions.")?;
+    Ok(())
+}
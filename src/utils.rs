@@ -1,8 +1,9 @@
-use actix_web::http::header::LOCATION;
-use actix_web::HttpResponse;
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::http::header::{HeaderMap, LOCATION, USER_AGENT};
+use actix_web::{web, HttpRequest, HttpResponse};
 use rand::distributions::Alphanumeric;
 use rand::Rng;
-use secrecy::{ExposeSecret, Secret};
 
 // Return an opaque 500 while preserving the error root's cause for logging.
 pub fn e500<T>(e: T) -> actix_web::Error
@@ -27,6 +28,73 @@ pub fn see_other(location: &str) -> HttpResponse {
         .finish()
 }
 
+/// The address of the client - the peer, or the address it forwarded the
+/// request for if it is one of the trusted proxies.
+pub fn client_ip(request: &HttpRequest) -> String {
+    let no_proxies = TrustedProxies::default();
+    let proxies = request
+        .app_data::<web::Data<TrustedProxies>>()
+        .map(|proxies| proxies.get_ref())
+        .unwrap_or(&no_proxies);
+    proxies.client_ip(request.peer_addr(), request.headers())
+}
+
+/// The reverse proxies whose `X-Forwarded-For` header is believed.
+#[derive(Clone, Default, Debug)]
+pub struct TrustedProxies(Vec<IpAddr>);
+
+impl TrustedProxies {
+    pub fn new(proxies: Vec<IpAddr>) -> Self {
+        Self(proxies)
+    }
+
+    /// The address of the client behind `peer`. `X-Forwarded-For` is walked
+    /// from the right - each hop is appended by the proxy that received it -
+    /// for as long as the hops are trusted proxies. Everything to the left of
+    /// the first untrusted hop could have been written by the client itself.
+    pub fn client_ip(&self, peer: Option<SocketAddr>, headers: &HeaderMap) -> String {
+        let Some(peer) = peer else {
+            return "unknown".into();
+        };
+        let mut client = peer.ip();
+        if !self.0.contains(&client) {
+            return client.to_string();
+        }
+        let hops: Vec<&str> = headers
+            .get_all("X-Forwarded-For")
+            .filter_map(|h| h.to_str().ok())
+            .flat_map(|h| h.split(','))
+            .collect();
+        for hop in hops.into_iter().rev() {
+            let Some(hop) = parse_hop(hop) else {
+                break;
+            };
+            client = hop;
+            if !self.0.contains(&client) {
+                break;
+            }
+        }
+        client.to_string()
+    }
+}
+
+/// An `X-Forwarded-For` entry - an address, possibly with a port.
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    let hop = hop.trim();
+    hop.parse::<IpAddr>()
+        .ok()
+        .or_else(|| hop.parse::<SocketAddr>().ok().map(|a| a.ip()))
+}
+
+/// The `User-Agent` of the client, empty if it did not send one.
+pub fn user_agent(request: &HttpRequest) -> &str {
+    request
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default()
+}
+
 /// Generate a random 25-characters-long case-sensitive token.
 pub fn generate_token() -> String {
     let mut rng = rand::thread_rng();
@@ -36,7 +104,65 @@ pub fn generate_token() -> String {
         .collect()
 }
 
-pub fn is_password_invalid(password: &Secret<String>) -> bool {
-    let new_pass_len = password.expose_secret().len();
-    !(13..=128).contains(&new_pass_len)
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+    use crate::utils::TrustedProxies;
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("x-forwarded-for"),
+            HeaderValue::from_str(value).unwrap(),
+        );
+        headers
+    }
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::new(vec![
+            "10.0.0.1".parse().unwrap(),
+            "10.0.0.2".parse().unwrap(),
+        ])
+    }
+
+    fn peer(ip: &str) -> Option<SocketAddr> {
+        Some(SocketAddr::new(ip.parse().unwrap(), 40000))
+    }
+
+    #[test]
+    fn forwarded_headers_from_untrusted_peers_are_ignored() {
+        let headers = forwarded_for("203.0.113.7");
+        assert_eq!(
+            proxies().client_ip(peer("198.51.100.1"), &headers),
+            "198.51.100.1"
+        );
+        assert_eq!(
+            TrustedProxies::default().client_ip(peer("10.0.0.1"), &headers),
+            "10.0.0.1"
+        );
+    }
+
+    #[test]
+    fn the_first_untrusted_hop_is_the_client() {
+        // The client made up the first entry, the proxies appended the rest.
+        let headers = forwarded_for("1.2.3.4, 203.0.113.7, 10.0.0.2");
+        assert_eq!(
+            proxies().client_ip(peer("10.0.0.1"), &headers),
+            "203.0.113.7"
+        );
+    }
+
+    #[test]
+    fn the_peer_is_kept_when_there_is_nothing_to_believe() {
+        assert_eq!(
+            proxies().client_ip(peer("10.0.0.1"), &HeaderMap::new()),
+            "10.0.0.1"
+        );
+        let headers = forwarded_for("not-an-address");
+        assert_eq!(proxies().client_ip(peer("10.0.0.1"), &headers), "10.0.0.1");
+        assert_eq!(proxies().client_ip(None, &headers), "unknown");
+    }
 }
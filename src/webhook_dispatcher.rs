@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::Utc;
+use reqwest::Client;
+use secrecy::Secret;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+use crate::configuration::Settings;
+use crate::startup::get_connection_pool;
+use crate::webhooks::{sign_payload, signing_secret};
+
+/// Deliveries still failing after this many attempts are given up on.
+pub const MAX_WEBHOOK_ATTEMPTS: i16 = 10;
+const MAX_RETRY_DELAY_SEC: u64 = 60 * 60;
+const REQUEST_TIMEOUT_SEC: u64 = 10;
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+struct Delivery {
+    delivery_id: Uuid,
+    event_id: Uuid,
+    event_type: String,
+    payload: String,
+    url: String,
+    n_attempts: i16,
+}
+
+pub fn webhook_client() -> Client {
+    Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SEC))
+        .build()
+        .unwrap()
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    http_client: Client,
+    signing_secret: Secret<String>,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_execute_delivery(&pool, &http_client, &signing_secret).await {
+            Ok(ExecutionOutcome::EmptyQueue) => tokio::time::sleep(Duration::from_secs(10)).await,
+            Err(_) => tokio::time::sleep(Duration::from_secs(1)).await,
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    worker_loop(
+        connection_pool,
+        webhook_client(),
+        signing_secret(&configuration.application.hmac_secret),
+    )
+    .await
+}
+
+/// Send the oldest due webhook delivery. Failed attempts are retried with an
+/// exponential backoff, up to `MAX_WEBHOOK_ATTEMPTS` attempts in total.
+#[tracing::instrument(
+    skip_all,
+    fields(delivery_id=tracing::field::Empty, event_type=tracing::field::Empty),
+    err
+)]
+pub async fn try_execute_delivery(
+    pool: &PgPool,
+    http_client: &Client,
+    signing_secret: &Secret<String>,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let Some((transaction, delivery)) = dequeue_delivery(pool).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current()
+        .record("delivery_id", &display(delivery.delivery_id))
+        .record("event_type", &display(&delivery.event_type));
+
+    let timestamp = Utc::now().timestamp();
+    let outcome = http_client
+        .post(&delivery.url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Id", delivery.event_id.to_string())
+        .header("X-Webhook-Event", &delivery.event_type)
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header(
+            "X-Webhook-Signature",
+            sign_payload(signing_secret, timestamp, &delivery.payload),
+        )
+        .body(delivery.payload.clone())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    let attempt = match outcome {
+        Ok(response) => Attempt {
+            status_code: Some(response.status().as_u16() as i16),
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to deliver a webhook.",
+            );
+            Attempt {
+                status_code: e.status().map(|s| s.as_u16() as i16),
+                error: Some(e.to_string()),
+            }
+        }
+    };
+    record_attempt(transaction, &delivery, attempt).await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_delivery(
+    pool: &PgPool,
+) -> Result<Option<(PgTransaction, Delivery)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let delivery = sqlx::query_as!(
+        Delivery,
+        r#"
+        SELECT o.delivery_id, o.event_id, o.event_type, o.payload, e.url, o.n_attempts
+        FROM webhook_outbox o
+        JOIN webhook_endpoints e ON e.endpoint_id = o.endpoint_id
+        WHERE o.status = 'pending' AND COALESCE(o.execute_after, now()) <= now()
+        ORDER BY o.created_at
+        FOR UPDATE OF o
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    Ok(delivery.map(|delivery| (transaction, delivery)))
+}
+
+struct Attempt {
+    status_code: Option<i16>,
+    error: Option<String>,
+}
+
+#[tracing::instrument(skip_all)]
+async fn record_attempt(
+    mut transaction: PgTransaction,
+    delivery: &Delivery,
+    attempt: Attempt,
+) -> Result<(), anyhow::Error> {
+    let n_attempts = delivery.n_attempts + 1;
+    let (status, execute_after) = match attempt.error {
+        None => ("delivered", None),
+        Some(_) if n_attempts >= MAX_WEBHOOK_ATTEMPTS => ("failed", None),
+        Some(_) => {
+            let backoff_delay =
+                Duration::from_secs(2_u64.pow(n_attempts as u32).min(MAX_RETRY_DELAY_SEC));
+            ("pending", Some(Utc::now() + backoff_delay))
+        }
+    };
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            INSERT INTO webhook_attempts (delivery_id, attempted_at, status_code, error)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            delivery.delivery_id,
+            Utc::now(),
+            attempt.status_code,
+            attempt.error
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            UPDATE webhook_outbox
+            SET status = $2, n_attempts = $3, execute_after = $4
+            WHERE delivery_id = $1
+            "#,
+            delivery.delivery_id,
+            status,
+            n_attempts,
+            execute_after
+        ))
+        .await
+        .context("Failed to record the webhook delivery attempt.")?;
+    transaction.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,303 @@
+//! Outbound webhooks: endpoints configured by admins get a signed `POST` for
+//! every event they subscribed to. Events go through an outbox table, written
+//! in the same transaction as the change they describe, and are sent by the
+//! `webhook_dispatcher`.
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+const SIGNING_SECRET_CONTEXT: &[u8] = b"webhook-signing-secret";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEventType {
+    SubscriberCreated,
+    SubscriberConfirmed,
+    SubscriberUnsubscribed,
+    IssueDelivered,
+}
+
+impl WebhookEventType {
+    pub const ALL: [WebhookEventType; 4] = [
+        WebhookEventType::SubscriberCreated,
+        WebhookEventType::SubscriberConfirmed,
+        WebhookEventType::SubscriberUnsubscribed,
+        WebhookEventType::IssueDelivered,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::SubscriberCreated => "subscriber.created",
+            WebhookEventType::SubscriberConfirmed => "subscriber.confirmed",
+            WebhookEventType::SubscriberUnsubscribed => "subscriber.unsubscribed",
+            WebhookEventType::IssueDelivered => "issue.delivered",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.as_str() == s)
+    }
+}
+
+/// Something that happened, with the data sent along with it. `list_id` is
+/// `None` when a token only confirmed the subscriber's email address.
+pub enum WebhookEvent {
+    SubscriberCreated {
+        subscriber_id: Uuid,
+        email: String,
+        list_id: Option<Uuid>,
+    },
+    SubscriberConfirmed {
+        subscriber_id: Uuid,
+        email: String,
+        list_id: Option<Uuid>,
+    },
+    SubscriberUnsubscribed {
+        subscriber_id: Uuid,
+        email: String,
+        list_id: Option<Uuid>,
+    },
+    IssueDelivered {
+        newsletter_issue_id: Uuid,
+        recipients: Option<i32>,
+    },
+}
+
+impl WebhookEvent {
+    pub fn event_type(&self) -> WebhookEventType {
+        match self {
+            WebhookEvent::SubscriberCreated { .. } => WebhookEventType::SubscriberCreated,
+            WebhookEvent::SubscriberConfirmed { .. } => WebhookEventType::SubscriberConfirmed,
+            WebhookEvent::SubscriberUnsubscribed { .. } => WebhookEventType::SubscriberUnsubscribed,
+            WebhookEvent::IssueDelivered { .. } => WebhookEventType::IssueDelivered,
+        }
+    }
+
+    fn data(&self) -> serde_json::Value {
+        match self {
+            WebhookEvent::SubscriberCreated {
+                subscriber_id,
+                email,
+                list_id,
+            }
+            | WebhookEvent::SubscriberConfirmed {
+                subscriber_id,
+                email,
+                list_id,
+            }
+            | WebhookEvent::SubscriberUnsubscribed {
+                subscriber_id,
+                email,
+                list_id,
+            } => serde_json::json!({
+                "subscriber_id": subscriber_id,
+                "email": email,
+                "list_id": list_id,
+            }),
+            WebhookEvent::IssueDelivered {
+                newsletter_issue_id,
+                recipients,
+            } => serde_json::json!({
+                "newsletter_issue_id": newsletter_issue_id,
+                "recipients": recipients,
+            }),
+        }
+    }
+}
+
+/// Queue `event` for every endpoint that subscribed to its type. Nothing is
+/// sent until `transaction` commits.
+#[tracing::instrument(name = "Enqueue a webhook event", skip_all, fields(event_type = event.event_type().as_str()))]
+pub async fn enqueue_webhook_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    event: WebhookEvent,
+) -> Result<(), anyhow::Error> {
+    let event_id = Uuid::new_v4();
+    let created_at = Utc::now();
+    let event_type = event.event_type().as_str();
+    let payload = serde_json::json!({
+        "id": event_id,
+        "type": event_type,
+        "created_at": created_at,
+        "data": event.data(),
+    })
+    .to_string();
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_outbox (
+            delivery_id, endpoint_id, event_id, event_type, payload, created_at
+        )
+        SELECT gen_random_uuid(), endpoint_id, $1, $2, $3, $4
+        FROM webhook_endpoints
+        WHERE $2 = ANY(events)
+        "#,
+        event_id,
+        event_type,
+        payload,
+        created_at
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to enqueue the webhook event.")?;
+    Ok(())
+}
+
+/// The secret receivers check signatures with. It is derived from the
+/// application's HMAC secret, which never leaves the server.
+pub fn signing_secret(secret: &Secret<String>) -> Secret<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(SIGNING_SECRET_CONTEXT);
+    Secret::new(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// The `X-Webhook-Signature` of a payload sent at `timestamp`:
+/// `sha256=` followed by the hex HMAC of `{timestamp}.{payload}`, keyed with
+/// the signing secret.
+pub fn sign_payload(signing_secret: &Secret<String>, timestamp: i64, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+pub struct WebhookEndpoint {
+    pub endpoint_id: Uuid,
+    pub url: String,
+    pub events: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Create a webhook endpoint", skip(pool))]
+pub async fn create_webhook_endpoint(
+    pool: &PgPool,
+    url: &str,
+    events: &[WebhookEventType],
+) -> Result<Uuid, anyhow::Error> {
+    let endpoint_id = Uuid::new_v4();
+    let events: Vec<String> = events.iter().map(|e| e.as_str().to_owned()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_endpoints (endpoint_id, url, events, created_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        endpoint_id,
+        url,
+        &events,
+        Utc::now()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the webhook endpoint.")?;
+    Ok(endpoint_id)
+}
+
+#[tracing::instrument(name = "Get webhook endpoints", skip(pool))]
+pub async fn get_webhook_endpoints(pool: &PgPool) -> Result<Vec<WebhookEndpoint>, anyhow::Error> {
+    let endpoints = sqlx::query_as!(
+        WebhookEndpoint,
+        r#"
+        SELECT endpoint_id, url, events, created_at
+        FROM webhook_endpoints
+        ORDER BY created_at
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the webhook endpoints.")?;
+    Ok(endpoints)
+}
+
+/// Remove an endpoint together with its delivery history. Returns `false` if
+/// there was no such endpoint.
+#[tracing::instrument(name = "Delete a webhook endpoint", skip(pool))]
+pub async fn delete_webhook_endpoint(
+    pool: &PgPool,
+    endpoint_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM webhook_endpoints WHERE endpoint_id = $1",
+        endpoint_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to delete the webhook endpoint.")?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub struct WebhookDelivery {
+    pub delivery_id: Uuid,
+    pub url: String,
+    pub event_type: String,
+    pub created_at: DateTime<Utc>,
+    pub status: String,
+    pub n_attempts: i16,
+    pub last_status_code: Option<i16>,
+    pub last_error: Option<String>,
+}
+
+/// The latest deliveries to any endpoint, with the outcome of their last attempt.
+#[tracing::instrument(name = "Get webhook deliveries", skip(executor))]
+pub async fn get_webhook_deliveries(
+    executor: impl PgExecutor<'_>,
+    limit: i64,
+) -> Result<Vec<WebhookDelivery>, anyhow::Error> {
+    let deliveries = sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+        SELECT o.delivery_id, e.url, o.event_type, o.created_at, o.status, o.n_attempts,
+            a.status_code AS last_status_code, a.error AS last_error
+        FROM webhook_outbox o
+        JOIN webhook_endpoints e ON e.endpoint_id = o.endpoint_id
+        LEFT JOIN LATERAL (
+            SELECT status_code, error FROM webhook_attempts
+            WHERE delivery_id = o.delivery_id
+            ORDER BY attempted_at DESC
+            LIMIT 1
+        ) a ON true
+        ORDER BY o.created_at DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to fetch the webhook deliveries.")?;
+    Ok(deliveries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign_payload, signing_secret, WebhookEventType};
+    use secrecy::{ExposeSecret, Secret};
+
+    #[test]
+    fn event_types_round_trip() {
+        for event in WebhookEventType::ALL {
+            assert_eq!(WebhookEventType::parse(event.as_str()), Some(event));
+        }
+        assert_eq!(WebhookEventType::parse("issue.deleted"), None);
+    }
+
+    #[test]
+    fn the_signing_secret_is_not_the_hmac_secret() {
+        let secret = Secret::new("super-secret".to_owned());
+        let signing = signing_secret(&secret);
+        assert_ne!(signing.expose_secret(), secret.expose_secret());
+        assert_eq!(signing.expose_secret().len(), 64);
+    }
+
+    #[test]
+    fn signatures_cover_the_timestamp_and_the_payload() {
+        let secret = Secret::new("signing".to_owned());
+        let signature = sign_payload(&secret, 1_700_000_000, "{}");
+        assert!(signature.starts_with("sha256="));
+        assert_ne!(signature, sign_payload(&secret, 1_700_000_001, "{}"));
+        assert_ne!(signature, sign_payload(&secret, 1_700_000_000, "{ }"));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn a_token_authenticates_api_requests_as_its_owner() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+    let token = app
+        .create_api_token(&["newsletters:read", "newsletters:publish"])
+        .await;
+
+    // Act
+    let response = app.get_api("/me", Some(&token)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["username"], app.collabolator_user.username);
+    assert!(body["email"].is_null());
+    assert_eq!(body["role"], "collabolator");
+    let permissions = body["permissions"].as_array().unwrap();
+    assert!(permissions.contains(&"issues:draft".into()));
+    assert!(!permissions.contains(&"issues:publish".into()));
+    assert!(!permissions.contains(&"users:manage".into()));
+    assert_eq!(
+        body["scopes"],
+        serde_json::json!(["newsletters:read", "newsletters:publish"])
+    );
+}
+
+#[tokio::test]
+async fn requests_without_a_token_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_api("/me", None).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(response.headers()["WWW-Authenticate"], "Bearer");
+}
+
+#[tokio::test]
+async fn a_session_cookie_is_not_enough_for_the_api() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let response = app.get_api("/me", None).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn unknown_tokens_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_api("/me", Some("z2p_not-a-real-token")).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn expired_tokens_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["subscribers:read"]).await;
+    sqlx::query!("UPDATE api_tokens SET expires_at = now() - interval '1 second'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app.get_api("/me", Some(&token)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(app.get_api_tokens_html().await.contains("expired"));
+}
+
+#[tokio::test]
+async fn revoked_tokens_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["subscribers:read"]).await;
+    let token_id = sqlx::query!("SELECT token_id FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .token_id;
+
+    // Act
+    let response = app
+        .post_api_tokens("/revoke", &serde_json::json!({ "token_id": token_id }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+    assert!(app
+        .get_api_tokens_html()
+        .await
+        .contains("The API token has been revoked."));
+    assert_eq!(
+        app.get_api("/me", Some(&token)).await.status().as_u16(),
+        401
+    );
+}
+
+#[tokio::test]
+async fn tokens_are_stored_hashed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let token = app.create_api_token(&["subscribers:read"]).await;
+
+    // Assert
+    let stored = sqlx::query!("SELECT token_hash FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_ne!(stored.token_hash, token);
+    assert!(!stored.token_hash.contains(&token));
+}
+
+#[tokio::test]
+async fn a_token_needs_at_least_one_scope() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let response = app
+        .post_api_tokens("", &[("name", "ci"), ("expires_in_days", "30")])
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+    let html = app.get_api_tokens_html().await;
+    assert!(html.contains("Choose at least one scope."));
+    assert!(html.contains("You have no API tokens."));
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_api_tokens() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_api_tokens().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
@@ -0,0 +1,413 @@
+use fake::faker::internet::en::SafeEmail;
+use fake::faker::name::en::Name;
+use fake::Fake;
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+
+use crate::helpers::{spawn_app, when_sending_an_email, TestApp};
+
+fn subscriber_body() -> serde_json::Value {
+    let name: String = Name().fake();
+    let email: String = SafeEmail().fake();
+    serde_json::json!({ "name": name, "email": email })
+}
+
+fn issue_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })
+}
+
+/// Add a subscriber through the API and return the subscription token from
+/// their confirmation email.
+async fn create_unconfirmed_subscriber(app: &TestApp, token: &str) -> String {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    let key = Uuid::new_v4().to_string();
+    let response = app
+        .post_api("/subscribers", token, Some(&key), &subscriber_body())
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let link = app.get_confirmation_links(&email_request).html;
+    link.query_pairs()
+        .find(|(k, _)| k == "subscription_token")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
+async fn assert_api_error(response: reqwest::Response, status: u16, code: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], code);
+    assert!(body["error"]["message"].is_string());
+}
+
+#[tokio::test]
+async fn errors_share_the_same_json_shape() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_api("/subscribers", None).await;
+
+    // Assert
+    assert_eq!(response.headers()["WWW-Authenticate"], "Bearer");
+    assert_api_error(response, 401, "authentication_failed").await;
+}
+
+#[tokio::test]
+async fn endpoints_require_the_matching_scope() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["newsletters:read"]).await;
+
+    // Act
+    let read = app.get_api("/subscribers", Some(&token)).await;
+    let write = app
+        .post_api("/subscribers", &token, Some("key"), &subscriber_body())
+        .await;
+    let publish = app
+        .post_api("/issues", &token, Some("key"), &issue_body())
+        .await;
+
+    // Assert
+    assert_api_error(read, 403, "permission_denied").await;
+    assert_api_error(write, 403, "permission_denied").await;
+    assert_api_error(publish, 403, "permission_denied").await;
+}
+
+#[tokio::test]
+async fn subscriber_and_collaborator_endpoints_are_for_admins_only() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+    let token = app
+        .create_api_token(&["subscribers:read", "collaborators:read"])
+        .await;
+
+    // Act
+    let subscribers = app.get_api("/subscribers", Some(&token)).await;
+    let collaborators = app.get_api("/collaborators", Some(&token)).await;
+
+    // Assert
+    assert_api_error(subscribers, 403, "permission_denied").await;
+    assert_api_error(collaborators, 403, "permission_denied").await;
+}
+
+#[tokio::test]
+async fn subscribers_are_listed_page_by_page() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+    create_unconfirmed_subscriber(&app, &token).await;
+    create_unconfirmed_subscriber(&app, &token).await;
+
+    // Act
+    let response = app
+        .get_api("/subscribers?per_page=1&page=2", Some(&token))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["status"], "pending_confirmation");
+    assert_eq!(body["pagination"]["page"], 2);
+    assert_eq!(body["pagination"]["per_page"], 1);
+    assert_eq!(body["pagination"]["total"], 2);
+    assert_eq!(body["pagination"]["total_pages"], 2);
+}
+
+#[tokio::test]
+async fn invalid_pagination_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["newsletters:read"]).await;
+
+    for query in ["?per_page=1000", "?page=0", "?page=first"] {
+        // Act
+        let response = app.get_api(&format!("/issues{query}"), Some(&token)).await;
+
+        // Assert
+        assert_api_error(response, 400, "validation_error").await;
+    }
+}
+
+#[tokio::test]
+async fn a_created_subscriber_can_be_fetched() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    let body = subscriber_body();
+    let response = app
+        .post_api("/subscribers", &token, Some("create"), &body)
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let created: serde_json::Value = response.json().await.unwrap();
+
+    // Act
+    let response = app
+        .get_api(
+            &format!("/subscribers/{}", created["id"].as_str().unwrap()),
+            Some(&token),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let subscriber: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(subscriber["email"], body["email"]);
+    assert_eq!(subscriber["lists"][0]["status"], "pending_confirmation");
+}
+
+#[tokio::test]
+async fn posts_without_an_idempotency_key_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["subscribers:write"]).await;
+
+    // Act
+    let response = app
+        .post_api("/subscribers", &token, None, &subscriber_body())
+        .await;
+
+    // Assert
+    assert_api_error(response, 400, "validation_error").await;
+}
+
+#[tokio::test]
+async fn retrying_a_post_returns_the_saved_response() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["subscribers:write"]).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let body = subscriber_body();
+
+    // Act
+    let first = app
+        .post_api("/subscribers", &token, Some("retry"), &body)
+        .await;
+    let second = app
+        .post_api("/subscribers", &token, Some("retry"), &body)
+        .await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 201);
+    assert_eq!(second.status().as_u16(), 201);
+    assert_eq!(first.text().await.unwrap(), second.text().await.unwrap());
+    // Mock verifies on Drop that we have sent a single confirmation email
+}
+
+#[tokio::test]
+async fn invalid_subscriber_data_is_a_validation_error() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["subscribers:write"]).await;
+    let test_cases = [
+        serde_json::json!({ "name": "le guin", "email": "definitely-not-an-email" }),
+        serde_json::json!({ "name": "", "email": "ursula_le_guin@gmail.com" }),
+        serde_json::json!({ "name": "le guin" }),
+    ];
+
+    for body in test_cases {
+        // Act
+        let key = Uuid::new_v4().to_string();
+        let response = app
+            .post_api("/subscribers", &token, Some(&key), &body)
+            .await;
+
+        // Assert
+        assert_api_error(response, 400, "validation_error").await;
+    }
+}
+
+#[tokio::test]
+async fn a_subscription_can_be_confirmed_with_its_token() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app
+        .create_api_token(&["subscribers:read", "subscribers:write"])
+        .await;
+    let subscription_token = create_unconfirmed_subscriber(&app, &token).await;
+
+    // Act
+    let response = app
+        .post_api(
+            "/subscribers/confirm",
+            &token,
+            Some("confirm"),
+            &serde_json::json!({ "subscription_token": subscription_token }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "confirmed");
+    let response = app
+        .get_api("/subscribers?status=confirmed", Some(&token))
+        .await;
+    let list: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(list["pagination"]["total"], 1);
+    assert_eq!(list["data"][0]["id"], body["id"]);
+}
+
+#[tokio::test]
+async fn confirming_an_unknown_token_is_not_found() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["subscribers:write"]).await;
+
+    // Act
+    let response = app
+        .post_api(
+            "/subscribers/confirm",
+            &token,
+            Some("confirm"),
+            &serde_json::json!({ "subscription_token": "a".repeat(25) }),
+        )
+        .await;
+
+    // Assert
+    assert_api_error(response, 404, "not_found").await;
+}
+
+#[tokio::test]
+async fn a_published_issue_reports_its_delivery_status() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app
+        .create_api_token(&[
+            "newsletters:read",
+            "newsletters:publish",
+            "subscribers:write",
+        ])
+        .await;
+    let subscription_token = create_unconfirmed_subscriber(&app, &token).await;
+    let response = app
+        .post_api(
+            "/subscribers/confirm",
+            &token,
+            Some("confirm"),
+            &serde_json::json!({ "subscription_token": subscription_token }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Act - Part 1 - Publish
+    let response = app
+        .post_api("/issues", &token, Some("publish"), &issue_body())
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+    let location = response.headers()["Location"].to_str().unwrap().to_owned();
+    let published: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(published["recipients"], 1);
+    let issue_id = published["id"].as_str().unwrap();
+    assert_eq!(location, format!("/api/v1/issues/{issue_id}"));
+
+    // Act - Part 2 - Delivery is pending
+    let response = app
+        .get_api(&format!("/issues/{issue_id}/delivery"), Some(&token))
+        .await;
+    let delivery: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(delivery["recipients"], 1);
+    assert_eq!(delivery["pending"], 1);
+    assert_eq!(delivery["completed"], false);
+
+    // Act - Part 3 - Deliver
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await.unwrap();
+    let response = app
+        .get_api(&format!("/issues/{issue_id}"), Some(&token))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let issue: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(issue["title"], "Newsletter title");
+    assert_eq!(issue["delivery"]["pending"], 0);
+    assert_eq!(issue["delivery"]["completed"], true);
+    let response = app.get_api("/issues", Some(&token)).await;
+    let list: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(list["data"][0]["id"], issue_id);
+}
+
+#[tokio::test]
+async fn unknown_issues_are_not_found() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["newsletters:read"]).await;
+
+    // Act
+    let response = app
+        .get_api(&format!("/issues/{}", Uuid::new_v4()), Some(&token))
+        .await;
+
+    // Assert
+    assert_api_error(response, 404, "not_found").await;
+}
+
+#[tokio::test]
+async fn admins_can_list_collaborators() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["collaborators:read"]).await;
+
+    // Act
+    let response = app.get_api("/collaborators", Some(&token)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let usernames: Vec<_> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["username"].as_str().unwrap().to_owned())
+        .collect();
+    assert!(usernames.contains(&app.admin_user.username));
+    assert!(usernames.contains(&app.collabolator_user.username));
+}
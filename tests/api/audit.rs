@@ -0,0 +1,351 @@
+use chrono::Utc;
+use wiremock::ResponseTemplate;
+use zero2prod::audit::email_digest;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, when_sending_an_email, TestApp};
+
+const NO_FILTER: [(&str, &str); 0] = [];
+
+async fn audit_rows(app: &TestApp, query: &[(&str, &str)]) -> Vec<String> {
+    app.get_audit_log_html(&query)
+        .await
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("<tr><td>"))
+        .map(str::to_owned)
+        .collect()
+}
+
+#[tokio::test]
+async fn the_audit_log_needs_the_audit_permission() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let response = app.get_audit_log(&NO_FILTER).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(!app.get_admin_dashboard_html().await.contains("Audit log"));
+}
+
+#[tokio::test]
+async fn logins_password_changes_and_logouts_are_recorded() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+    let new_password = uuid::Uuid::new_v4().to_string();
+    app.post_change_password(&serde_json::json!({
+        "current_password": &app.collabolator_user.password,
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+    }))
+    .await;
+    app.post_logout().await;
+
+    // Act
+    app.login_with_admin_user().await;
+
+    // Assert
+    let rows = audit_rows(&app, &[("actor", &app.collabolator_user.username)]).await;
+    let actions: Vec<_> = rows
+        .iter()
+        .map(|row| row.split("</td><td>").nth(2).unwrap())
+        .collect();
+    assert_eq!(
+        actions,
+        ["user.logged_out", "user.password_changed", "user.logged_in"]
+    );
+    assert!(rows[0].ends_with("<td>-</td><td>127.0.0.1</td></tr>"));
+}
+
+#[tokio::test]
+async fn forwarded_headers_do_not_forge_the_recorded_address() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    app.api_client
+        .post(&format!("{}/login", &app.address))
+        .header("X-Forwarded-For", "203.0.113.7")
+        .form(&serde_json::json!({
+            "username": &app.admin_user.username,
+            "password": &app.admin_user.password,
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    let rows = audit_rows(&app, &[("action", "user.logged_in")]).await;
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].ends_with("<td>127.0.0.1</td></tr>"));
+}
+
+#[tokio::test]
+async fn published_issues_are_recorded_with_their_id() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let issue_id = sqlx::query_scalar!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let rows = audit_rows(&app, &[("action", "issue.published")]).await;
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].contains(&format!(
+        "<td>{}</td><td>issue.published</td><td>issue:{issue_id}</td>",
+        app.admin_user.username
+    )));
+}
+
+#[tokio::test]
+async fn invitations_and_activations_are_recorded() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let email = "new-collaborator@example.com";
+    app.insert_invitation(email, "kwwa2wjfgjk34673jdfg", Utc::now())
+        .await;
+
+    // Act
+    let response = app
+        .post_account_activate(&serde_json::json!({
+            "username": "newbie",
+            "password": "a-long-enough-password",
+            "password_check": "a-long-enough-password",
+            "token": "kwwa2wjfgjk34673jdfg",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let digest = email_digest(email);
+    let rows = audit_rows(&app, &[("target", &digest)]).await;
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].contains(&format!(
+        "<td>newbie</td><td>collaborator.activated</td><td>invitation:{digest}</td>"
+    )));
+    assert!(audit_rows(&app, &[("target", email)]).await.is_empty());
+}
+
+#[tokio::test]
+async fn administration_of_users_roles_and_settings_is_recorded() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let user_id = app.collabolator_user.user_id;
+
+    // Act
+    app.post_users("/deactivate", &serde_json::json!({ "user_id": user_id }))
+        .await;
+    app.post_roles("", &[("name", "editor"), ("permission", "issues:draft")])
+        .await;
+    app.post_users(
+        "/role",
+        &serde_json::json!({ "user_id": user_id, "role": "editor" }),
+    )
+    .await;
+    app.post_lockouts(&serde_json::json!({ "scope": "ip", "key": "10.0.0.1" }))
+        .await;
+    app.post_webhooks(&[("url", "https://example.com"), ("event", "issue.delivered")])
+        .await;
+    app.post_security_settings(
+        "/policy",
+        &serde_json::json!({ "require_two_factor": "on" }),
+    )
+    .await;
+    app.post_security_settings("/policy", &serde_json::json!({}))
+        .await;
+
+    // Assert
+    let admin = &app.admin_user.username;
+    let endpoint_id = sqlx::query_scalar!("SELECT endpoint_id FROM webhook_endpoints")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let expected = [
+        ("user.deactivated", format!("user:{user_id}")),
+        ("role.created", "role:editor".to_owned()),
+        ("user.role_changed", format!("user:{user_id} role:editor")),
+        ("security.two_factor_required", "-".to_owned()),
+        ("security.two_factor_optional", "-".to_owned()),
+        ("login.unlocked", "ip:10.0.0.1".to_owned()),
+        ("webhook.created", format!("webhook:{endpoint_id}")),
+    ];
+    for (action, target) in expected {
+        let rows = audit_rows(&app, &[("action", action)]).await;
+        assert_eq!(rows.len(), 1, "{action} was not recorded once");
+        assert!(
+            rows[0].contains(&format!(
+                "<td>{admin}</td><td>{action}</td><td>{target}</td>"
+            )),
+            "{}",
+            rows[0]
+        );
+    }
+}
+
+#[tokio::test]
+async fn events_keep_the_username_of_deleted_users() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+    app.post_logout().await;
+    app.login_with_admin_user().await;
+    let collabolator = &app.collabolator_user;
+
+    // Act
+    app.post_users(
+        "/delete",
+        &serde_json::json!({ "user_id": collabolator.user_id }),
+    )
+    .await;
+
+    // Assert
+    let rows = audit_rows(&app, &[("actor", &collabolator.username)]).await;
+    assert_eq!(rows.len(), 2);
+    assert!(rows[0].contains(&format!(
+        "<td>{}</td><td>user.logged_out</td>",
+        collabolator.username
+    )));
+    let rows = audit_rows(&app, &[("action", "user.deleted")]).await;
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].contains(&format!(
+        "<td>user:{} username:{}</td>",
+        collabolator.user_id, collabolator.username
+    )));
+}
+
+#[tokio::test]
+async fn refused_administration_is_not_recorded() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+
+    // Act
+    app.post_roles("/delete", &[("role", "collabolator")]).await;
+    app.post_users(
+        "/delete",
+        &serde_json::json!({ "user_id": uuid::Uuid::new_v4() }),
+    )
+    .await;
+
+    // Assert
+    assert!(audit_rows(&app, &[("action", "role.deleted")])
+        .await
+        .is_empty());
+    assert!(audit_rows(&app, &[("action", "user.deleted")])
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn invitation_events_do_not_keep_the_invited_address() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let email = "new-collaborator@example.com";
+    let form = serde_json::json!({ "email": email });
+
+    // Act
+    app.post_invite(&form).await;
+    app.post_invitation("/resend", &form).await;
+    app.post_invitation("/revoke", &form).await;
+
+    // Assert
+    let rows = audit_rows(&app, &[("target", &email_digest(email))]).await;
+    let actions: Vec<_> = rows
+        .iter()
+        .filter_map(|row| row.split("</td><td>").nth(2))
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            "collaborator.invitation_revoked",
+            "collaborator.invitation_resent",
+            "collaborator.invited"
+        ]
+    );
+    assert!(rows.iter().all(|row| !row.contains("new-collaborator")));
+}
+
+#[tokio::test]
+async fn erasures_do_not_keep_the_erased_address() {
+    // Arrange
+    let app = spawn_app().await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    app.login_with_admin_user().await;
+
+    // Act
+    app.post_subscriber_erase("ursula_le_guin@gmail.com").await;
+
+    // Assert
+    let rows = audit_rows(&app, &[("action", "subscriber.erased")]).await;
+    assert_eq!(rows.len(), 1);
+    assert!(!rows[0].contains("ursula"));
+}
+
+#[tokio::test]
+async fn invalid_filters_are_reported() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let test_cases = [
+        (
+            ("since", "yesterday"),
+            "yesterday is not a valid date (YYYY-MM-DD).",
+        ),
+        (
+            ("action", "user.hacked"),
+            "user.hacked is not a known action.",
+        ),
+    ];
+
+    for (filter, message) in test_cases {
+        // Act
+        let html = app.get_audit_log_html(&[filter]).await;
+
+        // Assert
+        assert!(
+            html.contains(&format!("<p><i>{message}</i></p>")),
+            "{filter:?} was not refused with '{message}'"
+        );
+    }
+}
+
+#[tokio::test]
+async fn events_can_be_filtered_by_day() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let today = Utc::now().date_naive().to_string();
+    let yesterday = (Utc::now().date_naive() - chrono::Days::new(1)).to_string();
+
+    // Act
+    let until_today = audit_rows(&app, &[("since", &today), ("until", &today)]).await;
+    let until_yesterday = audit_rows(&app, &[("until", &yesterday)]).await;
+
+    // Assert
+    assert_eq!(until_today.len(), 1);
+    assert!(until_yesterday.is_empty());
+}
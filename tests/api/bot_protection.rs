@@ -0,0 +1,158 @@
+use chrono::{Duration, Utc};
+use wiremock::ResponseTemplate;
+use zero2prod::bot_protection::{generate_form_token, verify_form_token};
+
+use crate::helpers::{spawn_app, when_sending_an_email, TestApp, CAPTCHA_RESPONSE};
+
+const VALID_BODY: &str = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+#[tokio::test]
+async fn the_home_page_renders_a_valid_form_token() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let html = app
+        .api_client
+        .get(&app.address)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    let token = html
+        .split(r#"name="form_token" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap();
+    assert!(verify_form_token(
+        &app.hmac_secret,
+        token,
+        Utc::now(),
+        Duration::zero(),
+        Duration::hours(1)
+    )
+    .is_ok());
+    assert!(html.contains(r#"name="website""#));
+    assert!(html.contains(r#"name="captcha_response""#));
+}
+
+#[tokio::test]
+async fn submissions_with_a_filled_in_honeypot_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    no_email_is_sent(&app).await;
+
+    // Act
+    let response = app
+        .post_subscriptions(format!("{}&website=spam.example.com", VALID_BODY))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_nothing_is_stored(&app).await;
+}
+
+#[tokio::test]
+async fn submissions_without_a_valid_form_token_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    no_email_is_sent(&app).await;
+    let issued_at = Utc::now() - Duration::seconds(5);
+    let test_cases = vec![
+        ("".to_string(), "missing token"),
+        ("not-a-token".to_string(), "malformed token"),
+        (
+            generate_form_token(&"another-secret".to_string().into(), issued_at),
+            "token signed with another secret",
+        ),
+        (
+            generate_form_token(&app.hmac_secret, Utc::now()),
+            "form submitted too quickly",
+        ),
+        (
+            generate_form_token(&app.hmac_secret, Utc::now() - Duration::hours(2)),
+            "expired token",
+        ),
+    ];
+
+    for (token, description) in test_cases {
+        // Act
+        let response = app
+            .post_raw_subscriptions(format!(
+                "{}&form_token={}&captcha_response={}",
+                VALID_BODY, token, CAPTCHA_RESPONSE
+            ))
+            .await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not reject a submission with a {}.",
+            description
+        );
+    }
+    assert_nothing_is_stored(&app).await;
+}
+
+#[tokio::test]
+async fn submissions_with_an_unsolved_captcha_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    no_email_is_sent(&app).await;
+    let token = generate_form_token(&app.hmac_secret, Utc::now() - Duration::seconds(5));
+
+    for captcha in ["", "&captcha_response=i-am-a-robot"] {
+        // Act
+        let response = app
+            .post_raw_subscriptions(format!("{}&form_token={}{}", VALID_BODY, token, captcha))
+            .await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400);
+    }
+    assert_nothing_is_stored(&app).await;
+}
+
+#[tokio::test]
+async fn the_hcaptcha_response_field_is_accepted() {
+    // Arrange
+    let app = spawn_app().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let token = generate_form_token(&app.hmac_secret, Utc::now() - Duration::seconds(5));
+
+    // Act
+    let response = app
+        .post_raw_subscriptions(format!(
+            "{}&form_token={}&h-captcha-response={}",
+            VALID_BODY, token, CAPTCHA_RESPONSE
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn no_email_is_sent(app: &TestApp) {
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn assert_nothing_is_stored(app: &TestApp) {
+    let saved = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(saved.is_empty());
+}
@@ -0,0 +1,175 @@
+use reqwest::Url;
+use wiremock::ResponseTemplate;
+
+use crate::helpers::{
+    assert_is_redirect_to, log_in_elsewhere, spawn_app, when_sending_an_email, TestApp,
+};
+
+async fn admin_email(app: &TestApp) -> Option<String> {
+    sqlx::query_scalar!(
+        "SELECT email FROM users WHERE username = $1",
+        app.admin_user.username
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+/// Ask for the admin's address to change to `email` and return the link from
+/// the verification email.
+async fn request_change(app: &TestApp, email: &str) -> Url {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "email": email,
+            "current_password": &app.admin_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/email");
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], email);
+    let links = app.get_links(&email_request, "/admin/email/confirm");
+    assert_eq!(links.html, links.plain_text);
+    links.html
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_change_your_email() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_change_email().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_new_email_is_only_stored_once_verified() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    assert!(app
+        .get_change_email_html()
+        .await
+        .contains("<p>You have no email address yet.</p>"));
+
+    // Act - Part 1 - Ask for the change
+    let link = request_change(&app, "admin@example.com").await;
+
+    // Assert
+    assert_eq!(admin_email(&app).await, None);
+    let html = app.get_change_email_html().await;
+    assert!(html.contains("A verification link was sent to admin@example.com"));
+
+    // Act - Part 2 - Follow the link
+    let response = app.api_client.get(link).send().await.unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/email");
+    assert_eq!(
+        admin_email(&app).await.as_deref(),
+        Some("admin@example.com")
+    );
+    let html = app.get_change_email_html().await;
+    assert!(html.contains("<p><i>Your email address is now admin@example.com.</i></p>"));
+    assert!(html.contains("<p>Your email address is admin@example.com.</p>"));
+    let logged = sqlx::query_scalar!(
+        "SELECT COUNT(*) AS \"n!\" FROM audit_events WHERE action = 'user.email_changed'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(logged, 1);
+}
+
+#[tokio::test]
+async fn changing_the_email_requires_a_valid_address_and_the_current_password() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let test_cases = [
+        (
+            "not-an-email",
+            app.admin_user.password.as_str(),
+            "Email is invalid.",
+        ),
+        (
+            "admin@example.com",
+            "wrong-password",
+            "The current password is incorrect.",
+        ),
+    ];
+
+    for (email, password, message) in test_cases {
+        // Act
+        let response = app
+            .post_change_email(&serde_json::json!({
+                "email": email,
+                "current_password": password,
+            }))
+            .await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/email");
+        let html = app.get_change_email_html().await;
+        assert!(
+            html.contains(&format!("<p><i>{message}</i></p>")),
+            "{email} was not refused with '{message}'"
+        );
+    }
+}
+
+#[tokio::test]
+async fn verification_links_only_work_for_their_user_until_they_expire() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let link = request_change(&app, "admin@example.com").await;
+    let collaborator = log_in_elsewhere(&app, &app.collabolator_user).await;
+
+    // Act - Part 1 - Someone else follows the link
+    let response = collaborator.get(link.clone()).send().await.unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/email");
+    let html = collaborator
+        .get(&format!("{}/admin/email", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("The verification link is invalid or has expired."));
+
+    // Act - Part 2 - The link has expired
+    sqlx::query!("UPDATE email_verification_tokens SET created_at = now() - interval '25 hours'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.api_client.get(link).send().await.unwrap();
+
+    // Assert
+    let html = app.get_change_email_html().await;
+    assert!(html.contains("The verification link is invalid or has expired."));
+    assert_eq!(admin_email(&app).await, None);
+}
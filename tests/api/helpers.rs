@@ -4,17 +4,33 @@ use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
 use reqwest::Url;
+use secrecy::Secret;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockBuilder, MockServer};
-use zero2prod::authentication::UserRole;
-use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use zero2prod::account_email_worker::{
+    try_execute_task as try_send_account_email, ExecutionOutcome as AccountEmailOutcome,
+};
+use zero2prod::authentication::{ADMIN_ROLE, COLLABORATOR_ROLE};
+use zero2prod::bot_protection::generate_form_token;
+use zero2prod::configuration::{
+    get_configuration, CaptchaSettings, DatabaseSettings, Settings, SubscriptionSettings,
+};
 use zero2prod::email_client::EmailClient;
 use zero2prod::idempotency;
+use zero2prod::invitation_cleanup_worker::try_delete_expired_invitations;
 use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
 use zero2prod::startup::{get_connection_pool, Application};
+use zero2prod::subscription_cleanup_worker::try_delete_stale_subscriptions;
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
+use zero2prod::webhook_dispatcher::{
+    try_execute_delivery, webhook_client, ExecutionOutcome as WebhookOutcome,
+};
+use zero2prod::webhooks::signing_secret;
+
+/// The answer accepted by the local stand-in CAPTCHA.
+pub const CAPTCHA_RESPONSE: &str = "not-a-robot";
 
 // Ensure that the `tracing` stack is only initialised once using `once_cell`
 static TRACING: Lazy<()> = Lazy::new(|| {
@@ -33,16 +49,16 @@ pub struct TestUser {
     pub user_id: Uuid,
     pub username: String,
     pub password: String,
-    pub role: UserRole,
+    pub role: String,
 }
 
 impl TestUser {
-    pub fn generate(role: UserRole) -> Self {
+    pub fn generate(role: &str) -> Self {
         Self {
             user_id: Uuid::new_v4(),
             username: Uuid::new_v4().to_string(),
             password: Uuid::new_v4().to_string(),
-            role,
+            role: role.to_owned(),
         }
     }
 
@@ -62,7 +78,7 @@ impl TestUser {
             self.user_id,
             self.username,
             password_hash,
-            self.role.to_string()
+            self.role
         )
         .execute(pool)
         .await
@@ -70,8 +86,8 @@ impl TestUser {
     }
 }
 
-/// Confirmation links embedded in the request to the email API.
-pub struct ConfirmationLinks {
+/// Links embedded in the request to the email API.
+pub struct EmailLinks {
     pub html: reqwest::Url,
     pub plain_text: reqwest::Url,
 }
@@ -85,16 +101,32 @@ pub struct Task {
 pub struct TestApp {
     pub address: String,
     pub port: u16,
+    pub base_url: String,
     pub db_pool: PgPool,
     pub email_server: MockServer,
     pub admin_user: TestUser,
     pub collabolator_user: TestUser,
     pub api_client: reqwest::Client,
     pub email_client: EmailClient,
+    pub subscription_settings: SubscriptionSettings,
+    pub hmac_secret: Secret<String>,
 }
 
 impl TestApp {
+    /// Submit the subscribe form the way a human would: with a valid form token
+    /// and a solved CAPTCHA.
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
+        let issued_at = Utc::now() - chrono::Duration::seconds(5);
+        let body = format!(
+            "{}&form_token={}&captcha_response={}",
+            body,
+            generate_form_token(&self.hmac_secret, issued_at),
+            CAPTCHA_RESPONSE
+        );
+        self.post_raw_subscriptions(body).await
+    }
+
+    pub async fn post_raw_subscriptions(&self, body: String) -> reqwest::Response {
         self.api_client
             .post(&format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
@@ -105,7 +137,17 @@ impl TestApp {
     }
 
     /// Extract the confirmation links embedded in the request to the email API.
-    pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
+    pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> EmailLinks {
+        self.get_links(email_request, "/subscriptions/confirm")
+    }
+
+    /// Extract the subscription preferences links embedded in the request to the email API.
+    pub fn get_preferences_links(&self, email_request: &wiremock::Request) -> EmailLinks {
+        self.get_links(email_request, "/preferences")
+    }
+
+    /// Extract the links pointing to `path` embedded in the request to the email API.
+    pub fn get_links(&self, email_request: &wiremock::Request, path: &str) -> EmailLinks {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
 
         // Extract the link from one of the request fields.
@@ -113,22 +155,23 @@ impl TestApp {
             let links: Vec<_> = linkify::LinkFinder::new()
                 .links(s)
                 .filter(|l| *l.kind() == linkify::LinkKind::Url)
+                .filter_map(|l| reqwest::Url::parse(l.as_str()).ok())
+                .filter(|l| l.path() == path)
                 .collect();
 
             assert_eq!(links.len(), 1);
 
-            let raw_link = links[0].as_str().to_owned();
-            let mut confirmation_link = reqwest::Url::parse(&raw_link).unwrap();
+            let mut link = links[0].clone();
 
             // Let's make sure we don't call random APIs on the web
-            assert_eq!(confirmation_link.host_str().unwrap(), "127.0.0.1");
-            confirmation_link.set_port(Some(self.port)).unwrap();
-            confirmation_link
+            assert_eq!(link.host_str().unwrap(), "127.0.0.1");
+            link.set_port(Some(self.port)).unwrap();
+            link
         };
 
         let html = get_link(body["HtmlBody"].as_str().unwrap());
         let plain_text = get_link(body["TextBody"].as_str().unwrap());
-        ConfirmationLinks { html, plain_text }
+        EmailLinks { html, plain_text }
     }
 
     pub async fn get_newsletter_form(&self) -> reqwest::Response {
@@ -139,6 +182,18 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_newsletter_form_with_query<Query: serde::Serialize>(
+        &self,
+        query: &Query,
+    ) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/newsletters", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_publish_newsletter_form_html(&self) -> String {
         self.get_newsletter_form().await.text().await.unwrap()
     }
@@ -213,6 +268,30 @@ impl TestApp {
         self.get_change_password().await.text().await.unwrap()
     }
 
+    pub async fn get_change_email(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/email", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!("{}/admin/email", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_change_email_html(&self) -> String {
+        self.get_change_email().await.text().await.unwrap()
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
         self.api_client
             .post(&format!("{}/admin/logout", &self.address))
@@ -221,6 +300,82 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_preferences<Query: serde::Serialize>(
+        &self,
+        query: &Query,
+    ) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/preferences", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_preferences_html(&self, token: &str) -> String {
+        self.get_preferences(&[("token", token)])
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_preferences<Body: serde::Serialize>(&self, body: &Body) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/preferences", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_preferences_export(&self, token: &str) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/preferences/export", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_preferences_erase(&self, token: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/preferences/erase", &self.address))
+            .form(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_data_html(&self) -> String {
+        self.api_client
+            .get(&format!("{}/admin/subscribers", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn get_subscriber_export(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/subscribers/export", &self.address))
+            .query(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriber_erase(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/subscribers/erase", &self.address))
+            .form(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn login_with_admin_user(&self) {
         self.post_login(&serde_json::json!({
             "username": &self.admin_user.username,
@@ -239,7 +394,7 @@ impl TestApp {
 
     pub async fn dispatch_all_pending_emails(&self) -> Result<(), anyhow::Error> {
         loop {
-            match try_execute_task(&self.db_pool, &self.email_client).await {
+            match try_execute_task(&self.db_pool, &self.email_client, &self.base_url).await {
                 Ok(status) => {
                     if let ExecutionOutcome::EmptyQueue = status {
                         break;
@@ -251,6 +406,28 @@ impl TestApp {
         Ok(())
     }
 
+    pub async fn send_all_account_emails(&self) -> Result<(), anyhow::Error> {
+        loop {
+            match try_send_account_email(&self.db_pool, &self.email_client, &self.base_url).await? {
+                AccountEmailOutcome::EmptyQueue => break,
+                AccountEmailOutcome::TaskCompleted => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn dispatch_all_pending_webhooks(&self) -> Result<(), anyhow::Error> {
+        let http_client = webhook_client();
+        let signing_secret = signing_secret(&self.hmac_secret);
+        loop {
+            match try_execute_delivery(&self.db_pool, &http_client, &signing_secret).await? {
+                WebhookOutcome::EmptyQueue => break,
+                WebhookOutcome::TaskCompleted => {}
+            }
+        }
+        Ok(())
+    }
+
     pub async fn count_idempotency_keys(&self) -> i64 {
         sqlx::query_scalar("SELECT COUNT(*) FROM idempotency")
             .fetch_one(&self.db_pool)
@@ -264,6 +441,32 @@ impl TestApp {
             .unwrap()
     }
 
+    pub async fn remove_stale_subscriptions(&self) {
+        try_delete_stale_subscriptions(&self.db_pool, &self.subscription_settings)
+            .await
+            .unwrap()
+    }
+
+    pub async fn remove_expired_invitations(&self) {
+        try_delete_expired_invitations(&self.db_pool).await.unwrap()
+    }
+
+    /// An invitation as if it had been sent at `created_at`.
+    pub async fn insert_invitation(&self, email: &str, token: &str, created_at: DateTime<Utc>) {
+        sqlx::query!(
+            r#"
+            INSERT INTO collabolator_activation_tokens (email, token, created_at)
+            VALUES ($1, $2, $3)
+            "#,
+            email,
+            token,
+            created_at
+        )
+        .execute(&self.db_pool)
+        .await
+        .unwrap();
+    }
+
     pub async fn fetch_task(&self) -> Task {
         sqlx::query_as!(
             Task,
@@ -276,6 +479,396 @@ impl TestApp {
         .unwrap()
     }
 
+    pub async fn get_mailing_lists(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/lists", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_mailing_lists_html(&self) -> String {
+        self.get_mailing_lists()
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn post_mailing_lists<Body: serde::Serialize>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/lists", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_tags_html(&self) -> String {
+        self.api_client
+            .get(&format!("{}/admin/tags", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn post_subscriber_tags<Body: serde::Serialize>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/tags", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_security_settings(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/security", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_security_settings_html(&self) -> String {
+        self.get_security_settings()
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    /// POST to one of the forms under `/admin/security`.
+    pub async fn post_security_settings<Body: serde::Serialize>(
+        &self,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/security{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_two_factor_login_html(&self) -> String {
+        self.api_client
+            .get(&format!("{}/login/two-factor", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_two_factor_login(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/login/two-factor", &self.address))
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password_reset(&self, username: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/password-reset", &self.address))
+            .form(&[("username", username)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_password_reset_html(&self) -> String {
+        self.api_client
+            .get(&format!("{}/password-reset", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_password_reset_confirm<Body: serde::Serialize>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/password-reset/confirm", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_lockouts(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/lockouts", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_lockouts_html(&self) -> String {
+        self.get_lockouts()
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn post_lockouts<Body: serde::Serialize>(&self, body: &Body) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/lockouts", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_webhooks(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/webhooks", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_webhooks_html(&self) -> String {
+        self.get_webhooks()
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn post_webhooks<Body: serde::Serialize>(&self, body: &Body) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/webhooks", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_tokens(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/api-tokens", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_tokens_html(&self) -> String {
+        self.get_api_tokens()
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn post_api_tokens<Body: serde::Serialize>(
+        &self,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/api-tokens{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Create a token with `scopes` for the logged in user and return it.
+    pub async fn create_api_token(&self, scopes: &[&str]) -> String {
+        let mut form = vec![("name", "ci"), ("expires_in_days", "30")];
+        form.extend(scopes.iter().map(|scope| ("scope", *scope)));
+        let response = self.post_api_tokens("", &form).await;
+        assert_is_redirect_to(&response, "/admin/api-tokens");
+        let html = self.get_api_tokens_html().await;
+        html.split("it will not be shown again: ")
+            .nth(1)
+            .and_then(|s| s.split("</i>").next())
+            .unwrap()
+            .to_owned()
+    }
+
+    /// Call the JSON API, authenticated with `token` if there is one.
+    pub async fn get_api(&self, path: &str, token: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .api_client
+            .get(format!("{}/api/v1{}", &self.address, path));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    /// POST `body` as JSON to the API, with an `Idempotency-Key` header if
+    /// there is one.
+    pub async fn post_api<Body: serde::Serialize>(
+        &self,
+        path: &str,
+        token: &str,
+        idempotency_key: Option<&str>,
+        body: &Body,
+    ) -> reqwest::Response {
+        let mut request = self
+            .api_client
+            .post(format!("{}/api/v1{}", &self.address, path))
+            .bearer_auth(token)
+            .json(body);
+        if let Some(key) = idempotency_key {
+            request = request.header("Idempotency-Key", key);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions_html(&self) -> String {
+        self.get_sessions()
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn post_sessions<Body: serde::Serialize>(
+        &self,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/sessions{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_users(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/users", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_users_html(&self) -> String {
+        self.get_users()
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn post_users<Body: serde::Serialize>(
+        &self,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/users{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_roles(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/roles", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_roles_html(&self) -> String {
+        self.get_roles()
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn post_roles<Body: serde::Serialize>(
+        &self,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/roles{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_reviews(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/reviews", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_reviews_html(&self) -> String {
+        self.get_reviews()
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
+    pub async fn post_reviews<Body: serde::Serialize>(
+        &self,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/reviews{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_audit_log<Query: serde::Serialize>(&self, query: &Query) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/audit", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_audit_log_html<Query: serde::Serialize>(&self, query: &Query) -> String {
+        self.get_audit_log(query)
+            .await
+            .text()
+            .await
+            .expect("Cannot fetch HTML content")
+    }
+
     pub async fn get_invite_form(&self) -> reqwest::Response {
         self.api_client
             .get(&format!("{}/admin/collabolators", &self.address))
@@ -301,6 +894,19 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_invitation<Body: serde::Serialize>(
+        &self,
+        path: &str,
+        body: &Body,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/collabolators{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_account_activate_form<Query: serde::Serialize>(
         &self,
         query: &Query,
@@ -334,8 +940,13 @@ impl TestApp {
     }
 }
 
-#[allow(clippy::let_underscore_future)]
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawn the application after `configure` has adjusted its configuration.
+#[allow(clippy::let_underscore_future)]
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     let email_server = MockServer::start().await;
@@ -349,6 +960,13 @@ pub async fn spawn_app() -> TestApp {
         c.application.port = 0;
         // Use the mock server as email API
         c.email_client.base_url = Url::parse(&email_server.uri()).unwrap().into();
+        // Use the local stand-in CAPTCHA
+        c.subscriptions.captcha = CaptchaSettings::Local {
+            expected_response: Secret::new(CAPTCHA_RESPONSE.into()),
+        };
+        // Do not share rate limits with other test cases
+        c.rate_limits.key_prefix = Uuid::new_v4().to_string();
+        configure(&mut c);
         c
     };
 
@@ -372,12 +990,15 @@ pub async fn spawn_app() -> TestApp {
     let test_app = TestApp {
         address,
         port,
+        base_url: configuration.application.base_url.clone(),
         db_pool: get_connection_pool(&configuration.database),
         email_server,
-        admin_user: TestUser::generate(UserRole::Admin),
-        collabolator_user: TestUser::generate(UserRole::Collabolator),
+        admin_user: TestUser::generate(ADMIN_ROLE),
+        collabolator_user: TestUser::generate(COLLABORATOR_ROLE),
         api_client: client,
         email_client: configuration.email_client.client(),
+        subscription_settings: configuration.subscriptions,
+        hmac_secret: configuration.application.hmac_secret,
     };
     test_app.admin_user.store(&test_app.db_pool).await;
     test_app.collabolator_user.store(&test_app.db_pool).await;
@@ -412,6 +1033,37 @@ pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
 }
 
 // Short-hand for a common mocking setup
+pub const OTHER_USER_AGENT: &str = "Other browser/1.0";
+
+/// Log `user` in with a client of its own, like from another device.
+pub async fn log_in_elsewhere(app: &TestApp, user: &TestUser) -> reqwest::Client {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .user_agent(OTHER_USER_AGENT)
+        .build()
+        .unwrap();
+    let response = client
+        .post(&format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &user.username,
+            "password": &user.password
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    client
+}
+
+pub async fn get_dashboard(app: &TestApp, client: &reqwest::Client) -> reqwest::Response {
+    client
+        .get(&format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap()
+}
+
 pub fn when_sending_an_email() -> MockBuilder {
     Mock::given(path("/email")).and(method("POST"))
 }
@@ -0,0 +1,39 @@
+use chrono::{Duration, Utc};
+use zero2prod::invitations::invitation_ttl;
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn expired_invitations_are_removed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_invitation("fresh@example.com", "fresh-token", Utc::now())
+        .await;
+    app.insert_invitation(
+        "expired@example.com",
+        "expired-token",
+        Utc::now() - invitation_ttl() - Duration::minutes(1),
+    )
+    .await;
+    sqlx::query!(
+        r#"
+        INSERT INTO collabolator_activation_tokens (email, token, created_at, valid_days)
+        VALUES ('long@example.com', 'long-token', $1, 7)
+        "#,
+        Utc::now() - invitation_ttl() - Duration::minutes(1)
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    app.remove_expired_invitations().await;
+
+    // Assert
+    let emails =
+        sqlx::query_scalar!("SELECT email FROM collabolator_activation_tokens ORDER BY email")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(emails, vec!["fresh@example.com", "long@example.com"]);
+}
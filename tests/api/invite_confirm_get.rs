@@ -29,3 +29,27 @@ async fn should_fetch_html_when_token_is_set_in_query() {
         r#"<input type="hidden" name="token" value="{token}">"#
     )))
 }
+
+#[tokio::test]
+async fn the_form_is_prefilled_from_the_invitation() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = "1239754769274fdgfknbser";
+    sqlx::query!(
+        r#"
+        INSERT INTO collabolator_activation_tokens (email, token, created_at, role)
+        VALUES ('janedoe@example.com', $1, now(), 'admin')
+        "#,
+        token
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let html = app.get_account_activate_form_html(token).await;
+
+    // Assert
+    assert!(html.contains(r#"name="username" value="janedoe""#));
+    assert!(html.contains("You are invited as admin."));
+}
@@ -1,7 +1,7 @@
 use chrono::{Duration, Utc};
 use reqwest::Response;
 use sqlx::PgPool;
-use zero2prod::authentication::{User, UserRole};
+use zero2prod::authentication::{User, COLLABORATOR_ROLE};
 
 use crate::helpers::{assert_is_redirect_to, spawn_app};
 
@@ -137,7 +137,7 @@ async fn should_activate_new_account() {
     assert!(user.is_some());
     let user = user.unwrap();
     assert_eq!(username, user.username);
-    assert!(matches!(user.role, UserRole::Collabolator));
+    assert_eq!(user.role, COLLABORATOR_ROLE);
 
     // Act 2 - follow redirect
     let html = app.get_login_html().await;
@@ -157,6 +157,69 @@ async fn should_activate_new_account() {
     assert_is_redirect_to(&response, "/admin/dashboard");
 }
 
+#[tokio::test]
+async fn activated_accounts_get_the_invited_role_and_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let pass = generate_pass_of_size(20);
+    let username = "user123456";
+    // Older than the default expiry, but invited for a week.
+    sqlx::query!(
+        r#"
+        INSERT INTO collabolator_activation_tokens (email, token, created_at, role, valid_days)
+        VALUES ('test_email@a.com', $1, $2, 'admin', 7)
+        "#,
+        DEFAULT_TOKEN,
+        Utc::now() - Duration::days(4)
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let form = generate_form_data(username, &pass, &pass);
+    let response = app.post_account_activate(&form).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let user = sqlx::query!(
+        "SELECT role, email FROM users WHERE username = $1",
+        username
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(user.role, "admin");
+    assert_eq!(user.email.as_deref(), Some("test_email@a.com"));
+}
+
+#[tokio::test]
+async fn the_password_must_not_contain_the_username() {
+    // Arrange
+    let app = spawn_app().await;
+    let username = "mallory";
+    sqlx::query!(
+        r#"
+        INSERT INTO collabolator_activation_tokens (email, token, created_at)
+        VALUES ('mallory@a.com', $1, now())
+        "#,
+        DEFAULT_TOKEN,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let form = generate_form_data(username, "Mallory#Q7z!X2w", "Mallory#Q7z!X2w");
+    let response = app.post_account_activate(&form).await;
+
+    // Assert
+    assert_is_redirect_to_form(&response);
+    let html = app.get_account_activate_form_html(DEFAULT_TOKEN).await;
+    assert!(html.contains("The new password must not contain your username."));
+    assert!(get_user_from_db(username, &app.db_pool).await.is_none());
+}
+
 fn generate_form_data(
     username: &str,
     password: &str,
@@ -171,11 +234,7 @@ fn generate_form_data(
 }
 
 fn generate_pass_of_size(size: usize) -> String {
-    let mut s = String::new();
-    for _ in 0..size {
-        s.push('0');
-    }
-    s
+    "Tq7#mZ2!xW9@kR4$vL6%".chars().cycle().take(size).collect()
 }
 
 fn assert_is_redirect_to_form(response: &Response) {
@@ -187,8 +246,11 @@ async fn get_user_from_db(username: &str, pool: &PgPool) -> Option<User> {
     sqlx::query_as!(
         User,
         r#"
-        SELECT user_id, username, password_hash, role as "role: UserRole"
-        FROM users
+        SELECT user_id, username, password_hash, email, role,
+            ARRAY(
+                SELECT permission FROM role_permissions WHERE role = u.role
+            ) AS "permissions!"
+        FROM users u
         WHERE username = $1
         "#,
         username,
@@ -1,3 +1,4 @@
+use chrono::{Duration, Utc};
 use reqwest::StatusCode;
 
 use crate::helpers::spawn_app;
@@ -29,3 +30,32 @@ async fn should_fetch_html_form_for_authorized_user() {
     let html = response.text().await.unwrap();
     assert!(html.contains(r#"<form action="/admin/collabolators" method="post"#))
 }
+
+#[tokio::test]
+async fn pending_invitations_are_listed_with_their_expiry() {
+    // Arrange
+    let app = spawn_app().await;
+    app.insert_invitation(
+        "fresh@example.com",
+        "fresh-token",
+        Utc::now() - Duration::hours(1),
+    )
+    .await;
+    app.insert_invitation(
+        "old@example.com",
+        "old-token",
+        Utc::now() - Duration::days(4),
+    )
+    .await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let html = app.get_invite_form_html().await;
+
+    // Assert
+    assert!(
+        html.contains("fresh@example.com as collabolator - sent 0 day(s) ago, expires in 2 day(s)")
+    );
+    assert!(html.contains("old@example.com as collabolator - sent 4 day(s) ago, expired"));
+    assert!(html.contains(r#"<form action="/admin/collabolators/revoke" method="post">"#));
+}
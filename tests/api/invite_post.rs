@@ -1,6 +1,7 @@
 use crate::helpers::assert_is_redirect_to;
 use crate::helpers::spawn_app;
 use crate::helpers::when_sending_an_email;
+use chrono::{Duration, Utc};
 use reqwest::StatusCode;
 use sqlx::PgPool;
 use wiremock::ResponseTemplate;
@@ -188,3 +189,204 @@ async fn token_was_stored_in_db(pool: &PgPool, email: &str) -> bool {
     .unwrap()
     .is_some()
 }
+
+#[tokio::test]
+async fn resending_an_invitation_rotates_the_activation_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "test_email@abc.com";
+    app.insert_invitation(email, "old-token", Utc::now() - Duration::days(2))
+        .await;
+    app.login_with_admin_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_invitation("/resend", &serde_json::json!({ "email": email }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/collabolators");
+    let html = app.get_invite_form_html().await;
+    assert!(html.contains("A new activation link was sent to test_email@abc.com."));
+    let invitation = sqlx::query!(
+        "SELECT token, created_at FROM collabolator_activation_tokens WHERE email = $1",
+        email
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_ne!(invitation.token, "old-token");
+    assert!(Utc::now() - invitation.created_at < Duration::minutes(1));
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body = std::str::from_utf8(&email_request.body).unwrap();
+    assert!(body.contains(&invitation.token));
+}
+
+#[tokio::test]
+async fn only_pending_invitations_can_be_resent() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_invitation(
+            "/resend",
+            &serde_json::json!({ "email": "test_email@abc.com" }),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/collabolators");
+    let html = app.get_invite_form_html().await;
+    assert!(html.contains("There is no pending invitation for test_email@abc.com."));
+    assert!(!token_was_stored_in_db(&app.db_pool, "test_email@abc.com").await);
+}
+
+#[tokio::test]
+async fn revoked_invitations_can_no_longer_be_accepted() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "test_email@abc.com";
+    app.insert_invitation(email, "some-token", Utc::now()).await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let response = app
+        .post_invitation("/revoke", &serde_json::json!({ "email": email }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/collabolators");
+    let html = app.get_invite_form_html().await;
+    assert!(html.contains("The invitation of test_email@abc.com was revoked."));
+    assert!(html.contains("No pending invitations."));
+    assert!(!token_was_stored_in_db(&app.db_pool, email).await);
+}
+
+#[tokio::test]
+async fn collaborators_cannot_manage_invitations() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "test_email@abc.com";
+    app.insert_invitation(email, "some-token", Utc::now()).await;
+    app.login_with_collabolator_user().await;
+
+    for path in ["/resend", "/revoke"] {
+        // Act
+        let response = app
+            .post_invitation(path, &serde_json::json!({ "email": email }))
+            .await;
+
+        // Assert
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+    }
+    assert!(token_was_stored_in_db(&app.db_pool, email).await);
+}
+
+#[tokio::test]
+async fn invitations_store_the_chosen_role_and_expiry() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let email = "test_email@abc.com";
+
+    // Act
+    let response = app
+        .post_invite(&serde_json::json!({
+            "email": email,
+            "role": "admin",
+            "expires_in_days": "7",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/collabolators");
+    let invitation = sqlx::query!(
+        "SELECT role, valid_days FROM collabolator_activation_tokens WHERE email = $1",
+        email
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(invitation.role, "admin");
+    assert_eq!(invitation.valid_days, 7);
+    let html = app.get_invite_form_html().await;
+    assert!(html.contains("test_email@abc.com as admin - sent 0 day(s) ago, expires in 6 day(s)"));
+
+    // Act - Resend
+    app.post_invitation("/resend", &serde_json::json!({ "email": email }))
+        .await;
+
+    // Assert
+    let invitation = sqlx::query!(
+        "SELECT role, valid_days FROM collabolator_activation_tokens WHERE email = $1",
+        email
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(invitation.role, "admin");
+    assert_eq!(invitation.valid_days, 7);
+}
+
+#[tokio::test]
+async fn invitations_need_a_known_role_and_a_valid_expiry() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let email = "test_email@abc.com";
+    let test_cases = [
+        ("ghost", "3", "There is no such role."),
+        ("admin", "0", "Invitations must expire within 1 to 30 days."),
+        (
+            "admin",
+            "31",
+            "Invitations must expire within 1 to 30 days.",
+        ),
+        (
+            "admin",
+            "soon",
+            "Invitations must expire within 1 to 30 days.",
+        ),
+    ];
+
+    for (role, expires_in_days, message) in test_cases {
+        // Act
+        let response = app
+            .post_invite(&serde_json::json!({
+                "email": email,
+                "role": role,
+                "expires_in_days": expires_in_days,
+            }))
+            .await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/collabolators");
+        let html = app.get_invite_form_html().await;
+        assert!(
+            html.contains(&format!("<p><i>{message}</i></p>")),
+            "{role} for {expires_in_days} days was not refused with '{message}'"
+        );
+        assert!(!token_was_stored_in_db(&app.db_pool, email).await);
+    }
+}
@@ -0,0 +1,240 @@
+use wiremock::ResponseTemplate;
+use zero2prod::configuration::{LoginProtectionSettings, Settings};
+
+use crate::helpers::{
+    assert_is_redirect_to, spawn_app_with, when_sending_an_email, TestApp, TestUser,
+};
+
+fn policy(free_attempts: u32, base_delay_seconds: u32, threshold: u32) -> LoginProtectionSettings {
+    LoginProtectionSettings {
+        free_attempts,
+        base_delay_seconds,
+        max_delay_seconds: 600,
+        lockout_threshold_per_username: threshold,
+        lockout_threshold_per_ip: 100,
+        lockout_seconds: 900,
+    }
+}
+
+async fn spawn_app_with_policy(policy: LoginProtectionSettings) -> TestApp {
+    spawn_app_with(|c: &mut Settings| c.login_protection = policy).await
+}
+
+async fn login(app: &TestApp, username: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": username,
+        "password": password,
+    }))
+    .await
+}
+
+async fn fail_to_login(app: &TestApp, username: &str) {
+    let response = login(app, username, "wrong-password").await;
+    assert_is_redirect_to(&response, "/login");
+    assert!(app.get_login_html().await.contains("Authentication failed"));
+}
+
+async fn login_as(app: &TestApp, user: &TestUser) -> reqwest::Response {
+    login(app, &user.username, &user.password).await
+}
+
+const LOCKED_OUT: &str = "Too many failed login attempts - logging in is temporarily locked.";
+
+#[tokio::test]
+async fn attempts_after_the_free_ones_are_delayed() {
+    // Arrange
+    let app = spawn_app_with_policy(policy(1, 60, 100)).await;
+    fail_to_login(&app, &app.admin_user.username).await;
+
+    // Act - the right password does not help during the delay
+    let response = login_as(&app, &app.admin_user).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html = app.get_login_html().await;
+    assert!(html.contains("Too many failed login attempts - try again in 60 seconds."));
+}
+
+#[tokio::test]
+async fn accounts_are_locked_after_too_many_failed_attempts() {
+    // Arrange
+    let app = spawn_app_with_policy(policy(100, 0, 3)).await;
+    for _ in 0..3 {
+        fail_to_login(&app, &app.admin_user.username).await;
+    }
+
+    // Act
+    let response = login_as(&app, &app.admin_user).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app.get_login_html().await.contains(LOCKED_OUT));
+}
+
+#[tokio::test]
+async fn unknown_usernames_are_locked_like_existing_ones() {
+    // Arrange
+    let app = spawn_app_with_policy(policy(100, 0, 2)).await;
+    for _ in 0..2 {
+        fail_to_login(&app, "not-a-user").await;
+    }
+
+    // Act
+    login(&app, "not-a-user", "wrong-password").await;
+
+    // Assert
+    assert!(app.get_login_html().await.contains(LOCKED_OUT));
+}
+
+#[tokio::test]
+async fn clients_are_locked_after_too_many_failed_attempts() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| {
+        c.login_protection = LoginProtectionSettings {
+            lockout_threshold_per_ip: 3,
+            ..policy(100, 0, 100)
+        }
+    })
+    .await;
+    for username in ["ursula", "le", "guin"] {
+        fail_to_login(&app, username).await;
+    }
+
+    // Act - a different, untouched account from the same client
+    let response = login_as(&app, &app.admin_user).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app.get_login_html().await.contains(LOCKED_OUT));
+}
+
+#[tokio::test]
+async fn forwarded_headers_cannot_dodge_the_client_lockout() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| {
+        c.login_protection = LoginProtectionSettings {
+            lockout_threshold_per_ip: 3,
+            ..policy(100, 0, 100)
+        }
+    })
+    .await;
+    for (username, forwarded_for) in [
+        ("ursula", "203.0.113.1"),
+        ("le", "203.0.113.2"),
+        ("guin", "203.0.113.3"),
+    ] {
+        app.api_client
+            .post(&format!("{}/login", &app.address))
+            .header("X-Forwarded-For", forwarded_for)
+            .form(&serde_json::json!({
+                "username": username,
+                "password": "wrong-password",
+            }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+    }
+
+    // Act
+    let response = login_as(&app, &app.admin_user).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app.get_login_html().await.contains(LOCKED_OUT));
+}
+
+#[tokio::test]
+async fn a_successful_login_resets_the_failed_attempts() {
+    // Arrange
+    let app = spawn_app_with_policy(policy(100, 0, 2)).await;
+    fail_to_login(&app, &app.admin_user.username).await;
+    assert_is_redirect_to(&login_as(&app, &app.admin_user).await, "/admin/dashboard");
+    app.post_logout().await;
+
+    // Act
+    fail_to_login(&app, &app.admin_user.username).await;
+    let response = login_as(&app, &app.admin_user).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn the_account_owner_is_notified_by_email_on_lockout() {
+    // Arrange
+    let app = spawn_app_with_policy(policy(100, 0, 2)).await;
+    sqlx::query!(
+        "UPDATE users SET email = 'ursula@gmail.com' WHERE username = $1",
+        app.admin_user.username
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - the third attempt is refused without notifying again
+    for _ in 0..3 {
+        login(&app, &app.admin_user.username, "wrong-password").await;
+    }
+    app.send_all_account_emails().await.unwrap();
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "ursula@gmail.com");
+    assert_eq!(body["Subject"], "Your account has been locked");
+}
+
+#[tokio::test]
+async fn admins_can_unlock_accounts() {
+    // Arrange
+    let app = spawn_app_with_policy(policy(100, 0, 2)).await;
+    let username = app.collabolator_user.username.clone();
+    for _ in 0..2 {
+        fail_to_login(&app, &username).await;
+    }
+    app.login_with_admin_user().await;
+    assert!(app.get_lockouts_html().await.contains(&username));
+
+    // Act
+    let response = app
+        .post_lockouts(&serde_json::json!({
+            "scope": "username",
+            "key": &username,
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/lockouts");
+    let html = app.get_lockouts_html().await;
+    assert!(html.contains(&format!(
+        "The username &#x27;{username}&#x27; has been unlocked."
+    )));
+    app.post_logout().await;
+    let response = login_as(&app, &app.collabolator_user).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn collaborators_cannot_unlock_accounts() {
+    // Arrange
+    let app = spawn_app_with_policy(policy(100, 0, 100)).await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let page = app.get_lockouts().await;
+    let unlock = app
+        .post_lockouts(&serde_json::json!({
+            "scope": "username",
+            "key": &app.admin_user.username,
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(page.status().as_u16(), 403);
+    assert_eq!(unlock.status().as_u16(), 403);
+}
@@ -0,0 +1,224 @@
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::mailing_lists::DEFAULT_LIST_ID;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, when_sending_an_email, TestApp};
+
+#[tokio::test]
+async fn admins_can_create_mailing_lists() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+
+    // Act 1 - Create a list
+    let response = app
+        .post_mailing_lists(&serde_json::json!({ "name": "Weekly digest" }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/lists");
+
+    // Act 2 - Follow the redirect
+    let html = app.get_mailing_lists_html().await;
+    assert!(html.contains("<p><i>The list &#x27;Weekly digest&#x27; has been created.</i></p>"));
+    assert!(html.contains("<li>Weekly digest</li>"));
+
+    // Act 3 - Create the same list again
+    app.post_mailing_lists(&serde_json::json!({ "name": "Weekly digest" }))
+        .await;
+    let html = app.get_mailing_lists_html().await;
+    assert!(html.contains("A list named &#x27;Weekly digest&#x27; already exists."));
+}
+
+#[tokio::test]
+async fn collaborators_cannot_create_mailing_lists() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let response = app
+        .post_mailing_lists(&serde_json::json!({ "name": "Weekly digest" }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_400_for_an_unknown_list() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriptions(format!(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&list_id={}",
+            Uuid::new_v4()
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn subscribing_to_another_list_has_to_be_confirmed_separately() {
+    // Arrange
+    let app = spawn_app().await;
+    let list_id = create_list(&app, "Weekly digest").await;
+    subscribe_and_confirm(&app, DEFAULT_LIST_ID).await;
+
+    // Act
+    subscribe(&app, list_id).await;
+
+    // Assert
+    let saved = sqlx::query!("SELECT id, status FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].status, "confirmed");
+    assert_eq!(
+        membership_status(&app, list_id).await,
+        "pending_confirmation"
+    );
+    assert_eq!(membership_status(&app, DEFAULT_LIST_ID).await, "confirmed");
+
+    // Act 2 - Click on the second confirmation link
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    assert_eq!(membership_status(&app, list_id).await, "confirmed");
+}
+
+#[tokio::test]
+async fn subscribing_again_to_a_confirmed_list_does_not_send_an_email() {
+    // Arrange
+    let app = spawn_app().await;
+    subscribe_and_confirm(&app, DEFAULT_LIST_ID).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_subscriptions(format!(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&list_id={}",
+            DEFAULT_LIST_ID
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn newsletter_issues_are_delivered_only_to_members_of_the_list() {
+    // Arrange
+    let app = spawn_app().await;
+    let list_id = create_list(&app, "Weekly digest").await;
+    subscribe_and_confirm(&app, DEFAULT_LIST_ID).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string(),
+            "list_id": list_id.to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await.unwrap();
+}
+
+#[tokio::test]
+async fn the_newsletter_form_lists_all_mailing_lists() {
+    // Arrange
+    let app = spawn_app().await;
+    create_list(&app, "Weekly digest").await;
+
+    // Act
+    let html = app.get_publish_newsletter_form_html().await;
+
+    // Assert
+    assert!(html.contains(&format!(
+        r#"<option value="{}" selected>Newsletter</option>"#,
+        DEFAULT_LIST_ID
+    )));
+    assert!(html.contains(">Weekly digest</option>"));
+}
+
+/// Create a list as the admin user, who stays logged in afterwards.
+async fn create_list(app: &TestApp, name: &str) -> Uuid {
+    app.login_with_admin_user().await;
+    app.post_mailing_lists(&serde_json::json!({ "name": name }))
+        .await;
+    sqlx::query!("SELECT list_id FROM lists WHERE name = $1", name)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .list_id
+}
+
+async fn subscribe(app: &TestApp, list_id: Uuid) {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(format!(
+        "name=le%20guin&email=ursula_le_guin%40gmail.com&list_id={}",
+        list_id
+    ))
+    .await
+    .error_for_status()
+    .unwrap();
+}
+
+async fn subscribe_and_confirm(app: &TestApp, list_id: Uuid) {
+    subscribe(app, list_id).await;
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn membership_status(app: &TestApp, list_id: Uuid) -> String {
+    sqlx::query!(
+        "SELECT status FROM list_memberships WHERE list_id = $1",
+        list_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .status
+}
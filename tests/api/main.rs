@@ -1,12 +1,35 @@
 mod admin_dashboard;
+mod api_tokens;
+mod api_v1;
+mod audit;
+mod bot_protection;
+mod change_email;
 mod change_password;
 mod health_check;
 mod helpers;
+mod invitation_cleanup;
 mod invite_confirm_get;
 mod invite_confirm_post;
 mod invite_get;
 mod invite_post;
 mod login;
+mod login_protection;
+mod mailing_lists;
 mod newsletter;
+mod openapi;
+mod password_hashing;
+mod password_policy;
+mod password_reset;
+mod preferences;
+mod rate_limiting;
+mod reviews;
+mod roles;
+mod sessions;
+mod subscriber_data;
+mod subscriber_tags;
+mod subscription_cleanup;
 mod subscriptions;
 mod subscriptions_confirm;
+mod two_factor;
+mod users;
+mod webhooks;
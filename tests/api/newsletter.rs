@@ -4,7 +4,7 @@ use fake::Fake;
 use std::time::Duration;
 
 use crate::helpers::{
-    assert_is_redirect_to, spawn_app, when_sending_an_email, ConfirmationLinks, TestApp,
+    assert_is_redirect_to, spawn_app, when_sending_an_email, EmailLinks, TestApp,
 };
 use wiremock::{matchers::any, Mock, ResponseTemplate};
 
@@ -321,7 +321,7 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     // Mock verifies on Drop that we have sent the newsletter email **once**
 }
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+async fn create_unconfirmed_subscriber(app: &TestApp) -> EmailLinks {
     let name: String = Name().fake();
     let email: String = SafeEmail().fake();
     let body = serde_urlencoded::to_string(serde_json::json!({
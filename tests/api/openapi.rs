@@ -0,0 +1,143 @@
+use crate::helpers::{spawn_app, TestApp};
+
+async fn get_document(app: &TestApp) -> serde_json::Value {
+    let response = reqwest::get(format!("{}/api/openapi.json", app.address))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn every_documented_route_is_served() {
+    // Arrange
+    let app = spawn_app().await;
+    let document = get_document(&app).await;
+    assert_eq!(document["openapi"], "3.0.3");
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let mut n_routes = 0;
+
+    for (path, operations) in document["paths"].as_object().unwrap() {
+        let url = path
+            .split('/')
+            .map(|segment| match segment.starts_with('{') {
+                true => uuid::Uuid::new_v4().to_string(),
+                false => segment.to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        for method in operations.as_object().unwrap().keys() {
+            n_routes += 1;
+
+            // Act
+            let response = client
+                .request(
+                    method.to_uppercase().parse().unwrap(),
+                    format!("{}{url}", app.address),
+                )
+                .send()
+                .await
+                .unwrap();
+
+            // Assert
+            let status = response.status().as_u16();
+            assert!(
+                status != 404 && status != 405,
+                "{method} {path} is documented but not served: {status}"
+            );
+        }
+    }
+    assert!(n_routes > 70, "Only {n_routes} routes are documented");
+}
+
+#[tokio::test]
+async fn routes_document_their_security_and_parameters() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let document = get_document(&app).await;
+
+    // Assert
+    let paths = &document["paths"];
+    let force_relogin = &paths["/admin/sessions/force-relogin"]["post"];
+    assert_eq!(
+        force_relogin["security"],
+        serde_json::json!([{ "session": [] }])
+    );
+    assert_eq!(force_relogin["x-permission"], "users:manage");
+    assert!(force_relogin["responses"]["403"].is_object());
+
+    let login = &paths["/login"]["post"];
+    assert!(login.get("security").is_none());
+    assert!(login["responses"]["429"].is_object());
+
+    let subscriber = &paths["/api/v1/subscribers/{subscriber_id}"]["get"];
+    assert_eq!(subscriber["security"], serde_json::json!([{ "token": [] }]));
+    assert_eq!(
+        subscriber["parameters"][0],
+        serde_json::json!({
+            "name": "subscriber_id",
+            "in": "path",
+            "required": true,
+            "schema": { "type": "string" },
+        })
+    );
+    assert!(subscriber["responses"]["401"].is_object());
+
+    let subscribers = &paths["/api/v1/subscribers"]["get"];
+    let query_parameters: Vec<_> = subscribers["parameters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|p| p["in"] == "query")
+        .map(|p| {
+            (
+                p["name"].as_str().unwrap(),
+                p["required"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert!(query_parameters.contains(&("page", false)));
+    assert!(query_parameters.contains(&("per_page", false)));
+    assert_eq!(
+        subscribers["x-scopes"],
+        serde_json::json!(["subscribers:read"])
+    );
+}
+
+#[tokio::test]
+async fn request_schemas_follow_the_extractor_types() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let document: serde_json::Value = reqwest::get(format!("{}/api/openapi.json", app.address))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    // Assert
+    let subscribe = &document["paths"]["/subscriptions"]["post"]["requestBody"]["content"]
+        ["application/x-www-form-urlencoded"]["schema"];
+    assert_eq!(subscribe["required"], serde_json::json!(["email", "name"]));
+    assert_eq!(subscribe["properties"]["list_id"]["format"], "uuid");
+
+    let publish = &document["paths"]["/admin/newsletters"]["post"]["requestBody"]["content"]
+        ["application/x-www-form-urlencoded"]["schema"];
+    // Flattened segment fields
+    assert_eq!(publish["properties"]["include_tags"]["type"], "string");
+
+    let issues = &document["paths"]["/api/v1/issues"]["post"];
+    assert_eq!(
+        issues["x-scopes"],
+        serde_json::json!(["newsletters:publish"])
+    );
+    assert_eq!(issues["parameters"][0]["name"], "Idempotency-Key");
+    assert!(issues["responses"]["202"].is_object());
+}
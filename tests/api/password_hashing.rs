@@ -0,0 +1,156 @@
+use argon2::password_hash::SaltString;
+use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+
+use crate::helpers::{assert_is_redirect_to, log_in_elsewhere, spawn_app, spawn_app_with, TestApp};
+
+const CURRENT_PARAMS: &str = "$argon2id$v=19$m=15000,t=2,p=1$";
+
+fn hash(password: &str, algorithm: Algorithm, params: Params) -> String {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    Argon2::new(algorithm, Version::V0x13, params)
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+async fn admin_password_hash(app: &TestApp) -> String {
+    sqlx::query_scalar!(
+        "SELECT password_hash FROM users WHERE user_id = $1",
+        app.admin_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+async fn set_admin_password_hash(app: &TestApp, password_hash: &str) {
+    sqlx::query!(
+        "UPDATE users SET password_hash = $2 WHERE user_id = $1",
+        app.admin_user.user_id,
+        password_hash
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn outdated_password_hashes_are_replaced_on_login() {
+    // Arrange
+    let app = spawn_app().await;
+    let password = &app.admin_user.password;
+    let test_cases = [
+        (Algorithm::Argon2id, Params::new(4096, 1, 1, None).unwrap()),
+        (Algorithm::Argon2i, Params::new(15000, 2, 1, None).unwrap()),
+    ];
+
+    for (algorithm, params) in test_cases {
+        let outdated = hash(password, algorithm, params);
+        set_admin_password_hash(&app, &outdated).await;
+
+        // Act
+        let response = app
+            .post_login(&serde_json::json!({
+                "username": &app.admin_user.username,
+                "password": password,
+            }))
+            .await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/dashboard");
+        let password_hash = admin_password_hash(&app).await;
+        assert_ne!(password_hash, outdated);
+        assert!(
+            password_hash.starts_with(CURRENT_PARAMS),
+            "{outdated} was replaced with {password_hash}"
+        );
+        assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+        app.post_logout().await;
+    }
+}
+
+#[tokio::test]
+async fn current_password_hashes_are_kept() {
+    // Arrange
+    let app = spawn_app().await;
+    let password_hash = admin_password_hash(&app).await;
+
+    // Act
+    app.login_with_admin_user().await;
+
+    // Assert
+    assert_eq!(admin_password_hash(&app).await, password_hash);
+}
+
+#[tokio::test]
+async fn failed_logins_do_not_replace_outdated_hashes() {
+    // Arrange
+    let app = spawn_app().await;
+    let outdated = hash(
+        &app.admin_user.password,
+        Algorithm::Argon2id,
+        Params::new(4096, 1, 1, None).unwrap(),
+    );
+    set_admin_password_hash(&app, &outdated).await;
+
+    // Act
+    app.post_login(&serde_json::json!({
+        "username": &app.admin_user.username,
+        "password": "wrong-password",
+    }))
+    .await;
+
+    // Assert
+    assert_eq!(admin_password_hash(&app).await, outdated);
+}
+
+#[tokio::test]
+async fn hashing_parameters_are_configurable() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.password_hashing.memory_kib = 8192;
+        c.password_hashing.iterations = 3;
+    })
+    .await;
+
+    // Act
+    app.login_with_admin_user().await;
+
+    // Assert
+    let password_hash = admin_password_hash(&app).await;
+    assert!(password_hash.starts_with("$argon2id$v=19$m=8192,t=3,p=1$"));
+}
+
+#[tokio::test]
+async fn sessions_survive_the_rehash_of_their_password() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.session_cache_milliseconds = 0).await;
+    app.login_with_admin_user().await;
+    // As if the session had been started before the parameters changed.
+    let outdated = hash(
+        &app.admin_user.password,
+        Algorithm::Argon2id,
+        Params::new(4096, 1, 1, None).unwrap(),
+    );
+    sqlx::query!(
+        r#"
+        UPDATE user_sessions
+        SET password_fingerprint = encode(sha256(convert_to($2, 'UTF8')), 'hex')
+        WHERE user_id = $1
+        "#,
+        app.admin_user.user_id,
+        outdated
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    set_admin_password_hash(&app, &outdated).await;
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+
+    // Act
+    log_in_elsewhere(&app, &app.admin_user).await;
+
+    // Assert
+    assert!(admin_password_hash(&app).await.starts_with(CURRENT_PARAMS));
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+}
@@ -0,0 +1,83 @@
+use sha1::{Digest, Sha1};
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+
+async fn change_password(app: &TestApp, new_password: &str) -> String {
+    app.post_login(&serde_json::json!({
+        "username": &app.admin_user.username,
+        "password": &app.admin_user.password
+    }))
+    .await;
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.admin_user.password,
+            "new_password": new_password,
+            "new_password_check": new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+    app.get_change_password_html().await
+}
+
+#[tokio::test]
+async fn weak_passwords_are_refused() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = [
+        (
+            format!("my-{}-pass", app.admin_user.username.to_uppercase()),
+            "The new password must not contain your username.",
+        ),
+        (
+            "CorrectHorseBatteryStaple".to_string(),
+            "The new password is too common - choose one that is harder to guess.",
+        ),
+        (
+            "00000000000000".to_string(),
+            "The new password is too easy to guess - make it longer or less repetitive.",
+        ),
+    ];
+
+    for (password, message) in test_cases {
+        // Act
+        let html_page = change_password(&app, &password).await;
+
+        // Assert
+        assert!(
+            html_page.contains(&format!("<p><i>{message}</i></p>")),
+            "{password} was not refused with '{message}'"
+        );
+    }
+}
+
+#[tokio::test]
+async fn breached_passwords_are_refused_when_a_dataset_is_configured() {
+    // Arrange
+    let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir).unwrap();
+    let breached = "Tq7#mZ2!xW9@kR4$";
+    let hash = hex::encode_upper(Sha1::digest(breached.as_bytes()));
+    let (prefix, suffix) = hash.split_at(5);
+    std::fs::write(
+        dir.join(format!("{prefix}.txt")),
+        format!("0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n{suffix}:42\r\n"),
+    )
+    .unwrap();
+    let breached_passwords_dir = dir.to_str().unwrap().to_owned();
+    let app = spawn_app_with(|c| {
+        c.password_policy.breached_passwords_dir = Some(breached_passwords_dir);
+    })
+    .await;
+
+    // Act
+    let html_page = change_password(&app, breached).await;
+
+    // Assert
+    assert!(html_page.contains(
+        "<p><i>The new password has appeared in a data breach - choose another one.</i></p>"
+    ));
+    let html_page = change_password(&app, &Uuid::new_v4().to_string()).await;
+    assert!(html_page.contains("<p><i>Your password has been changed.</i></p>"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
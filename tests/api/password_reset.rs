@@ -0,0 +1,271 @@
+use wiremock::ResponseTemplate;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, when_sending_an_email, TestApp};
+
+const NEUTRAL_ANSWER: &str =
+    "If the account exists and has an email address, a link to reset the password has been sent to it.";
+
+async fn set_admin_email(app: &TestApp) {
+    sqlx::query!(
+        "UPDATE users SET email = 'admin@gmail.com' WHERE username = $1",
+        app.admin_user.username
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+/// Request a reset for the admin user and return the token from the email.
+async fn request_reset_token(app: &TestApp) -> String {
+    set_admin_email(app).await;
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_password_reset(&app.admin_user.username).await;
+    app.send_all_account_emails().await.unwrap();
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let links = app.get_links(&email_request, "/password-reset/confirm");
+    assert_eq!(links.html, links.plain_text);
+    links
+        .html
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.into_owned())
+        .unwrap()
+}
+
+async fn reset_password(app: &TestApp, token: &str, password: &str) -> reqwest::Response {
+    app.post_password_reset_confirm(&serde_json::json!({
+        "token": token,
+        "password": password,
+        "password_check": password,
+    }))
+    .await
+}
+
+async fn login(app: &TestApp, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": &app.admin_user.username,
+        "password": password,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn a_reset_link_is_emailed_to_the_user() {
+    // Arrange
+    let app = spawn_app().await;
+    set_admin_email(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_password_reset(&app.admin_user.username).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/password-reset");
+    assert!(app.get_password_reset_html().await.contains(NEUTRAL_ANSWER));
+    app.send_all_account_emails().await.unwrap();
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "admin@gmail.com");
+}
+
+#[tokio::test]
+async fn unknown_users_get_the_same_answer_without_an_email() {
+    // Arrange
+    let app = spawn_app().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_password_reset("not-a-user").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/password-reset");
+    assert!(app.get_password_reset_html().await.contains(NEUTRAL_ANSWER));
+    app.send_all_account_emails().await.unwrap();
+}
+
+#[tokio::test]
+async fn failing_to_send_the_email_gets_the_same_answer_and_is_retried() {
+    // Arrange
+    let app = spawn_app().await;
+    set_admin_email(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_password_reset(&app.admin_user.username).await;
+    app.send_all_account_emails().await.unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/password-reset");
+    assert!(app.get_password_reset_html().await.contains(NEUTRAL_ANSWER));
+    let n_retries = sqlx::query_scalar!("SELECT n_retries FROM account_email_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_retries, 1);
+}
+
+#[tokio::test]
+async fn the_password_can_be_reset_with_the_emailed_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = request_reset_token(&app).await;
+    let new_password = "a-brand-new-password";
+
+    // Act
+    let response = reset_password(&app, &token, new_password).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("Your password has been reset - you can log in now."));
+    assert_is_redirect_to(&login(&app, &app.admin_user.password).await, "/login");
+    assert_is_redirect_to(&login(&app, new_password).await, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn reset_links_can_only_be_used_once() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = request_reset_token(&app).await;
+    reset_password(&app, &token, "a-brand-new-password").await;
+
+    // Act
+    let response = reset_password(&app, &token, "another-new-password").await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/password-reset/confirm?token={token}"));
+    assert_is_redirect_to(&login(&app, "another-new-password").await, "/login");
+}
+
+#[tokio::test]
+async fn expired_reset_links_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = request_reset_token(&app).await;
+    sqlx::query!("UPDATE password_reset_tokens SET created_at = now() - interval '2 hours'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = reset_password(&app, &token, "a-brand-new-password").await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/password-reset/confirm?token={token}"));
+    assert_is_redirect_to(
+        &login(&app, &app.admin_user.password).await,
+        "/admin/dashboard",
+    );
+    let n_tokens = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM password_reset_tokens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_tokens, 0);
+}
+
+#[tokio::test]
+async fn a_reset_spends_every_link_sent_to_the_user() {
+    // Arrange
+    let app = spawn_app().await;
+    let first_token = request_reset_token(&app).await;
+    let second_token = request_reset_token(&app).await;
+    reset_password(&app, &second_token, "a-brand-new-password").await;
+
+    // Act
+    let response = reset_password(&app, &first_token, "another-new-password").await;
+
+    // Assert
+    assert_is_redirect_to(
+        &response,
+        &format!("/password-reset/confirm?token={first_token}"),
+    );
+    assert_is_redirect_to(&login(&app, "another-new-password").await, "/login");
+    assert_is_redirect_to(
+        &login(&app, "a-brand-new-password").await,
+        "/admin/dashboard",
+    );
+}
+
+#[tokio::test]
+async fn the_new_password_has_to_be_valid() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = request_reset_token(&app).await;
+
+    // Act
+    let response = reset_password(&app, &token, "too-short").await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/password-reset/confirm?token={token}"));
+    // The link is still valid
+    let response = reset_password(&app, &token, "a-brand-new-password").await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_new_password_has_to_pass_the_password_policy() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = request_reset_token(&app).await;
+
+    // Act
+    let response = reset_password(&app, &token, "iloveyou12345").await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/password-reset/confirm?token={token}"));
+    let location = response.headers()["Location"].to_str().unwrap();
+    let html = app
+        .api_client
+        .get(&format!("{}{}", app.address, location))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("The new password is too common - choose one that is harder to guess."));
+    // The link is still valid
+    let response = reset_password(&app, &token, "a-brand-new-password").await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn existing_sessions_are_logged_out_after_a_reset() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+    let token = request_reset_token(&app).await;
+
+    // Act
+    reset_password(&app, &token, "a-brand-new-password").await;
+
+    // Assert
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
@@ -0,0 +1,417 @@
+use wiremock::ResponseTemplate;
+use zero2prod::mailing_lists::DEFAULT_LIST_ID;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, when_sending_an_email, TestApp};
+
+#[tokio::test]
+async fn preferences_are_rejected_without_a_valid_token() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_preferences(&[("token", "unknown-token")]).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn the_confirmation_email_links_to_the_preferences_page() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let token = create_subscriber(&app).await;
+    let html = app.get_preferences_html(&token).await;
+
+    // Assert
+    let name = htmlescape::encode_attribute("le guin");
+    let email = htmlescape::encode_attribute("ursula_le_guin@gmail.com");
+    assert!(html.contains(&format!(r#"value="{name}""#)));
+    assert!(html.contains(&format!(r#"value="{email}""#)));
+}
+
+#[tokio::test]
+async fn subscribers_can_change_their_name() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_subscriber(&app).await;
+
+    // Act 1 - Update preferences
+    let response = app
+        .post_preferences(&serde_json::json!({
+            "token": &token,
+            "name": "Ursula Le Guin",
+            "email": "ursula_le_guin@gmail.com",
+            default_list(): "on",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/preferences?token={token}"));
+    let saved = sqlx::query!("SELECT name, status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "Ursula Le Guin");
+    assert_eq!(saved.status, "pending_confirmation");
+
+    // Act 2 - Follow the redirect
+    let html = app.get_preferences_html(&token).await;
+    assert!(html.contains("<p><i>Your preferences have been updated.</i></p>"));
+}
+
+#[tokio::test]
+async fn invalid_preferences_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_subscriber(&app).await;
+    let test_cases = vec![
+        (
+            "",
+            "ursula_le_guin@gmail.com",
+            " is not a valid subscriber name.",
+        ),
+        (
+            "le guin",
+            "definitely-not-an-email",
+            "definitely-not-an-email is not a valid email.",
+        ),
+    ];
+
+    for (name, email, error_message) in test_cases {
+        // Act 1 - Update preferences
+        let response = app
+            .post_preferences(&serde_json::json!({
+                "token": &token,
+                "name": name,
+                "email": email,
+                default_list(): "on",
+            }))
+            .await;
+        assert_is_redirect_to(&response, &format!("/preferences?token={token}"));
+
+        // Act 2 - Follow the redirect
+        let html = app.get_preferences_html(&token).await;
+        assert!(html.contains(error_message));
+    }
+    let saved = sqlx::query!("SELECT name, email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "le guin");
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_do_not_receive_newsletters() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_confirmed_subscriber(&app).await;
+    app.login_with_admin_user().await;
+
+    // Act 1 - Unsubscribe
+    app.post_preferences(&serde_json::json!({
+        "token": &token,
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com",
+    }))
+    .await;
+
+    // Assert
+    let saved = sqlx::query!("SELECT status FROM list_memberships")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+
+    // Act 2 - Publish a newsletter issue
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_newsletter(&newsletter_request_body())
+        .await;
+    app.dispatch_all_pending_emails().await.unwrap();
+
+    // Act 3 - Subscribe again
+    app.post_preferences(&serde_json::json!({
+        "token": &token,
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com",
+        default_list(): "on",
+    }))
+    .await;
+
+    // Assert
+    let saved = sqlx::query!("SELECT status FROM list_memberships")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn a_changed_email_address_has_to_be_confirmed_again() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_confirmed_subscriber(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act 1 - Change the email address
+    let response = app
+        .post_preferences(&serde_json::json!({
+            "token": &token,
+            "name": "le guin",
+            "email": "ursula@gmail.com",
+            default_list(): "on",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/preferences?token={token}"));
+    let saved = sqlx::query!("SELECT email, status, pending_email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.status, "confirmed");
+    assert_eq!(saved.pending_email.as_deref(), Some("ursula@gmail.com"));
+
+    // Act 2 - Click on the new confirmation link
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "ursula@gmail.com");
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let saved = sqlx::query!("SELECT email, status, pending_email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.email, "ursula@gmail.com");
+    assert_eq!(saved.status, "confirmed");
+    assert_eq!(saved.pending_email, None);
+}
+
+#[tokio::test]
+async fn only_the_link_of_the_latest_email_change_confirms_it() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_confirmed_subscriber(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_preferences(&serde_json::json!({
+        "token": &token,
+        "name": "le guin",
+        "email": "ursula@gmail.com",
+        default_list(): "on",
+    }))
+    .await;
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let first_link = app.get_confirmation_links(&email_request).html;
+
+    // Act - Change the address again, then click on the first link
+    app.post_preferences(&serde_json::json!({
+        "token": &token,
+        "name": "le guin",
+        "email": "le_guin@gmail.com",
+        default_list(): "on",
+    }))
+    .await;
+    let response = reqwest::get(first_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = sqlx::query!("SELECT email, pending_email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.pending_email.as_deref(), Some("le_guin@gmail.com"));
+}
+
+#[tokio::test]
+async fn changing_only_the_name_keeps_a_pending_email_change() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_confirmed_subscriber(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_preferences(&serde_json::json!({
+        "token": &token,
+        "name": "le guin",
+        "email": "ursula@gmail.com",
+        default_list(): "on",
+    }))
+    .await;
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(&email_request).html;
+
+    // Act - Change the name, then click on the link
+    app.post_preferences(&serde_json::json!({
+        "token": &token,
+        "name": "ursula k. le guin",
+        "email": "ursula_le_guin@gmail.com",
+        default_list(): "on",
+    }))
+    .await;
+    reqwest::get(confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let saved = sqlx::query!("SELECT name, email, pending_email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "ursula k. le guin");
+    assert_eq!(saved.email, "ursula@gmail.com");
+    assert_eq!(saved.pending_email, None);
+}
+
+#[tokio::test]
+async fn an_email_address_of_another_subscriber_cannot_be_taken() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_subscriber(&app).await;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, preferences_token)
+        VALUES ($1, 'taken@gmail.com', 'other', now(), 'confirmed', 'other-token')
+        "#,
+        uuid::Uuid::new_v4()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act 1 - Try to change the email address
+    app.post_preferences(&serde_json::json!({
+        "token": &token,
+        "name": "le guin",
+        "email": "taken@gmail.com",
+        default_list(): "on",
+    }))
+    .await;
+
+    // Act 2 - Follow the redirect
+    let html = app.get_preferences_html(&token).await;
+
+    // Assert
+    assert!(html.contains("<p><i>This email address is already subscribed.</i></p>"));
+}
+
+#[tokio::test]
+async fn newsletter_issues_link_to_the_preferences_page() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_confirmed_subscriber(&app).await;
+    app.login_with_admin_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_publish_newsletter(&newsletter_request_body())
+        .await;
+    app.dispatch_all_pending_emails().await.unwrap();
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let links = app.get_preferences_links(&email_request);
+    assert_eq!(links.html, links.plain_text);
+    assert_eq!(token_from_link(&links.html), token);
+}
+
+/// Subscribe and return the preferences token sent in the confirmation email.
+async fn create_subscriber(app: &TestApp) -> String {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let links = app.get_preferences_links(email_request);
+    assert_eq!(links.html, links.plain_text);
+    token_from_link(&links.html)
+}
+
+async fn create_confirmed_subscriber(app: &TestApp) -> String {
+    let token = create_subscriber(app).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    token
+}
+
+fn token_from_link(link: &reqwest::Url) -> String {
+    link.query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.into_owned())
+        .unwrap()
+}
+
+fn default_list() -> String {
+    format!("list_{}", DEFAULT_LIST_ID)
+}
+
+fn newsletter_request_body() -> impl serde::Serialize {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    })
+}
@@ -0,0 +1,242 @@
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::configuration::{RateLimit, Settings};
+
+use crate::helpers::{spawn_app_with, when_sending_an_email, TestApp};
+
+fn limits(max_requests_per_ip: u32, max_requests_per_key: u32) -> RateLimit {
+    RateLimit {
+        window_seconds: 60,
+        max_requests_per_ip,
+        max_requests_per_key,
+    }
+}
+
+fn assert_is_rate_limited(response: &reqwest::Response) {
+    assert_eq!(response.status().as_u16(), 429);
+    let retry_after: u64 = response.headers()["Retry-After"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after));
+}
+
+async fn post_login(app: &TestApp, username: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": username,
+        "password": "wrong-password",
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn login_attempts_are_limited_per_username() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| c.rate_limits.login = limits(100, 2)).await;
+
+    // Act
+    for _ in 0..2 {
+        assert_eq!(post_login(&app, "ursula").await.status().as_u16(), 303);
+    }
+    let response = post_login(&app, "Ursula ").await;
+
+    // Assert
+    assert_is_rate_limited(&response);
+    assert_eq!(post_login(&app, "le-guin").await.status().as_u16(), 303);
+}
+
+#[tokio::test]
+async fn login_attempts_are_limited_per_client() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| c.rate_limits.login = limits(2, 100)).await;
+
+    // Act
+    for username in ["ursula", "le-guin"] {
+        assert_eq!(post_login(&app, username).await.status().as_u16(), 303);
+    }
+    let response = post_login(&app, "someone-else").await;
+
+    // Assert
+    assert_is_rate_limited(&response);
+}
+
+async fn post_login_forwarded_for(app: &TestApp, forwarded_for: &str) -> reqwest::Response {
+    app.api_client
+        .post(&format!("{}/login", &app.address))
+        .header("X-Forwarded-For", forwarded_for)
+        .form(&serde_json::json!({
+            "username": Uuid::new_v4().to_string(),
+            "password": "wrong-password",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn forwarded_headers_cannot_dodge_the_limit_per_client() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| c.rate_limits.login = limits(2, 100)).await;
+
+    // Act
+    for forwarded_for in ["203.0.113.1", "203.0.113.2"] {
+        let response = post_login_forwarded_for(&app, forwarded_for).await;
+        assert_eq!(response.status().as_u16(), 303);
+    }
+    let response = post_login_forwarded_for(&app, "203.0.113.3").await;
+
+    // Assert
+    assert_is_rate_limited(&response);
+}
+
+#[tokio::test]
+async fn clients_behind_a_trusted_proxy_are_limited_on_their_own() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| {
+        c.application.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+        c.rate_limits.login = limits(1, 100);
+    })
+    .await;
+
+    // Act
+    for forwarded_for in ["203.0.113.1", "203.0.113.2"] {
+        let response = post_login_forwarded_for(&app, forwarded_for).await;
+        assert_eq!(response.status().as_u16(), 303);
+    }
+    let response = post_login_forwarded_for(&app, "203.0.113.1").await;
+
+    // Assert
+    assert_is_rate_limited(&response);
+}
+
+#[tokio::test]
+async fn rejected_requests_do_not_count_against_the_other_limits() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| c.rate_limits.login = limits(3, 1)).await;
+    assert_eq!(post_login(&app, "ursula").await.status().as_u16(), 303);
+
+    // Act
+    for _ in 0..2 {
+        assert_is_rate_limited(&post_login(&app, "ursula").await);
+    }
+
+    // Assert
+    for username in ["le-guin", "someone-else"] {
+        assert_eq!(post_login(&app, username).await.status().as_u16(), 303);
+    }
+}
+
+#[tokio::test]
+async fn rendering_the_login_form_is_not_limited() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| c.rate_limits.login = limits(1, 1)).await;
+
+    for _ in 0..3 {
+        // Act
+        let response = app
+            .api_client
+            .get(&format!("{}/login", &app.address))
+            .send()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[tokio::test]
+async fn subscriptions_are_limited_per_email() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| c.rate_limits.subscriptions = limits(100, 1)).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    // Act
+    let first = app.post_subscriptions(body.into()).await;
+    let second = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_is_rate_limited(&second);
+}
+
+#[tokio::test]
+async fn subscriptions_are_limited_per_client() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| c.rate_limits.subscriptions = limits(1, 100)).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let first = app
+        .post_subscriptions("name=le%20guin&email=ursula%40gmail.com".into())
+        .await;
+    let second = app
+        .post_subscriptions("name=le%20guin&email=le_guin%40gmail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_is_rate_limited(&second);
+}
+
+#[tokio::test]
+async fn account_activation_attempts_are_limited_per_username() {
+    // Arrange
+    let app =
+        spawn_app_with(|c: &mut Settings| c.rate_limits.account_activation = limits(100, 1)).await;
+    let body = serde_json::json!({
+        "token": "invalid-token",
+        "username": "ursula",
+        "password": "a-long-enough-password",
+        "password_check": "a-long-enough-password",
+    });
+
+    // Act
+    let first = app.post_account_activate(&body).await;
+    let second = app.post_account_activate(&body).await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 303);
+    assert_is_rate_limited(&second);
+}
+
+#[tokio::test]
+async fn requests_are_accepted_again_once_the_window_has_passed() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| {
+        c.rate_limits.account_activation = RateLimit {
+            window_seconds: 2,
+            max_requests_per_ip: 1,
+            max_requests_per_key: 1,
+        }
+    })
+    .await;
+    let body = serde_json::json!({
+        "token": "invalid-token",
+        "username": "ursula",
+        "password": "a-long-enough-password",
+        "password_check": "a-long-enough-password",
+    });
+    assert_eq!(
+        app.post_account_activate(&body).await.status().as_u16(),
+        303
+    );
+    assert_is_rate_limited(&app.post_account_activate(&body).await);
+
+    // Act
+    tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+    let response = app.post_account_activate(&body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+}
@@ -0,0 +1,314 @@
+use crate::helpers::{
+    assert_is_redirect_to, log_in_elsewhere, spawn_app, when_sending_an_email, TestApp,
+};
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::webhooks::{create_webhook_endpoint, WebhookEventType};
+
+fn issue_body(title: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": title,
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    })
+}
+
+/// Submitted by the collaborator, who has to be logged in.
+async fn submit_issue(app: &TestApp, title: &str) -> Uuid {
+    let response = app.post_publish_newsletter(&issue_body(title)).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    sqlx::query_scalar!(
+        "SELECT newsletter_issue_id FROM newsletter_issues WHERE title = $1",
+        title
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn count_queued_emails(app: &TestApp) -> i64 {
+    sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn issues_of_collaborators_wait_for_a_review() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_collabolator_user().await;
+    assert!(app
+        .get_publish_newsletter_form_html()
+        .await
+        .contains("Submit for review"));
+
+    // Act
+    let issue_id = submit_issue(&app, "Draft title").await;
+
+    // Assert
+    let html = app.get_publish_newsletter_form_html().await;
+    assert!(html.contains("<p><i>The newsletter issue has been submitted for review.</i></p>"));
+    assert!(html.contains("<td>Draft title</td>"));
+    assert!(html.contains("<td>pending review</td>"));
+    assert_eq!(count_queued_emails(&app).await, 0);
+    let issue = sqlx::query!(
+        "SELECT review_status, author_id FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        issue_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issue.review_status, "pending_review");
+    assert_eq!(issue.author_id, Some(app.collabolator_user.user_id));
+}
+
+#[tokio::test]
+async fn reviewers_are_notified_by_email() {
+    // Arrange
+    let app = spawn_app().await;
+    sqlx::query!(
+        "UPDATE users SET email = 'reviewer@example.com' WHERE user_id = $1",
+        app.admin_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    submit_issue(&app, "Draft title").await;
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "reviewer@example.com");
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains(&format!("{}/admin/reviews", app.base_url)));
+}
+
+#[tokio::test]
+async fn approved_issues_are_sent() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_collabolator_user().await;
+    let issue_id = submit_issue(&app, "Draft title").await;
+    let admin = log_in_elsewhere(&app, &app.admin_user).await;
+    let reviews = admin
+        .get(&format!("{}/admin/reviews", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(reviews.contains("<h2>Draft title</h2>"));
+
+    // Act
+    let response = admin
+        .post(&format!("{}/admin/reviews/approve", &app.address))
+        .form(&serde_json::json!({ "newsletter_issue_id": issue_id }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/reviews");
+    assert_eq!(count_queued_emails(&app).await, 1);
+    let html = app.get_publish_newsletter_form_html().await;
+    assert!(html.contains("<td>published</td>"));
+}
+
+#[tokio::test]
+async fn approved_issues_without_recipients_are_reported_delivered() {
+    // Arrange
+    let app = spawn_app().await;
+    create_webhook_endpoint(
+        &app.db_pool,
+        "https://example.com/hook",
+        &[WebhookEventType::IssueDelivered],
+    )
+    .await
+    .unwrap();
+    app.login_with_collabolator_user().await;
+    let issue_id = submit_issue(&app, "Draft title").await;
+    let admin = log_in_elsewhere(&app, &app.admin_user).await;
+
+    // Act
+    admin
+        .post(&format!("{}/admin/reviews/approve", &app.address))
+        .form(&serde_json::json!({ "newsletter_issue_id": issue_id }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    let payload = sqlx::query_scalar!(
+        "SELECT payload FROM webhook_outbox WHERE event_type = 'issue.delivered'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    let event: serde_json::Value = serde_json::from_str(&payload).unwrap();
+    assert_eq!(event["data"]["newsletter_issue_id"], issue_id.to_string());
+    assert_eq!(event["data"]["recipients"], 0);
+}
+
+#[tokio::test]
+async fn rejected_issues_show_the_comment_to_their_author() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+    let issue_id = submit_issue(&app, "Draft title").await;
+    let admin = log_in_elsewhere(&app, &app.admin_user).await;
+
+    // Act
+    let response = admin
+        .post(&format!("{}/admin/reviews/reject", &app.address))
+        .form(&serde_json::json!({
+            "newsletter_issue_id": issue_id,
+            "comment": "Too <b>long</b>",
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/reviews");
+    assert_eq!(count_queued_emails(&app).await, 0);
+    let html = app.get_publish_newsletter_form_html().await;
+    assert!(html.contains("<td>rejected</td>"));
+    assert!(html.contains(&format!(
+        "{}: Too &lt;b&gt;long&lt;/b&gt;",
+        app.admin_user.username
+    )));
+}
+
+#[tokio::test]
+async fn issues_cannot_be_rejected_without_a_comment() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+    let issue_id = submit_issue(&app, "Draft title").await;
+    app.post_logout().await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let response = app
+        .post_reviews(
+            "/reject",
+            &serde_json::json!({ "newsletter_issue_id": issue_id, "comment": "  " }),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/reviews");
+    let html = app.get_reviews_html().await;
+    assert!(html.contains("<p><i>Tell the author why the issue is rejected.</i></p>"));
+    assert!(html.contains("<h2>Draft title</h2>"));
+}
+
+#[tokio::test]
+async fn issues_are_reviewed_only_once() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_collabolator_user().await;
+    let issue_id = submit_issue(&app, "Draft title").await;
+    app.post_logout().await;
+    app.login_with_admin_user().await;
+    let form = serde_json::json!({ "newsletter_issue_id": issue_id, "comment": "Late" });
+    app.post_reviews("/approve", &form).await;
+
+    for path in ["/approve", "/reject"] {
+        // Act
+        let response = app.post_reviews(path, &form).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/reviews");
+        let html = app.get_reviews_html().await;
+        assert!(
+            html.contains("<p><i>The issue is no longer waiting for a review.</i></p>"),
+            "{path} was not refused"
+        );
+    }
+    assert_eq!(count_queued_emails(&app).await, 1);
+}
+
+#[tokio::test]
+async fn only_publishers_can_review_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+    let issue_id = submit_issue(&app, "Draft title").await;
+
+    // Act
+    let page = app.get_reviews().await;
+    let approve = app
+        .post_reviews(
+            "/approve",
+            &serde_json::json!({ "newsletter_issue_id": issue_id }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(page.status().as_u16(), 403);
+    assert_eq!(approve.status().as_u16(), 403);
+    assert_eq!(count_queued_emails(&app).await, 0);
+}
+
+#[tokio::test]
+async fn issues_under_review_are_not_listed_in_the_api() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+    let issue_id = submit_issue(&app, "Draft title").await;
+    app.post_logout().await;
+    app.login_with_admin_user().await;
+    let token = app.create_api_token(&["newsletters:read"]).await;
+
+    // Act
+    let list = app.get_api("/issues", Some(&token)).await;
+    let issue = app
+        .get_api(&format!("/issues/{issue_id}"), Some(&token))
+        .await;
+
+    // Assert
+    let list: serde_json::Value = list.json().await.unwrap();
+    assert_eq!(list["pagination"]["total"], 0);
+    assert_eq!(issue.status().as_u16(), 404);
+}
@@ -0,0 +1,309 @@
+use crate::helpers::{assert_is_redirect_to, get_dashboard, log_in_elsewhere, spawn_app, TestApp};
+
+async fn get_page(app: &TestApp, client: &reqwest::Client, path: &str) -> reqwest::Response {
+    client
+        .get(&format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .unwrap()
+}
+
+fn assign_role(app: &TestApp, role: &str) -> serde_json::Value {
+    serde_json::json!({
+        "user_id": app.collabolator_user.user_id,
+        "role": role,
+    })
+}
+
+#[tokio::test]
+async fn roles_can_only_be_managed_by_users_who_can_manage_users() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let page = app.get_roles().await;
+    let create = app
+        .post_roles("", &[("name", "editor"), ("permission", "users:manage")])
+        .await;
+
+    // Assert
+    assert_eq!(page.status().as_u16(), 403);
+    assert_eq!(create.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn built_in_roles_are_listed_with_their_permissions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let html = app.get_roles_html().await;
+
+    // Assert
+    assert!(html.contains("<td>admin</td>"));
+    assert!(html.contains("<td>collabolator</td>"));
+    assert!(html.contains(r#"value="users:manage" checked"#));
+    assert!(html.contains(r#"value="stats:view" checked"#));
+}
+
+#[tokio::test]
+async fn a_new_role_grants_exactly_its_permissions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let other = log_in_elsewhere(&app, &app.collabolator_user).await;
+
+    // Act
+    let response = app
+        .post_roles(
+            "",
+            &[
+                ("name", "list-keeper"),
+                ("permission", "subscribers:manage"),
+            ],
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/roles");
+    assert!(app
+        .get_roles_html()
+        .await
+        .contains("<p><i>The role list-keeper has been created.</i></p>"));
+    let response = app
+        .post_users("/role", &assign_role(&app, "list-keeper"))
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    // Assert
+    assert_eq!(
+        get_page(&app, &other, "/admin/lists")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    assert_eq!(
+        get_page(&app, &other, "/admin/users")
+            .await
+            .status()
+            .as_u16(),
+        403
+    );
+    assert_eq!(
+        get_page(&app, &other, "/admin/newsletters")
+            .await
+            .status()
+            .as_u16(),
+        403
+    );
+    let dashboard = get_dashboard(&app, &other).await.text().await.unwrap();
+    assert!(dashboard.contains("Manage mailing lists"));
+    assert!(!dashboard.contains("Manage users"));
+    assert!(!dashboard.contains("Send a newsletter issue"));
+}
+
+#[tokio::test]
+async fn changed_permissions_apply_to_logged_in_users_right_away() {
+    // Arrange
+    let app = spawn_app().await;
+    let other = log_in_elsewhere(&app, &app.collabolator_user).await;
+    assert_eq!(
+        get_page(&app, &other, "/admin/newsletters")
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+    app.login_with_admin_user().await;
+
+    // Act - Keep stats:view only
+    let response = app
+        .post_roles(
+            "/permissions",
+            &[("role", "collabolator"), ("permission", "stats:view")],
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/roles");
+    assert_eq!(
+        get_page(&app, &other, "/admin/newsletters")
+            .await
+            .status()
+            .as_u16(),
+        403
+    );
+}
+
+#[tokio::test]
+async fn publishing_lets_collaborators_skip_the_review() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    app.post_roles(
+        "/permissions",
+        &[
+            ("role", "collabolator"),
+            ("permission", "issues:draft"),
+            ("permission", "issues:publish"),
+        ],
+    )
+    .await;
+    app.post_logout().await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let form = app.get_publish_newsletter_form_html().await;
+    let publish = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    assert!(form.contains("Send issue"));
+    assert_is_redirect_to(&publish, "/admin/newsletters");
+    let status = sqlx::query_scalar!("SELECT review_status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "published");
+}
+
+#[tokio::test]
+async fn only_unused_custom_roles_can_be_deleted() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    app.post_roles("", &[("name", "editor"), ("permission", "issues:draft")])
+        .await;
+    app.post_users("/role", &assign_role(&app, "editor")).await;
+    let test_cases = [
+        ("editor", "The role is still given to some users."),
+        ("collabolator", "Built-in roles cannot be deleted."),
+        ("ghost", "There is no such role."),
+    ];
+
+    for (role, message) in test_cases {
+        // Act
+        let response = app.post_roles("/delete", &[("role", role)]).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/roles");
+        let html = app.get_roles_html().await;
+        assert!(
+            html.contains(&format!("<p><i>{message}</i></p>")),
+            "{role} was not refused with '{message}'"
+        );
+    }
+
+    // Act - Once nobody has it
+    app.post_users("/role", &assign_role(&app, "collabolator"))
+        .await;
+    app.post_roles("/delete", &[("role", "editor")]).await;
+
+    // Assert
+    let html = app.get_roles_html().await;
+    assert!(html.contains("<p><i>The role editor has been deleted.</i></p>"));
+    assert!(!html.contains("<td>editor</td>"));
+}
+
+#[tokio::test]
+async fn roles_offered_in_pending_invitations_cannot_be_deleted() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    app.post_roles("", &[("name", "editor"), ("permission", "issues:draft")])
+        .await;
+    sqlx::query!(
+        r#"
+        INSERT INTO collabolator_activation_tokens (email, token, created_at, role)
+        VALUES ('editor@example.com', 'editor-token', now(), 'editor')
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = app.post_roles("/delete", &[("role", "editor")]).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/roles");
+    let html = app.get_roles_html().await;
+    assert!(html.contains("<p><i>The role is still offered in pending invitations.</i></p>"));
+    assert!(html.contains("<td>editor</td>"));
+}
+
+#[tokio::test]
+async fn roles_need_a_valid_unique_name() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let test_cases = [
+        (
+            "",
+            "Role names are 1 to 32 lowercase letters, digits, '-' or '_'.",
+        ),
+        (
+            "Chief Editor",
+            "Role names are 1 to 32 lowercase letters, digits, '-' or '_'.",
+        ),
+        ("admin", "A role with that name already exists."),
+    ];
+
+    for (name, message) in test_cases {
+        // Act
+        let response = app.post_roles("", &[("name", name)]).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/roles");
+        let html = app.get_roles_html().await;
+        assert!(
+            html.contains(&format!(
+                "<p><i>{}</i></p>",
+                htmlescape::encode_minimal(message)
+            )),
+            "'{name}' was not refused with '{message}'"
+        );
+    }
+    let unknown_permission = app
+        .post_roles("", &[("name", "editor"), ("permission", "users:delete")])
+        .await;
+    assert_eq!(unknown_permission.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn users_cannot_be_left_without_anyone_to_manage_them() {
+    // Arrange
+    let app = spawn_app().await;
+    // The admin seeded by the migrations does not count
+    sqlx::query!(
+        "UPDATE users SET deactivated_at = now() WHERE user_id != $1 AND role = 'admin'",
+        app.admin_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app.login_with_admin_user().await;
+
+    // Act
+    let response = app
+        .post_roles(
+            "/permissions",
+            &[("role", "admin"), ("permission", "subscribers:manage")],
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/roles");
+    assert!(app
+        .get_roles_html()
+        .await
+        .contains("<p><i>At least one active user has to be able to manage users.</i></p>"));
+    assert_eq!(app.get_users().await.status().as_u16(), 200);
+}
@@ -0,0 +1,230 @@
+use crate::helpers::{
+    assert_is_redirect_to, get_dashboard, log_in_elsewhere, spawn_app, spawn_app_with,
+    OTHER_USER_AGENT,
+};
+
+/// The id of the only session on the page that can be revoked.
+fn revocable_session_id(html: &str) -> String {
+    html.split(r#"name="session_id" value=""#)
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn the_sessions_of_the_user_are_listed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    log_in_elsewhere(&app, &app.admin_user).await;
+
+    // Act
+    let html = app.get_sessions_html().await;
+
+    // Assert
+    assert!(html.contains("This session"));
+    assert!(html.contains(OTHER_USER_AGENT));
+    assert!(html.contains("127.0.0.1"));
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_your_sessions() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_sessions().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_revoked_session_is_logged_out() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let other = log_in_elsewhere(&app, &app.admin_user).await;
+    let session_id = revocable_session_id(&app.get_sessions_html().await);
+
+    // Act
+    let response = app
+        .post_sessions("", &serde_json::json!({ "session_id": session_id }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/sessions");
+    let html = app.get_sessions_html().await;
+    assert!(html.contains("The session has been revoked."));
+    assert!(!html.contains(OTHER_USER_AGENT));
+    assert_is_redirect_to(&get_dashboard(&app, &other).await, "/login");
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn logging_out_everywhere_else_keeps_the_current_session() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let other = log_in_elsewhere(&app, &app.admin_user).await;
+
+    // Act
+    let response = app.post_sessions("/others", &serde_json::json!({})).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/sessions");
+    assert_is_redirect_to(&get_dashboard(&app, &other).await, "/login");
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn sessions_of_other_users_cannot_be_revoked() {
+    // Arrange
+    let app = spawn_app().await;
+    let other = log_in_elsewhere(&app, &app.collabolator_user).await;
+    app.login_with_admin_user().await;
+    let session_id = sqlx::query!(
+        "SELECT session_id FROM user_sessions WHERE user_id = $1",
+        app.collabolator_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .session_id;
+
+    // Act
+    app.post_sessions("", &serde_json::json!({ "session_id": session_id }))
+        .await;
+
+    // Assert
+    let html = app.get_sessions_html().await;
+    assert!(html.contains("The session does not exist or has already ended."));
+    assert_eq!(get_dashboard(&app, &other).await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn changing_the_password_logs_out_the_other_sessions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let other = log_in_elsewhere(&app, &app.admin_user).await;
+    let new_password = uuid::Uuid::new_v4().to_string();
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.admin_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/password");
+    assert_is_redirect_to(&get_dashboard(&app, &other).await, "/login");
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn logged_out_sessions_are_no_longer_listed() {
+    // Arrange
+    let app = spawn_app().await;
+    let other = log_in_elsewhere(&app, &app.admin_user).await;
+    other
+        .post(&format!("{}/admin/logout", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    // Act
+    app.login_with_admin_user().await;
+    let html = app.get_sessions_html().await;
+
+    // Assert
+    assert!(!html.contains(OTHER_USER_AGENT));
+}
+
+#[tokio::test]
+async fn demoted_admins_lose_access_without_logging_out() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.session_cache_milliseconds = 0).await;
+    app.login_with_admin_user().await;
+    assert_eq!(app.get_lockouts().await.status().as_u16(), 200);
+
+    // Act
+    sqlx::query!(
+        "UPDATE users SET role = 'collabolator' WHERE user_id = $1",
+        app.admin_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(app.get_lockouts().await.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn deleted_users_are_logged_out() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.session_cache_milliseconds = 0).await;
+    app.login_with_collabolator_user().await;
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+
+    // Act
+    sqlx::query!(
+        "DELETE FROM users WHERE user_id = $1",
+        app.collabolator_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+}
+
+#[tokio::test]
+async fn admins_can_force_a_user_to_log_in_again() {
+    // Arrange
+    let app = spawn_app().await;
+    let other = log_in_elsewhere(&app, &app.collabolator_user).await;
+    assert_eq!(get_dashboard(&app, &other).await.status().as_u16(), 200);
+    app.login_with_admin_user().await;
+
+    // Act
+    let response = app
+        .post_sessions(
+            "/force-relogin",
+            &serde_json::json!({ "username": &app.collabolator_user.username }),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/sessions");
+    let html = app.get_sessions_html().await;
+    assert!(html.contains(&format!(
+        "&#x27;{}&#x27; has to log in again.",
+        app.collabolator_user.username
+    )));
+    assert_is_redirect_to(&get_dashboard(&app, &other).await, "/login");
+}
+
+#[tokio::test]
+async fn collaborators_cannot_force_other_users_to_log_in_again() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let response = app
+        .post_sessions(
+            "/force-relogin",
+            &serde_json::json!({ "username": &app.admin_user.username }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+}
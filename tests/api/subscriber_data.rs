@@ -0,0 +1,202 @@
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::webhooks::{create_webhook_endpoint, WebhookEventType};
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, when_sending_an_email, TestApp};
+
+#[tokio::test]
+async fn subscribers_can_export_their_data() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_subscriber(&app).await;
+
+    // Act
+    let response = app.get_preferences_export(&token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Disposition"],
+        r#"attachment; filename="subscriber-data.json""#
+    );
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["email"], "ursula_le_guin@gmail.com");
+    assert_eq!(data["subscription"]["name"], "le guin");
+    assert_eq!(data["subscription"]["status"], "pending_confirmation");
+    assert_eq!(data["list_memberships"][0]["list_name"], "Newsletter");
+    assert_eq!(data["subscription_tokens"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn data_export_is_rejected_without_a_valid_token() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_preferences_export("unknown-token").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn subscribers_can_erase_their_data() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = create_subscriber(&app).await;
+    confirm_subscriber(&app).await;
+    publish_newsletter(&app).await;
+
+    // Act
+    let response = app.post_preferences_erase(&token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_nothing_is_stored(&app).await;
+    assert_eq!(
+        app.get_preferences_export(&token).await.status().as_u16(),
+        401
+    );
+}
+
+#[tokio::test]
+async fn erasure_removes_the_address_from_webhook_events() {
+    // Arrange
+    let app = spawn_app().await;
+    create_webhook_endpoint(
+        &app.db_pool,
+        "https://example.com/hook",
+        &WebhookEventType::ALL,
+    )
+    .await
+    .unwrap();
+    let token = create_subscriber(&app).await;
+    confirm_subscriber(&app).await;
+
+    // Act
+    app.post_preferences_erase(&token).await;
+
+    // Assert
+    let payloads = sqlx::query_scalar!("SELECT payload FROM webhook_outbox ORDER BY created_at")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(payloads.len(), 2);
+    for payload in payloads {
+        assert!(!payload.contains("ursula_le_guin@gmail.com"));
+        let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert!(payload["data"]["email"].is_null());
+        assert!(payload["data"]["subscriber_id"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn admins_can_export_and_erase_subscriber_data() {
+    // Arrange
+    let app = spawn_app().await;
+    create_subscriber(&app).await;
+    confirm_subscriber(&app).await;
+    publish_newsletter(&app).await;
+
+    // Act 1 - Export
+    let response = app.get_subscriber_export("ursula_le_guin@gmail.com").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["subscription"]["status"], "confirmed");
+    assert_eq!(data["deliveries"][0]["title"], "Newsletter title");
+
+    // Act 2 - Erase
+    let response = app.post_subscriber_erase("ursula_le_guin@gmail.com").await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+
+    // Assert
+    let html = app.get_subscriber_data_html().await;
+    assert!(html.contains("All data stored for this email address has been erased."));
+    assert_nothing_is_stored(&app).await;
+
+    // Act 3 - Export again
+    let response = app.get_subscriber_export("ursula_le_guin@gmail.com").await;
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    let html = app.get_subscriber_data_html().await;
+    assert!(html.contains("No data is stored for this email address."));
+}
+
+#[tokio::test]
+async fn collaborators_cannot_access_subscriber_data() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let export = app.get_subscriber_export("ursula_le_guin@gmail.com").await;
+    let erase = app.post_subscriber_erase("ursula_le_guin@gmail.com").await;
+
+    // Assert
+    assert_eq!(export.status().as_u16(), 403);
+    assert_eq!(erase.status().as_u16(), 403);
+}
+
+/// Subscribe and return the preferences token sent in the confirmation email.
+async fn create_subscriber(app: &TestApp) -> String {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let links = app.get_preferences_links(email_request);
+    links
+        .html
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .map(|(_, value)| value.into_owned())
+        .unwrap()
+}
+
+async fn confirm_subscriber(app: &TestApp) {
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+/// Publish an issue as the admin, who stays logged in, without delivering it.
+async fn publish_newsletter(app: &TestApp) {
+    app.login_with_admin_user().await;
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+}
+
+async fn assert_nothing_is_stored(app: &TestApp) {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM subscriptions) AS "subscriptions!",
+            (SELECT COUNT(*) FROM subscription_tokens) AS "subscription_tokens!",
+            (SELECT COUNT(*) FROM list_memberships) AS "list_memberships!",
+            (SELECT COUNT(*) FROM issue_delivery_queue) AS "issue_delivery_queue!"
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(row.subscriptions, 0);
+    assert_eq!(row.subscription_tokens, 0);
+    assert_eq!(row.list_memberships, 0);
+    assert_eq!(row.issue_delivery_queue, 0);
+}
@@ -0,0 +1,282 @@
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::mailing_lists::DEFAULT_LIST_ID;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, when_sending_an_email, TestApp};
+
+#[tokio::test]
+async fn admins_can_tag_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app, "ursula@gmail.com").await;
+    app.login_with_admin_user().await;
+
+    // Act 1 - Add tags
+    let response = app
+        .post_subscriber_tags(&serde_json::json!({
+            "email": "ursula@gmail.com",
+            "tags": "Beta, early-adopter",
+            "action": "add",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/tags");
+
+    // Assert
+    let html = app.get_subscriber_tags_html().await;
+    assert!(html.contains("<li>beta (1 subscriber(s))</li>"));
+    assert!(html.contains("<li>early-adopter (1 subscriber(s))</li>"));
+
+    // Act 2 - Remove a tag
+    app.post_subscriber_tags(&serde_json::json!({
+        "email": "ursula@gmail.com",
+        "tags": "beta",
+        "action": "remove",
+    }))
+    .await;
+
+    // Assert
+    let tags = sqlx::query!("SELECT tag FROM subscriber_tags")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].tag, "early-adopter");
+}
+
+#[tokio::test]
+async fn invalid_tags_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app, "ursula@gmail.com").await;
+    app.login_with_admin_user().await;
+    let test_cases = vec![
+        ("ursula@gmail.com", "beta!", "beta! is not a valid tag."),
+        ("ursula@gmail.com", " , ", "Enter at least one tag."),
+        (
+            "nobody@gmail.com",
+            "beta",
+            "There is no subscriber with this email address.",
+        ),
+    ];
+
+    for (email, tags, error_message) in test_cases {
+        // Act
+        app.post_subscriber_tags(&serde_json::json!({
+            "email": email,
+            "tags": tags,
+            "action": "add",
+        }))
+        .await;
+
+        // Assert
+        let html = app.get_subscriber_tags_html().await;
+        assert!(html.contains(error_message), "{}", error_message);
+    }
+}
+
+#[tokio::test]
+async fn issues_are_delivered_only_to_subscribers_in_the_segment() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app, "beta@gmail.com").await;
+    create_confirmed_subscriber(&app, "beta-staff@gmail.com").await;
+    create_confirmed_subscriber(&app, "other@gmail.com").await;
+    app.login_with_admin_user().await;
+    tag(&app, "beta@gmail.com", "beta").await;
+    tag(&app, "beta-staff@gmail.com", "beta, staff").await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string(),
+            "include_tags": "beta",
+            "exclude_tags": "staff",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await.unwrap();
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[3];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "beta@gmail.com");
+}
+
+#[tokio::test]
+async fn issues_can_be_sent_to_subscribers_who_joined_in_a_date_range() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app, "ursula@gmail.com").await;
+    app.login_with_admin_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "subscribed_after": "2000-01-01",
+        "subscribed_before": "2000-02-01",
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await.unwrap();
+}
+
+#[tokio::test]
+async fn the_newsletter_form_shows_the_number_of_recipients() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app, "beta@gmail.com").await;
+    create_confirmed_subscriber(&app, "other@gmail.com").await;
+    app.login_with_admin_user().await;
+    tag(&app, "beta@gmail.com", "beta").await;
+
+    // Act
+    let html = app
+        .get_newsletter_form_with_query(&[
+            ("list_id", DEFAULT_LIST_ID.to_string().as_str()),
+            ("title", "Beta news"),
+            ("include_tags", "beta"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html.contains("<p><i>The segment contains 1 subscriber(s).</i></p>"));
+    assert!(html.contains(r#"value="Beta&#x20;news""#));
+}
+
+#[tokio::test]
+async fn the_recipient_count_follows_the_status_of_the_segment() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app, "confirmed@gmail.com").await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=pending%40gmail.com".into())
+        .await;
+    app.login_with_admin_user().await;
+
+    let list_id = DEFAULT_LIST_ID.to_string();
+
+    for status in ["confirmed", "pending_confirmation"] {
+        // Act
+        let html = app
+            .get_newsletter_form_with_query(&[("list_id", list_id.as_str()), ("status", status)])
+            .await
+            .text()
+            .await
+            .unwrap();
+
+        // Assert
+        assert!(
+            html.contains("<p><i>The segment contains 1 subscriber(s).</i></p>"),
+            "{status}"
+        );
+        assert!(html.contains(&format!(r#"<option value="{status}" selected>"#)));
+    }
+}
+
+#[tokio::test]
+async fn invalid_segments_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let test_cases = vec![
+        (
+            serde_json::json!({ "subscribed_after": "yesterday" }),
+            "yesterday is not a valid date (YYYY-MM-DD).",
+        ),
+        (
+            serde_json::json!({
+                "subscribed_after": "2024-02-01",
+                "subscribed_before": "2024-02-01",
+            }),
+            "2024-02-01 is not before 2024-02-01.",
+        ),
+        (
+            serde_json::json!({ "status": "unsubscribed" }),
+            "Newsletter issues can only be sent to confirmed subscribers.",
+        ),
+    ];
+
+    for (segment, error_message) in test_cases {
+        // Act
+        let mut body = serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string(),
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(segment.as_object().unwrap().clone());
+        let response = app.post_publish_newsletter(&body).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+
+        // Assert
+        let html = app.get_publish_newsletter_form_html().await;
+        assert!(html.contains(error_message), "{}", error_message);
+    }
+    let n_issues = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_issues, 0);
+}
+
+async fn create_confirmed_subscriber(app: &TestApp, email: &str) {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(format!(
+        "name=le%20guin&email={}",
+        urlencoding::encode(email)
+    ))
+    .await
+    .error_for_status()
+    .unwrap();
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn tag(app: &TestApp, email: &str, tags: &str) {
+    app.post_subscriber_tags(&serde_json::json!({
+        "email": email,
+        "tags": tags,
+        "action": "add",
+    }))
+    .await;
+}
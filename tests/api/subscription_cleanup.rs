@@ -0,0 +1,267 @@
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn expired_tokens_are_removed() {
+    // Arrange
+    let app = spawn_app().await;
+    let token_ttl = app.subscription_settings.confirmation_token_ttl();
+    let subscriber_id = insert_subscriber(&app.db_pool, "pending_confirmation", Utc::now()).await;
+    insert_token(&app.db_pool, subscriber_id, "fresh_token", Utc::now()).await;
+    insert_token(
+        &app.db_pool,
+        subscriber_id,
+        "expired_token",
+        Utc::now() - token_ttl - Duration::minutes(1),
+    )
+    .await;
+
+    // Act
+    app.remove_stale_subscriptions().await;
+
+    // Assert
+    let tokens = sqlx::query_scalar!("SELECT subscription_token FROM subscription_tokens")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(tokens, vec!["fresh_token".to_string()]);
+}
+
+#[tokio::test]
+async fn stale_pending_subscribers_are_removed() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_ttl = app.subscription_settings.pending_subscriber_ttl();
+    let stale_subscribed_at = Utc::now() - subscriber_ttl - Duration::minutes(1);
+    let stale_id =
+        insert_subscriber(&app.db_pool, "pending_confirmation", stale_subscribed_at).await;
+    insert_token(&app.db_pool, stale_id, "stale_token", stale_subscribed_at).await;
+    let fresh_id = insert_subscriber(&app.db_pool, "pending_confirmation", Utc::now()).await;
+    let confirmed_id = insert_subscriber(&app.db_pool, "confirmed", stale_subscribed_at).await;
+
+    // Act
+    app.remove_stale_subscriptions().await;
+
+    // Assert
+    let mut ids = sqlx::query_scalar!("SELECT id FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    ids.sort();
+    let mut expected = vec![fresh_id, confirmed_id];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    let n_tokens = sqlx::query_scalar!("SELECT COUNT(*) FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_tokens, Some(0));
+}
+
+#[tokio::test]
+async fn pending_subscribers_with_a_live_token_are_kept() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_ttl = app.subscription_settings.pending_subscriber_ttl();
+    let subscribed_at = Utc::now() - subscriber_ttl - Duration::minutes(1);
+    // e.g. someone who has just subscribed again
+    let subscriber_id =
+        insert_subscriber(&app.db_pool, "pending_confirmation", subscribed_at).await;
+    insert_token(&app.db_pool, subscriber_id, "live_token", Utc::now()).await;
+
+    // Act
+    app.remove_stale_subscriptions().await;
+
+    // Assert
+    let ids = sqlx::query_scalar!("SELECT id FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(ids, vec![subscriber_id]);
+}
+
+#[tokio::test]
+async fn an_unconfirmed_email_change_is_dropped_but_not_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let token_ttl = app.subscription_settings.confirmation_token_ttl();
+    let subscriber_ttl = app.subscription_settings.pending_subscriber_ttl();
+    let subscribed_at = Utc::now() - subscriber_ttl - Duration::minutes(1);
+    let subscriber_id = insert_subscriber(&app.db_pool, "confirmed", subscribed_at).await;
+    sqlx::query!(
+        "UPDATE subscriptions SET pending_email = 'new@example.com' WHERE id = $1",
+        subscriber_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    insert_token(
+        &app.db_pool,
+        subscriber_id,
+        "expired_token",
+        Utc::now() - token_ttl - Duration::minutes(1),
+    )
+    .await;
+
+    // Act
+    app.remove_stale_subscriptions().await;
+
+    // Assert
+    let saved = sqlx::query!("SELECT id, email, pending_email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.id, subscriber_id);
+    assert_eq!(saved.email, format!("{subscriber_id}@example.com"));
+    assert_eq!(saved.pending_email, None);
+}
+
+#[tokio::test]
+async fn a_live_email_change_is_kept() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = insert_subscriber(&app.db_pool, "confirmed", Utc::now()).await;
+    sqlx::query!(
+        "UPDATE subscriptions SET pending_email = 'new@example.com' WHERE id = $1",
+        subscriber_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    insert_token(&app.db_pool, subscriber_id, "live_token", Utc::now()).await;
+
+    // Act
+    app.remove_stale_subscriptions().await;
+
+    // Assert
+    let pending_email = sqlx::query_scalar!("SELECT pending_email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(pending_email.as_deref(), Some("new@example.com"));
+}
+
+#[tokio::test]
+async fn pending_list_memberships_are_removed_with_their_token() {
+    // Arrange
+    let app = spawn_app().await;
+    let token_ttl = app.subscription_settings.confirmation_token_ttl();
+    let subscriber_id = insert_subscriber(&app.db_pool, "confirmed", Utc::now()).await;
+    let expired_list = insert_list(&app.db_pool, "Expired").await;
+    let live_list = insert_list(&app.db_pool, "Live").await;
+    let joined_list = insert_list(&app.db_pool, "Joined").await;
+    for (list_id, status) in [
+        (expired_list, "pending_confirmation"),
+        (live_list, "pending_confirmation"),
+        (joined_list, "confirmed"),
+    ] {
+        sqlx::query!(
+            r#"
+            INSERT INTO list_memberships (list_id, subscriber_id, status, subscribed_at)
+            VALUES ($1, $2, $3, now())
+            "#,
+            list_id,
+            subscriber_id,
+            status
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+    for (list_id, token, created_at) in [
+        (
+            expired_list,
+            "expired_token",
+            Utc::now() - token_ttl - Duration::minutes(1),
+        ),
+        (live_list, "live_token", Utc::now()),
+    ] {
+        sqlx::query!(
+            r#"
+            INSERT INTO subscription_tokens (subscription_token, subscriber_id, list_id, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            token,
+            subscriber_id,
+            list_id,
+            created_at
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+
+    // Act
+    app.remove_stale_subscriptions().await;
+
+    // Assert
+    let mut list_ids = sqlx::query_scalar!("SELECT list_id FROM list_memberships")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    list_ids.sort();
+    let mut expected = vec![live_list, joined_list];
+    expected.sort();
+    assert_eq!(list_ids, expected);
+}
+
+async fn insert_list(pool: &PgPool, name: &str) -> Uuid {
+    let list_id = Uuid::new_v4();
+    sqlx::query!(
+        "INSERT INTO lists (list_id, name, created_at) VALUES ($1, $2, now())",
+        list_id,
+        name
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+    list_id
+}
+
+async fn insert_subscriber(
+    pool: &PgPool,
+    status: &str,
+    subscribed_at: chrono::DateTime<Utc>,
+) -> Uuid {
+    let subscriber_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, preferences_token)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        subscriber_id,
+        format!("{}@example.com", subscriber_id),
+        "le guin",
+        subscribed_at,
+        status,
+        subscriber_id.to_string()
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+    subscriber_id
+}
+
+async fn insert_token(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    token: &str,
+    created_at: chrono::DateTime<Utc>,
+) {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_tokens (subscription_token, subscriber_id, created_at)
+        VALUES ($1, $2, $3)
+        "#,
+        token,
+        subscriber_id,
+        created_at
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
@@ -0,0 +1,273 @@
+use chrono::Utc;
+use zero2prod::authentication::totp_code;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+
+struct Enrollment {
+    secret: String,
+    recovery_codes: Vec<String>,
+}
+
+/// The code of the next period - the one of the current period may already
+/// have been used.
+fn next_code(secret: &str) -> String {
+    totp_code(secret, Utc::now().timestamp() + 30).unwrap()
+}
+
+/// Set up a second factor for the logged in user.
+async fn enable_two_factor(app: &TestApp) -> Enrollment {
+    let response = app
+        .post_security_settings("/two-factor", &serde_json::json!({}))
+        .await;
+    assert_is_redirect_to(&response, "/admin/security");
+    let html = app.get_security_settings_html().await;
+    let secret = html
+        .split("<code>")
+        .nth(1)
+        .and_then(|s| s.split("</code>").next())
+        .unwrap()
+        .to_owned();
+
+    let code = totp_code(&secret, Utc::now().timestamp()).unwrap();
+    let response = app
+        .post_security_settings("/two-factor/confirm", &serde_json::json!({ "code": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    let recovery_codes = html
+        .split("<li><code>")
+        .skip(1)
+        .filter_map(|s| s.split("</code>").next())
+        .map(str::to_owned)
+        .collect();
+    Enrollment {
+        secret,
+        recovery_codes,
+    }
+}
+
+async fn log_in_again(app: &TestApp) -> reqwest::Response {
+    app.post_logout().await;
+    app.post_login(&serde_json::json!({
+        "username": &app.admin_user.username,
+        "password": &app.admin_user.password
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn enabling_two_factor_authentication_adds_a_second_login_step() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let enrollment = enable_two_factor(&app).await;
+    assert_eq!(enrollment.recovery_codes.len(), 10);
+
+    // Act 1 - The password alone is not enough
+    let response = log_in_again(&app).await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Act 2 - Provide the second factor
+    let response = app
+        .post_two_factor_login(&next_code(&enrollment.secret))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html = app.get_admin_dashboard_html().await;
+    assert!(html.contains(&format!("Welcome {}", app.admin_user.username)));
+}
+
+#[tokio::test]
+async fn recovery_codes_are_only_shown_in_the_confirmation_response() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    app.post_security_settings("/two-factor", &serde_json::json!({}))
+        .await;
+    let html = app.get_security_settings_html().await;
+    let secret = html
+        .split("<code>")
+        .nth(1)
+        .and_then(|s| s.split("</code>").next())
+        .unwrap();
+    let code = totp_code(secret, Utc::now().timestamp()).unwrap();
+
+    // Act
+    let response = app
+        .post_security_settings("/two-factor/confirm", &serde_json::json!({ "code": code }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let cookies: Vec<_> = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .map(|c| c.to_str().unwrap().to_owned())
+        .collect();
+    let html = response.text().await.unwrap();
+    let first_code = html
+        .split("<li><code>")
+        .nth(1)
+        .and_then(|s| s.split("</code>").next())
+        .unwrap();
+    assert!(cookies.iter().all(|c| !c.contains(first_code)));
+    assert!(!app.get_security_settings_html().await.contains(first_code));
+}
+
+#[tokio::test]
+async fn an_invalid_code_does_not_enable_two_factor_authentication() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    app.post_security_settings("/two-factor", &serde_json::json!({}))
+        .await;
+
+    // Act
+    let response = app
+        .post_security_settings(
+            "/two-factor/confirm",
+            &serde_json::json!({ "code": "000000" }),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/security");
+    let html = app.get_security_settings_html().await;
+    assert!(html.contains("The code is not valid - try again."));
+    let response = log_in_again(&app).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn an_invalid_second_factor_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    enable_two_factor(&app).await;
+    log_in_again(&app).await;
+
+    // Act
+    let response = app.post_two_factor_login("000000").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/two-factor");
+    let html = app.get_two_factor_login_html().await;
+    assert!(html.contains("<p><i>Authentication failed</i></p>"));
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn recovery_codes_can_only_be_used_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let enrollment = enable_two_factor(&app).await;
+    let recovery_code = &enrollment.recovery_codes[0];
+
+    // Act 1
+    log_in_again(&app).await;
+    let response = app.post_two_factor_login(recovery_code).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // Act 2
+    log_in_again(&app).await;
+    let response = app.post_two_factor_login(recovery_code).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn the_second_step_requires_a_correct_password_first() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let form = app
+        .api_client
+        .get(&format!("{}/login/two-factor", &app.address))
+        .send()
+        .await
+        .unwrap();
+    let response = app.post_two_factor_login("000000").await;
+
+    // Assert
+    assert_is_redirect_to(&form, "/login");
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn two_factor_authentication_can_be_disabled_with_a_valid_code() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let enrollment = enable_two_factor(&app).await;
+
+    // Act
+    let response = app
+        .post_security_settings(
+            "/two-factor/disable",
+            &serde_json::json!({ "code": next_code(&enrollment.secret) }),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/security");
+    let html = app.get_security_settings_html().await;
+    assert!(html.contains("Two-factor authentication is disabled."));
+    let response = log_in_again(&app).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn users_must_enable_two_factor_authentication_when_an_admin_requires_it() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let response = app
+        .post_security_settings(
+            "/policy",
+            &serde_json::json!({ "require_two_factor": "on" }),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/security");
+    app.post_logout().await;
+    app.login_with_collabolator_user().await;
+
+    // Act 1 - Everything but the security settings is off limits
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/admin/security");
+    let html = app.get_security_settings_html().await;
+    assert!(html.contains("An administrator requires two-factor authentication"));
+
+    // Act 2 - Enable two-factor authentication
+    enable_two_factor(&app).await;
+
+    // Assert
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn collaborators_cannot_change_the_security_policy() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let response = app
+        .post_security_settings(
+            "/policy",
+            &serde_json::json!({ "require_two_factor": "on" }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+}
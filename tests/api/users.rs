@@ -0,0 +1,211 @@
+use crate::helpers::{assert_is_redirect_to, get_dashboard, log_in_elsewhere, spawn_app, TestApp};
+
+const LAST_MANAGER_ERROR: &str =
+    "<p><i>At least one active user has to be able to manage users.</i></p>";
+
+fn user_form(user_id: uuid::Uuid) -> serde_json::Value {
+    serde_json::json!({ "user_id": user_id })
+}
+
+async fn get_lockouts(app: &TestApp, client: &reqwest::Client) -> reqwest::Response {
+    client
+        .get(&format!("{}/admin/lockouts", &app.address))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn users_can_only_be_managed_by_admins() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let page = app.get_users().await;
+    let delete = app
+        .post_users("/delete", &user_form(app.admin_user.user_id))
+        .await;
+
+    // Assert
+    assert_eq!(page.status().as_u16(), 403);
+    assert_eq!(delete.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn every_user_is_listed_with_their_role() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let html = app.get_users_html().await;
+
+    // Assert
+    assert!(html.contains(&format!(
+        "<td>{} (you)</td><td>-</td><td>admin</td>",
+        app.admin_user.username
+    )));
+    assert!(html.contains(&format!(
+        "<td>{}</td><td>-</td><td>collabolator</td>",
+        app.collabolator_user.username
+    )));
+    assert!(html.contains(r#"<option value="admin">admin</option>"#));
+    assert!(html.contains(r#"<option value="collabolator" selected>collabolator</option>"#));
+}
+
+#[tokio::test]
+async fn deactivated_users_are_logged_out_and_cannot_log_in() {
+    // Arrange
+    let app = spawn_app().await;
+    let other = log_in_elsewhere(&app, &app.collabolator_user).await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let response = app
+        .post_users("/deactivate", &user_form(app.collabolator_user.user_id))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/users");
+    let html = app.get_users_html().await;
+    assert!(html.contains("<p><i>The user has been deactivated.</i></p>"));
+    assert!(html.contains("Reactivate"));
+    assert_is_redirect_to(&get_dashboard(&app, &other).await, "/login");
+    let login = app
+        .post_login(&serde_json::json!({
+            "username": &app.collabolator_user.username,
+            "password": &app.collabolator_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&login, "/login");
+}
+
+#[tokio::test]
+async fn reactivated_users_can_log_in_again() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let form = user_form(app.collabolator_user.user_id);
+    app.post_users("/deactivate", &form).await;
+
+    // Act
+    let response = app.post_users("/reactivate", &form).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/users");
+    log_in_elsewhere(&app, &app.collabolator_user).await;
+}
+
+#[tokio::test]
+async fn the_last_admin_cannot_be_deactivated_demoted_or_deleted() {
+    // Arrange
+    let app = spawn_app().await;
+    // The admin seeded by the migrations does not count
+    sqlx::query!(
+        "UPDATE users SET deactivated_at = now() WHERE user_id != $1 AND role = 'admin'",
+        app.admin_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app.login_with_admin_user().await;
+    let form = user_form(app.admin_user.user_id);
+    let demote = serde_json::json!({
+        "user_id": app.admin_user.user_id,
+        "role": "collabolator",
+    });
+
+    for (path, body) in [
+        ("/deactivate", &form),
+        ("/role", &demote),
+        ("/delete", &form),
+    ] {
+        // Act
+        let response = app.post_users(path, body).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/users");
+        let html = app.get_users_html().await;
+        assert!(html.contains(LAST_MANAGER_ERROR), "{path} was not refused");
+    }
+    let admin = sqlx::query!(
+        "SELECT role, deactivated_at FROM users WHERE user_id = $1",
+        app.admin_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(admin.role, "admin");
+    assert!(admin.deactivated_at.is_none());
+}
+
+#[tokio::test]
+async fn promoted_collaborators_become_admins_right_away() {
+    // Arrange
+    let app = spawn_app().await;
+    let other = log_in_elsewhere(&app, &app.collabolator_user).await;
+    assert_eq!(get_lockouts(&app, &other).await.status().as_u16(), 403);
+    app.login_with_admin_user().await;
+
+    // Act 1 - Promote the collaborator
+    let response = app
+        .post_users(
+            "/role",
+            &serde_json::json!({
+                "user_id": app.collabolator_user.user_id,
+                "role": "admin",
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/users");
+    assert!(app
+        .get_users_html()
+        .await
+        .contains("<p><i>The user is now admin.</i></p>"));
+    assert_eq!(get_lockouts(&app, &other).await.status().as_u16(), 200);
+
+    // Act 2 - With another admin around, the first one can step down
+    app.post_users(
+        "/role",
+        &serde_json::json!({
+            "user_id": app.admin_user.user_id,
+            "role": "collabolator",
+        }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(app.get_users().await.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn deleted_users_are_gone() {
+    // Arrange
+    let app = spawn_app().await;
+    let other = log_in_elsewhere(&app, &app.collabolator_user).await;
+    app.login_with_admin_user().await;
+
+    // Act
+    let response = app
+        .post_users("/delete", &user_form(app.collabolator_user.user_id))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/users");
+    let html = app.get_users_html().await;
+    assert!(html.contains("<p><i>The user has been deleted.</i></p>"));
+    assert!(!html.contains(&app.collabolator_user.username));
+    assert_is_redirect_to(&get_dashboard(&app, &other).await, "/login");
+
+    let unknown = app
+        .post_users("/delete", &user_form(app.collabolator_user.user_id))
+        .await;
+    assert_is_redirect_to(&unknown, "/admin/users");
+    assert!(app
+        .get_users_html()
+        .await
+        .contains("<p><i>There is no such user.</i></p>"));
+}
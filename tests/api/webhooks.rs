@@ -0,0 +1,339 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use zero2prod::mailing_lists::DEFAULT_LIST_ID;
+use zero2prod::webhooks::{sign_payload, signing_secret};
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, when_sending_an_email, TestApp};
+
+#[tokio::test]
+async fn webhooks_are_only_for_admins() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_collabolator_user().await;
+
+    // Act
+    let page = app.get_webhooks().await;
+    let create = app
+        .post_webhooks(&[("url", "https://example.com"), ("event", "issue.delivered")])
+        .await;
+
+    // Assert
+    assert_eq!(page.status().as_u16(), 403);
+    assert_eq!(create.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn endpoints_need_an_http_url_and_an_event() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_admin_user().await;
+    let test_cases = [
+        (
+            vec![("url", "ftp://example.com"), ("event", "issue.delivered")],
+            "The endpoint needs an http(s) URL.",
+        ),
+        (
+            vec![("url", "not a url"), ("event", "issue.delivered")],
+            "The endpoint needs an http(s) URL.",
+        ),
+        (
+            vec![("url", "https://example.com")],
+            "Choose at least one event.",
+        ),
+    ];
+
+    for (body, message) in test_cases {
+        // Act
+        let response = app.post_webhooks(&body).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/webhooks");
+        let html = app.get_webhooks_html().await;
+        assert!(
+            html.contains(&format!("<p><i>{message}</i></p>")),
+            "{body:?} was not rejected with '{message}'"
+        );
+    }
+    let unknown_event = app
+        .post_webhooks(&[("url", "https://example.com"), ("event", "issue.deleted")])
+        .await;
+    assert_eq!(unknown_event.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn subscription_events_are_signed_and_sent_to_the_endpoint() {
+    // Arrange
+    let app = spawn_app().await;
+    let receiver = add_endpoint(&app, &["subscriber.created", "subscriber.confirmed"]).await;
+
+    // Act
+    let confirmation_link = subscribe(&app).await;
+    reqwest::get(confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_webhooks().await.unwrap();
+
+    // Assert
+    let requests = receiver.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let signing_secret = signing_secret(&app.hmac_secret);
+    for (request, expected_type) in requests
+        .iter()
+        .zip(["subscriber.created", "subscriber.confirmed"])
+    {
+        let header = |name: &str| request.headers.get(&name.into()).unwrap().as_str();
+        let payload = std::str::from_utf8(&request.body).unwrap();
+        let timestamp: i64 = header("X-Webhook-Timestamp").parse().unwrap();
+        assert_eq!(
+            header("X-Webhook-Signature"),
+            sign_payload(&signing_secret, timestamp, payload)
+        );
+        assert_eq!(header("X-Webhook-Event"), expected_type);
+
+        let event: serde_json::Value = serde_json::from_str(payload).unwrap();
+        assert_eq!(event["type"], expected_type);
+        assert_eq!(event["id"], header("X-Webhook-Id"));
+        assert_eq!(event["data"]["email"], "ursula_le_guin@gmail.com");
+        assert_eq!(event["data"]["list_id"], DEFAULT_LIST_ID.to_string());
+    }
+}
+
+#[tokio::test]
+async fn endpoints_only_get_the_events_they_chose() {
+    // Arrange
+    let app = spawn_app().await;
+    let receiver = add_endpoint(&app, &["issue.delivered"]).await;
+
+    // Act
+    subscribe(&app).await;
+    app.dispatch_all_pending_webhooks().await.unwrap();
+
+    // Assert
+    assert!(receiver.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn leaving_a_list_sends_an_unsubscribed_event() {
+    // Arrange
+    let app = spawn_app().await;
+    let receiver = add_endpoint(&app, &["subscriber.unsubscribed"]).await;
+    subscribe(&app).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let preferences_link = app.get_preferences_links(email_request).html;
+    let token = preferences_link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .into_owned();
+
+    // Act - No list checked anymore
+    app.post_preferences(&serde_json::json!({
+        "token": token,
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com",
+    }))
+    .await;
+    app.dispatch_all_pending_webhooks().await.unwrap();
+
+    // Assert
+    let requests = receiver.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    let event: serde_json::Value = requests[0].body_json().unwrap();
+    assert_eq!(event["type"], "subscriber.unsubscribed");
+    assert_eq!(event["data"]["list_id"], DEFAULT_LIST_ID.to_string());
+}
+
+#[tokio::test]
+async fn an_issue_is_reported_delivered_once_every_email_went_out() {
+    // Arrange
+    let app = spawn_app().await;
+    let receiver = add_endpoint(&app, &["issue.delivered"]).await;
+    reqwest::get(subscribe(&app).await)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.login_with_admin_user().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+
+    // Act 1 - Nothing was delivered yet
+    app.dispatch_all_pending_webhooks().await.unwrap();
+    assert!(receiver.received_requests().await.unwrap().is_empty());
+
+    // Act 2 - Deliver the issue
+    app.dispatch_all_pending_emails().await.unwrap();
+    app.dispatch_all_pending_webhooks().await.unwrap();
+
+    // Assert
+    let requests = receiver.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    let event: serde_json::Value = requests[0].body_json().unwrap();
+    assert_eq!(event["type"], "issue.delivered");
+    assert_eq!(event["data"]["recipients"], 1);
+}
+
+#[tokio::test]
+async fn an_issue_without_recipients_is_reported_delivered_when_published() {
+    // Arrange
+    let app = spawn_app().await;
+    let receiver = add_endpoint(&app, &["issue.delivered"]).await;
+
+    // Act
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_webhooks().await.unwrap();
+
+    // Assert
+    let requests = receiver.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    let event: serde_json::Value = requests[0].body_json().unwrap();
+    assert_eq!(event["type"], "issue.delivered");
+    assert_eq!(event["data"]["recipients"], 0);
+}
+
+#[tokio::test]
+async fn an_issue_is_reported_delivered_once_its_last_recipient_is_erased() {
+    // Arrange
+    let app = spawn_app().await;
+    let receiver = add_endpoint(&app, &["issue.delivered"]).await;
+    reqwest::get(subscribe(&app).await)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.post_publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+
+    // Act
+    app.post_subscriber_erase("ursula_le_guin@gmail.com").await;
+    app.dispatch_all_pending_webhooks().await.unwrap();
+
+    // Assert
+    let requests = receiver.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    let event: serde_json::Value = requests[0].body_json().unwrap();
+    assert_eq!(event["type"], "issue.delivered");
+    assert_eq!(event["data"]["recipients"], 1);
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried_later_and_shown_in_the_history() {
+    // Arrange
+    let app = spawn_app().await;
+    let receiver = MockServer::start().await;
+    Mock::given(path("/hooks"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&receiver)
+        .await;
+    app.login_with_admin_user().await;
+    app.post_webhooks(&[
+        ("url", format!("{}/hooks", receiver.uri()).as_str()),
+        ("event", "subscriber.created"),
+    ])
+    .await;
+
+    // Act
+    subscribe(&app).await;
+    // The retry is scheduled for later, so a second pass sends nothing.
+    app.dispatch_all_pending_webhooks().await.unwrap();
+    app.dispatch_all_pending_webhooks().await.unwrap();
+
+    // Assert
+    let delivery = sqlx::query!("SELECT status, n_attempts, execute_after FROM webhook_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "pending");
+    assert_eq!(delivery.n_attempts, 1);
+    assert!(delivery.execute_after.unwrap() > chrono::Utc::now());
+
+    let html = app.get_webhooks_html().await;
+    assert!(html.contains("subscriber.created"));
+    assert!(html.contains("500 Internal Server Error"));
+}
+
+#[tokio::test]
+async fn removed_endpoints_get_no_more_events() {
+    // Arrange
+    let app = spawn_app().await;
+    let receiver = add_endpoint(&app, &["subscriber.created"]).await;
+    let endpoint_id = sqlx::query_scalar!("SELECT endpoint_id FROM webhook_endpoints")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app
+        .api_client
+        .post(&format!("{}/admin/webhooks/delete", &app.address))
+        .form(&[("endpoint_id", endpoint_id.to_string())])
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/webhooks");
+    assert!(app
+        .get_webhooks_html()
+        .await
+        .contains("<p><i>The webhook endpoint has been removed.</i></p>"));
+    subscribe(&app).await;
+    app.dispatch_all_pending_webhooks().await.unwrap();
+
+    // Assert
+    assert!(receiver.received_requests().await.unwrap().is_empty());
+}
+
+/// Register an endpoint on a fresh mock server, logged in as the admin.
+async fn add_endpoint(app: &TestApp, events: &[&str]) -> MockServer {
+    let receiver = MockServer::start().await;
+    Mock::given(path("/hooks"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&receiver)
+        .await;
+    app.login_with_admin_user().await;
+    let url = format!("{}/hooks", receiver.uri());
+    let mut body = vec![("url", url.as_str())];
+    body.extend(events.iter().map(|event| ("event", *event)));
+    let response = app.post_webhooks(&body).await;
+    assert_is_redirect_to(&response, "/admin/webhooks");
+    receiver
+}
+
+/// Subscribe to the default list, returning the confirmation link.
+async fn subscribe(app: &TestApp) -> reqwest::Url {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    app.get_confirmation_links(email_request).html
+}